@@ -0,0 +1,177 @@
+use std::str::FromStr;
+
+use rust_decimal::Decimal;
+use serde_json::{self, json};
+
+use errors::Error;
+use net::Net;
+use spread::EXCHANGES;
+use ticker::Currency;
+
+/// How the prices from several sources are combined.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Method {
+    /// the middle price, or the mean of the middle two, so one odd print
+    /// can't move it
+    Median,
+    Mean,
+}
+
+/// The names `--aggregate` accepts.
+pub const METHODS: &[&str] = &["median", "mean"];
+
+impl FromStr for Method {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Method, String> {
+        match s.trim().to_lowercase().as_str() {
+            "median" => Ok(Method::Median),
+            "mean" => Ok(Method::Mean),
+            _ => Err(format!("{:?} should be one of {}", s, METHODS.join(", "))),
+        }
+    }
+}
+
+/// `prices` combined by `method`, or `None` if there are none.
+pub fn combine(method: Method, prices: &[Decimal]) -> Option<Decimal> {
+    if prices.is_empty() {
+        return None;
+    }
+    let count = Decimal::from(prices.len());
+    match method {
+        Method::Mean => prices.iter().sum::<Decimal>().checked_div(count),
+        Method::Median => {
+            let mut sorted = prices.to_vec();
+            sorted.sort();
+            let middle = sorted.len() / 2;
+            if sorted.len() % 2 == 1 {
+                Some(sorted[middle])
+            } else {
+                (sorted[middle - 1] + sorted[middle]).checked_div(Decimal::TWO)
+            }
+        }
+    }
+}
+
+/// Prices each coin from the ticker API and the exchanges `spread`
+/// asks, shown as one combined price.
+pub struct Aggregate {
+    method: Method,
+    exchanges: Vec<&'static str>,
+}
+
+impl Aggregate {
+    /// `exchanges` are names from `spread::EXCHANGES`; none means all of
+    /// them.
+    pub fn new(method: Method, exchanges: &[String]) -> Result<Aggregate, String> {
+        let known: Vec<&'static str> = EXCHANGES.iter().map(|&(name, _)| name).collect();
+        if exchanges.is_empty() {
+            return Ok(Aggregate {
+                method,
+                exchanges: known,
+            });
+        }
+        let exchanges = exchanges
+            .iter()
+            .map(|name| {
+                known
+                    .iter()
+                    .find(|&&known| known == name.trim().to_lowercase())
+                    .cloned()
+                    .ok_or_else(|| format!("{:?} should be one of {}", name, known.join(", ")))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Aggregate { method, exchanges })
+    }
+
+    /// The exchanges asked besides the ticker API.
+    pub fn exchanges(&self) -> &[&'static str] {
+        &self.exchanges
+    }
+
+    /// The coin `id` with the combined price of `ticker`, what the ticker
+    /// API gave, and each exchange's. Sources that fail are left out, and
+    /// when the ticker API is one of them the quote is only the price.
+    pub fn quote(
+        &self,
+        id: &str,
+        symbol: &str,
+        ticker: Result<Currency, Error>,
+        net: &Net,
+    ) -> Result<Currency, Error> {
+        let mut prices: Vec<Decimal> = ticker
+            .as_ref()
+            .ok()
+            .and_then(|ticker| ticker.price_usd)
+            .into_iter()
+            .collect();
+        let symbol = match ticker {
+            Ok(ref ticker) => ticker.symbol.to_uppercase(),
+            Err(_) => symbol.to_uppercase(),
+        };
+        for &(name, source) in EXCHANGES {
+            if !self.exchanges.contains(&name) {
+                continue;
+            }
+            match source(&symbol, net) {
+                Ok(price) => prices.push(price),
+                Err(err) => debug!("no {} price for {}: {}", name, id, err),
+            }
+        }
+        let price = match combine(self.method, &prices) {
+            Some(price) => price,
+            None => return ticker,
+        };
+        match ticker {
+            Ok(mut ticker) => {
+                ticker.price_usd = Some(price);
+                Ok(ticker)
+            }
+            Err(err) => {
+                debug!("{}, showing what the exchanges say", err);
+                Ok(serde_json::from_value(json!({
+                    "id": id,
+                    "name": id,
+                    "symbol": symbol,
+                    "rank": "",
+                    "price_usd": price.to_string(),
+                }))?)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prices(prices: &[&str]) -> Vec<Decimal> {
+        prices.iter().map(|price| price.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn combines_prices() {
+        let odd = prices(&["64250", "64287", "1"]);
+        assert_eq!(combine(Method::Median, &odd), Some(Decimal::from(64250)));
+        assert_eq!(combine(Method::Mean, &odd), Some(Decimal::from(42846)));
+        let even = prices(&["64250", "64210.10", "64287", "64300"]);
+        assert_eq!(
+            combine(Method::Median, &even),
+            Some("64268.5".parse().unwrap())
+        );
+        assert_eq!(combine(Method::Median, &[]), None);
+    }
+
+    #[test]
+    fn reads_methods_and_exchanges() {
+        assert_eq!("Median".parse(), Ok(Method::Median));
+        assert!("mode".parse::<Method>().is_err());
+        let aggregate = Aggregate::new(Method::Mean, &["Kraken".to_string()]).unwrap();
+        assert_eq!(aggregate.exchanges, vec!["kraken"]);
+        assert_eq!(
+            Aggregate::new(Method::Mean, &[]).unwrap().exchanges.len(),
+            EXCHANGES.len()
+        );
+        assert!(Aggregate::new(Method::Mean, &["ftx".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,1049 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{stderr, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use condition::{Comparison, Condition};
+use dirs;
+use errors::Error;
+use events::Event;
+use history::{self, format_span};
+use reminders::Reminder;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde_json;
+use template::{compact, rounded};
+use termux;
+use time::Tm;
+use ticker::{to_f64, Currency};
+use unlocks::{self, Unlock};
+
+/// One `[[alert]]` table from the config file, picked by its `rule` key.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "rule", rename_all = "lowercase")]
+pub enum Rule {
+    /// fires when `condition`, like `price>50000`, starts holding for
+    /// `coin`, but not again within `cooldown` seconds, nor before the
+    /// price has gone back past the threshold by `rearm` percent of it
+    Price {
+        coin: String,
+        condition: String,
+        cooldown: Option<u64>,
+        rearm: Option<f64>,
+    },
+    /// fires when `coin` (any watched coin if unset) crosses into or out of
+    /// the top N. `enters_top` without a coin watches the whole top N.
+    Rank {
+        coin: Option<String>,
+        enters_top: Option<u32>,
+        leaves_top: Option<u32>,
+    },
+    /// fires when the `[[holding]]` tables are worth less than
+    /// `value_below` USD, or are down more than `loss_beyond` percent on
+    /// what they cost
+    Portfolio {
+        value_below: Option<Decimal>,
+        loss_beyond: Option<f64>,
+    },
+    /// fires when `coin` comes to more than `above` or less than `below`
+    /// percent of what the `[[holding]]` tables are worth, to rebalance
+    Allocation {
+        coin: String,
+        above: Option<f64>,
+        below: Option<f64>,
+    },
+}
+
+/// One `[[holding]]` table: how much of a coin is held, and optionally
+/// what it cost in USD all told.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Holding {
+    pub coin: String,
+    pub amount: Decimal,
+    pub cost: Option<Decimal>,
+}
+
+/// Evaluates alert rules against the quotes interval mode fetches. Rules
+/// fire on transitions, so the first round only sets the baseline (price
+/// rules that already hold excepted).
+pub struct Alerts {
+    rules: Vec<(Rule, Option<Condition>)>,
+    /// the last rank seen for each coin
+    ranks: HashMap<String, u32>,
+    /// whether each portfolio or allocation rule held last time, by rule
+    /// index
+    holding: HashMap<usize, bool>,
+    /// where each price rule stands, by coin and condition
+    triggers: HashMap<String, Trigger>,
+    /// where `triggers` are kept between runs, if anywhere
+    state: Option<PathBuf>,
+    /// the last top-N list seen, by N
+    tops: HashMap<u32, HashSet<String>>,
+    /// `[[event]]` tables to remind about as they approach
+    events: Vec<Event>,
+    /// the events already reminded about, by index
+    reminded: HashSet<usize>,
+    /// how long before a watched coin's token unlock to remind about it
+    unlock_remind: Option<i64>,
+    /// the unlocks already reminded about, by coin and time
+    unlocks_reminded: HashSet<(String, i64)>,
+    /// `[[reminder]]` tables, raised on their schedule
+    reminders: Vec<Reminder>,
+    /// the day each reminder last came due, by index
+    reminded_on: HashMap<usize, i32>,
+    /// whether reminders have been checked before, so the first round
+    /// only sets the baseline
+    reminders_checked: bool,
+    /// what portfolio rules measure
+    holdings: Vec<Holding>,
+    /// the last price seen for each coin
+    prices: HashMap<String, Decimal>,
+    command: Option<String>,
+    /// whether alerts ring the terminal bell
+    bell: bool,
+    /// run, split on spaces, when alerts go off
+    sound: Option<String>,
+    /// the least time between two rings, however many alerts go off
+    quiet_for: Duration,
+    /// when the last ring was
+    rung: Option<Instant>,
+}
+
+/// Where a price rule stands. Kept across restarts, so a condition that
+/// still holds isn't alerted about again, nor a cooldown forgotten.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+struct Trigger {
+    /// whether the rule has fired and not rearmed since
+    held: bool,
+    /// when it last fired, in unix seconds
+    fired: Option<i64>,
+}
+
+/// What a price rule's trigger is kept under, which survives other
+/// rules coming and going, unlike its index.
+fn trigger_key(coin: &str, condition: &str) -> String {
+    format!("{} {}", coin, condition)
+}
+
+/// Whether `price` has gone back past `condition`'s threshold by
+/// `rearm` percent of it, so the rule may fire again.
+fn rearmed(condition: &Condition, price: f64, rearm: f64) -> bool {
+    let margin = condition.threshold.abs() * rearm / 100.0;
+    match condition.comparison {
+        Comparison::Above | Comparison::AtLeast => price <= condition.threshold - margin,
+        Comparison::Below | Comparison::AtMost => price >= condition.threshold + margin,
+        Comparison::Equal => (price - condition.threshold).abs() >= margin,
+    }
+}
+
+/// How long alerts stay quiet after ringing when `alert_sound_cooldown`
+/// doesn't say.
+pub const DEFAULT_QUIET: Duration = Duration::from_secs(60);
+
+impl Alerts {
+    pub fn new(rules: &[Rule], command: Option<String>) -> Result<Alerts, String> {
+        let mut parsed = Vec::new();
+        for rule in rules {
+            let condition = match *rule {
+                Rule::Price {
+                    rearm: Some(rearm), ..
+                } if rearm.is_nan() || rearm < 0.0 => {
+                    return Err(format!(
+                        "rearm should be a percentage of the threshold, like 2, not {}",
+                        rearm
+                    ))
+                }
+                Rule::Price { ref condition, .. } => Some(condition.parse::<Condition>()?),
+                Rule::Rank {
+                    enters_top: None,
+                    leaves_top: None,
+                    ..
+                } => return Err("rank alerts need enters_top or leaves_top".to_string()),
+                Rule::Rank { .. } => None,
+                Rule::Portfolio {
+                    value_below: None,
+                    loss_beyond: None,
+                } => return Err("portfolio alerts need value_below or loss_beyond".to_string()),
+                Rule::Portfolio { .. } => None,
+                Rule::Allocation {
+                    above: None,
+                    below: None,
+                    ..
+                } => return Err("allocation alerts need above or below".to_string()),
+                Rule::Allocation { .. } => None,
+            };
+            parsed.push((rule.clone(), condition));
+        }
+        Ok(Alerts {
+            rules: parsed,
+            ranks: HashMap::new(),
+            holding: HashMap::new(),
+            triggers: HashMap::new(),
+            state: None,
+            tops: HashMap::new(),
+            events: vec![],
+            reminded: HashSet::new(),
+            unlock_remind: None,
+            unlocks_reminded: HashSet::new(),
+            reminders: vec![],
+            reminded_on: HashMap::new(),
+            reminders_checked: false,
+            holdings: vec![],
+            prices: HashMap::new(),
+            command,
+            bell: false,
+            sound: None,
+            quiet_for: DEFAULT_QUIET,
+            rung: None,
+        })
+    }
+
+    /// Keeps where price rules stand in `alerts.json` in the cache
+    /// directory, picking up where the last run left off.
+    pub fn remembering(self) -> Alerts {
+        match dirs::cache_dir() {
+            Ok(dir) => self.remembering_in(dir.join("alerts.json")),
+            Err(err) => {
+                debug!("alerts won't be remembered: {}", err);
+                self
+            }
+        }
+    }
+
+    fn remembering_in(mut self, path: PathBuf) -> Alerts {
+        let mut triggers: HashMap<String, Trigger> = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        // the rules taken out of the config since are forgotten
+        let keys: HashSet<String> = self
+            .rules
+            .iter()
+            .filter_map(|(rule, _)| match *rule {
+                Rule::Price {
+                    ref coin,
+                    ref condition,
+                    ..
+                } => Some(trigger_key(coin, condition)),
+                _ => None,
+            })
+            .collect();
+        triggers.retain(|key, _| keys.contains(key));
+        self.triggers = triggers;
+        self.state = Some(path);
+        self
+    }
+
+    fn save_triggers(&self) -> Result<(), Error> {
+        if let Some(ref path) = self.state {
+            fs::write(path, serde_json::to_string(&self.triggers)?)?;
+        }
+        Ok(())
+    }
+
+    /// Reminds about `events` once each, when they come within their
+    /// `remind` span.
+    pub fn with_events(mut self, events: &[Event]) -> Alerts {
+        self.events = events.to_vec();
+        self
+    }
+
+    /// Reminds about each watched coin's token unlocks once, `remind`
+    /// seconds before them.
+    pub fn with_unlock_remind(mut self, remind: Option<i64>) -> Alerts {
+        self.unlock_remind = remind;
+        self
+    }
+
+    /// Whether token unlocks have to be fetched for their reminders.
+    pub fn wants_unlocks(&self) -> bool {
+        self.unlock_remind.is_some()
+    }
+
+    /// Gives portfolio rules the holdings to value.
+    pub fn with_holdings(mut self, holdings: &[Holding]) -> Alerts {
+        self.holdings = holdings.to_vec();
+        self
+    }
+
+    /// Raises `reminders` when they come due.
+    pub fn with_reminders(mut self, reminders: &[Reminder]) -> Alerts {
+        self.reminders = reminders.to_vec();
+        self
+    }
+
+    /// Rings the terminal `bell` on stderr and runs `sound` when alerts go
+    /// off, no more than once every `quiet_for`, so a price hovering
+    /// around a line doesn't beep on and on.
+    pub fn with_sound(mut self, bell: bool, sound: Option<String>, quiet_for: Duration) -> Alerts {
+        self.bell = bell;
+        self.sound = sound;
+        self.quiet_for = quiet_for;
+        self
+    }
+
+    /// The coins portfolio rules and reminders need prices for, watched
+    /// or not.
+    pub fn priced_coins(&self) -> Vec<&str> {
+        let valued = self
+            .rules
+            .iter()
+            .any(|(rule, _)| matches!(*rule, Rule::Portfolio { .. } | Rule::Allocation { .. }));
+        let mut coins: Vec<&str> = self
+            .holdings
+            .iter()
+            .filter(|_| valued)
+            .map(|holding| holding.coin.as_str())
+            .chain(
+                self.reminders
+                    .iter()
+                    .filter_map(|reminder| reminder.coin.as_deref()),
+            )
+            .collect();
+        coins.sort();
+        coins.dedup();
+        coins
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+            && self.events.is_empty()
+            && self.reminders.is_empty()
+            && self.unlock_remind.is_none()
+    }
+
+    /// The sizes of the top lists that have to be fetched for rules
+    /// watching for any coin entering them.
+    pub fn top_sizes(&self) -> Vec<u32> {
+        let mut sizes: Vec<u32> = self
+            .rules
+            .iter()
+            .filter_map(|(rule, _)| match *rule {
+                Rule::Rank {
+                    coin: None,
+                    enters_top: Some(n),
+                    ..
+                } => Some(n),
+                _ => None,
+            })
+            .collect();
+        sizes.sort();
+        sizes.dedup();
+        sizes
+    }
+
+    /// Checks one freshly fetched watched coin, returning the messages of
+    /// the rules it set off.
+    pub fn observe(&mut self, ticker: &Currency) -> Vec<String> {
+        self.observe_at(ticker, history::now())
+    }
+
+    fn observe_at(&mut self, ticker: &Currency, now: i64) -> Vec<String> {
+        let mut fired = Vec::new();
+        let mut changed = false;
+        let rank = ticker.rank.trim().parse::<u32>().ok();
+        if let Some(price) = ticker.price_usd {
+            self.prices.insert(ticker.id.clone(), price);
+        }
+        let previous = match rank {
+            Some(rank) => self.ranks.insert(ticker.id.clone(), rank),
+            None => None,
+        };
+        for (rule, condition) in &self.rules {
+            match *rule {
+                Rule::Price {
+                    ref coin,
+                    condition: ref text,
+                    cooldown,
+                    rearm,
+                } if *coin == ticker.id => {
+                    let (price, condition) = match (to_f64(ticker.price_usd), condition.as_ref()) {
+                        (Some(price), Some(condition)) => (price, condition),
+                        _ => continue,
+                    };
+                    let trigger = self.triggers.entry(trigger_key(coin, text)).or_default();
+                    let before = *trigger;
+                    if condition.holds(price) {
+                        // left unfired while cooling down, so it fires once
+                        // the cooldown is over if the condition still holds
+                        let cooling = cooldown
+                            .zip(trigger.fired)
+                            .is_some_and(|(secs, last)| now - last < secs as i64);
+                        if !trigger.held && !cooling {
+                            trigger.held = true;
+                            trigger.fired = Some(now);
+                            fired.push(format!(
+                                "{} {} (now {})",
+                                ticker.id,
+                                text,
+                                ticker.price_usd.map_or(String::new(), |p| p.to_string())
+                            ));
+                        }
+                    } else if trigger.held {
+                        trigger.held = !rearmed(condition, price, rearm.unwrap_or(0.0));
+                    }
+                    changed |= *trigger != before;
+                }
+                Rule::Rank {
+                    ref coin,
+                    enters_top,
+                    leaves_top,
+                } => {
+                    if coin.as_ref().is_some_and(|coin| *coin != ticker.id) {
+                        continue;
+                    }
+                    let (rank, previous) = match (rank, previous) {
+                        (Some(rank), Some(previous)) => (rank, previous),
+                        _ => continue,
+                    };
+                    if let Some(n) = leaves_top {
+                        if previous <= n && rank > n {
+                            fired.push(format!("{} left the top {} (now #{})", ticker.id, n, rank));
+                        }
+                    }
+                    // without a coin, entering is caught by observe_top
+                    if let (Some(n), Some(_)) = (enters_top, coin.as_ref()) {
+                        if previous > n && rank <= n {
+                            fired.push(format!(
+                                "{} entered the top {} (now #{})",
+                                ticker.id, n, rank
+                            ));
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        if changed {
+            if let Err(err) = self.save_triggers() {
+                debug!("could not keep the alert state: {}", err);
+            }
+        }
+        fired
+    }
+
+    /// Values the holdings at the last prices seen, once every held coin
+    /// has one, and checks the portfolio and allocation rules against
+    /// that.
+    pub fn observe_portfolio(&mut self) -> Vec<String> {
+        let mut value = Decimal::ZERO;
+        let mut cost = Decimal::ZERO;
+        for holding in &self.holdings {
+            match self.prices.get(&holding.coin) {
+                Some(price) => value += price * holding.amount,
+                None => return vec![],
+            }
+            cost += holding.cost.unwrap_or_default();
+        }
+        let loss = if cost > Decimal::ZERO {
+            ((cost - value) / cost * Decimal::ONE_HUNDRED).to_f64()
+        } else {
+            None
+        };
+        let mut fired = Vec::new();
+        for (index, (rule, _)) in self.rules.iter().enumerate() {
+            let (value_below, loss_beyond) = match *rule {
+                Rule::Portfolio {
+                    value_below,
+                    loss_beyond,
+                } => (value_below, loss_beyond),
+                Rule::Allocation {
+                    ref coin,
+                    above,
+                    below,
+                } => {
+                    let share = match self.share(coin, value) {
+                        Some(share) => share,
+                        None => continue,
+                    };
+                    let over = above.filter(|&above| share > above);
+                    let under = below.filter(|&below| share < below);
+                    let holds = over.is_some() || under.is_some();
+                    let held = self.holding.insert(index, holds).unwrap_or(false);
+                    if holds && !held {
+                        let (side, line) = match over {
+                            Some(above) => ("above", above),
+                            None => ("below", under.unwrap_or_default()),
+                        };
+                        fired.push(format!(
+                            "{} is {:.1}% of the portfolio, {} {}%",
+                            coin, share, side, line
+                        ));
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+            let low = value_below.is_some_and(|below| value < below);
+            let losing = match (loss_beyond, loss) {
+                (Some(beyond), Some(loss)) => loss > beyond,
+                _ => false,
+            };
+            let holds = low || losing;
+            let held = self.holding.insert(index, holds).unwrap_or(false);
+            if holds && !held {
+                fired.push(match loss {
+                    Some(loss) if losing => {
+                        format!(
+                            "portfolio down {:.1}% (now {:.2} USD)",
+                            loss,
+                            rounded(value, 2)
+                        )
+                    }
+                    _ => format!(
+                        "portfolio below {} USD (now {:.2})",
+                        value_below.unwrap_or_default(),
+                        rounded(value, 2)
+                    ),
+                });
+            }
+        }
+        fired
+    }
+
+    /// What percent of `value`, what the holdings are worth, is held in
+    /// `coin`.
+    fn share(&self, coin: &str, value: Decimal) -> Option<f64> {
+        if value <= Decimal::ZERO {
+            return None;
+        }
+        let amount: Decimal = self
+            .holdings
+            .iter()
+            .filter(|holding| holding.coin == coin)
+            .map(|holding| holding.amount)
+            .sum();
+        let held = self
+            .prices
+            .get(coin)
+            .map_or(Decimal::ZERO, |price| price * amount);
+        (held / value * Decimal::ONE_HUNDRED).to_f64()
+    }
+
+    /// Checks a freshly fetched top-`n` list for newcomers.
+    pub fn observe_top(&mut self, n: u32, top: &[Currency]) -> Vec<String> {
+        let ids: HashSet<String> = top.iter().map(|ticker| ticker.id.clone()).collect();
+        let previous = match self.tops.insert(n, ids) {
+            Some(previous) => previous,
+            None => return vec![],
+        };
+        top.iter()
+            .filter(|ticker| !previous.contains(&ticker.id))
+            .map(|ticker| {
+                format!(
+                    "{} entered the top {} (now #{})",
+                    ticker.id,
+                    n,
+                    ticker.rank.trim()
+                )
+            })
+            .collect()
+    }
+
+    /// Checks for events that have come within their reminder span since
+    /// the last round.
+    pub fn observe_events(&mut self, now: i64) -> Vec<String> {
+        let mut fired = Vec::new();
+        for (index, event) in self.events.iter().enumerate() {
+            let (at, remind) = match (event.timestamp(), event.remind()) {
+                (Ok(at), Ok(remind)) => (at, remind),
+                _ => continue,
+            };
+            if now < at && now >= at - remind && self.reminded.insert(index) {
+                fired.push(format!(
+                    "{} {} in {}",
+                    event.coin,
+                    event.name,
+                    format_span(at - now)
+                ));
+            }
+        }
+        fired
+    }
+
+    /// Checks for token unlocks of `coins` that have come within
+    /// `unlock_remind` since the last round.
+    pub fn observe_unlocks(
+        &mut self,
+        unlocks: &HashMap<String, Vec<Unlock>>,
+        coins: &[String],
+        now: i64,
+    ) -> Vec<String> {
+        let remind = match self.unlock_remind {
+            Some(remind) => remind,
+            None => return vec![],
+        };
+        let mut fired = Vec::new();
+        for coin in coins {
+            let (unlock, left) = match unlocks::next(unlocks, coin, now) {
+                Some(next) => next,
+                None => continue,
+            };
+            if left <= remind
+                && self
+                    .unlocks_reminded
+                    .insert((coin.clone(), unlock.timestamp))
+            {
+                let amount = unlock
+                    .amount
+                    .map_or_else(String::new, |amount| format!(" {} tokens", compact(amount)));
+                fired.push(format!(
+                    "{} unlocks{} in {}",
+                    coin,
+                    amount,
+                    format_span(left)
+                ));
+            }
+        }
+        fired
+    }
+
+    /// Checks for reminders that have come due since the last round,
+    /// `now` being the local time.
+    pub fn observe_reminders(&mut self, now: &Tm) -> Vec<String> {
+        let first = !self.reminders_checked;
+        self.reminders_checked = true;
+        let mut fired = Vec::new();
+        for (index, reminder) in self.reminders.iter().enumerate() {
+            let day = match reminder.due(now) {
+                Some(day) => day,
+                None => continue,
+            };
+            if self.reminded_on.insert(index, day) != Some(day) && !first {
+                let price = reminder
+                    .coin
+                    .as_ref()
+                    .and_then(|coin| self.prices.get(coin))
+                    .cloned();
+                fired.push(reminder.text(price));
+            }
+        }
+        fired
+    }
+
+    pub fn notify(&mut self, messages: &[String]) {
+        for message in messages {
+            deliver(message, self.command.as_deref());
+        }
+        if !messages.is_empty() && self.ring(Instant::now()) {
+            if self.bell {
+                let _ = write!(stderr(), "\x07");
+            }
+            if let Some(ref sound) = self.sound {
+                play(sound);
+            }
+        }
+    }
+
+    /// Whether alerts going off at `now` should ring, counting it as a
+    /// ring if so.
+    fn ring(&mut self, now: Instant) -> bool {
+        if !self.bell && self.sound.is_none() {
+            return false;
+        }
+        if self
+            .rung
+            .is_some_and(|rung| now.duration_since(rung) < self.quiet_for)
+        {
+            return false;
+        }
+        self.rung = Some(now);
+        true
+    }
+}
+
+/// Runs `alert_sound`, like `paplay /usr/share/sounds/bell.oga`, without
+/// waiting for it to finish.
+fn play(sound: &str) {
+    let mut words = sound.split_whitespace();
+    let program = match words.next() {
+        Some(program) => program,
+        None => return,
+    };
+    let spawned = Command::new(program)
+        .args(words)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    if let Err(err) = spawned {
+        warn!("could not run alert_sound {}: {}", sound, err);
+    }
+}
+
+/// Prints `message` to stderr and hands it to `alert_command`, if one is
+/// configured, as its only argument. Termux's `termux-notification` gets
+/// it as a notification's content instead.
+pub fn deliver(message: &str, command: Option<&str>) {
+    let _ = writeln!(stderr(), "alert: {}", message);
+    if let Some(command) = command {
+        let mut child = Command::new(command);
+        if termux::is_notifier(command) {
+            child.args(["--title", "cryptoticker", "--content"]);
+        }
+        let spawned = child
+            .arg(message)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .spawn();
+        if let Err(err) = spawned {
+            warn!("could not run alert_command {}: {}", command, err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    fn ticker(id: &str, rank: u32, price: &str) -> Currency {
+        serde_json::from_str(&format!(
+            r#"{{"id": "{0}", "name": "{0}", "symbol": "{0}", "rank": "{1}", "price_usd": "{2}"}}"#,
+            id, rank, price
+        ))
+        .unwrap()
+    }
+
+    fn rank(coin: Option<&str>, enters_top: Option<u32>, leaves_top: Option<u32>) -> Rule {
+        Rule::Rank {
+            coin: coin.map(|coin| coin.to_string()),
+            enters_top,
+            leaves_top,
+        }
+    }
+
+    #[test]
+    fn rank_changes_fire_once() {
+        let mut alerts = Alerts::new(&[rank(None, None, Some(100))], None).unwrap();
+        assert!(alerts.observe(&ticker("golem", 99, "1")).is_empty());
+        assert_eq!(
+            alerts.observe(&ticker("golem", 101, "1")),
+            vec!["golem left the top 100 (now #101)"]
+        );
+        assert!(alerts.observe(&ticker("golem", 102, "1")).is_empty());
+    }
+
+    #[test]
+    fn specific_coins_enter() {
+        let mut alerts = Alerts::new(&[rank(Some("golem"), Some(10), None)], None).unwrap();
+        alerts.observe(&ticker("golem", 11, "1"));
+        alerts.observe(&ticker("ethereum", 12, "1"));
+        assert!(alerts.observe(&ticker("ethereum", 9, "1")).is_empty());
+        assert_eq!(
+            alerts.observe(&ticker("golem", 10, "1")),
+            vec!["golem entered the top 10 (now #10)"]
+        );
+    }
+
+    #[test]
+    fn newcomers_to_the_top() {
+        let mut alerts = Alerts::new(&[rank(None, Some(2), None)], None).unwrap();
+        assert_eq!(alerts.top_sizes(), vec![2]);
+        let first = [ticker("bitcoin", 1, "1"), ticker("ethereum", 2, "1")];
+        assert!(alerts.observe_top(2, &first).is_empty());
+        let second = [ticker("bitcoin", 1, "1"), ticker("ripple", 2, "1")];
+        assert_eq!(
+            alerts.observe_top(2, &second),
+            vec!["ripple entered the top 2 (now #2)"]
+        );
+    }
+
+    #[test]
+    fn price_rules_fire_when_they_start_holding() {
+        let rule = Rule::Price {
+            coin: "bitcoin".to_string(),
+            condition: "price>100".to_string(),
+            cooldown: None,
+            rearm: None,
+        };
+        let mut alerts = Alerts::new(&[rule], None).unwrap();
+        assert_eq!(
+            alerts.observe(&ticker("bitcoin", 1, "150")),
+            vec!["bitcoin price>100 (now 150)"]
+        );
+        assert!(alerts.observe(&ticker("bitcoin", 1, "160")).is_empty());
+        assert!(alerts.observe(&ticker("bitcoin", 1, "90")).is_empty());
+        assert_eq!(alerts.observe(&ticker("bitcoin", 1, "101")).len(), 1);
+    }
+
+    #[test]
+    fn price_rules_cool_down_and_rearm() {
+        let rule = Rule::Price {
+            coin: "bitcoin".to_string(),
+            condition: "price>100".to_string(),
+            cooldown: Some(3600),
+            rearm: Some(5.0),
+        };
+        let mut alerts = Alerts::new(&[rule], None).unwrap();
+        assert_eq!(alerts.observe_at(&ticker("bitcoin", 1, "101"), 0).len(), 1);
+        // not back under 95 yet, so crossing 100 again is the same crossing
+        assert!(alerts
+            .observe_at(&ticker("bitcoin", 1, "97"), 60)
+            .is_empty());
+        assert!(alerts
+            .observe_at(&ticker("bitcoin", 1, "102"), 120)
+            .is_empty());
+        // rearmed, but within the hour
+        assert!(alerts
+            .observe_at(&ticker("bitcoin", 1, "94"), 180)
+            .is_empty());
+        assert!(alerts
+            .observe_at(&ticker("bitcoin", 1, "103"), 240)
+            .is_empty());
+        assert_eq!(
+            alerts.observe_at(&ticker("bitcoin", 1, "104"), 3600),
+            vec!["bitcoin price>100 (now 104)"]
+        );
+
+        let below: Condition = "price<100".parse().unwrap();
+        assert!(!rearmed(&below, 104.0, 5.0));
+        assert!(rearmed(&below, 105.0, 5.0));
+    }
+
+    #[test]
+    fn price_rules_remember_across_restarts() {
+        let path = ::std::env::temp_dir().join(format!(
+            "cryptoticker-test-alerts-{}.json",
+            ::std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        let rules = [Rule::Price {
+            coin: "bitcoin".to_string(),
+            condition: "price>100".to_string(),
+            cooldown: None,
+            rearm: None,
+        }];
+        let mut alerts = Alerts::new(&rules, None)
+            .unwrap()
+            .remembering_in(path.clone());
+        assert_eq!(alerts.observe_at(&ticker("bitcoin", 1, "150"), 0).len(), 1);
+
+        // still above 100 after the restart, which was already said
+        let mut alerts = Alerts::new(&rules, None)
+            .unwrap()
+            .remembering_in(path.clone());
+        assert!(alerts
+            .observe_at(&ticker("bitcoin", 1, "150"), 60)
+            .is_empty());
+        assert!(alerts
+            .observe_at(&ticker("bitcoin", 1, "90"), 120)
+            .is_empty());
+        let mut alerts = Alerts::new(&rules, None)
+            .unwrap()
+            .remembering_in(path.clone());
+        assert_eq!(
+            alerts.observe_at(&ticker("bitcoin", 1, "150"), 180).len(),
+            1
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn unlocks_remind_once() {
+        let unlock = Unlock {
+            timestamp: 10 * 86_400,
+            amount: Some(92_650_000.0),
+        };
+        let unlocks = vec![("arbitrum".to_string(), vec![unlock])]
+            .into_iter()
+            .collect();
+        let coins = ["arbitrum".to_string()];
+        let mut alerts = Alerts::new(&[], None).unwrap();
+        assert!(alerts.is_empty());
+        assert!(alerts
+            .observe_unlocks(&unlocks, &coins, 9 * 86_400)
+            .is_empty());
+        let mut alerts = alerts.with_unlock_remind(Some(3 * 86_400));
+        assert!(!alerts.is_empty() && alerts.wants_unlocks());
+        assert!(alerts
+            .observe_unlocks(&unlocks, &coins, 6 * 86_400)
+            .is_empty());
+        assert_eq!(
+            alerts.observe_unlocks(&unlocks, &coins, 8 * 86_400),
+            vec!["arbitrum unlocks 92.65M tokens in 2d 0h"]
+        );
+        assert!(alerts
+            .observe_unlocks(&unlocks, &coins, 9 * 86_400)
+            .is_empty());
+        // only watched coins are reminded about
+        assert!(alerts.observe_unlocks(&unlocks, &[], 8 * 86_400).is_empty());
+    }
+
+    #[test]
+    fn events_remind_once() {
+        let halving = Event {
+            coin: "bitcoin".to_string(),
+            name: "halving".to_string(),
+            date: "1970-01-08".to_string(),
+            remind: Some("2d".to_string()),
+        };
+        let mut alerts = Alerts::new(&[], None).unwrap().with_events(&[halving]);
+        assert!(!alerts.is_empty());
+        assert!(alerts.observe_events(86_400).is_empty());
+        assert_eq!(
+            alerts.observe_events(5 * 86_400 + 3600),
+            vec!["bitcoin halving in 1d 23h"]
+        );
+        assert!(alerts.observe_events(6 * 86_400).is_empty());
+    }
+
+    #[test]
+    fn portfolio_rules_value_the_holdings() {
+        let rules = [
+            Rule::Portfolio {
+                value_below: Some(Decimal::new(1000, 0)),
+                loss_beyond: None,
+            },
+            Rule::Portfolio {
+                value_below: None,
+                loss_beyond: Some(20.0),
+            },
+        ];
+        let holdings = [
+            Holding {
+                coin: "bitcoin".to_string(),
+                amount: Decimal::new(5, 1),
+                cost: Some(Decimal::new(1000, 0)),
+            },
+            Holding {
+                coin: "ethereum".to_string(),
+                amount: Decimal::new(2, 0),
+                cost: Some(Decimal::new(500, 0)),
+            },
+        ];
+        let mut alerts = Alerts::new(&rules, None).unwrap().with_holdings(&holdings);
+        assert_eq!(alerts.priced_coins(), vec!["bitcoin", "ethereum"]);
+        alerts.observe(&ticker("bitcoin", 1, "2000"));
+        // nothing to go on until every held coin has a price
+        assert!(alerts.observe_portfolio().is_empty());
+        alerts.observe(&ticker("ethereum", 2, "250"));
+        assert!(alerts.observe_portfolio().is_empty());
+
+        // 0.5 * 1500 + 2 * 100 = 950, down 36.7% on 1500
+        alerts.observe(&ticker("bitcoin", 1, "1500"));
+        alerts.observe(&ticker("ethereum", 2, "100"));
+        assert_eq!(
+            alerts.observe_portfolio(),
+            vec![
+                "portfolio below 1000 USD (now 950.00)",
+                "portfolio down 36.7% (now 950.00 USD)"
+            ]
+        );
+        assert!(alerts.observe_portfolio().is_empty());
+    }
+
+    #[test]
+    fn allocation_rules_watch_the_mix() {
+        let rules = [
+            Rule::Allocation {
+                coin: "bitcoin".to_string(),
+                above: Some(60.0),
+                below: None,
+            },
+            Rule::Allocation {
+                coin: "ethereum".to_string(),
+                above: None,
+                below: Some(30.0),
+            },
+        ];
+        let holdings = [
+            Holding {
+                coin: "bitcoin".to_string(),
+                amount: Decimal::ONE,
+                cost: None,
+            },
+            Holding {
+                coin: "ethereum".to_string(),
+                amount: Decimal::new(10, 0),
+                cost: None,
+            },
+        ];
+        let mut alerts = Alerts::new(&rules, None).unwrap().with_holdings(&holdings);
+        assert_eq!(alerts.priced_coins(), vec!["bitcoin", "ethereum"]);
+        // 50/50
+        alerts.observe(&ticker("bitcoin", 1, "1000"));
+        alerts.observe(&ticker("ethereum", 2, "100"));
+        assert!(alerts.observe_portfolio().is_empty());
+        // 1600 of 2000 in bitcoin, 400 in ethereum
+        alerts.observe(&ticker("bitcoin", 1, "1600"));
+        alerts.observe(&ticker("ethereum", 2, "40"));
+        assert_eq!(
+            alerts.observe_portfolio(),
+            vec![
+                "bitcoin is 80.0% of the portfolio, above 60%",
+                "ethereum is 20.0% of the portfolio, below 30%"
+            ]
+        );
+        assert!(alerts.observe_portfolio().is_empty());
+        let rule = Rule::Allocation {
+            coin: "bitcoin".to_string(),
+            above: None,
+            below: None,
+        };
+        assert!(Alerts::new(&[rule], None).is_err());
+    }
+
+    #[test]
+    fn reminders_fire_once_a_day() {
+        let dca = Reminder {
+            message: "DCA day".to_string(),
+            every: "friday".to_string(),
+            at: Some("09:00".to_string()),
+            coin: Some("bitcoin".to_string()),
+            budget: Some(Decimal::new(100, 0)),
+        };
+        let mut alerts = Alerts::new(&[], None).unwrap().with_reminders(&[dca]);
+        assert!(!alerts.is_empty());
+        assert_eq!(alerts.priced_coins(), vec!["bitcoin"]);
+        alerts.observe(&ticker("bitcoin", 1, "50000"));
+        // 1970-01-02 was a Friday
+        let at = |hour: i64| ::time::at_utc(::time::Timespec::new(86_400 + hour * 3600, 0));
+        assert!(alerts.observe_reminders(&at(8)).is_empty());
+        assert_eq!(
+            alerts.observe_reminders(&at(9)),
+            vec!["DCA day: bitcoin at 50000.00, 100 USD buys 0.002000"]
+        );
+        assert!(alerts.observe_reminders(&at(10)).is_empty());
+        assert!(alerts.observe_reminders(&at(7 * 24 + 8)).is_empty());
+        assert_eq!(alerts.observe_reminders(&at(7 * 24 + 9)).len(), 1);
+    }
+
+    #[test]
+    fn rejects_bad_rules() {
+        assert!(Alerts::new(&[rank(None, None, None)], None).is_err());
+        let rule = Rule::Price {
+            coin: "bitcoin".to_string(),
+            condition: "cheap".to_string(),
+            cooldown: None,
+            rearm: None,
+        };
+        assert!(Alerts::new(&[rule], None).is_err());
+        let rule = Rule::Price {
+            coin: "bitcoin".to_string(),
+            condition: "price>100".to_string(),
+            cooldown: None,
+            rearm: Some(-1.0),
+        };
+        assert!(Alerts::new(&[rule], None).is_err());
+        let rule = Rule::Portfolio {
+            value_below: None,
+            loss_beyond: None,
+        };
+        assert!(Alerts::new(&[rule], None).is_err());
+    }
+
+    #[test]
+    fn rings_once_in_a_while() {
+        let start = Instant::now();
+        let mut alerts = Alerts::new(&[], None).unwrap();
+        assert!(!alerts.ring(start));
+        let mut alerts = alerts.with_sound(true, None, Duration::from_secs(60));
+        assert!(alerts.ring(start));
+        assert!(!alerts.ring(start + Duration::from_secs(30)));
+        assert!(alerts.ring(start + Duration::from_secs(60)));
+    }
+}
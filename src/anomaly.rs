@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+
+use ticker::Currency;
+
+/// The largest move between two rounds taken at face value, as a
+/// fraction of the last good price.
+const MAX_JUMP: f64 = 0.5;
+
+/// How many suspect rounds in a row it takes to believe a move after
+/// all, so a real crash isn't held back forever.
+const CONFIRMATIONS: u32 = 3;
+
+/// What happens to a quote that looks wrong.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Policy {
+    /// keep showing the last good quote, marked with `?`
+    Hold,
+    /// show the quote anyway, marked with `?`
+    Mark,
+    /// take every quote at face value
+    Off,
+}
+
+/// The names `--anomalies` accepts.
+pub const POLICIES: &[&str] = &["hold", "mark", "off"];
+
+impl FromStr for Policy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Policy, String> {
+        match s.trim().to_lowercase().as_str() {
+            "hold" => Ok(Policy::Hold),
+            "mark" => Ok(Policy::Mark),
+            "off" => Ok(Policy::Off),
+            _ => Err(format!("{:?} should be one of {}", s, POLICIES.join(", "))),
+        }
+    }
+}
+
+/// Why `current` looks like bad data rather than the market, if it
+/// does. A missing price isn't suspect, only a wrong one.
+pub fn suspect(last: Option<&Currency>, current: &Currency) -> Option<String> {
+    let price = current.price_usd?;
+    if price <= Decimal::ZERO {
+        return Some(format!("{} was quoted at ${}", current.id, price));
+    }
+    let last = last
+        .and_then(|last| last.price_usd)
+        .and_then(|last| last.to_f64())?;
+    let price = price.to_f64()?;
+    let jump = (price - last) / last;
+    if last > 0.0 && jump.abs() > MAX_JUMP {
+        return Some(format!(
+            "{} moved {:+.0}% in one round, from ${} to ${}",
+            current.id,
+            jump * 100.0,
+            last,
+            price
+        ));
+    }
+    None
+}
+
+/// A quote after screening.
+#[derive(Debug, Clone)]
+pub enum Screened {
+    Good(Currency),
+    /// what to show in place of a suspect quote, and why it's suspect
+    Suspect(Currency, String),
+}
+
+/// Screens each coin's quotes against the last good one, so bogus API
+/// data stays out of alerts and history. In one-shot mode there is no
+/// last quote, and only zero or negative prices are caught.
+pub struct Guard {
+    policy: Policy,
+    last: HashMap<String, Currency>,
+    /// suspect rounds in a row, per coin
+    doubts: HashMap<String, u32>,
+}
+
+impl Guard {
+    pub fn new(policy: Policy) -> Guard {
+        Guard {
+            policy,
+            last: HashMap::new(),
+            doubts: HashMap::new(),
+        }
+    }
+
+    pub fn screen(&mut self, ticker: Currency) -> Screened {
+        if self.policy == Policy::Off {
+            return Screened::Good(ticker);
+        }
+        let reason = match suspect(self.last.get(&ticker.id), &ticker) {
+            Some(reason) => reason,
+            None => {
+                self.doubts.remove(&ticker.id);
+                self.last.insert(ticker.id.clone(), ticker.clone());
+                return Screened::Good(ticker);
+            }
+        };
+        let positive = ticker.price_usd.is_some_and(|price| price > Decimal::ZERO);
+        let doubts = self.doubts.entry(ticker.id.clone()).or_insert(0);
+        *doubts += 1;
+        // a move that sticks is the market, but nothing makes a
+        // non-positive price real
+        if positive && *doubts >= CONFIRMATIONS {
+            debug!("{}, taking it after {} rounds", reason, doubts);
+            self.doubts.remove(&ticker.id);
+            self.last.insert(ticker.id.clone(), ticker.clone());
+            return Screened::Good(ticker);
+        }
+        match (self.policy, self.last.get(&ticker.id)) {
+            (Policy::Hold, Some(last)) => Screened::Suspect(last.clone(), reason),
+            _ => Screened::Suspect(ticker, reason),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    fn quote(price: &str) -> Currency {
+        serde_json::from_str(&format!(
+            r#"{{"id": "bitcoin", "name": "Bitcoin", "symbol": "BTC", "rank": "1",
+                "price_usd": "{}"}}"#,
+            price
+        ))
+        .unwrap()
+    }
+
+    /// The price shown, and whether it was suspect.
+    fn shown(screened: Screened) -> (String, bool) {
+        match screened {
+            Screened::Good(ticker) => (ticker.price_usd.unwrap().to_string(), false),
+            Screened::Suspect(ticker, _) => (ticker.price_usd.unwrap().to_string(), true),
+        }
+    }
+
+    #[test]
+    fn flags_bogus_prices() {
+        assert!(suspect(None, &quote("0")).is_some());
+        assert!(suspect(None, &quote("-3")).is_some());
+        assert_eq!(suspect(None, &quote("2545.19")), None);
+        assert_eq!(suspect(Some(&quote("2545.19")), &quote("?")), None);
+        assert_eq!(suspect(Some(&quote("100")), &quote("149")), None);
+        assert_eq!(suspect(Some(&quote("100")), &quote("51")), None);
+        assert_eq!(
+            suspect(Some(&quote("100")), &quote("160")),
+            Some("bitcoin moved +60% in one round, from $100 to $160".to_string())
+        );
+        assert!(suspect(Some(&quote("100")), &quote("0.5")).is_some());
+    }
+
+    #[test]
+    fn holds_the_last_good_quote() {
+        let mut guard = Guard::new(Policy::Hold);
+        assert_eq!(
+            shown(guard.screen(quote("100"))),
+            ("100".to_string(), false)
+        );
+        assert_eq!(
+            shown(guard.screen(quote("1000"))),
+            ("100".to_string(), true)
+        );
+        assert_eq!(
+            shown(guard.screen(quote("110"))),
+            ("110".to_string(), false)
+        );
+        // nothing to hold yet
+        let mut guard = Guard::new(Policy::Hold);
+        assert_eq!(shown(guard.screen(quote("0"))), ("0".to_string(), true));
+    }
+
+    #[test]
+    fn believes_a_move_that_sticks() {
+        let mut guard = Guard::new(Policy::Mark);
+        guard.screen(quote("100"));
+        assert_eq!(shown(guard.screen(quote("30"))), ("30".to_string(), true));
+        assert_eq!(shown(guard.screen(quote("31"))), ("31".to_string(), true));
+        assert_eq!(shown(guard.screen(quote("30"))), ("30".to_string(), false));
+        assert_eq!(shown(guard.screen(quote("29"))), ("29".to_string(), false));
+        for _ in 0..5 {
+            assert_eq!(shown(guard.screen(quote("0"))), ("0".to_string(), true));
+        }
+    }
+
+    #[test]
+    fn off_takes_everything() {
+        let mut guard = Guard::new(Policy::Off);
+        assert_eq!(shown(guard.screen(quote("0"))), ("0".to_string(), false));
+        assert!("sometimes".parse::<Policy>().is_err());
+        assert_eq!("Hold".parse(), Ok(Policy::Hold));
+    }
+}
@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+
+use clap::ArgMatches;
+use serde_json::{self, Value};
+
+use errors::Error;
+use history::{timestamp, History, Sample};
+use net::Net;
+use symbols::Symbols;
+
+/// How far back CoinGecko's free market charts go.
+pub const MAX_DAYS: u32 = 365;
+
+/// Reads a market chart's prices and volumes into samples of `coin`. A
+/// day of it comes in 5-minute steps, up to 90 days hourly, and more
+/// than that daily.
+fn samples(coin: &str, content: &str) -> Result<Vec<Sample>, Error> {
+    let chart: Value = serde_json::from_str(content)?;
+    let points = |name: &str| -> Vec<(i64, f64)> {
+        chart[name]
+            .as_array()
+            .map(|points| {
+                points
+                    .iter()
+                    .filter_map(|point| {
+                        let millis = point[0].as_f64()?;
+                        let value = point[1].as_f64().filter(|value| value.is_finite())?;
+                        Some(((millis / 1000.0) as i64, value))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let volumes: HashMap<i64, f64> = points("total_volumes").into_iter().collect();
+    let prices = points("prices");
+    if prices.is_empty() {
+        return Err(Error::Parse(format!(
+            "CoinGecko sent no prices for {}",
+            coin
+        )));
+    }
+    Ok(prices
+        .into_iter()
+        .map(|(timestamp, price)| Sample {
+            coin: coin.to_string(),
+            price_usd: Some(price),
+            volume_usd_24h: volumes.get(&timestamp).cloned(),
+            available_supply: None,
+            timestamp,
+        })
+        .collect())
+}
+
+fn fetch(coin: &str, days: u32, net: &Net) -> Result<Vec<Sample>, Error> {
+    let url = format!(
+        "https://api.coingecko.com/api/v3/coins/{}/market_chart?vs_currency=usd&days={}",
+        coin, days
+    );
+    debug!("retrieving {} days of prices for {}", days, coin);
+    let resp = net.get(&url)?;
+    if !resp.status.is_success() {
+        return Err(Error::InvalidTicker {
+            name: coin.to_string(),
+        });
+    }
+    samples(coin, &resp.text())
+}
+
+/// The `samples` from before `oldest`, the first one recorded, so
+/// backfilling only fills in before the history starts and can be run
+/// again without doubling anything up.
+fn before(samples: Vec<Sample>, oldest: Option<i64>) -> Vec<Sample> {
+    match oldest {
+        Some(oldest) => samples
+            .into_iter()
+            .filter(|sample| sample.timestamp < oldest)
+            .collect(),
+        None => samples,
+    }
+}
+
+/// Runs `backfill`, seeding the history with CoinGecko's prices for the
+/// last `--days`, so indicators and charts have something to go on
+/// before interval mode has recorded any.
+pub fn backfill(
+    matches: &ArgMatches,
+    symbols: &Symbols,
+    overrides: &HashMap<String, String>,
+    net: &Net,
+) -> Result<(), Error> {
+    let coin = symbols.resolve(matches.value_of("TICKER").unwrap(), overrides);
+    let days = value_t!(matches, "days", u32).unwrap_or_else(|e| e.exit());
+    if days == 0 || days > MAX_DAYS {
+        return Err(Error::Other(format!(
+            "--days should be from 1 to {}",
+            MAX_DAYS
+        )));
+    }
+    let history = History::open()?;
+    let oldest = history.oldest(coin)?;
+    let samples = before(fetch(coin, days, net)?, oldest);
+    match (samples.first(), samples.last()) {
+        (Some(first), Some(last)) => {
+            history.import(&samples)?;
+            println!(
+                "added {} samples of {}, {} to {}",
+                samples.len(),
+                coin,
+                timestamp(first.timestamp),
+                timestamp(last.timestamp)
+            );
+        }
+        _ => println!("the history of {} already goes back {} days", coin, days),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_market_charts() {
+        let content = r#"{
+            "prices": [[1718000000000, 67000.5], [1718003600000, 67100.25], [1718007200000, null]],
+            "market_caps": [[1718000000000, 1.3e12]],
+            "total_volumes": [[1718000000000, 2.5e10], [1718003600000, 2.6e10]]
+        }"#;
+        let samples = samples("bitcoin", content).unwrap();
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[0].timestamp, 1_718_000_000);
+        assert_eq!(samples[1].price_usd, Some(67100.25));
+        assert_eq!(samples[1].volume_usd_24h, Some(2.6e10));
+        assert!(super::samples("bitcoin", r#"{"error": "coin not found"}"#).is_err());
+
+        assert_eq!(before(samples.clone(), None).len(), 2);
+        let kept = before(samples, Some(1_718_003_600));
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].timestamp, 1_718_000_000);
+    }
+}
@@ -0,0 +1,821 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use clap::ArgMatches;
+use fs2::FileExt;
+use serde_json;
+
+use dirs;
+use errors::Error;
+use history::{format_span, parse_span};
+use ticker::Currency;
+
+/// How long a cached ticker stays fresh unless `--cache-ttl` or the
+/// config file say otherwise.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(1800);
+
+/// A backend and how long its entries stay fresh. A `ttl` of `None`
+/// never goes cold.
+pub struct Cache {
+    pub backend: Box<dyn CacheBackend + Send + Sync>,
+    pub ttl: Option<Duration>,
+    /// serve whatever is cached, however cold, and never fetch
+    pub offline: bool,
+}
+
+/// Parses a TTL in seconds, where `0` always refreshes and `infinite`
+/// always trusts the cache.
+pub fn parse_ttl(ttl: &str) -> Result<Option<Duration>, String> {
+    match ttl.trim() {
+        "infinite" => Ok(None),
+        secs => secs
+            .parse()
+            .map(|secs| Some(Duration::from_secs(secs)))
+            .map_err(|_| format!("{:?} should be a number of seconds or \"infinite\"", ttl)),
+    }
+}
+
+/// The HTTP validators a ticker was served with. They are sent back when
+/// it is refreshed, so an unchanged ticker costs a 304 rather than a
+/// download.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+}
+
+/// Somewhere to keep recent quotes between invocations.
+pub trait CacheBackend {
+    /// Returns the cached ticker for `name` and how long ago it was stored.
+    fn get(&self, name: &str) -> Option<(Currency, Duration)>;
+    fn put(&self, name: &str, ticker: &Currency, validators: &Validators) -> Result<(), Error>;
+    /// The validators `name` was last stored with, if any.
+    fn validators(&self, name: &str) -> Validators;
+    /// The names of every cached coin, sorted.
+    fn names(&self) -> Result<Vec<String>, Error>;
+    fn remove(&self, name: &str) -> Result<(), Error>;
+
+    /// Held while `name` is refreshed, so concurrent invocations wait for
+    /// one fetch instead of all making their own. Backends that can't
+    /// coordinate return `None`.
+    fn lock(&self, _name: &str) -> Option<RefreshLock> {
+        None
+    }
+}
+
+/// How long to wait for another process's refresh before fetching anyway,
+/// in case it hung.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// An advisory lock on a coin, released when dropped.
+pub struct RefreshLock {
+    _file: fs::File,
+}
+
+/// Picks a backend from a `--cache-backend` value: `file`, `memory` or a
+/// `redis://[:password@]host[:port][/db]` URL.
+pub fn backend(spec: &str) -> Result<Box<dyn CacheBackend + Send + Sync>, String> {
+    match spec {
+        "file" => Ok(Box::new(FileCache::new().map_err(|err| err.to_string())?)),
+        "memory" => Ok(Box::new(MemoryCache::default())),
+        _ if spec.starts_with("redis://") => Ok(Box::new(RedisCache::parse(spec)?)),
+        _ => Err(format!(
+            "{:?} is not \"file\", \"memory\" or a redis:// URL",
+            spec
+        )),
+    }
+}
+
+/// Runs the `cache` subcommand: `list` every entry with its age, `show`
+/// one, or `clear` some or all of them.
+pub fn manage(matches: &ArgMatches, cache: &Cache) -> Result<(), Error> {
+    match matches.subcommand() {
+        ("show", Some(matches)) => {
+            let name = matches.value_of("TICKER").unwrap();
+            let (ticker, age) = cache
+                .backend
+                .get(name)
+                .ok_or_else(|| format!("{} is not cached", name))?;
+            println!("{}", serde_json::to_string_pretty(&ticker)?);
+            println!(
+                "stored {} ago{}",
+                format_span(age.as_secs() as i64),
+                cold(cache, age)
+            );
+        }
+        ("clear", Some(matches)) => {
+            let older_than = match matches.value_of("older-than") {
+                Some(span) => Some(Duration::from_secs(parse_span(span)? as u64)),
+                None => None,
+            };
+            let names = match matches.values_of("TICKER") {
+                Some(names) => names.map(|name| name.to_string()).collect(),
+                None => cache.backend.names()?,
+            };
+            for name in names {
+                if let Some(older_than) = older_than {
+                    // entries that can't be read are as good as gone anyway
+                    match cache.backend.get(&name) {
+                        Some((_, age)) if age < older_than => continue,
+                        _ => {}
+                    }
+                }
+                cache.backend.remove(&name)?;
+                println!("removed {}", name);
+            }
+        }
+        _ => {
+            for name in cache.backend.names()? {
+                match cache.backend.get(&name) {
+                    Some((ticker, age)) => println!(
+                        "{}\t{}\t{} old{}",
+                        name,
+                        ticker
+                            .price_usd
+                            .map_or("null".to_string(), |p| p.to_string()),
+                        format_span(age.as_secs() as i64),
+                        cold(cache, age)
+                    ),
+                    None => println!("{}\tunreadable", name),
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn cold(cache: &Cache, age: Duration) -> &'static str {
+    match cache.ttl {
+        Some(ttl) if age >= ttl => " (cold)",
+        _ => "",
+    }
+}
+
+/// One JSON file per coin in the user cache directory, aged by mtime.
+pub struct FileCache {
+    dir: PathBuf,
+}
+
+impl FileCache {
+    pub fn new() -> Result<FileCache, Error> {
+        Ok(FileCache {
+            dir: dirs::cache_dir()?,
+        })
+    }
+}
+
+impl CacheBackend for FileCache {
+    fn get(&self, name: &str) -> Option<(Currency, Duration)> {
+        let path = self.dir.join(format!("{}{}", name, ".json"));
+        let age = fs::metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())?;
+        read(&path).map(|ticker| (ticker, age))
+    }
+
+    fn put(&self, name: &str, ticker: &Currency, validators: &Validators) -> Result<(), Error> {
+        write(
+            &self.dir.join(format!("{}{}", name, ".json")),
+            ticker,
+            validators,
+        )
+    }
+
+    fn validators(&self, name: &str) -> Validators {
+        fs::read_to_string(self.dir.join(format!("{}{}", name, ".json")))
+            .ok()
+            .and_then(|content| serde_json::from_str::<Entry>(&content).ok())
+            .map(|entry| entry.validators)
+            .unwrap_or_default()
+    }
+
+    fn names(&self) -> Result<Vec<String>, Error> {
+        let mut names = Vec::new();
+        let error = |err| file_error(&self.dir, err);
+        for entry in fs::read_dir(&self.dir).map_err(error)? {
+            let path = entry.map_err(error)?.path();
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            // the Fear & Greed Index, symbols, listings and the like are
+            // cached next to the tickers
+            match file_name.strip_suffix(".json") {
+                Some(name) if is_ticker(&path) => names.push(name.to_string()),
+                _ => {}
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    fn remove(&self, name: &str) -> Result<(), Error> {
+        let path = self.dir.join(format!("{}{}", name, ".json"));
+        fs::remove_file(&path).map_err(|err| file_error(&path, err))?;
+        let _ = fs::remove_file(self.dir.join(format!("{}{}", name, ".lock")));
+        Ok(())
+    }
+
+    fn lock(&self, name: &str) -> Option<RefreshLock> {
+        let path = self.dir.join(format!("{}{}", name, ".lock"));
+        let file = match fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+        {
+            Ok(file) => file,
+            Err(err) => {
+                warn!("{} could not be opened: {}", path.display(), err);
+                return None;
+            }
+        };
+        let deadline = Instant::now() + LOCK_TIMEOUT;
+        while file.try_lock_exclusive().is_err() {
+            if Instant::now() >= deadline {
+                warn!("gave up waiting on {}", path.display());
+                return None;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        Some(RefreshLock { _file: file })
+    }
+}
+
+/// Only lives as long as the process, for long-running modes and tests.
+#[derive(Default)]
+pub struct MemoryCache {
+    entries: Mutex<HashMap<String, (Currency, Validators, Instant)>>,
+}
+
+impl CacheBackend for MemoryCache {
+    fn get(&self, name: &str) -> Option<(Currency, Duration)> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .get(name)
+            .map(|(ticker, _, stored)| (ticker.clone(), stored.elapsed()))
+    }
+
+    fn put(&self, name: &str, ticker: &Currency, validators: &Validators) -> Result<(), Error> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.insert(
+            name.to_string(),
+            (ticker.clone(), validators.clone(), Instant::now()),
+        );
+        Ok(())
+    }
+
+    fn validators(&self, name: &str) -> Validators {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .get(name)
+            .map(|(_, validators, _)| validators.clone())
+            .unwrap_or_default()
+    }
+
+    fn names(&self) -> Result<Vec<String>, Error> {
+        let mut names: Vec<String> = self.entries.lock().unwrap().keys().cloned().collect();
+        names.sort();
+        Ok(names)
+    }
+
+    fn remove(&self, name: &str) -> Result<(), Error> {
+        self.entries.lock().unwrap().remove(name);
+        Ok(())
+    }
+}
+
+/// A Redis server shared by several machines, so only one of them has
+/// to hit the API per TTL.
+#[derive(Debug, PartialEq)]
+pub struct RedisCache {
+    address: String,
+    password: Option<String>,
+    db: Option<u32>,
+}
+
+/// Stored in Redis, which has no mtime to age entries by.
+#[derive(Serialize, Deserialize)]
+struct StampedEntry {
+    version: u32,
+    stored: u64,
+    ticker: Currency,
+    #[serde(default, skip_serializing_if = "Validators::is_empty")]
+    validators: Validators,
+}
+
+const REDIS_TIMEOUT: Duration = Duration::from_secs(2);
+/// Redis drops entries on its own after this long, well past any TTL.
+const REDIS_EXPIRY: &str = "86400";
+
+impl RedisCache {
+    fn parse(url: &str) -> Result<RedisCache, String> {
+        let rest = &url["redis://".len()..];
+        let (password, rest) = match rest.rfind('@') {
+            Some(at) => {
+                let credentials = &rest[..at];
+                let password = match credentials.find(':') {
+                    Some(colon) => &credentials[colon + 1..],
+                    None => credentials,
+                };
+                (Some(password.to_string()), &rest[at + 1..])
+            }
+            None => (None, rest),
+        };
+        let (host, db) = match rest.find('/') {
+            Some(slash) if slash + 1 < rest.len() => {
+                let db = rest[slash + 1..]
+                    .parse()
+                    .map_err(|_| format!("{:?} has an invalid database number", url))?;
+                (&rest[..slash], Some(db))
+            }
+            Some(slash) => (&rest[..slash], None),
+            None => (rest, None),
+        };
+        if host.is_empty() {
+            return Err(format!("{:?} has no host", url));
+        }
+        let address = if host.contains(':') {
+            host.to_string()
+        } else {
+            format!("{}:6379", host)
+        };
+        Ok(RedisCache {
+            address,
+            password,
+            db,
+        })
+    }
+
+    /// Connects, authenticates and selects the database, ready for a
+    /// command.
+    fn connect(&self) -> Result<(TcpStream, BufReader<TcpStream>), Error> {
+        let address = self
+            .address
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| format!("{} did not resolve", self.address))?;
+        let stream = TcpStream::connect_timeout(&address, REDIS_TIMEOUT)
+            .map_err(|err| Error::Cache(format!("redis: {}", err)))?;
+        stream.set_read_timeout(Some(REDIS_TIMEOUT))?;
+        stream.set_write_timeout(Some(REDIS_TIMEOUT))?;
+        let mut writer = stream.try_clone()?;
+        let mut reader = BufReader::new(stream);
+
+        if let Some(ref password) = self.password {
+            send(&mut writer, &["AUTH", password])?;
+            reply(&mut reader)?;
+        }
+        if let Some(db) = self.db {
+            send(&mut writer, &["SELECT", &db.to_string()])?;
+            reply(&mut reader)?;
+        }
+        Ok((writer, reader))
+    }
+
+    fn command(&self, args: &[&str]) -> Result<Option<String>, Error> {
+        let (mut writer, mut reader) = self.connect()?;
+        send(&mut writer, args)?;
+        reply(&mut reader)
+    }
+}
+
+impl CacheBackend for RedisCache {
+    fn get(&self, name: &str) -> Option<(Currency, Duration)> {
+        let value = match self.command(&["GET", &format!("cryptoticker:{}", name)]) {
+            Ok(value) => value?,
+            Err(err) => {
+                debug!("{}", err);
+                return None;
+            }
+        };
+        match serde_json::from_str::<StampedEntry>(&value) {
+            Ok(ref entry) if entry.version == CACHE_VERSION => {
+                let age = now().saturating_sub(entry.stored);
+                Some((entry.ticker.clone(), Duration::from_secs(age)))
+            }
+            _ => {
+                debug!(
+                    "redis entry for {} is not cache version {}",
+                    name, CACHE_VERSION
+                );
+                None
+            }
+        }
+    }
+
+    fn put(&self, name: &str, ticker: &Currency, validators: &Validators) -> Result<(), Error> {
+        let value = serde_json::to_string(&StampedEntry {
+            version: CACHE_VERSION,
+            stored: now(),
+            ticker: ticker.clone(),
+            validators: validators.clone(),
+        })?;
+        self.command(&[
+            "SET",
+            &format!("cryptoticker:{}", name),
+            &value,
+            "EX",
+            REDIS_EXPIRY,
+        ])?;
+        Ok(())
+    }
+
+    fn validators(&self, name: &str) -> Validators {
+        self.command(&["GET", &format!("cryptoticker:{}", name)])
+            .ok()
+            .and_then(|value| value)
+            .and_then(|value| serde_json::from_str::<StampedEntry>(&value).ok())
+            .map(|entry| entry.validators)
+            .unwrap_or_default()
+    }
+
+    fn names(&self) -> Result<Vec<String>, Error> {
+        let (mut writer, mut reader) = self.connect()?;
+        send(&mut writer, &["KEYS", "cryptoticker:*"])?;
+        let mut names: Vec<String> = array(&mut reader)?
+            .iter()
+            .map(|key| key["cryptoticker:".len()..].to_string())
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    fn remove(&self, name: &str) -> Result<(), Error> {
+        self.command(&["DEL", &format!("cryptoticker:{}", name)])?;
+        Ok(())
+    }
+}
+
+fn send<W: Write>(writer: &mut W, args: &[&str]) -> Result<(), Error> {
+    let mut request = format!("*{}\r\n", args.len());
+    for arg in args {
+        request.push_str(&format!("${}\r\n{}\r\n", arg.len(), arg));
+    }
+    writer.write_all(request.as_bytes())?;
+    Ok(())
+}
+
+/// Reads one RESP reply; a nil bulk string comes back as `None`.
+fn reply<R: BufRead>(reader: &mut R) -> Result<Option<String>, Error> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let line = line.trim_end_matches("\r\n");
+    let (kind, rest) = line.split_at(line.len().min(1));
+    match kind {
+        "+" | ":" => Ok(Some(rest.to_string())),
+        "-" => Err(Error::Cache(format!("redis: {}", rest))),
+        "$" => {
+            let len: i64 = rest
+                .parse()
+                .map_err(|_| format!("redis: bad bulk length {:?}", rest))?;
+            if len < 0 {
+                return Ok(None);
+            }
+            let mut data = vec![0; len as usize + 2];
+            reader.read_exact(&mut data)?;
+            data.truncate(len as usize);
+            String::from_utf8(data)
+                .map(Some)
+                .map_err(|_| Error::Cache("redis: reply is not UTF-8".to_string()))
+        }
+        _ => Err(Error::Cache(format!("redis: unexpected reply {:?}", line))),
+    }
+}
+
+/// Reads a RESP array of bulk strings, skipping nils.
+fn array<R: BufRead>(reader: &mut R) -> Result<Vec<String>, Error> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let line = line.trim_end_matches("\r\n");
+    if let Some(err) = line.strip_prefix('-') {
+        return Err(Error::Cache(format!("redis: {}", err)));
+    }
+    let len: i64 = line
+        .strip_prefix('*')
+        .and_then(|len| len.parse().ok())
+        .ok_or_else(|| format!("redis: expected an array, got {:?}", line))?;
+    let mut items = Vec::new();
+    for _ in 0..len.max(0) {
+        if let Some(item) = reply(reader)? {
+            items.push(item);
+        }
+    }
+    Ok(items)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Bumped whenever the layout of cache files changes. Files from older
+/// versions are migrated on read; files that can't be understood are
+/// treated as a cache miss and refetched.
+pub const CACHE_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    version: u32,
+    ticker: Currency,
+    #[serde(default, skip_serializing_if = "Validators::is_empty")]
+    validators: Validators,
+}
+
+/// Reads a cached ticker, upgrading files written by older versions in
+/// place.
+fn read(path: &Path) -> Option<Currency> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) => {
+            debug!("{} could not be read, refetching: {}", path.display(), err);
+            return None;
+        }
+    };
+
+    if let Ok(entry) = serde_json::from_str::<Entry>(&content) {
+        if entry.version == CACHE_VERSION {
+            return Some(entry.ticker);
+        }
+        debug!(
+            "{} has unknown cache version {}, refetching",
+            path.display(),
+            entry.version
+        );
+        return None;
+    }
+
+    // version 0 stored the bare ticker
+    match serde_json::from_str::<Currency>(&content) {
+        Ok(ticker) => {
+            debug!(
+                "{} migrated to cache version {}",
+                path.display(),
+                CACHE_VERSION
+            );
+            if let Err(err) = migrate(path, &ticker) {
+                warn!("{} could not be migrated: {}", path.display(), err);
+            }
+            Some(ticker)
+        }
+        Err(err) => {
+            warn!("{} is corrupt, refetching: {}", path.display(), err);
+            None
+        }
+    }
+}
+
+/// Whether `path` holds a cached ticker, of any version, rather than
+/// something else kept in the cache directory.
+fn is_ticker(path: &Path) -> bool {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(_) => return false,
+    };
+    serde_json::from_str::<Entry>(&content).is_ok()
+        || serde_json::from_str::<Currency>(&content).is_ok()
+}
+
+/// A cache file that couldn't be read or written, said as such rather
+/// than as any other I/O error.
+fn file_error<E: Display>(path: &Path, err: E) -> Error {
+    Error::Cache(format!("{}: {}", path.display(), err))
+}
+
+/// Writes through a temporary file and renames it into place, so readers
+/// never see a half-written entry.
+fn write(path: &Path, ticker: &Currency, validators: &Validators) -> Result<(), Error> {
+    let temp = path.with_extension(format!("json.{}.tmp", process::id()));
+    let written = fs::File::create(&temp)
+        .map_err(|err| file_error(&temp, err))
+        .and_then(|file| {
+            serde_json::to_writer(
+                file,
+                &Entry {
+                    version: CACHE_VERSION,
+                    ticker: ticker.clone(),
+                    validators: validators.clone(),
+                },
+            )
+            .map_err(|err| file_error(&temp, err))
+        })
+        .and_then(|()| fs::rename(&temp, path).map_err(|err| file_error(path, err)));
+    if written.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    written
+}
+
+/// Rewrites a legacy file without touching its age, so migrating doesn't
+/// extend how long the entry is trusted.
+fn migrate(path: &Path, ticker: &Currency) -> Result<(), Error> {
+    let error = |err| file_error(path, err);
+    let modified = fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .map_err(error)?;
+    write(path, ticker, &Validators::default())?;
+    fs::OpenOptions::new()
+        .write(true)
+        .open(path)
+        .and_then(|file| file.set_modified(modified))
+        .map_err(error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::io::Cursor;
+
+    fn temp_file(name: &str, content: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!(
+            "cryptoticker-test-{}-{}",
+            name,
+            ::std::process::id()
+        ));
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    const LEGACY: &str = r#"{"id": "bitcoin", "name": "Bitcoin", "symbol": "BTC", "rank": "1",
+        "price_usd": "2545.19"}"#;
+
+    #[test]
+    fn migrates_legacy_files_keeping_their_age() {
+        let path = temp_file("legacy", LEGACY);
+        let modified = SystemTime::now() - Duration::from_secs(600);
+        fs::OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+
+        let ticker = read(&path).unwrap();
+        assert_eq!(ticker.price_usd.unwrap().to_string(), "2545.19");
+
+        let entry: Entry = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(entry.version, CACHE_VERSION);
+        assert_eq!(fs::metadata(&path).unwrap().modified().unwrap(), modified);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn unreadable_files_are_misses() {
+        let corrupt = temp_file("corrupt", "{\"id\": ");
+        assert!(read(&corrupt).is_none());
+        fs::remove_file(corrupt).unwrap();
+
+        let future = temp_file(
+            "future",
+            &format!(r#"{{"version": 99, "ticker": {}}}"#, LEGACY),
+        );
+        assert!(read(&future).is_none());
+        fs::remove_file(future).unwrap();
+    }
+
+    #[test]
+    fn writes_replace_entries_atomically() {
+        let path = temp_file("atomic", "{ not json");
+        let ticker: Currency = serde_json::from_str(LEGACY).unwrap();
+        write(&path, &ticker, &Validators::default()).unwrap();
+        assert_eq!(read(&path).unwrap().id, "bitcoin");
+        assert!(!path
+            .with_extension(format!("json.{}.tmp", process::id()))
+            .exists());
+        fs::remove_file(&path).unwrap();
+
+        let nowhere = path.join("bitcoin.json");
+        match write(&nowhere, &ticker, &Validators::default()) {
+            Err(Error::Cache(message)) => assert!(message.contains("bitcoin.json")),
+            other => panic!("expected a cache error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn locks_are_exclusive() {
+        let dir = env::temp_dir().join(format!("cryptoticker-test-locks-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let cache = FileCache { dir: dir.clone() };
+        let held = cache.lock("bitcoin").unwrap();
+        let file = fs::File::open(dir.join("bitcoin.lock")).unwrap();
+        assert!(file.try_lock_exclusive().is_err());
+        drop(held);
+        assert!(file.try_lock_exclusive().is_ok());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn parses_ttls() {
+        assert_eq!(parse_ttl("0"), Ok(Some(Duration::from_secs(0))));
+        assert_eq!(parse_ttl("600"), Ok(Some(Duration::from_secs(600))));
+        assert_eq!(parse_ttl("infinite"), Ok(None));
+        assert!(parse_ttl("-1").is_err());
+        assert!(parse_ttl("1h").is_err());
+    }
+
+    #[test]
+    fn parses_redis_urls() {
+        assert_eq!(
+            RedisCache::parse("redis://cache.lan"),
+            Ok(RedisCache {
+                address: "cache.lan:6379".to_string(),
+                password: None,
+                db: None,
+            })
+        );
+        assert_eq!(
+            RedisCache::parse("redis://:hunter2@10.0.0.2:6380/3"),
+            Ok(RedisCache {
+                address: "10.0.0.2:6380".to_string(),
+                password: Some("hunter2".to_string()),
+                db: Some(3),
+            })
+        );
+        assert!(RedisCache::parse("redis://").is_err());
+        assert!(RedisCache::parse("redis://host/x").is_err());
+    }
+
+    #[test]
+    fn speaks_resp() {
+        let mut request = Vec::new();
+        send(&mut request, &["GET", "cryptoticker:bitcoin"]).unwrap();
+        assert_eq!(
+            request,
+            b"*2\r\n$3\r\nGET\r\n$20\r\ncryptoticker:bitcoin\r\n".to_vec()
+        );
+
+        let mut replies = Cursor::new(&b"+OK\r\n$-1\r\n$5\r\nhello\r\n-ERR nope\r\n"[..]);
+        assert_eq!(reply(&mut replies).unwrap(), Some("OK".to_string()));
+        assert_eq!(reply(&mut replies).unwrap(), None);
+        assert_eq!(reply(&mut replies).unwrap(), Some("hello".to_string()));
+        assert!(reply(&mut replies).is_err());
+
+        let mut keys = Cursor::new(&b"*2\r\n$5\r\nfirst\r\n$-1\r\n*0\r\n-ERR nope\r\n"[..]);
+        assert_eq!(array(&mut keys).unwrap(), vec!["first".to_string()]);
+        assert!(array(&mut keys).unwrap().is_empty());
+        assert!(array(&mut keys).is_err());
+    }
+
+    #[test]
+    fn lists_only_tickers() {
+        let dir = env::temp_dir().join(format!("cryptoticker-test-names-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let cache = FileCache { dir: dir.clone() };
+        let ticker: Currency = serde_json::from_str(LEGACY).unwrap();
+        let validators = Validators {
+            etag: Some("\"abc\"".to_string()),
+            last_modified: None,
+        };
+        cache
+            .put("ethereum", &ticker, &Validators::default())
+            .unwrap();
+        cache.put("bitcoin", &ticker, &validators).unwrap();
+        assert_eq!(cache.validators("bitcoin"), validators);
+        assert!(cache.validators("ethereum").is_empty());
+        drop(cache.lock("bitcoin"));
+        fs::write(dir.join("listings-all-all.json"), "[]").unwrap();
+        // what else is cached next to the tickers
+        fs::write(dir.join("fng.json"), r#"{"fetched": 0, "value": 25}"#).unwrap();
+        fs::write(dir.join("symbols.json"), r#"{"btc": "bitcoin"}"#).unwrap();
+        assert_eq!(cache.names().unwrap(), vec!["bitcoin", "ethereum"]);
+
+        cache.remove("bitcoin").unwrap();
+        assert_eq!(cache.names().unwrap(), vec!["ethereum"]);
+        assert!(!dir.join("bitcoin.lock").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn memory_cache_ages_entries() {
+        let cache = MemoryCache::default();
+        assert!(cache.get("bitcoin").is_none());
+        cache
+            .put(
+                "bitcoin",
+                &serde_json::from_str(LEGACY).unwrap(),
+                &Validators::default(),
+            )
+            .unwrap();
+        let (ticker, age) = cache.get("bitcoin").unwrap();
+        assert_eq!(ticker.id, "bitcoin");
+        assert!(age < Duration::from_secs(5));
+        assert_eq!(cache.names().unwrap(), vec!["bitcoin"]);
+        cache.remove("bitcoin").unwrap();
+        assert!(cache.get("bitcoin").is_none());
+    }
+}
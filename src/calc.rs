@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use clap::ArgMatches;
+use rust_decimal::Decimal;
+
+use cache::Cache;
+use errors::Error;
+use fx::Rates;
+use history;
+use net::Net;
+use symbols::Symbols;
+use template::rounded;
+use ticker::usd_price;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(Decimal),
+    Name(String),
+    Op(char),
+    Open,
+    Close,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Number(number) => write!(f, "{}", number),
+            Token::Name(name) => write!(f, "{}", name),
+            Token::Op(op) => write!(f, "{}", op),
+            Token::Open => write!(f, "("),
+            Token::Close => write!(f, ")"),
+        }
+    }
+}
+
+fn tokens(expr: &str) -> Result<Vec<Token>, Error> {
+    let mut tokens = Vec::new();
+    let mut chars = expr.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut number = String::new();
+            while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit() || **c == '.') {
+                number.push(c);
+                chars.next();
+            }
+            let number = number
+                .parse()
+                .map_err(|_| Error::Parse(format!("{:?} isn't a number", number)))?;
+            tokens.push(Token::Number(number));
+        } else if c.is_alphabetic() || c == '_' {
+            let mut name = String::new();
+            while let Some(&c) = chars.peek().filter(|c| c.is_alphanumeric() || **c == '_') {
+                name.push(c);
+                chars.next();
+            }
+            tokens.push(Token::Name(name));
+        } else {
+            tokens.push(match c {
+                '+' | '-' | '*' | '/' => Token::Op(c),
+                '(' => Token::Open,
+                ')' => Token::Close,
+                _ => return Err(Error::Parse(format!("{:?} can't be in a calculation", c))),
+            });
+            chars.next();
+        }
+    }
+    Ok(tokens)
+}
+
+fn overflow() -> Error {
+    Error::Other("The calculation is too large to work out.".to_string())
+}
+
+/// Works out `tokens` from left to right by precedence: a sum of terms,
+/// each a product of factors.
+struct Parser<'a, F> {
+    tokens: &'a [Token],
+    at: usize,
+    price: F,
+}
+
+impl<'a, F: FnMut(&str) -> Result<Decimal, Error>> Parser<'a, F> {
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.at);
+        self.at += 1;
+        token
+    }
+
+    fn op(&mut self, ops: &[char]) -> Option<char> {
+        match self.tokens.get(self.at) {
+            Some(&Token::Op(op)) if ops.contains(&op) => {
+                self.at += 1;
+                Some(op)
+            }
+            _ => None,
+        }
+    }
+
+    fn sum(&mut self) -> Result<Decimal, Error> {
+        let mut value = self.product()?;
+        while let Some(op) = self.op(&['+', '-']) {
+            let term = self.product()?;
+            value = match op {
+                '+' => value.checked_add(term),
+                _ => value.checked_sub(term),
+            }
+            .ok_or_else(overflow)?;
+        }
+        Ok(value)
+    }
+
+    fn product(&mut self) -> Result<Decimal, Error> {
+        let mut value = self.factor()?;
+        while let Some(op) = self.op(&['*', '/']) {
+            let factor = self.factor()?;
+            value = match op {
+                '*' => value.checked_mul(factor).ok_or_else(overflow)?,
+                _ if factor.is_zero() => {
+                    return Err(Error::Other("The calculation divides by zero.".to_string()))
+                }
+                _ => value.checked_div(factor).ok_or_else(overflow)?,
+            };
+        }
+        Ok(value)
+    }
+
+    fn factor(&mut self) -> Result<Decimal, Error> {
+        match self.next() {
+            Some(&Token::Number(number)) => Ok(number),
+            Some(Token::Name(name)) => (self.price)(name),
+            Some(&Token::Op('-')) => Ok(-self.factor()?),
+            Some(&Token::Op('+')) => self.factor(),
+            Some(Token::Open) => {
+                let value = self.sum()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(value),
+                    _ => Err(Error::Parse(
+                        "A \"(\" in the calculation isn't closed.".to_string(),
+                    )),
+                }
+            }
+            Some(token) => Err(Error::Parse(format!(
+                "\"{}\" is out of place in the calculation",
+                token
+            ))),
+            None => Err(Error::Parse("The calculation ends too soon.".to_string())),
+        }
+    }
+}
+
+/// Works out `expr`, with `+`, `-`, `*`, `/` and parentheses over numbers
+/// and names, asking `price` what each name is worth.
+pub fn evaluate<F>(expr: &str, price: F) -> Result<Decimal, Error>
+where
+    F: FnMut(&str) -> Result<Decimal, Error>,
+{
+    let tokens = tokens(expr)?;
+    let mut parser = Parser {
+        tokens: &tokens,
+        at: 0,
+        price,
+    };
+    let value = parser.sum()?;
+    match tokens.get(parser.at) {
+        Some(token) => Err(Error::Parse(format!(
+            "\"{}\" is out of place in the calculation",
+            token
+        ))),
+        None => Ok(value),
+    }
+}
+
+/// Runs `calc EXPR`, like `0.5*btc + 2*eth - 1500`, where each coin is its
+/// live price and plain numbers are amounts of `--currency`, in which the
+/// result is printed.
+pub fn calc(
+    matches: &ArgMatches,
+    cache: Option<&Cache>,
+    symbols: &Symbols,
+    overrides: &HashMap<String, String>,
+    offline: bool,
+    net: &Net,
+) -> Result<(), Error> {
+    let expr = matches.value_of("EXPR").unwrap();
+    let currency = matches.value_of("currency").unwrap_or("USD").to_uppercase();
+    let rate = if currency == "USD" {
+        Decimal::ONE
+    } else {
+        Rates::load(offline, net)?.usd_to(&currency, history::now())?
+    };
+    // a coin named twice is only fetched once
+    let mut prices: HashMap<String, Decimal> = HashMap::new();
+    let value = evaluate(expr, |name| {
+        let coin = symbols.resolve(name, overrides);
+        if let Some(&price) = prices.get(coin) {
+            return Ok(price);
+        }
+        let price = if coin.eq_ignore_ascii_case("usd") {
+            rate
+        } else {
+            usd_price(coin, cache, net)? * rate
+        };
+        prices.insert(coin.to_string(), price);
+        Ok(price)
+    })?;
+    println!("{:.2} {}", rounded(value, 2), currency);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prices(name: &str) -> Result<Decimal, Error> {
+        match name {
+            "btc" => Ok(Decimal::from(60000)),
+            "eth" => Ok(Decimal::from(3000)),
+            _ => Err(Error::InvalidTicker {
+                name: name.to_string(),
+            }),
+        }
+    }
+
+    #[test]
+    fn evaluates_with_prices() {
+        let value = |expr| evaluate(expr, prices).unwrap();
+        assert_eq!(value("0.5*btc + 2*eth - 1500"), Decimal::from(34500));
+        assert_eq!(value("1 - 2 - 3"), Decimal::from(-4));
+        assert_eq!(value("2 * (btc - eth) / 1000"), Decimal::from(114));
+        assert_eq!(value("-eth + -(1)"), Decimal::from(-3001));
+        assert_eq!(value("12 / 4 / 3"), Decimal::ONE);
+        assert!(evaluate("doge * 2", prices).is_err());
+        assert!(evaluate("1 / (eth - 3000)", prices).is_err());
+        for broken in &["", "1 +", "(1 + 2", "1 2", "btc)", "1 % 2", "1..2"] {
+            assert!(evaluate(broken, prices).is_err(), "{:?}", broken);
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use std::thread::sleep;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use errors::Error;
+
+/// Failure injection for exercising status bar integrations against a
+/// misbehaving API. Both knobs default to off.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Chaos {
+    /// percentage of fetches (0-100) that fail before hitting the network
+    pub failure_rate: u32,
+    /// extra latency added to every fetch
+    pub latency: Duration,
+}
+
+impl Chaos {
+    pub fn inject(&self, name: &str) -> Result<(), Error> {
+        if self.latency > Duration::from_secs(0) {
+            debug!("chaos: delaying {} by {:?}", name, self.latency);
+            sleep(self.latency);
+        }
+        if self.failure_rate > 0 && roll() < self.failure_rate {
+            return Err(Error::Network(format!(
+                "chaos: injected failure for {}",
+                name
+            )));
+        }
+        Ok(())
+    }
+}
+
+// a number in 0..100; the clock is plenty random for this
+pub fn roll() -> u32 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    let mut x = nanos ^ 0x9e37_79b9;
+    x ^= x << 13;
+    x ^= x >> 17;
+    x ^= x << 5;
+    x % 100
+}
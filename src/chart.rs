@@ -0,0 +1,165 @@
+use clap::ArgMatches;
+use crossterm::style::Stylize;
+use crossterm::terminal;
+use serde_json;
+use time;
+
+use errors::Error;
+use net::Net;
+
+/// One OHLC candle as CoinGecko returns it: a millisecond timestamp,
+/// then open, high, low and close in USD.
+pub type Candle = (f64, f64, f64, f64, f64);
+
+/// Maps a `--range` value to CoinGecko's `days` parameter, which only
+/// accepts a few fixed spans for OHLC data.
+fn days(range: &str) -> Option<&'static str> {
+    match range {
+        "1d" => Some("1"),
+        "7d" => Some("7"),
+        "14d" => Some("14"),
+        "30d" => Some("30"),
+        "90d" => Some("90"),
+        "180d" => Some("180"),
+        "1y" | "365d" => Some("365"),
+        "max" => Some("max"),
+        _ => None,
+    }
+}
+
+pub const RANGES: &[&str] = &["1d", "7d", "14d", "30d", "90d", "180d", "1y", "max"];
+
+fn fetch_candles(name: &str, range: &str, net: &Net) -> Result<Vec<Candle>, Error> {
+    let url = format!(
+        "https://api.coingecko.com/api/v3/coins/{}/ohlc?vs_currency=usd&days={}",
+        name,
+        days(range).unwrap()
+    );
+    debug!("retrieving {} of OHLC data for {}", range, name);
+    let resp = net.get(url.as_str())?;
+    if !resp.status.is_success() {
+        return Err(Error::InvalidTicker {
+            name: name.to_string(),
+        });
+    }
+    let candles: Vec<Candle> = serde_json::from_str(&resp.text())?;
+    Ok(candles
+        .into_iter()
+        .filter(|&(_, open, high, low, close)| {
+            [open, high, low, close].iter().all(|v| v.is_finite())
+        })
+        .collect())
+}
+
+/// Prints a candlestick chart sized to the terminal.
+pub fn chart(matches: &ArgMatches, net: &Net) -> Result<(), Error> {
+    let name = matches.value_of("TICKER").unwrap();
+    let range = matches.value_of("range").unwrap();
+    let candles = fetch_candles(name, range, net)?;
+    if candles.is_empty() {
+        return Err(Error::InvalidTicker {
+            name: name.to_string(),
+        });
+    }
+
+    let (width, height) = terminal::size().unwrap_or((80, 24));
+    let height = if matches.is_present("height") {
+        value_t!(matches, "height", usize).unwrap_or_else(|e| e.exit())
+    } else {
+        (height as usize).saturating_sub(4).max(5)
+    };
+    let color = !matches.is_present("no-color");
+
+    println!("{} {} (USD)", name, range);
+    for line in render(&candles, width as usize, height, color) {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+const AXIS_WIDTH: usize = 12;
+
+/// Draws `candles` into `height` rows of at most `width` columns, newest
+/// candle on the right. Older candles are dropped if they don't fit.
+pub fn render(candles: &[Candle], width: usize, height: usize, color: bool) -> Vec<String> {
+    let columns = width.saturating_sub(AXIS_WIDTH).max(1);
+    let candles = &candles[candles.len().saturating_sub(columns)..];
+    let high = candles.iter().map(|c| c.2).fold(f64::MIN, f64::max);
+    let low = candles.iter().map(|c| c.3).fold(f64::MAX, f64::min);
+    let height = height.max(2);
+
+    let row = |price: f64| -> usize {
+        if high == low {
+            return height / 2;
+        }
+        (((high - price) / (high - low)) * (height - 1) as f64).round() as usize
+    };
+
+    let mut lines = Vec::with_capacity(height + 1);
+    for r in 0..height {
+        let mut line = if r == 0 || r == height - 1 || r % 4 == 0 {
+            let price = high - (high - low) * r as f64 / (height - 1) as f64;
+            format!("{:>10.2} ┤", price)
+        } else {
+            format!("{:>10} │", "")
+        };
+        for &(_, open, candle_high, candle_low, close) in candles {
+            let body = row(open.max(close))..row(open.min(close)) + 1;
+            let wick = row(candle_high)..row(candle_low) + 1;
+            let glyph = if body.contains(&r) {
+                "█"
+            } else if wick.contains(&r) {
+                "│"
+            } else {
+                " "
+            };
+            if color && glyph != " " {
+                let styled = if close >= open {
+                    glyph.green()
+                } else {
+                    glyph.red()
+                };
+                line.push_str(&styled.to_string());
+            } else {
+                line.push_str(glyph);
+            }
+        }
+        lines.push(line.trim_end().to_string());
+    }
+
+    let first = date(candles[0].0);
+    let last = date(candles[candles.len() - 1].0);
+    let gap = candles
+        .len()
+        .saturating_sub(first.len() + last.len())
+        .max(1);
+    lines.push(format!("{:>12}{}{}{}", "", first, " ".repeat(gap), last));
+    lines
+}
+
+fn date(millis: f64) -> String {
+    let tm = time::at_utc(time::Timespec::new((millis / 1000.0) as i64, 0));
+    time::strftime("%Y-%m-%d", &tm).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_candles() {
+        let day = 86_400_000.0;
+        let candles = vec![
+            (1_497_484_800_000.0, 10.0, 14.0, 9.0, 13.0),
+            (1_497_484_800_000.0 + day, 13.0, 13.5, 8.0, 9.0),
+            (1_497_484_800_000.0 + 2.0 * day, 9.0, 12.0, 9.0, 11.0),
+        ];
+        insta::assert_snapshot!(render(&candles, 40, 7, false).join("\n"));
+    }
+
+    #[test]
+    fn flat_prices_do_not_divide_by_zero() {
+        let candles = vec![(0.0, 1.0, 1.0, 1.0, 1.0)];
+        assert_eq!(render(&candles, 20, 3, false).len(), 4);
+    }
+}
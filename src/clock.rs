@@ -0,0 +1,114 @@
+use std::str::FromStr;
+
+use time;
+
+/// The time zone `{last_updated}` is shown in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Zone {
+    Local,
+    Utc,
+    /// seconds east of UTC, from an offset like `+05:30`
+    Offset(i32),
+}
+
+impl FromStr for Zone {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Zone, String> {
+        let invalid = || format!("{:?} should be local, utc or an offset like +02:00", s);
+        let zone = s.trim().to_lowercase();
+        match zone.as_str() {
+            "local" => return Ok(Zone::Local),
+            "utc" | "z" => return Ok(Zone::Utc),
+            _ => {}
+        }
+        let sign = match zone.chars().next() {
+            Some('+') => 1,
+            Some('-') => -1,
+            _ => return Err(invalid()),
+        };
+        let mut parts = zone[1..].splitn(2, ':');
+        let hours: i32 = parts.next().unwrap_or("").parse().map_err(|_| invalid())?;
+        let minutes: i32 = parts.next().unwrap_or("0").parse().map_err(|_| invalid())?;
+        if hours > 14 || minutes > 59 {
+            return Err(invalid());
+        }
+        Ok(Zone::Offset(sign * (hours * 3600 + minutes * 60)))
+    }
+}
+
+/// How timestamps from the API, like `{last_updated}`, are shown.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Clock {
+    zone: Zone,
+    /// a strftime format, like `%H:%M`
+    format: String,
+}
+
+/// What `time_format` is when the config doesn't set it.
+pub const DEFAULT_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+impl Default for Clock {
+    fn default() -> Clock {
+        Clock {
+            zone: Zone::Local,
+            format: DEFAULT_FORMAT.to_string(),
+        }
+    }
+}
+
+impl Clock {
+    /// The config's `timezone` and `time_format`, local time and
+    /// `DEFAULT_FORMAT` when they aren't set.
+    pub fn new(zone: Option<&str>, format: Option<&str>) -> Result<Clock, String> {
+        let zone = match zone {
+            Some(zone) => zone.parse().map_err(|err| format!("timezone: {}", err))?,
+            None => Zone::Local,
+        };
+        let format = format.unwrap_or(DEFAULT_FORMAT);
+        time::strftime(format, &time::now_utc()).map_err(|err| {
+            format!(
+                "time_format: {:?} is not a strftime format: {}",
+                format, err
+            )
+        })?;
+        Ok(Clock {
+            zone,
+            format: format.to_string(),
+        })
+    }
+
+    /// The unix time `secs`, in this clock's zone and format.
+    pub fn show(&self, secs: i64) -> String {
+        let at = time::Timespec::new(secs, 0);
+        let tm = match self.zone {
+            Zone::Local => time::at(at),
+            Zone::Utc => time::at_utc(at),
+            Zone::Offset(offset) => {
+                let mut tm = time::at_utc(at + time::Duration::seconds(offset as i64));
+                // so %z says where the clock is
+                tm.tm_utcoff = offset;
+                tm
+            }
+        };
+        time::strftime(&self.format, &tm).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shows_times_in_the_zone() {
+        let clock = Clock::new(Some("utc"), None).unwrap();
+        assert_eq!(clock.show(1_700_000_000), "2023-11-14 22:13:20");
+        let clock = Clock::new(Some("+05:30"), Some("%H:%M %z")).unwrap();
+        assert_eq!(clock.show(1_700_000_000), "03:43 +0530");
+        let clock = Clock::new(Some("-8"), Some("%d %H:%M")).unwrap();
+        assert_eq!(clock.show(1_700_000_000), "14 14:13");
+        assert!("Europe/Berlin".parse::<Zone>().is_err());
+        assert!("+25:00".parse::<Zone>().is_err());
+        assert!(Clock::new(None, Some("%Q")).is_err());
+    }
+}
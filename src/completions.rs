@@ -0,0 +1,108 @@
+use clap::{App, Shell};
+
+/// The shells `cryptoticker completions` writes scripts for.
+pub const SHELLS: &[&str] = &["bash", "zsh", "fish"];
+
+/// Completes coin IDs for bash on top of clap's completions, asking
+/// `complete-coins` whether the word is a coin at all.
+const BASH: &str = r#"_cryptoticker_coins() {
+    _cryptoticker "$@"
+    local cur="${COMP_WORDS[COMP_CWORD]}"
+    if [[ ${cur} != -* ]]; then
+        COMPREPLY+=($(compgen -W "$(cryptoticker complete-coins -- "${COMP_WORDS[@]:0:COMP_CWORD}" 2>/dev/null)" -- "${cur}"))
+    fi
+}
+
+complete -F _cryptoticker_coins -o bashdefault -o default cryptoticker
+"#;
+
+/// zsh already knows which positional the word is, so this only lists
+/// the IDs.
+const ZSH: &str = r#"_cryptoticker_coins() {
+    local -a coins
+    coins=(${(f)"$(cryptoticker complete-coins 2>/dev/null)"})
+    compadd -a coins
+}
+"#;
+
+const FISH: &str =
+    "complete -c cryptoticker -a \"(cryptoticker complete-coins -- (commandline -opc))\"\n";
+
+/// `app`'s completion script for `shell`, with the coin IDs from the
+/// cached coin list completed wherever a TICKER goes.
+pub fn script(mut app: App, shell: Shell) -> String {
+    let mut out = vec![];
+    app.gen_completions_to("cryptoticker", shell, &mut out);
+    let script = String::from_utf8_lossy(&out).into_owned();
+    match shell {
+        Shell::Bash => {
+            let clap = script
+                .find("complete -F _cryptoticker ")
+                .unwrap_or(script.len());
+            format!("{}{}", &script[..clap], BASH)
+        }
+        Shell::Zsh => {
+            let script = tickers_complete_coins(&script);
+            match script.find("autoload -U is-at-least\n") {
+                Some(at) => {
+                    let (head, tail) = script.split_at(at + "autoload -U is-at-least\n".len());
+                    format!("{}\n{}{}", head, ZSH, tail)
+                }
+                None => format!("{}\n{}", ZSH, script),
+            }
+        }
+        _ => format!("{}{}", script, FISH),
+    }
+}
+
+/// Swaps the file completion clap gives TICKER positionals in zsh for
+/// `_cryptoticker_coins`.
+fn tickers_complete_coins(script: &str) -> String {
+    script
+        .lines()
+        .map(|line| {
+            if line.contains(":TICKER -- ") && line.ends_with(":_files' \\") {
+                let end = line.len() - ":_files' \\".len();
+                format!("{}:_cryptoticker_coins' \\\n", &line[..end])
+            } else {
+                format!("{}\n", line)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{Arg, SubCommand};
+
+    fn app() -> App<'static, 'static> {
+        App::new("cryptoticker")
+            .arg(Arg::with_name("TICKER").multiple(true).help("The currency"))
+            .subcommand(
+                SubCommand::with_name("wait").arg(Arg::with_name("TICKER").help("The currency")),
+            )
+    }
+
+    #[test]
+    fn bash_wraps_clap() {
+        let script = script(app(), Shell::Bash);
+        assert!(script.contains("_cryptoticker() {"));
+        assert!(script.ends_with(BASH));
+        assert!(!script.contains("complete -F _cryptoticker -o"));
+    }
+
+    #[test]
+    fn zsh_completes_tickers() {
+        let script = script(app(), Shell::Zsh);
+        assert!(script.starts_with("#compdef cryptoticker\n"));
+        assert!(script.contains(ZSH));
+        assert_eq!(script.matches("currency:_cryptoticker_coins'").count(), 2);
+        assert!(!script.contains("currency:_files"));
+    }
+
+    #[test]
+    fn fish_adds_a_rule() {
+        assert!(script(app(), Shell::Fish).ends_with(FISH));
+    }
+}
@@ -0,0 +1,116 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// A comparison between a live price and a fixed threshold.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Above,
+    AtLeast,
+    Below,
+    AtMost,
+    Equal,
+}
+
+impl Comparison {
+    pub fn parse(op: &str) -> Option<Comparison> {
+        match op {
+            ">" => Some(Comparison::Above),
+            ">=" => Some(Comparison::AtLeast),
+            "<" => Some(Comparison::Below),
+            "<=" => Some(Comparison::AtMost),
+            "=" | "==" => Some(Comparison::Equal),
+            _ => None,
+        }
+    }
+
+    pub fn holds(&self, value: f64, threshold: f64) -> bool {
+        match *self {
+            Comparison::Above => value > threshold,
+            Comparison::AtLeast => value >= threshold,
+            Comparison::Below => value < threshold,
+            Comparison::AtMost => value <= threshold,
+            Comparison::Equal => value == threshold,
+        }
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let op = match *self {
+            Comparison::Above => ">",
+            Comparison::AtLeast => ">=",
+            Comparison::Below => "<",
+            Comparison::AtMost => "<=",
+            Comparison::Equal => "==",
+        };
+        write!(f, "{}", op)
+    }
+}
+
+/// A condition on a ticker's price, written like `price>50000`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Condition {
+    pub comparison: Comparison,
+    pub threshold: f64,
+}
+
+impl Condition {
+    pub fn holds(&self, price: f64) -> bool {
+        self.comparison.holds(price, self.threshold)
+    }
+}
+
+impl FromStr for Condition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Condition, String> {
+        let s: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+        if !s.starts_with("price") {
+            return Err(format!(
+                "{:?} must start with \"price\", like price>50000",
+                s
+            ));
+        }
+        let rest = &s["price".len()..];
+        let split = rest.find(|c| !"<>=".contains(c)).unwrap_or(rest.len());
+        let comparison = Comparison::parse(&rest[..split])
+            .ok_or_else(|| format!("{:?} is not one of >, >=, <, <=, ==", &rest[..split]))?;
+        let threshold = rest[split..]
+            .parse::<f64>()
+            .ok()
+            .filter(|threshold| threshold.is_finite())
+            .ok_or_else(|| format!("{:?} is not a valid price", &rest[split..]))?;
+        Ok(Condition {
+            comparison,
+            threshold,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_conditions() {
+        let condition: Condition = " price >= 50000.5".parse().unwrap();
+        assert_eq!(condition.comparison, Comparison::AtLeast);
+        assert_eq!(condition.threshold, 50000.5);
+        assert!(condition.holds(50000.5));
+        assert!(!condition.holds(50000.0));
+    }
+
+    #[test]
+    fn rejects_bad_conditions() {
+        for condition in &[
+            "volume>5",
+            "price",
+            "price!5",
+            "price>",
+            "price>NaN",
+            "price<>5",
+        ] {
+            assert!(condition.parse::<Condition>().is_err(), "{:?}", condition);
+        }
+    }
+}
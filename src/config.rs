@@ -0,0 +1,683 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Deserializer};
+use toml;
+
+use aggregate::{Aggregate, Method};
+use alerts::{Holding, Rule};
+use anomaly::Policy;
+use clock::Clock;
+use cache::parse_ttl;
+use dirs;
+use errors::Error;
+use events::{self, Event};
+use firstpaint::FirstPaint;
+use freshness::Freshness;
+use history::parse_span;
+use keyring::{self, SECRETS};
+use mining::{self, Miner};
+use net;
+use onchain::{self, Provider};
+use pages::Page;
+use period::Period;
+use reminders::{self, Reminder};
+use sources;
+use symbols::Collisions;
+use template::{self, FormatRule, Template};
+use termux;
+
+/// Every config key can be set as `CRYPTOTICKER_` and the key in upper
+/// case, like `CRYPTOTICKER_ONCHAIN_API_KEY`, over what the file says.
+const ENV_PREFIX: &str = "CRYPTOTICKER_";
+
+/// Runs in interval mode every so many seconds, as `-i -t` does.
+pub const INTERVAL_VAR: &str = "CRYPTOTICKER_INTERVAL";
+/// What `{price}` is in, as `--in` sets.
+pub const CURRENCY_VAR: &str = "CRYPTOTICKER_CURRENCY";
+
+/// The variables that stand in for command-line flags rather than config
+/// keys.
+const FLAG_VARS: &[&str] = &[
+    INTERVAL_VAR,
+    CURRENCY_VAR,
+    net::API_URL_VAR,
+    dirs::CACHE_DIR_VAR,
+    dirs::CONFIG_DIR_VAR,
+    termux::TERMUX_VAR,
+];
+
+/// Settings read from `config.toml` in the user config directory, or the
+/// file given with `--config`. Command-line flags override them, then
+/// `CRYPTOTICKER_*` variables, then the keyring, then the file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub cache_ttl: Option<Ttl>,
+    pub cache_backend: Option<String>,
+    /// how many times a failed fetch is retried
+    pub retries: Option<u32>,
+    /// seconds before a request is given up on
+    pub timeout: Option<u64>,
+    /// where tickers are fetched from, like `["coinmarketcap",
+    /// "coingecko", "binance"]`, each tried when the one before it is down;
+    /// the ticker API alone when empty
+    #[serde(deserialize_with = "list")]
+    pub source: Vec<String>,
+    /// what `--anomalies` does when it isn't given
+    pub anomalies: Option<String>,
+    /// what `--change-period` is when it isn't given
+    pub change_period: Option<String>,
+    /// what `--first-paint` does when it isn't given
+    pub first_paint: Option<String>,
+    /// what `--first-paint banner` shows
+    pub banner: Option<String>,
+    /// what `--freshness` does when it isn't given
+    pub freshness: Option<String>,
+    /// what a symbol several coins share resolves to: `rank` or `ask`
+    pub collisions: Option<String>,
+    /// what `--aggregate` does when it isn't given
+    pub aggregate: Option<String>,
+    /// the exchanges `--aggregate` asks besides the ticker API, all of
+    /// them if empty
+    #[serde(deserialize_with = "list")]
+    pub aggregate_exchanges: Vec<String>,
+    /// what `open` and the dashboard's `w` key show when no `--page` or
+    /// `--exchange` is given: `provider`, `explorer` or an exchange
+    pub open_page: Option<String>,
+    /// the zone `{last_updated}` is shown in: `local`, `utc` or an
+    /// offset like `+02:00`
+    pub timezone: Option<String>,
+    /// the strftime format `{last_updated}` is shown in
+    pub time_format: Option<String>,
+    /// basis points off its peg a `--peg` coin may go
+    pub peg_threshold: Option<u32>,
+    /// `[[alert]]` tables, checked in interval mode
+    #[serde(rename = "alert")] pub alerts: Vec<Rule>,
+    /// `[formats]`: the format for each `--output`, like `waybar`, or for
+    /// a profile `query --profile` names, when `--format` isn't given
+    pub formats: HashMap<String, String>,
+    /// `[[format_rule]]` tables, each a format for coins that meet its
+    /// condition
+    #[serde(rename = "format_rule")] pub format_rules: Vec<FormatRule>,
+    /// `[[holding]]` tables, valued by portfolio alerts
+    #[serde(rename = "holding")] pub holdings: Vec<Holding>,
+    /// run with each alert's message as its argument
+    pub alert_command: Option<String>,
+    /// where `daemon` listens and `query` and `ctl` ask, like `--socket`
+    pub daemon_socket: Option<String>,
+    /// the group `daemon` shares its socket with, like `--group`
+    pub daemon_group: Option<String>,
+    /// whether alerts ring the terminal bell
+    pub alert_bell: bool,
+    /// run when alerts go off, like `paplay bell.oga`
+    pub alert_sound: Option<String>,
+    /// the least seconds between two rings of the bell or sound
+    pub alert_sound_cooldown: Option<u64>,
+    /// `[missing]`: what each field shows when the provider left it out,
+    /// like `change_7d = ["provider", "history", "dash"]`
+    pub missing: HashMap<String, Vec<String>>,
+    /// `[[event]]` tables, counted down to by `{countdown}`
+    #[serde(rename = "event")] pub events: Vec<Event>,
+    /// how long before a watched coin's next token unlock to raise an
+    /// alert, like `3d`; there are no unlock alerts without it
+    pub unlock_remind: Option<String>,
+    /// a DefiLlama-compatible emissions API for `{next_unlock}` and
+    /// unlock alerts to read instead of DefiLlama's
+    pub unlocks_url: Option<String>,
+    /// `[[reminder]]` tables, raised on their schedule in interval mode
+    #[serde(rename = "reminder")] pub reminders: Vec<Reminder>,
+    /// the feed `news:<coin>` reads, with `{coin}` for the coin's name
+    pub news_url: Option<String>,
+    /// how many characters of a headline fit in the status bar
+    pub news_width: Option<usize>,
+    /// the API key for on-chain metrics like `{realized_cap}`
+    pub onchain_api_key: Option<String>,
+    /// a Glassnode-compatible API to ask for them instead of Glassnode
+    pub onchain_url: Option<String>,
+    /// raises the rate limit on the gas oracle `gas` reads
+    pub etherscan_api_key: Option<String>,
+    /// credentials for `--mqtt` brokers whose URL has none
+    pub mqtt_username: Option<String>,
+    pub mqtt_password: Option<String>,
+    /// the secrets, like `onchain_api_key`, read from the OS keyring
+    /// instead of this file
+    #[serde(deserialize_with = "list")]
+    pub keyring: Vec<String>,
+    /// where `--mqtt-discovery` announces sensors, `homeassistant` by
+    /// default
+    pub mqtt_discovery_prefix: Option<String>,
+    /// `[mining.<algo>]` tables, the rigs `mining:<algo>` estimates for
+    pub mining: HashMap<String, Miner>,
+    /// `[symbols]` table of ticker symbols and the IDs they stand for,
+    /// for symbols several coins share
+    pub symbols: HashMap<String, String>,
+}
+
+/// Written as a number of seconds or the string `"infinite"`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Ttl {
+    Seconds(u64),
+    Named(String),
+}
+
+impl Ttl {
+    pub fn duration(&self) -> Result<Option<Duration>, String> {
+        match *self {
+            Ttl::Seconds(secs) => Ok(Some(Duration::from_secs(secs))),
+            Ttl::Named(ref name) => parse_ttl(name),
+        }
+    }
+}
+
+/// The `CRYPTOTICKER_*` variables. One that isn't UTF-8 is warned about
+/// and left out; any other variable is none of ours, whatever it holds.
+fn env_vars() -> Vec<(String, String)> {
+    env::vars_os()
+        .filter(|(var, _)| var.to_string_lossy().starts_with(ENV_PREFIX))
+        .filter_map(
+            |(var, value)| match (var.into_string(), value.into_string()) {
+                (Ok(var), Ok(value)) => Some((var, value)),
+                (var, _) => {
+                    let var = var.unwrap_or_else(|var| var.to_string_lossy().into_owned());
+                    warn!("ignoring {}, which isn't UTF-8", var);
+                    None
+                }
+            },
+        )
+        .collect()
+}
+
+/// Whether the config has a `key` to set.
+fn is_setting(key: &str) -> bool {
+    let mut alone = toml::value::Table::new();
+    alone.insert(key.to_string(), toml::Value::Boolean(false));
+    match toml::Value::Table(alone).try_into::<Config>() {
+        Ok(_) => true,
+        Err(err) => !err.to_string().starts_with("unknown field"),
+    }
+}
+
+/// Which variables were likely meant by the one for `key`, which isn't a
+/// setting.
+fn hint(key: &str) -> String {
+    if key != "api_key" {
+        return String::new();
+    }
+    let keys: Vec<String> = SECRETS
+        .iter()
+        .filter(|secret| secret.ends_with("_api_key"))
+        .map(|secret| env_var(secret))
+        .collect();
+    format!(
+        "; the ticker API needs no key, and the providers that do read {}",
+        keys.join(" and ")
+    )
+}
+
+/// What `raw`, from the variable for `key`, sets it to. A key that takes
+/// text gets `raw` as it is, so a password or group ID of digits stays
+/// text, unless it's written as a TOML array, table or string. Anything
+/// else is read as TOML, like `600` or `true`, or as text if it isn't.
+fn env_value(key: &str, raw: &str) -> toml::Value {
+    let text = toml::Value::String(raw.to_string());
+    let mut alone = toml::value::Table::new();
+    alone.insert(key.to_string(), text.clone());
+    let takes_text = toml::Value::Table(alone).try_into::<Config>().is_ok();
+    if takes_text && !raw.trim_start().starts_with(['[', '{', '"']) {
+        return text;
+    }
+    toml::from_str::<toml::value::Table>(&format!("value = {}", raw))
+        .ok()
+        .and_then(|mut parsed| parsed.remove("value"))
+        .unwrap_or(text)
+}
+
+/// A list of names, which can also be given as one string with commas
+/// between them, like `CRYPTOTICKER_SOURCE=coinmarketcap,coingecko`.
+fn list<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum List {
+        Joined(String),
+        Items(Vec<String>),
+    }
+    Ok(match List::deserialize(deserializer)? {
+        List::Joined(joined) => joined
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(String::from)
+            .collect(),
+        List::Items(items) => items,
+    })
+}
+
+impl Config {
+    /// The format given for `--format` on the command line, else the
+    /// one `[formats]` has for `profile`, else the default.
+    pub fn format<'a>(&'a self, given: Option<&'a str>, profile: &str) -> &'a str {
+        given
+            .or_else(|| self.formats.get(profile).map(String::as_str))
+            .unwrap_or(template::DEFAULT)
+    }
+
+    /// How `{last_updated}` is shown.
+    pub fn clock(&self) -> Clock {
+        // checked when the config loaded
+        Clock::new(self.timezone.as_deref(), self.time_format.as_deref()).unwrap()
+    }
+
+    /// The on-chain metrics provider, if an API key is configured.
+    pub fn onchain(&self) -> Option<Provider> {
+        self.onchain_api_key.as_ref().map(|key| Provider {
+            url: self
+                .onchain_url
+                .clone()
+                .unwrap_or_else(|| onchain::DEFAULT_URL.to_string()),
+            key: key.clone(),
+        })
+    }
+
+    /// How many seconds before a token unlock its alert fires, if they
+    /// fire at all.
+    pub fn unlock_remind(&self) -> Result<Option<i64>, String> {
+        self.unlock_remind
+            .as_deref()
+            .map(parse_span)
+            .transpose()
+            .map_err(|err| format!("unlock_remind: {}", err))
+    }
+
+    /// The secret config key `name` names, like `onchain_api_key`.
+    fn secret_mut(&mut self, name: &str) -> Option<&mut Option<String>> {
+        match name {
+            "onchain_api_key" => Some(&mut self.onchain_api_key),
+            "etherscan_api_key" => Some(&mut self.etherscan_api_key),
+            "mqtt_password" => Some(&mut self.mqtt_password),
+            _ => None,
+        }
+    }
+
+    /// Loads `path`, or the default config file if there is one, with the
+    /// `CRYPTOTICKER_*` variables over it and the secrets in `keyring`
+    /// filled in. Only an explicitly given file has to exist.
+    pub fn load(path: Option<&Path>) -> Result<Config, Error> {
+        let path = match path {
+            Some(path) => Some(path.to_path_buf()),
+            None => default_path().filter(|path| path.exists()),
+        };
+        let content = match path {
+            Some(ref path) => fs::read_to_string(path)
+                .map_err(|err| format!("Could not read {}: {}", path.display(), err))?,
+            None => String::new(),
+        };
+        let vars = env_vars();
+        let mut config = Config::parse_with(&content, &vars).map_err(|err| match path {
+            Some(ref path) => Error::Parse(format!("{}: {}", path.display(), err)),
+            None => Error::Parse(err),
+        })?;
+        for name in config.keyring.clone() {
+            // the environment wins over the keyring
+            if vars.iter().any(|(var, _)| *var == env_var(&name)) {
+                continue;
+            }
+            match keyring::get(&name) {
+                Ok(Some(secret)) => *config.secret_mut(&name).unwrap() = Some(secret),
+                Ok(None) => warn!("the keyring has no {}", name),
+                Err(err) => warn!("{}", err),
+            }
+        }
+        Ok(config)
+    }
+
+    /// `content` alone, without the environment.
+    #[cfg(test)]
+    fn parse(content: &str) -> Result<Config, String> {
+        Config::parse_with(content, &[])
+    }
+
+    /// Parses `content` with the `CRYPTOTICKER_*` variables among `vars`
+    /// set over it, read by `env_value`.
+    fn parse_with(content: &str, vars: &[(String, String)]) -> Result<Config, String> {
+        let mut table: toml::value::Table =
+            toml::from_str(content).map_err(|err| err.to_string())?;
+        let mut set = Vec::new();
+        for (var, value) in vars {
+            let key = match var.strip_prefix(ENV_PREFIX) {
+                Some(key) if !FLAG_VARS.contains(&var.as_str()) => key.to_lowercase(),
+                _ => continue,
+            };
+            // like a variable meant for some other program
+            if !is_setting(&key) {
+                warn!("ignoring {}, which isn't a setting{}", var, hint(&key));
+                continue;
+            }
+            let value = env_value(&key, value);
+            table.insert(key, value);
+            set.push(var.as_str());
+        }
+        Config::check(table).map_err(|err| match set.len() {
+            0 => err,
+            _ => format!("{} (with {} set)", err, set.join(", ")),
+        })
+    }
+
+    /// Reads the settings in `table`, checking them as far as they can be
+    /// without the network.
+    fn check(table: toml::value::Table) -> Result<Config, String> {
+        let config: Config = toml::Value::Table(table)
+            .try_into()
+            .map_err(|err: toml::de::Error| err.to_string())?;
+        for name in &config.keyring {
+            if !SECRETS.contains(&name.as_str()) {
+                return Err(format!(
+                    "keyring: {:?} should be one of {}",
+                    name,
+                    SECRETS.join(", ")
+                ));
+            }
+        }
+        events::validate(&config.events)?;
+        config.unlock_remind()?;
+        sources::parse(&config.source)?;
+        Template::default().with_rules(&config.format_rules)?;
+        Template::default().with_fallbacks(&config.missing)?;
+        for (profile, format) in &config.formats {
+            format
+                .parse::<Template>()
+                .map_err(|err| format!("[formats] {}: {}", profile, err))?;
+        }
+        reminders::validate(&config.reminders)?;
+        if let Some(ref policy) = config.anomalies {
+            policy.parse::<Policy>()?;
+        }
+        if let Some(ref period) = config.change_period {
+            period.parse::<Period>()?;
+        }
+        if let Some(ref mode) = config.first_paint {
+            mode.parse::<FirstPaint>()?;
+        }
+        if let Some(ref marker) = config.freshness {
+            marker.parse::<Freshness>()?;
+        }
+        if let Some(ref policy) = config.collisions {
+            policy.parse::<Collisions>()?;
+        }
+        Clock::new(config.timezone.as_deref(), config.time_format.as_deref())?;
+        if let Some(ref page) = config.open_page {
+            page.parse::<Page>()?;
+        }
+        if let Some(ref method) = config.aggregate {
+            method.parse::<Method>()?;
+        }
+        Aggregate::new(Method::Median, &config.aggregate_exchanges)
+            .map_err(|err| format!("aggregate_exchanges: {}", err))?;
+        for (algo, miner) in &config.mining {
+            mining::parse_hashrate(&miner.hashrate)
+                .map_err(|err| format!("[mining.{}]: {}", algo, err))?;
+        }
+        Ok(config)
+    }
+}
+
+/// The variable that sets config key `key`.
+fn env_var(key: &str) -> String {
+    format!("{}{}", ENV_PREFIX, key.to_uppercase())
+}
+
+/// Where `config.toml` is looked for when `--config` isn't given.
+pub fn default_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("config.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+
+    #[test]
+    fn parses_settings() {
+        let config = Config::parse("cache_ttl = 600\ncache_backend = \"memory\"\n").unwrap();
+        assert_eq!(
+            config.cache_ttl.unwrap().duration(),
+            Ok(Some(Duration::from_secs(600)))
+        );
+        assert_eq!(config.cache_backend.as_deref(), Some("memory"));
+
+        let config = Config::parse("cache_ttl = \"infinite\"").unwrap();
+        assert_eq!(config.cache_ttl.unwrap().duration(), Ok(None));
+
+        let config = Config::parse("[symbols]\nbtc = \"bitcoin\"").unwrap();
+        assert_eq!(config.symbols["btc"], "bitcoin");
+        assert!(config.onchain().is_none());
+
+        let config = Config::parse("onchain_api_key = \"secret\"").unwrap();
+        assert_eq!(config.onchain().unwrap().url, "https://api.glassnode.com");
+
+        let config = Config::parse("anomalies = \"hold\"").unwrap();
+        assert_eq!(config.anomalies.as_deref(), Some("hold"));
+        assert!(Config::parse("anomalies = \"ignore\"").is_err());
+        assert!(Config::parse("aggregate = \"mode\"").is_err());
+        assert!(Config::parse("collisions = \"ask\"").is_ok());
+        assert!(Config::parse("collisions = \"vote\"").is_err());
+        let config = Config::parse("freshness = \"age\"").unwrap();
+        assert_eq!(config.freshness.as_deref(), Some("age"));
+        assert!(Config::parse("freshness = \"stale\"").is_err());
+        let config = Config::parse("first_paint = \"banner\"\nbanner = \"₿…\"").unwrap();
+        assert_eq!(config.banner.as_deref(), Some("₿…"));
+        assert!(Config::parse("first_paint = \"spinner\"").is_err());
+        let config = Config::parse("change_period = \"7d\"").unwrap();
+        assert_eq!(config.change_period.as_deref(), Some("7d"));
+        assert!(Config::parse("change_period = \"30d\"").is_err());
+        let config = Config::parse("open_page = \"kraken\"").unwrap();
+        assert_eq!(config.open_page.as_deref(), Some("kraken"));
+        assert!(Config::parse("open_page = \"ftx\"").is_err());
+        let config = Config::parse("timezone = \"utc\"\ntime_format = \"%H:%M\"").unwrap();
+        assert_eq!(config.clock().show(1_700_000_000), "22:13");
+        assert!(Config::parse("timezone = \"mars\"").is_err());
+        let config = Config::parse(
+            "daemon_socket = \"/run/cryptoticker/daemon.sock\"\ndaemon_group = \"crypto\"",
+        )
+        .unwrap();
+        assert_eq!(
+            config.daemon_socket.as_deref(),
+            Some("/run/cryptoticker/daemon.sock")
+        );
+        assert_eq!(config.daemon_group.as_deref(), Some("crypto"));
+        let config = Config::parse("[formats]\nwaybar = \"{symbol} {price}\"").unwrap();
+        assert_eq!(config.format(None, "waybar"), "{symbol} {price}");
+        assert_eq!(config.format(None, "text"), template::DEFAULT);
+        assert_eq!(config.format(Some("{id}"), "waybar"), "{id}");
+        assert!(Config::parse("[formats]\ntext = \"{prise}\"").is_err());
+        let config =
+            Config::parse("[[format_rule]]\nwhen = \"change_24h < -5\"\nformat = \"{short}!\"")
+                .unwrap();
+        assert_eq!(config.format_rules[0].format, "{short}!");
+        assert!(Config::parse("[[format_rule]]\nwhen = \"soon\"\nformat = \"{short}\"").is_err());
+        assert!(Config::parse("aggregate_exchanges = [\"ftx\"]").is_err());
+        let config = Config::parse("[missing]\nchange_7d = [\"history\", \"dash\"]").unwrap();
+        assert_eq!(config.missing["change_7d"], ["history", "dash"]);
+        assert!(Config::parse("[missing]\nmarket_cap = [\"history\"]").is_err());
+        assert!(Config::parse("[missing]\nchange_1y = [\"dash\"]").is_err());
+    }
+
+    #[test]
+    fn empty_file_is_the_default() {
+        let config = Config::parse("").unwrap();
+        assert!(config.cache_ttl.is_none());
+        assert!(config.cache_backend.is_none());
+    }
+
+    #[test]
+    fn reads_the_environment() {
+        let vars = |vars: &[(&str, &str)]| -> Vec<(String, String)> {
+            vars.iter()
+                .map(|&(var, value)| (var.to_string(), value.to_string()))
+                .collect()
+        };
+        let config = Config::parse_with(
+            "retries = 5\nonchain_api_key = \"from the file\"",
+            &vars(&[
+                ("CRYPTOTICKER_RETRIES", "1"),
+                ("CRYPTOTICKER_ONCHAIN_API_KEY", "from the environment"),
+                ("CRYPTOTICKER_MQTT_PASSWORD", "123456"),
+                ("CRYPTOTICKER_DAEMON_GROUP", "1001"),
+                ("CRYPTOTICKER_NEWS_WIDTH", "40"),
+                ("CRYPTOTICKER_AGGREGATE_EXCHANGES", "[\"kraken\"]"),
+                ("CRYPTOTICKER_SOURCE", "coinmarketcap, coingecko"),
+                ("CRYPTOTICKER_INTERVAL", "60"),
+                ("CRYPTOTICKER_API_URL", "http://127.0.0.1:18777/"),
+                ("HOME", "/root"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(config.retries, Some(1));
+        assert_eq!(
+            config.onchain_api_key.as_deref(),
+            Some("from the environment")
+        );
+        assert_eq!(config.aggregate_exchanges, ["kraken"]);
+        assert_eq!(config.mqtt_password.as_deref(), Some("123456"));
+        assert_eq!(config.daemon_group.as_deref(), Some("1001"));
+        assert_eq!(config.news_width, Some(40));
+        assert_eq!(config.source, ["coinmarketcap", "coingecko"]);
+        // checked like the file is, apart from what isn't a setting at all
+        let config = Config::parse_with("", &vars(&[("CRYPTOTICKER_RETRYS", "1")])).unwrap();
+        assert_eq!(config.retries, None);
+        assert!(Config::parse_with("", &vars(&[("CRYPTOTICKER_API_KEY", "k")])).is_ok());
+        assert!(Config::parse_with("", &vars(&[("CRYPTOTICKER_RETRIES", "x")])).is_err());
+        assert_eq!(
+            hint("api_key"),
+            "; the ticker API needs no key, and the providers that do read \
+             CRYPTOTICKER_ONCHAIN_API_KEY and CRYPTOTICKER_ETHERSCAN_API_KEY"
+        );
+        assert!(Config::parse_with("", &vars(&[("CRYPTOTICKER_FIRST_PAINT", "spinner")])).is_err());
+        let config = Config::parse("keyring = [\"onchain_api_key\"]").unwrap();
+        assert_eq!(config.keyring, ["onchain_api_key"]);
+        assert!(Config::parse("keyring = [\"banner\"]").is_err());
+    }
+
+    #[test]
+    fn parses_alerts() {
+        let config = Config::parse(
+            "alert_command = \"notify-send\"
+             alert_bell = true
+             alert_sound = \"paplay bell.oga\"
+             alert_sound_cooldown = 300
+
+             [[alert]]
+             rule = \"rank\"
+             leaves_top = 100
+
+             [[alert]]
+             rule = \"price\"
+             coin = \"bitcoin\"
+             condition = \"price>50000\"
+
+             [[alert]]
+             rule = \"allocation\"
+             coin = \"bitcoin\"
+             above = 60
+            ",
+        )
+        .unwrap();
+        assert_eq!(config.alert_command.as_deref(), Some("notify-send"));
+        assert!(config.alert_bell);
+        assert_eq!(config.alert_sound.as_deref(), Some("paplay bell.oga"));
+        assert_eq!(config.alert_sound_cooldown, Some(300));
+        assert_eq!(
+            config.alerts,
+            vec![
+                Rule::Rank {
+                    coin: None,
+                    enters_top: None,
+                    leaves_top: Some(100),
+                },
+                Rule::Price {
+                    coin: "bitcoin".to_string(),
+                    condition: "price>50000".to_string(),
+                    cooldown: None,
+                    rearm: None,
+                },
+                Rule::Allocation {
+                    coin: "bitcoin".to_string(),
+                    above: Some(60.0),
+                    below: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_holdings() {
+        let config = Config::parse(
+            "[[alert]]
+             rule = \"portfolio\"
+             loss_beyond = 20.0
+
+             [[holding]]
+             coin = \"bitcoin\"
+             amount = 0.5
+             cost = 20000.0
+            ",
+        )
+        .unwrap();
+        assert_eq!(
+            config.alerts,
+            vec![Rule::Portfolio {
+                value_below: None,
+                loss_beyond: Some(20.0),
+            }]
+        );
+        assert_eq!(
+            config.holdings,
+            vec![Holding {
+                coin: "bitcoin".to_string(),
+                amount: Decimal::new(5, 1),
+                cost: Some(Decimal::new(20000, 0)),
+            }]
+        );
+    }
+
+    #[test]
+    fn parses_events() {
+        let config = Config::parse(
+            "[[event]]
+             coin = \"bitcoin\"
+             name = \"halving\"
+             date = \"2028-04-15\"
+             remind = \"7d\"
+            ",
+        )
+        .unwrap();
+        assert_eq!(config.events[0].name, "halving");
+        assert_eq!(config.events[0].remind(), Ok(7 * 86_400));
+    }
+
+    #[test]
+    fn parses_miners() {
+        let config = Config::parse(
+            "[mining.sha256]
+             hashrate = \"110TH/s\"
+             watts = 3250
+             power_cost = 0.12
+            ",
+        )
+        .unwrap();
+        assert_eq!(config.mining["sha256"].watts, 3250.0);
+        assert!(Config::parse(
+            "[mining.sha256]\nhashrate = \"fast\"\nwatts = 1.0\npower_cost = 0.1"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_settings() {
+        assert!(Config::parse("cache_tll = 5").is_err());
+    }
+}
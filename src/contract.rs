@@ -0,0 +1,221 @@
+use std::str::FromStr;
+
+use serde_json::{self, json, Value};
+
+use cache::{Cache, Validators};
+use errors::Error;
+use history::History;
+use net::Net;
+use template::Template;
+use ticker::Currency;
+
+/// Short names for CoinGecko's asset platforms, the chains a contract
+/// can be on.
+const PLATFORMS: &[(&str, &str)] = &[
+    ("eth", "ethereum"),
+    ("bsc", "binance-smart-chain"),
+    ("bnb", "binance-smart-chain"),
+    ("polygon", "polygon-pos"),
+    ("matic", "polygon-pos"),
+    ("arbitrum", "arbitrum-one"),
+];
+
+/// An ERC-20 or BEP-20 token, priced through CoinGecko by its contract
+/// address, since long-tail tokens often have no ID of their own.
+/// Written `ADDRESS:CHAIN`, optionally with a `:SYMBOL` to show it as.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Contract {
+    /// lowercase, as CoinGecko keys its answers
+    pub address: String,
+    /// a CoinGecko asset platform, like `ethereum`
+    pub platform: String,
+    pub symbol: Option<String>,
+}
+
+impl FromStr for Contract {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Contract, String> {
+        let mut parts = s.trim().splitn(3, ':');
+        let address = parts.next().unwrap_or("").to_lowercase();
+        let platform = match parts.next() {
+            Some(platform) if !platform.is_empty() => platform.to_lowercase(),
+            _ => {
+                return Err(format!(
+                    "{:?} should be ADDRESS:CHAIN, like 0x1f98...f984:ethereum",
+                    s
+                ))
+            }
+        };
+        let hex = address.strip_prefix("0x").unwrap_or("");
+        if hex.len() != 40 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!(
+                "{:?} isn't a contract address like 0x1f98...f984",
+                address
+            ));
+        }
+        let platform = PLATFORMS
+            .iter()
+            .find(|&&(short, _)| short == platform)
+            .map_or(platform.clone(), |&(_, full)| full.to_string());
+        Ok(Contract {
+            address,
+            platform,
+            symbol: parts
+                .next()
+                .filter(|symbol| !symbol.is_empty())
+                .map(String::from),
+        })
+    }
+}
+
+impl Contract {
+    /// What the token is cached and recorded under, like
+    /// `ethereum-0x1f98...f984`.
+    pub fn id(&self) -> String {
+        format!("{}-{}", self.platform, self.address)
+    }
+
+    /// What the line calls it: the symbol given, or the address cut
+    /// down to `0x1f98…f984`.
+    pub fn label(&self) -> String {
+        match self.symbol {
+            Some(ref symbol) => symbol.clone(),
+            None => format!("{}…{}", &self.address[..6], &self.address[38..]),
+        }
+    }
+
+    /// What CoinGecko is asked for the token's quote.
+    pub fn url(&self) -> String {
+        format!(
+            "https://api.coingecko.com/api/v3/simple/token_price/{}?contract_addresses={}\
+             &vs_currencies=usd&include_market_cap=true&include_24hr_vol=true\
+             &include_24hr_change=true",
+            self.platform, self.address
+        )
+    }
+
+    /// Reads CoinGecko's answer into the same shape as a coin.
+    fn parse(&self, content: &str) -> Result<Currency, Error> {
+        let answer: Value = serde_json::from_str(content)?;
+        let quote = match answer.get(&self.address) {
+            Some(quote) if quote.get("usd").is_some() => quote,
+            // unknown contracts come back as `{}`
+            _ => return Err(Error::InvalidTicker { name: self.id() }),
+        };
+        let label = self.label();
+        Ok(serde_json::from_value(json!({
+            "id": self.id(),
+            "name": label,
+            "symbol": label,
+            "rank": "",
+            "price_usd": quote["usd"],
+            "24h_volume_usd": quote.get("usd_24h_vol"),
+            "market_cap_usd": quote.get("usd_market_cap"),
+            "percent_change_24h": quote.get("usd_24h_change"),
+        }))?)
+    }
+
+    fn fetch(&self, net: &Net) -> Result<Currency, Error> {
+        let url = self.url();
+        debug!("retrieving {}", url);
+        let resp = net.get(&url)?;
+        if !resp.status.is_success() {
+            return Err(Error::Network(format!("{} answered {}", url, resp.status)));
+        }
+        self.parse(&resp.text())
+    }
+
+    /// The token's quote, from `cache` while it's fresh and fetched
+    /// otherwise, falling back to a cold entry when the fetch fails.
+    pub fn load(&self, cache: Option<&Cache>, net: &Net) -> Result<Currency, Error> {
+        let id = self.id();
+        let cached = cache.and_then(|cache| {
+            let (ticker, age) = cache.backend.get(&id)?;
+            let fresh = cache.ttl.is_none_or(|ttl| age < ttl);
+            Some((ticker, fresh || cache.offline))
+        });
+        let cold = match cached {
+            Some((ticker, true)) => return Ok(ticker),
+            None if cache.is_some_and(|cache| cache.offline) => {
+                return Err(Error::Network(format!(
+                    "{} is not cached, and --offline is set.",
+                    id
+                )))
+            }
+            cached => cached.map(|(ticker, _)| ticker),
+        };
+        match (self.fetch(net), cold) {
+            (Ok(ticker), _) => {
+                if let Some(cache) = cache {
+                    if let Err(err) = cache.backend.put(&id, &ticker, &Validators::default()) {
+                        debug!("{}", err);
+                    }
+                }
+                Ok(ticker)
+            }
+            (Err(ref err), Some(ticker)) if err.is_failed_fetch() => {
+                debug!("{}, using the cold cache entry for {}", err, id);
+                Ok(ticker)
+            }
+            (Err(err), _) => Err(err),
+        }
+    }
+}
+
+/// The segment to print for a `contract:ADDRESS:CHAIN` argument, in
+/// the same format as the coins.
+pub fn segment(
+    spec: &str,
+    cache: Option<&Cache>,
+    template: &Template,
+    history: Option<&History>,
+    net: &Net,
+) -> Result<String, Error> {
+    let contract: Contract = spec.parse().map_err(Error::Parse)?;
+    let ticker = contract.load(cache, net)?;
+    Ok(template.render(&contract.label(), &ticker, history))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ticker::to_f64;
+
+    const UNI: &str = "0x1F9840a85d5aF5bf1D1762F925BDADdC4201F984";
+
+    #[test]
+    fn reads_specs() {
+        let contract: Contract = format!("{}:eth", UNI).parse().unwrap();
+        assert_eq!(contract.address, UNI.to_lowercase());
+        assert_eq!(contract.platform, "ethereum");
+        assert_eq!(contract.label(), "0x1f98…f984");
+        let contract: Contract = format!("{}:bsc:cake", UNI).parse().unwrap();
+        assert_eq!(contract.platform, "binance-smart-chain");
+        assert_eq!(contract.label(), "cake");
+        assert!(UNI.parse::<Contract>().is_err());
+        assert!("0x1234:ethereum".parse::<Contract>().is_err());
+    }
+
+    #[test]
+    fn prices_like_a_coin() {
+        let contract: Contract = format!("{}:ethereum", UNI).parse().unwrap();
+        let ticker = contract
+            .parse(
+                r#"{"0x1f9840a85d5af5bf1d1762f925bdaddc4201f984": {"usd": 7.23,
+                    "usd_market_cap": 4330000000.5, "usd_24h_vol": 120000000.0,
+                    "usd_24h_change": -2.5}}"#,
+            )
+            .unwrap();
+        assert_eq!(
+            ticker.id,
+            "ethereum-0x1f9840a85d5af5bf1d1762f925bdaddc4201f984"
+        );
+        assert_eq!(to_f64(ticker.price_usd), Some(7.23));
+        assert_eq!(to_f64(ticker.percent_change_24), Some(-2.5));
+        match contract.parse("{}") {
+            Err(Error::InvalidTicker { .. }) => {}
+            other => panic!("{:?}", other),
+        }
+    }
+}
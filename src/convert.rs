@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+
+use clap::ArgMatches;
+use rust_decimal::Decimal;
+
+use cache::Cache;
+use errors::Error;
+use net::Net;
+use symbols::{self, Symbols};
+use template::rounded;
+use ticker::usd_price;
+
+/// What a market order costs on top of the mid price, both in percent:
+/// the exchange's fee, and how far the price moves against the order
+/// while it fills.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Costs {
+    pub fee: Decimal,
+    pub slippage: Decimal,
+}
+
+impl Costs {
+    /// What is actually received for `amount` at the mid price.
+    pub fn apply(&self, amount: Decimal) -> Decimal {
+        let hundred = Decimal::ONE_HUNDRED;
+        amount * (hundred - self.fee) / hundred * (hundred - self.slippage) / hundred
+    }
+
+    fn is_empty(&self) -> bool {
+        self.fee.is_zero() && self.slippage.is_zero()
+    }
+}
+
+fn percent(matches: &ArgMatches, name: &str) -> Result<Decimal, Error> {
+    let value = match matches.value_of(name) {
+        Some(value) => value,
+        None => return Ok(Decimal::ZERO),
+    };
+    match value.trim_end_matches('%').parse::<Decimal>() {
+        Ok(percent) if (Decimal::ZERO..Decimal::ONE_HUNDRED).contains(&percent) => Ok(percent),
+        _ => Err(Error::Parse(format!(
+            "--{} should be a percentage from 0 up to 100, not {:?}",
+            name, value
+        ))),
+    }
+}
+
+/// The USD price of `coin`, which is 1 for `usd` itself.
+fn price(coin: &str, cache: Option<&Cache>, net: &Net) -> Result<Decimal, Error> {
+    if coin.eq_ignore_ascii_case("usd") {
+        return Ok(Decimal::ONE);
+    }
+    usd_price(coin, cache, net)
+}
+
+fn report(amount: Decimal, from: &str, to: &str, mid: Decimal, costs: &Costs) -> String {
+    let mut out = format!(
+        "{} {} = {:.6} {} at the mid price\n",
+        amount,
+        from,
+        rounded(mid, 6),
+        to
+    );
+    if !costs.is_empty() {
+        let received = costs.apply(mid);
+        out.push_str(&format!(
+            "{:.6} {} after a {}% fee and {}% slippage, {:.6} less\n",
+            rounded(received, 6),
+            to,
+            costs.fee,
+            costs.slippage,
+            rounded(mid - received, 6)
+        ));
+    }
+    out
+}
+
+/// Runs `convert`, converting AMOUNT of one coin, or USD, into another.
+pub fn convert(
+    matches: &ArgMatches,
+    cache: Option<&Cache>,
+    symbols: &Symbols,
+    overrides: &HashMap<String, String>,
+    net: &Net,
+) -> Result<(), Error> {
+    let amount = value_t!(matches, "AMOUNT", Decimal).unwrap_or_else(|e| e.exit());
+    if amount.is_sign_negative() {
+        return Err(Error::Other("AMOUNT can't be negative".to_string()));
+    }
+    let costs = Costs {
+        fee: percent(matches, "fee")?,
+        slippage: percent(matches, "slippage")?,
+    };
+    let args = [
+        matches.value_of("FROM").unwrap(),
+        matches.value_of("TO").unwrap(),
+    ];
+    let coins = symbols::resolve_all(symbols, &args, overrides, false);
+    let (from, to) = (&coins[0], &coins[1]);
+    let quoted = price(to, cache, net)?;
+    let mid = (amount * price(from, cache, net)?)
+        .checked_div(quoted)
+        .ok_or_else(|| Error::Other(format!("{} is priced at {} USD", to, quoted)))?;
+    print!("{}", report(amount, from, to, mid, &costs));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn takes_fee_and_slippage_off() {
+        let costs = Costs {
+            fee: Decimal::new(1, 1),
+            slippage: Decimal::new(5, 1),
+        };
+        let thousand = Decimal::new(1000, 0);
+        assert_eq!(costs.apply(thousand), Decimal::new(994005, 3));
+        assert_eq!(Costs::default().apply(thousand), thousand);
+    }
+
+    #[test]
+    fn reports_what_is_received() {
+        let mid = Decimal::new(50000, 0);
+        assert_eq!(
+            report(Decimal::ONE, "bitcoin", "usd", mid, &Costs::default()),
+            "1 bitcoin = 50000.000000 usd at the mid price\n"
+        );
+        let costs = Costs {
+            fee: Decimal::ONE,
+            slippage: Decimal::ZERO,
+        };
+        assert_eq!(
+            report(Decimal::ONE, "bitcoin", "usd", mid, &costs)
+                .lines()
+                .nth(1),
+            Some("49500.000000 usd after a 1% fee and 0% slippage, 500.000000 less")
+        );
+    }
+}
@@ -0,0 +1,175 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use time;
+
+use errors::Error;
+use ticker::Currency;
+
+const HEADER: &str = "timestamp,coin,price_usd,percent_change_24h\n";
+/// how many size-rotated files are kept next to the live one
+const KEEP: u32 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rotation {
+    Never,
+    /// rotate once the file reaches this many bytes
+    Size(u64),
+    /// rotate when the first row of a new (local) day is written
+    Daily,
+}
+
+impl FromStr for Rotation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Rotation, String> {
+        let s = s.trim().to_lowercase();
+        if s == "never" {
+            return Ok(Rotation::Never);
+        }
+        if s == "daily" {
+            return Ok(Rotation::Daily);
+        }
+        let (digits, scale) = match s.chars().last() {
+            Some('k') => (&s[..s.len() - 1], 1 << 10),
+            Some('m') => (&s[..s.len() - 1], 1 << 20),
+            Some('g') => (&s[..s.len() - 1], 1 << 30),
+            _ => (&s[..], 1),
+        };
+        match digits.parse::<u64>() {
+            Ok(size) if size > 0 => Ok(Rotation::Size(size * scale)),
+            _ => Err(format!(
+                "{:?} should be \"daily\", \"never\" or a size like 10M",
+                s
+            )),
+        }
+    }
+}
+
+/// Appends one CSV row per fetched sample, a lightweight alternative to
+/// the SQLite history.
+pub struct CsvLog {
+    path: PathBuf,
+    rotation: Rotation,
+}
+
+impl CsvLog {
+    pub fn new(path: &Path, rotation: Rotation) -> CsvLog {
+        CsvLog {
+            path: path.to_path_buf(),
+            rotation,
+        }
+    }
+
+    pub fn append(&self, ticker: &Currency) -> Result<(), Error> {
+        self.rotate()?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        if file.metadata()?.len() == 0 {
+            file.write_all(HEADER.as_bytes())?;
+        }
+        let row = [
+            time::strftime("%Y-%m-%dT%H:%M:%SZ", &time::now_utc()).unwrap_or_default(),
+            ticker.id.clone(),
+            ticker.price_usd.map(|p| p.to_string()).unwrap_or_default(),
+            ticker
+                .percent_change_24
+                .map(|c| c.to_string())
+                .unwrap_or_default(),
+        ];
+        let row: Vec<String> = row.iter().map(|field| escape(field)).collect();
+        writeln!(file, "{}", row.join(","))?;
+        Ok(())
+    }
+
+    fn rotate(&self) -> Result<(), Error> {
+        let metadata = match fs::metadata(&self.path) {
+            Ok(metadata) => metadata,
+            Err(_) => return Ok(()),
+        };
+        match self.rotation {
+            Rotation::Never => {}
+            Rotation::Size(limit) => {
+                if metadata.len() >= limit {
+                    for n in (1..KEEP).rev() {
+                        let from = numbered(&self.path, n);
+                        if from.exists() {
+                            fs::rename(from, numbered(&self.path, n + 1))?;
+                        }
+                    }
+                    fs::rename(&self.path, numbered(&self.path, 1))?;
+                }
+            }
+            Rotation::Daily => {
+                let modified = metadata.modified()?;
+                let day = |tm: time::Tm| time::strftime("%Y-%m-%d", &tm).unwrap_or_default();
+                let written = day(time::at(to_timespec(modified)));
+                if written != day(time::now()) {
+                    fs::rename(&self.path, dated(&self.path, &written))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn to_timespec(t: ::std::time::SystemTime) -> time::Timespec {
+    let secs = t
+        .duration_since(::std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    time::Timespec::new(secs, 0)
+}
+
+/// prices.csv -> prices.csv.1
+fn numbered(path: &Path, n: u32) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}", n));
+    path.with_file_name(name)
+}
+
+/// prices.csv -> prices-2017-06-15.csv
+fn dated(path: &Path, day: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(ext) => format!("{}-{}.{}", stem, day, ext.to_string_lossy()),
+        None => format!("{}-{}", stem, day),
+    };
+    path.with_file_name(name)
+}
+
+fn escape(field: &str) -> String {
+    if field.contains(&[',', '"', '\n'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_rotation() {
+        assert_eq!("daily".parse(), Ok(Rotation::Daily));
+        assert_eq!("10M".parse(), Ok(Rotation::Size(10 << 20)));
+        assert_eq!("4096".parse(), Ok(Rotation::Size(4096)));
+        assert!("0".parse::<Rotation>().is_err());
+        assert!("weekly".parse::<Rotation>().is_err());
+    }
+
+    #[test]
+    fn names_rotated_files() {
+        let path = Path::new("/tmp/prices.csv");
+        assert_eq!(numbered(path, 2), Path::new("/tmp/prices.csv.2"));
+        assert_eq!(
+            dated(path, "2017-06-15"),
+            Path::new("/tmp/prices-2017-06-15.csv")
+        );
+    }
+}
@@ -0,0 +1,731 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::fs::{chown, FileTypeExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use clap::ArgMatches;
+use serde_json;
+use time;
+
+use alerts::Alerts;
+use config::Config;
+use dirs;
+use errors::Error;
+use history::{self, History};
+use net::Net;
+use output::Frame;
+use symbols::Symbols;
+use template::Template;
+use ticker::{refresh_ticker, Currency};
+use trace;
+use watchlist::{self, Watchlist};
+
+/// A coin's latest price, and the refresh round that fetched it.
+#[derive(Debug, Clone)]
+struct Price {
+    ticker: Currency,
+    refresh: String,
+}
+
+/// The latest price of every coin the daemon keeps warm, by ID.
+type Prices = Arc<Mutex<HashMap<String, Price>>>;
+
+/// What one round of `keep_warm` did, for `ctl last-refresh`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Round {
+    id: String,
+    started: i64,
+    took_ms: u64,
+    fetched: Vec<String>,
+    /// the coins that failed, with why
+    failed: Vec<(String, String)>,
+}
+
+type LastRound = Arc<Mutex<Option<Round>>>;
+
+/// How often the daemon refreshes, which `ctl` can change while it runs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Schedule {
+    interval: Duration,
+    paused: bool,
+}
+
+/// The schedule, and what wakes `keep_warm` when `ctl` changes it, so a
+/// shorter interval or a resume doesn't wait out the old interval.
+type Control = Arc<(Mutex<Schedule>, Condvar)>;
+
+/// What `ctl status` answers, and the other commands once they're done.
+#[derive(Debug, Serialize, Deserialize)]
+struct Status {
+    paused: bool,
+    interval_secs: u64,
+    /// how many coins have a price to answer with
+    coins: usize,
+    last: Option<Round>,
+}
+
+/// What the daemon says about one of the tickers a query asked for.
+#[derive(Debug, Serialize, Deserialize)]
+struct Answer {
+    /// the ticker as it was asked for
+    arg: String,
+    /// the coin ID it stands for
+    id: String,
+    ticker: Option<Currency>,
+    error: Option<String>,
+    /// the refresh round the price is from
+    #[serde(default)]
+    refresh: Option<String>,
+}
+
+/// Where the daemon listens: `--socket`, or else the config's
+/// `daemon_socket`, or else `daemon.sock` in the cache directory.
+fn socket_path(matches: &ArgMatches, config: &Config) -> Result<PathBuf, Error> {
+    match matches
+        .value_of("socket")
+        .or(config.daemon_socket.as_deref())
+    {
+        Some(path) => Ok(PathBuf::from(path)),
+        None => Ok(dirs::cache_dir()?.join("daemon.sock")),
+    }
+}
+
+/// The ID of the group named `name` in a file like `/etc/group`, or
+/// `name` itself if it is a number.
+fn group_id(name: &str, groups: &str) -> Option<u32> {
+    if let Ok(gid) = name.parse() {
+        return Some(gid);
+    }
+    groups.lines().find_map(|line| {
+        let mut fields = line.split(':');
+        if fields.next()? != name {
+            return None;
+        }
+        fields.nth(1)?.parse().ok()
+    })
+}
+
+/// Hands the socket at `path` to `group`, readable and writable by its
+/// members, so other users on the machine can query the one daemon.
+fn share(path: &Path, group: &str) -> Result<(), Error> {
+    let groups = fs::read_to_string("/etc/group").unwrap_or_default();
+    let gid = group_id(group, &groups)
+        .ok_or_else(|| Error::Other(format!("There is no group {:?}.", group)))?;
+    chown(path, None, Some(gid))
+        .map_err(|err| Error::Other(format!("Could not give the socket to {}: {}", group, err)))?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o660))?;
+    Ok(())
+}
+
+/// Runs `daemon`: refreshes the given coins every `--interval`, and any
+/// coin a query asks for from then on, answering queries on the socket
+/// from memory. `alerts` are checked after every refresh round, the
+/// coins they value kept warm along with the rest.
+pub fn daemon(
+    matches: &ArgMatches,
+    config: &Config,
+    symbols: Symbols,
+    net: Net,
+    alerts: Alerts,
+) -> Result<(), Error> {
+    let interval =
+        Duration::from_secs(value_t!(matches, "interval", u64).unwrap_or_else(|e| e.exit()));
+    let overrides = config.symbols.clone();
+    let path = socket_path(matches, config)?;
+    if path.exists() {
+        if UnixStream::connect(&path).is_ok() {
+            return Err(Error::Other(format!(
+                "a daemon is already listening on {}",
+                path.display()
+            )));
+        }
+        // only a socket is safe to take, like one left behind by a daemon
+        // that didn't get to clean up, not a file named by mistake
+        if !fs::symlink_metadata(&path)?.file_type().is_socket() {
+            return Err(Error::Other(format!(
+                "{} is already there and isn't a socket, so it was left alone",
+                path.display()
+            )));
+        }
+        fs::remove_file(&path)?;
+    }
+    let listener = UnixListener::bind(&path)?;
+    if let Some(group) = matches.value_of("group").or(config.daemon_group.as_deref()) {
+        share(&path, group)?;
+        info!("shared with group {}", group);
+    }
+    info!("listening on {}", path.display());
+
+    let prices: Prices = Arc::default();
+    let last: LastRound = Arc::default();
+    let control: Control = Arc::new((
+        Mutex::new(Schedule {
+            interval,
+            paused: false,
+        }),
+        Condvar::new(),
+    ));
+    let mut names: Vec<String> = matches
+        .values_of("TICKER")
+        .map(|args| {
+            args.map(|arg| symbols.resolve(arg, &overrides).to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    for coin in alerts.priced_coins() {
+        if !names.iter().any(|name| name == coin) {
+            names.push(coin.to_string());
+        }
+    }
+    {
+        let (prices, last, control) = (prices.clone(), last.clone(), control.clone());
+        thread::spawn(move || keep_warm(&prices, &last, &control, names, alerts, &net));
+    }
+
+    let symbols = Arc::new(symbols);
+    let overrides = Arc::new(overrides);
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                warn!("could not accept a query: {}", err);
+                continue;
+            }
+        };
+        let (prices, last, control) = (prices.clone(), last.clone(), control.clone());
+        let (symbols, overrides) = (symbols.clone(), overrides.clone());
+        thread::spawn(move || {
+            let state = (&prices, &last, &control);
+            if let Err(err) = serve(stream, state, &symbols, &overrides, &net) {
+                debug!("query failed: {}", err);
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Refreshes `names`, and whatever queries have added since, as often
+/// as `control` says, checking `alerts` against each round. A failed
+/// refresh keeps the older price around.
+fn keep_warm(
+    prices: &Prices,
+    last: &LastRound,
+    control: &Control,
+    mut names: Vec<String>,
+    mut alerts: Alerts,
+    net: &Net,
+) {
+    loop {
+        let round = refresh(prices, &names, &mut alerts, net);
+        *last.lock().unwrap() = Some(round);
+        wait_for_round(control);
+        watch_queried(&mut names, prices);
+    }
+}
+
+/// Blocks until the next round is due: an interval after this one, by
+/// the latest interval `ctl` set, and not while the daemon is paused.
+/// Resuming after a pause longer than the interval refreshes at once.
+fn wait_for_round(control: &Control) {
+    let (schedule, changed) = &**control;
+    let start = Instant::now();
+    let mut schedule = schedule.lock().unwrap();
+    loop {
+        let elapsed = start.elapsed();
+        if !schedule.paused && elapsed >= schedule.interval {
+            return;
+        }
+        schedule = if schedule.paused {
+            changed.wait(schedule).unwrap()
+        } else {
+            let left = schedule.interval - elapsed;
+            changed.wait_timeout(schedule, left).unwrap().0
+        };
+    }
+}
+
+/// Carries out `ctl command`, one of `status`, `pause`, `resume` or
+/// `set-interval SECS`, and says how the daemon is doing afterwards.
+fn control(
+    command: &str,
+    prices: &Prices,
+    last: &LastRound,
+    control: &Control,
+) -> Result<Status, Error> {
+    let (schedule, changed) = &**control;
+    let mut schedule = schedule.lock().unwrap();
+    let mut words = command.split_whitespace();
+    match (words.next(), words.next()) {
+        (Some("status"), None) => {}
+        (Some("pause"), None) => schedule.paused = true,
+        (Some("resume"), None) => schedule.paused = false,
+        (Some("set-interval"), Some(secs)) => match secs.parse() {
+            Ok(secs) if secs > 0 => schedule.interval = Duration::from_secs(secs),
+            _ => {
+                return Err(Error::Parse(format!(
+                    "set-interval needs a number of seconds, not {:?}",
+                    secs
+                )))
+            }
+        },
+        _ => return Err(Error::Parse(format!("no such command: ctl {}", command))),
+    }
+    changed.notify_all();
+    Ok(Status {
+        paused: schedule.paused,
+        interval_secs: schedule.interval.as_secs(),
+        coins: prices.lock().unwrap().len(),
+        last: last.lock().unwrap().clone(),
+    })
+}
+
+/// One refresh round over `names`.
+fn refresh(prices: &Prices, names: &[String], alerts: &mut Alerts, net: &Net) -> Round {
+    let id = trace::begin();
+    let (started, clock) = (history::now(), Instant::now());
+    let mut fetched = Vec::new();
+    let mut failed = Vec::new();
+    for name in names {
+        match fetch(prices, name, net) {
+            Ok(ticker) => {
+                let fired = alerts.observe(&ticker);
+                alerts.notify(&fired);
+                fetched.push(name.clone());
+            }
+            Err(err) => {
+                warn!("{}", err);
+                failed.push((name.clone(), err.to_string()));
+            }
+        }
+    }
+    let fired = alerts.observe_portfolio();
+    alerts.notify(&fired);
+    let fired = alerts.observe_reminders(&time::now());
+    alerts.notify(&fired);
+    let fired = alerts.observe_events(history::now());
+    alerts.notify(&fired);
+    Round {
+        id,
+        started,
+        took_ms: clock.elapsed().as_millis() as u64,
+        fetched,
+        failed,
+    }
+}
+
+/// Adds the coins queries have asked for to `names`. Nothing leaves it,
+/// so a coin whose first fetch failed is tried again the next round.
+fn watch_queried(names: &mut Vec<String>, prices: &Prices) {
+    for name in prices.lock().unwrap().keys() {
+        if !names.contains(name) {
+            names.push(name.clone());
+        }
+    }
+}
+
+/// Fetches `name` as part of the refresh round running on this thread,
+/// or a round of its own.
+fn fetch(prices: &Prices, name: &str, net: &Net) -> Result<Currency, Error> {
+    let refresh = trace::current().unwrap_or_else(trace::begin);
+    let ticker = refresh_ticker(name, None, net)?;
+    prices.lock().unwrap().insert(
+        name.to_string(),
+        Price {
+            ticker: ticker.clone(),
+            refresh,
+        },
+    );
+    Ok(ticker)
+}
+
+/// Answers one query: a line of tickers separated by spaces, answered
+/// with a JSON array. A coin the daemon doesn't have yet is fetched on
+/// the spot, and kept warm from then on. `ctl last-refresh` asks about
+/// the last refresh round instead, and the other `ctl` commands about
+/// the daemon as a whole, or change its schedule.
+fn serve(
+    stream: UnixStream,
+    (prices, last, schedule): (&Prices, &LastRound, &Control),
+    symbols: &Symbols,
+    overrides: &HashMap<String, String>,
+    net: &Net,
+) -> Result<(), Error> {
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+    if line.trim() == "ctl last-refresh" {
+        let round = last.lock().unwrap().clone();
+        (&stream).write_all(serde_json::to_string(&round)?.as_bytes())?;
+        return Ok(());
+    }
+    if let Some(command) = line.trim().strip_prefix("ctl ") {
+        // the error goes back as text, which isn't a status
+        let answer = match control(command, prices, last, schedule) {
+            Ok(status) => serde_json::to_string(&status)?,
+            Err(err) => err.to_string(),
+        };
+        (&stream).write_all(answer.as_bytes())?;
+        return Ok(());
+    }
+    let answers: Vec<Answer> = line
+        .split_whitespace()
+        .map(|arg| {
+            let id = symbols.resolve(arg, overrides).to_string();
+            let known = prices.lock().unwrap().get(&id).cloned();
+            let result = match known {
+                Some(price) => Ok(price),
+                None => fetch(prices, &id, net).map(|ticker| Price {
+                    ticker,
+                    refresh: trace::current().unwrap_or_default(),
+                }),
+            };
+            Answer {
+                arg: arg.to_string(),
+                id,
+                error: result.as_ref().err().map(|err| err.to_string()),
+                refresh: result.as_ref().ok().map(|price| price.refresh.clone()),
+                ticker: result.ok().map(|price| price.ticker),
+            }
+        })
+        .collect();
+    (&stream).write_all(serde_json::to_string(&answers)?.as_bytes())?;
+    Ok(())
+}
+
+/// Sends `line` to the daemon on `path`, returning what it answers.
+fn send(path: &Path, line: &str) -> Result<String, Error> {
+    let mut stream = UnixStream::connect(path).map_err(|err| {
+        Error::Other(format!(
+            "no daemon is listening on {} ({}), start one with `cryptoticker daemon`",
+            path.display(),
+            err
+        ))
+    })?;
+    stream.write_all(format!("{}\n", line).as_bytes())?;
+    let mut content = String::new();
+    stream.read_to_string(&mut content)?;
+    Ok(content)
+}
+
+fn ask(path: &Path, args: &[&str]) -> Result<Vec<Answer>, Error> {
+    Ok(serde_json::from_str(&send(path, &args.join(" "))?)?)
+}
+
+/// Each answer's segment, the way the text output puts it on its line.
+fn render(answers: &[Answer], template: &Template, history: Option<&History>) -> Vec<String> {
+    let mut line = Vec::new();
+    for answer in answers {
+        if let Some(ref refresh) = answer.refresh {
+            info!("{} is from refresh {}", answer.arg, refresh);
+        }
+        match (&answer.ticker, &answer.error) {
+            (Some(ticker), _) => {
+                line.push(format!("{} ", template.render(&answer.id, ticker, history)))
+            }
+            (None, error) => {
+                warn!("{}", error.as_deref().unwrap_or("no answer"));
+                line.push(format!("{}:error ", answer.arg));
+            }
+        }
+    }
+    line
+}
+
+/// Runs `query`, printing the tickers the daemon has in memory the way a
+/// one-shot run would, so each consumer can ask with its own profile.
+/// Without tickers it asks for the coins on `--watchlist`, or the
+/// default watchlist, so users sharing a daemon each see their own.
+pub fn query(matches: &ArgMatches, config: &Config) -> Result<(), Error> {
+    let profile = matches.value_of("profile").unwrap();
+    if matches.value_of("format").is_none()
+        && profile != "text"
+        && !config.formats.contains_key(profile)
+    {
+        return Err(Error::Parse(format!("[formats] has no {:?}", profile)));
+    }
+    let template = config
+        .format(matches.value_of("format"), profile)
+        .parse::<Template>()
+        .map_err(|err| Error::Parse(format!("invalid --format: {}", err)))?
+        // checked when the config loaded
+        .with_fallbacks(&config.missing)
+        .unwrap()
+        .with_clock(config.clock());
+    let history = if template.needs_history() {
+        History::open().ok()
+    } else {
+        None
+    };
+    let (args, template) = match matches.values_of("TICKER") {
+        Some(args) => (args.map(String::from).collect(), template),
+        None => {
+            let path = matches
+                .value_of("watchlist")
+                .map(PathBuf::from)
+                .or_else(watchlist::default_path)
+                .ok_or_else(|| Error::Other("Name the coins, or keep a watchlist.".to_string()))?;
+            let entries = Watchlist::new(&path).reload()?.unwrap_or_default();
+            let coins: Vec<String> = entries.iter().map(|entry| entry.coin.clone()).collect();
+            (coins, template.with_aliases(watchlist::aliases(&entries)))
+        }
+    };
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let answers = ask(&socket_path(matches, config)?, &args)?;
+    let frame = Frame::of(matches);
+    print!(
+        "{}",
+        frame.wrap(&frame.join(&render(&answers, &template, history.as_ref())))
+    );
+    Ok(())
+}
+
+/// Like `refresh 6710f2a0-3 started 2024-10-17T11:30:40Z, took 840ms`,
+/// then what it fetched and what failed.
+fn describe(round: &Round) -> String {
+    let mut out = format!(
+        "refresh {} started {}, took {}ms\n",
+        round.id,
+        history::timestamp(round.started),
+        round.took_ms
+    );
+    if !round.fetched.is_empty() {
+        out.push_str(&format!("fetched {}\n", round.fetched.join(", ")));
+    }
+    for (name, err) in &round.failed {
+        out.push_str(&format!("failed {}: {}\n", name, err));
+    }
+    out
+}
+
+/// Like `refreshing 3 coins every 300s`, then the last round.
+fn describe_status(status: &Status) -> String {
+    let mut out = format!(
+        "{} {} coins every {}s\n",
+        if status.paused {
+            "paused, not refreshing"
+        } else {
+            "refreshing"
+        },
+        status.coins,
+        status.interval_secs
+    );
+    match status.last {
+        Some(ref round) => out.push_str(&describe(round)),
+        None => out.push_str("the daemon hasn't finished a refresh yet\n"),
+    }
+    out
+}
+
+/// Runs `ctl`, asking the daemon about itself, or pausing, resuming or
+/// rescheduling its refreshes.
+pub fn ctl(matches: &ArgMatches, config: &Config) -> Result<(), Error> {
+    let command = matches.value_of("COMMAND").unwrap();
+    let line = match command {
+        "set-interval" => {
+            let secs = value_t!(matches, "SECS", u64).unwrap_or_else(|e| e.exit());
+            if secs == 0 {
+                return Err(Error::Other(
+                    "set-interval needs at least one second".to_string(),
+                ));
+            }
+            format!("ctl set-interval {}", secs)
+        }
+        _ => format!("ctl {}", command),
+    };
+    let content = send(&socket_path(matches, config)?, &line)?;
+    if command == "last-refresh" {
+        match serde_json::from_str::<Option<Round>>(&content)? {
+            Some(round) => print!("{}", describe(&round)),
+            None => println!("the daemon hasn't finished a refresh yet"),
+        }
+        return Ok(());
+    }
+    match serde_json::from_str::<Status>(&content) {
+        Ok(status) => print!("{}", describe_status(&status)),
+        // an error from the daemon, or a daemon too old to know `command`
+        Err(_) => return Err(Error::Other(format!("the daemon answered: {}", content))),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    #[test]
+    fn answers_from_memory() {
+        let dir = env::temp_dir().join(format!("cryptoticker-test-daemon-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("daemon.sock");
+        let listener = UnixListener::bind(&path).unwrap();
+
+        let mut tickers: Vec<Currency> =
+            serde_json::from_str(include_str!("../tests/fixtures/ticker.json")).unwrap();
+        let bitcoin = tickers.remove(0);
+        let prices: Prices = Arc::default();
+        prices.lock().unwrap().insert(
+            "bitcoin".to_string(),
+            Price {
+                ticker: bitcoin,
+                refresh: "6710f2a0-3".to_string(),
+            },
+        );
+        let last: LastRound = Arc::default();
+        let mut overrides = HashMap::new();
+        overrides.insert("btc".to_string(), "bitcoin".to_string());
+        let server = thread::spawn(move || {
+            let stream = listener.accept().unwrap().0;
+            let control: Control = Arc::new((
+                Mutex::new(Schedule {
+                    interval: Duration::from_secs(60),
+                    paused: false,
+                }),
+                Condvar::new(),
+            ));
+            serve(
+                stream,
+                (&prices, &last, &control),
+                &Symbols::default(),
+                &overrides,
+                &Net::default(),
+            )
+            .unwrap();
+        });
+
+        let answers = ask(&path, &["btc"]).unwrap();
+        server.join().unwrap();
+        assert_eq!(answers[0].id, "bitcoin");
+        assert_eq!(answers[0].refresh.as_deref(), Some("6710f2a0-3"));
+        assert_eq!(
+            render(&answers, &Template::default(), None),
+            ["btc:2545.19 "]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn retries_coins_that_failed() {
+        use net::Retry;
+        use std::net::TcpListener;
+
+        // down for the first round, up for the second
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let api = format!("http://{}/", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let body = include_str!("../tests/fixtures/ticker.json");
+            for status in &["503 Service Unavailable", "200 OK"] {
+                let mut stream = listener.accept().unwrap().0;
+                let mut request = [0; 1024];
+                let _ = stream.read(&mut request);
+                let body = if status.starts_with("200") { body } else { "" };
+                write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                )
+                .unwrap();
+            }
+        });
+        let net = Net {
+            retry: Retry {
+                attempts: 0,
+                ..Retry::default()
+            },
+            api: Box::leak(api.into_boxed_str()),
+            ..Net::default()
+        };
+        let prices: Prices = Arc::default();
+        let mut alerts = Alerts::new(&[], None).unwrap();
+        let mut names = vec!["bitcoin".to_string()];
+
+        let round = refresh(&prices, &names, &mut alerts, &net);
+        assert_eq!(round.failed.len(), 1);
+        watch_queried(&mut names, &prices);
+        assert_eq!(names, ["bitcoin"]);
+        let round = refresh(&prices, &names, &mut alerts, &net);
+        assert_eq!(round.fetched, ["bitcoin"]);
+        server.join().unwrap();
+
+        // as if a query had asked for it
+        let bitcoin = prices.lock().unwrap()["bitcoin"].clone();
+        prices
+            .lock()
+            .unwrap()
+            .insert("ethereum".to_string(), bitcoin);
+        watch_queried(&mut names, &prices);
+        assert_eq!(names, ["bitcoin", "ethereum"]);
+    }
+
+    #[test]
+    fn pauses_and_reschedules() {
+        let prices: Prices = Arc::default();
+        let last: LastRound = Arc::default();
+        let schedule: Control = Arc::new((
+            Mutex::new(Schedule {
+                interval: Duration::from_secs(300),
+                paused: false,
+            }),
+            Condvar::new(),
+        ));
+
+        let status = control("pause", &prices, &last, &schedule).unwrap();
+        assert!(status.paused);
+        assert_eq!(
+            describe_status(&status),
+            "paused, not refreshing 0 coins every 300s\n\
+             the daemon hasn't finished a refresh yet\n"
+        );
+        let status = control("set-interval 30", &prices, &last, &schedule).unwrap();
+        assert_eq!((status.paused, status.interval_secs), (true, 30));
+        assert!(control("set-interval 0", &prices, &last, &schedule).is_err());
+        assert!(control("reboot", &prices, &last, &schedule).is_err());
+
+        // a paused daemon waits until it's resumed, however short the interval
+        schedule.0.lock().unwrap().interval = Duration::from_millis(1);
+        let resumer = {
+            let (prices, last, schedule) = (prices.clone(), last.clone(), schedule.clone());
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(50));
+                control("resume", &prices, &last, &schedule).unwrap()
+            })
+        };
+        let waited = Instant::now();
+        wait_for_round(&schedule);
+        assert!(waited.elapsed() >= Duration::from_millis(50));
+        assert!(!resumer.join().unwrap().paused);
+    }
+
+    #[test]
+    fn finds_groups() {
+        let groups = "root:x:0:\ncrypto:x:1001:alice,bob\nstaff:x:50:\n";
+        assert_eq!(group_id("crypto", groups), Some(1001));
+        assert_eq!(group_id("staff", groups), Some(50));
+        assert_eq!(group_id("2000", groups), Some(2000));
+        assert_eq!(group_id("wheel", groups), None);
+    }
+
+    #[test]
+    fn describes_rounds() {
+        let round = Round {
+            id: "6710f2a0-3".to_string(),
+            started: 1_729_164_960,
+            took_ms: 840,
+            fetched: vec!["bitcoin".to_string(), "ethereum".to_string()],
+            failed: vec![("solana".to_string(), "The API answered 502.".to_string())],
+        };
+        assert_eq!(
+            describe(&round),
+            "refresh 6710f2a0-3 started 2024-10-17T11:36:00Z, took 840ms\n\
+             fetched bitcoin, ethereum\n\
+             failed solana: The API answered 502.\n"
+        );
+    }
+}
@@ -0,0 +1,73 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use app_dirs::*;
+
+use errors::Error;
+use termux;
+
+use super::APP_INFO;
+
+/// Overrides the cache directory. `--cache-dir` sets it too, so the
+/// background refreshes cryptoticker spawns inherit the override.
+pub const CACHE_DIR_VAR: &str = "CRYPTOTICKER_CACHE_DIR";
+/// Overrides the directory `config.toml` is read from.
+pub const CONFIG_DIR_VAR: &str = "CRYPTOTICKER_CONFIG_DIR";
+
+/// The cache directory, created if it doesn't exist yet. Under Termux
+/// it's the one Android gives the app.
+pub fn cache_dir() -> Result<PathBuf, Error> {
+    let dir = match env::var_os(CACHE_DIR_VAR)
+        .map(PathBuf::from)
+        .or_else(|| termux::cache_dir().filter(|_| termux::active()))
+    {
+        Some(dir) => fs::create_dir_all(&dir)
+            .map(|_| dir)
+            .map_err(|err| err.to_string()),
+        None => app_root(AppDataType::UserCache, &APP_INFO).map_err(|err| err.to_string()),
+    };
+    dir.map_err(|err| {
+        Error::Cache(format!(
+            "Could not find or create the cache directory: {}",
+            err
+        ))
+    })
+}
+
+/// The data directory, where what can't be fetched again is kept, like
+/// the history.
+pub fn data_dir() -> Result<PathBuf, Error> {
+    app_root(AppDataType::UserData, &APP_INFO).map_err(|err| {
+        Error::Other(format!(
+            "Could not find or create the data directory: {}",
+            err
+        ))
+    })
+}
+
+/// The config directory. It isn't created, since nothing is ever written
+/// to it.
+pub fn config_dir() -> Option<PathBuf> {
+    match env::var_os(CONFIG_DIR_VAR) {
+        Some(dir) => Some(PathBuf::from(dir)),
+        None => get_app_root(AppDataType::UserConfig, &APP_INFO).ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process;
+
+    #[test]
+    fn overrides_create_the_cache_directory() {
+        let root = env::temp_dir().join(format!("cryptoticker-test-dirs-{}", process::id()));
+        let dir = root.join("nested");
+        env::set_var(CACHE_DIR_VAR, &dir);
+        assert_eq!(cache_dir().unwrap(), dir);
+        assert!(dir.is_dir());
+        env::remove_var(CACHE_DIR_VAR);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,440 @@
+use std::str::FromStr;
+use std::thread::sleep;
+use std::time::Duration;
+
+use errors::Error;
+
+/// Somewhere display bytes go: an I2C device, or a recording in tests.
+pub trait Bus {
+    fn send(&mut self, bytes: &[u8]) -> Result<(), Error>;
+}
+
+#[cfg(feature = "display")]
+impl Bus for ::i2cdev::linux::LinuxI2CDevice {
+    fn send(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        use i2cdev::core::I2CDevice;
+        self.write(bytes)
+            .map_err(|err| Error::Other(format!("could not write to the display: {}", err)))
+    }
+}
+
+/// The display controllers `--display` drives.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Controller {
+    /// a character LCD behind the usual PCF8574 I2C backpack
+    Hd44780,
+    /// a 128x64 OLED, drawn with a 5x7 font
+    Ssd1306,
+}
+
+/// A display as `--display` describes it, like
+/// `hd44780:/dev/i2c-1:0x27:20x4` or just `ssd1306`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spec {
+    pub controller: Controller,
+    pub device: String,
+    pub address: u16,
+    pub cols: usize,
+    pub rows: usize,
+}
+
+impl FromStr for Spec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Spec, String> {
+        let mut parts = s.split(':');
+        let (controller, address, cols, rows) = match parts.next() {
+            Some("hd44780") => (Controller::Hd44780, 0x27, 16, 2),
+            // 6 pixels a character, 8 a row
+            Some("ssd1306") => (Controller::Ssd1306, 0x3c, 21, 8),
+            _ => return Err(format!("{:?} should start with hd44780 or ssd1306", s)),
+        };
+        let device = parts
+            .next()
+            .filter(|device| !device.is_empty())
+            .unwrap_or("/dev/i2c-1")
+            .to_string();
+        let address = match parts.next() {
+            Some(hex) => u16::from_str_radix(hex.trim_start_matches("0x"), 16)
+                .map_err(|_| format!("{:?} should be an I2C address like 0x27", hex))?,
+            None => address,
+        };
+        let (cols, rows) = match parts.next() {
+            Some(size) if controller == Controller::Hd44780 => {
+                let mut size = size.split('x').map(str::parse::<usize>);
+                match (size.next(), size.next()) {
+                    (Some(Ok(cols)), Some(Ok(rows))) if cols > 0 && (1..=4).contains(&rows) => {
+                        (cols, rows)
+                    }
+                    _ => return Err(format!("{:?} should be a size like 16x2 or 20x4", s)),
+                }
+            }
+            Some(_) => return Err("an ssd1306 is always 128x64".to_string()),
+            None => (cols, rows),
+        };
+        Ok(Spec {
+            controller,
+            device,
+            address,
+            cols,
+            rows,
+        })
+    }
+}
+
+/// Fills `rows` rows of `cols` characters with `segments`, as many to a
+/// row as fit. What doesn't fit is cut off; these screens are tiny.
+pub fn layout(segments: &[&str], cols: usize, rows: usize) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    for segment in segments {
+        let segment: String = segment
+            .trim()
+            .chars()
+            .map(|c| if c.is_ascii() { c } else { '?' })
+            .take(cols)
+            .collect();
+        match lines.last_mut() {
+            Some(line) if line.len() + 1 + segment.len() <= cols => {
+                line.push(' ');
+                line.push_str(&segment);
+            }
+            _ => lines.push(segment),
+        }
+    }
+    lines.resize(rows, String::new());
+    lines
+}
+
+/// HD44780 lines start at these addresses, for up to four rows.
+const ROW_OFFSETS: [u8; 4] = [0x00, 0x40, 0x14, 0x54];
+
+/// The PCF8574's pins: the LCD's register select, enable and backlight,
+/// with the data nibble on the top four.
+const RS: u8 = 0x01;
+const ENABLE: u8 = 0x04;
+const BACKLIGHT: u8 = 0x08;
+
+/// Clocks one 4-bit nibble into the LCD by pulsing enable.
+fn nibble(bus: &mut dyn Bus, nibble: u8, rs: u8) -> Result<(), Error> {
+    let byte = (nibble << 4) | BACKLIGHT | rs;
+    bus.send(&[byte | ENABLE])?;
+    bus.send(&[byte])
+}
+
+fn lcd_byte(bus: &mut dyn Bus, byte: u8, rs: u8) -> Result<(), Error> {
+    nibble(bus, byte >> 4, rs)?;
+    nibble(bus, byte & 0x0f, rs)
+}
+
+fn hd44780_init(bus: &mut dyn Bus) -> Result<(), Error> {
+    // from whatever mode it was in, into 4-bit mode, as the datasheet says
+    sleep(Duration::from_millis(50));
+    for &wait in &[5, 1, 1] {
+        nibble(bus, 0x03, 0)?;
+        sleep(Duration::from_millis(wait));
+    }
+    nibble(bus, 0x02, 0)?;
+    // two lines of 5x8 dots, display on without a cursor, left to right
+    for &command in &[0x28, 0x0c, 0x06, 0x01] {
+        lcd_byte(bus, command, 0)?;
+    }
+    sleep(Duration::from_millis(2));
+    Ok(())
+}
+
+fn hd44780_show(bus: &mut dyn Bus, lines: &[String], cols: usize) -> Result<(), Error> {
+    for (row, line) in lines.iter().enumerate().take(ROW_OFFSETS.len()) {
+        lcd_byte(bus, 0x80 | ROW_OFFSETS[row], 0)?;
+        // padded, so what was there before is overwritten
+        for c in format!("{:<1$}", line, cols).bytes() {
+            lcd_byte(bus, c, RS)?;
+        }
+    }
+    Ok(())
+}
+
+/// The printable ASCII characters from space on, 5 columns each, the
+/// lowest bit at the top.
+const FONT: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x5f, 0x00, 0x00],
+    [0x00, 0x07, 0x00, 0x07, 0x00],
+    [0x14, 0x7f, 0x14, 0x7f, 0x14],
+    [0x24, 0x2a, 0x7f, 0x2a, 0x12],
+    [0x23, 0x13, 0x08, 0x64, 0x62],
+    [0x36, 0x49, 0x55, 0x22, 0x50],
+    [0x00, 0x05, 0x03, 0x00, 0x00],
+    [0x00, 0x1c, 0x22, 0x41, 0x00],
+    [0x00, 0x41, 0x22, 0x1c, 0x00],
+    [0x08, 0x2a, 0x1c, 0x2a, 0x08],
+    [0x08, 0x08, 0x3e, 0x08, 0x08],
+    [0x00, 0x50, 0x30, 0x00, 0x00],
+    [0x08, 0x08, 0x08, 0x08, 0x08],
+    [0x00, 0x60, 0x60, 0x00, 0x00],
+    [0x20, 0x10, 0x08, 0x04, 0x02],
+    [0x3e, 0x51, 0x49, 0x45, 0x3e],
+    [0x00, 0x42, 0x7f, 0x40, 0x00],
+    [0x42, 0x61, 0x51, 0x49, 0x46],
+    [0x21, 0x41, 0x45, 0x4b, 0x31],
+    [0x18, 0x14, 0x12, 0x7f, 0x10],
+    [0x27, 0x45, 0x45, 0x45, 0x39],
+    [0x3c, 0x4a, 0x49, 0x49, 0x30],
+    [0x01, 0x71, 0x09, 0x05, 0x03],
+    [0x36, 0x49, 0x49, 0x49, 0x36],
+    [0x06, 0x49, 0x49, 0x29, 0x1e],
+    [0x00, 0x36, 0x36, 0x00, 0x00],
+    [0x00, 0x56, 0x36, 0x00, 0x00],
+    [0x08, 0x14, 0x22, 0x41, 0x00],
+    [0x14, 0x14, 0x14, 0x14, 0x14],
+    [0x00, 0x41, 0x22, 0x14, 0x08],
+    [0x02, 0x01, 0x51, 0x09, 0x06],
+    [0x32, 0x49, 0x79, 0x41, 0x3e],
+    [0x7e, 0x11, 0x11, 0x11, 0x7e],
+    [0x7f, 0x49, 0x49, 0x49, 0x36],
+    [0x3e, 0x41, 0x41, 0x41, 0x22],
+    [0x7f, 0x41, 0x41, 0x22, 0x1c],
+    [0x7f, 0x49, 0x49, 0x49, 0x41],
+    [0x7f, 0x09, 0x09, 0x09, 0x01],
+    [0x3e, 0x41, 0x49, 0x49, 0x7a],
+    [0x7f, 0x08, 0x08, 0x08, 0x7f],
+    [0x00, 0x41, 0x7f, 0x41, 0x00],
+    [0x20, 0x40, 0x41, 0x3f, 0x01],
+    [0x7f, 0x08, 0x14, 0x22, 0x41],
+    [0x7f, 0x40, 0x40, 0x40, 0x40],
+    [0x7f, 0x02, 0x0c, 0x02, 0x7f],
+    [0x7f, 0x04, 0x08, 0x10, 0x7f],
+    [0x3e, 0x41, 0x41, 0x41, 0x3e],
+    [0x7f, 0x09, 0x09, 0x09, 0x06],
+    [0x3e, 0x41, 0x51, 0x21, 0x5e],
+    [0x7f, 0x09, 0x19, 0x29, 0x46],
+    [0x46, 0x49, 0x49, 0x49, 0x31],
+    [0x01, 0x01, 0x7f, 0x01, 0x01],
+    [0x3f, 0x40, 0x40, 0x40, 0x3f],
+    [0x1f, 0x20, 0x40, 0x20, 0x1f],
+    [0x3f, 0x40, 0x38, 0x40, 0x3f],
+    [0x63, 0x14, 0x08, 0x14, 0x63],
+    [0x07, 0x08, 0x70, 0x08, 0x07],
+    [0x61, 0x51, 0x49, 0x45, 0x43],
+    [0x00, 0x7f, 0x41, 0x41, 0x00],
+    [0x02, 0x04, 0x08, 0x10, 0x20],
+    [0x00, 0x41, 0x41, 0x7f, 0x00],
+    [0x04, 0x02, 0x01, 0x02, 0x04],
+    [0x40, 0x40, 0x40, 0x40, 0x40],
+    [0x00, 0x01, 0x02, 0x04, 0x00],
+    [0x20, 0x54, 0x54, 0x54, 0x78],
+    [0x7f, 0x48, 0x44, 0x44, 0x38],
+    [0x38, 0x44, 0x44, 0x44, 0x20],
+    [0x38, 0x44, 0x44, 0x48, 0x7f],
+    [0x38, 0x54, 0x54, 0x54, 0x18],
+    [0x08, 0x7e, 0x09, 0x01, 0x02],
+    [0x0c, 0x52, 0x52, 0x52, 0x3e],
+    [0x7f, 0x08, 0x04, 0x04, 0x78],
+    [0x00, 0x44, 0x7d, 0x40, 0x00],
+    [0x20, 0x40, 0x44, 0x3d, 0x00],
+    [0x7f, 0x10, 0x28, 0x44, 0x00],
+    [0x00, 0x41, 0x7f, 0x40, 0x00],
+    [0x7c, 0x04, 0x18, 0x04, 0x78],
+    [0x7c, 0x08, 0x04, 0x04, 0x78],
+    [0x38, 0x44, 0x44, 0x44, 0x38],
+    [0x7c, 0x14, 0x14, 0x14, 0x08],
+    [0x08, 0x14, 0x14, 0x18, 0x7c],
+    [0x7c, 0x08, 0x04, 0x04, 0x08],
+    [0x48, 0x54, 0x54, 0x54, 0x20],
+    [0x04, 0x3f, 0x44, 0x40, 0x20],
+    [0x3c, 0x40, 0x40, 0x20, 0x7c],
+    [0x1c, 0x20, 0x40, 0x20, 0x1c],
+    [0x3c, 0x40, 0x30, 0x40, 0x3c],
+    [0x44, 0x28, 0x10, 0x28, 0x44],
+    [0x0c, 0x50, 0x50, 0x50, 0x3c],
+    [0x44, 0x64, 0x54, 0x4c, 0x44],
+    [0x00, 0x08, 0x36, 0x41, 0x00],
+    [0x00, 0x00, 0x7f, 0x00, 0x00],
+    [0x00, 0x41, 0x36, 0x08, 0x00],
+    [0x02, 0x01, 0x02, 0x04, 0x02],
+];
+
+const OLED_WIDTH: usize = 128;
+const OLED_PAGES: usize = 8;
+
+/// SSD1306 commands go after a 0x00 control byte, pixels after 0x40.
+fn oled_commands(bus: &mut dyn Bus, commands: &[u8]) -> Result<(), Error> {
+    for &command in commands {
+        bus.send(&[0x00, command])?;
+    }
+    Ok(())
+}
+
+fn ssd1306_init(bus: &mut dyn Bus) -> Result<(), Error> {
+    oled_commands(
+        bus,
+        &[
+            0xae, // off while it's set up
+            0xd5, 0x80, // clock
+            0xa8, 0x3f, // 64 rows
+            0xd3, 0x00, // no offset
+            0x40, // start at the first line
+            0x8d, 0x14, // charge pump on
+            0x20, 0x00, // horizontal addressing
+            0xa1, 0xc8, // the right way up
+            0xda, 0x12, // COM pins for 128x64
+            0x81, 0xcf, // contrast
+            0xd9, 0xf1, // precharge
+            0xdb, 0x40, // VCOMH
+            0xa4, 0xa6, // show RAM, not inverted
+            0xaf, // on
+        ],
+    )
+}
+
+/// `lines` drawn into the OLED's memory, a page of 8 pixel rows per line.
+fn frame(lines: &[String]) -> Vec<u8> {
+    let mut frame = vec![0; OLED_WIDTH * OLED_PAGES];
+    for (page, line) in lines.iter().enumerate().take(OLED_PAGES) {
+        for (col, c) in line.bytes().enumerate().take(OLED_WIDTH / 6) {
+            let glyph = match c {
+                0x20..=0x7e => &FONT[(c - 0x20) as usize],
+                _ => &FONT[(b'?' - 0x20) as usize],
+            };
+            let start = page * OLED_WIDTH + col * 6;
+            frame[start..start + 5].copy_from_slice(glyph);
+        }
+    }
+    frame
+}
+
+fn ssd1306_show(bus: &mut dyn Bus, lines: &[String]) -> Result<(), Error> {
+    oled_commands(
+        bus,
+        &[
+            0x21,
+            0,
+            (OLED_WIDTH - 1) as u8,
+            0x22,
+            0,
+            (OLED_PAGES - 1) as u8,
+        ],
+    )?;
+    for chunk in frame(lines).chunks(16) {
+        let mut bytes = vec![0x40];
+        bytes.extend_from_slice(chunk);
+        bus.send(&bytes)?;
+    }
+    Ok(())
+}
+
+/// A display `--display` writes each round to.
+pub struct Display {
+    spec: Spec,
+    bus: Box<dyn Bus + Send>,
+    ready: bool,
+}
+
+impl Display {
+    /// Opens the I2C device `spec` names. The display is set up on the
+    /// first `show`, and again after a write fails, in case it lost power.
+    #[cfg(feature = "display")]
+    pub fn open(spec: Spec) -> Result<Display, Error> {
+        let bus =
+            ::i2cdev::linux::LinuxI2CDevice::new(&spec.device, spec.address).map_err(|err| {
+                Error::Other(format!(
+                    "could not open the display on {}: {}",
+                    spec.device, err
+                ))
+            })?;
+        Ok(Display::with_bus(spec, Box::new(bus)))
+    }
+
+    #[cfg(not(feature = "display"))]
+    pub fn open(_spec: Spec) -> Result<Display, Error> {
+        Err(Error::Other(
+            "--display needs cryptoticker built with --features display".to_string(),
+        ))
+    }
+
+    pub fn with_bus(spec: Spec, bus: Box<dyn Bus + Send>) -> Display {
+        Display {
+            spec,
+            bus,
+            ready: false,
+        }
+    }
+
+    /// Shows `segments`, as many to a row as fit.
+    pub fn show(&mut self, segments: &[&str]) -> Result<(), Error> {
+        let lines = layout(segments, self.spec.cols, self.spec.rows);
+        let bus = &mut *self.bus;
+        let spec = &self.spec;
+        let ready = self.ready;
+        let shown = match spec.controller {
+            Controller::Hd44780 if ready => hd44780_show(bus, &lines, spec.cols),
+            Controller::Hd44780 => {
+                hd44780_init(bus).and_then(|_| hd44780_show(bus, &lines, spec.cols))
+            }
+            Controller::Ssd1306 if ready => ssd1306_show(bus, &lines),
+            Controller::Ssd1306 => ssd1306_init(bus).and_then(|_| ssd1306_show(bus, &lines)),
+        };
+        self.ready = shown.is_ok();
+        shown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct Recording(Arc<Mutex<Vec<Vec<u8>>>>);
+
+    impl Bus for Recording {
+        fn send(&mut self, bytes: &[u8]) -> Result<(), Error> {
+            self.0.lock().unwrap().push(bytes.to_vec());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn parses_specs() {
+        let spec: Spec = "hd44780:/dev/i2c-0:0x3f:20x4".parse().unwrap();
+        assert_eq!(spec.device, "/dev/i2c-0");
+        assert_eq!(spec.address, 0x3f);
+        assert_eq!((spec.cols, spec.rows), (20, 4));
+        let spec: Spec = "ssd1306".parse().unwrap();
+        assert_eq!((spec.device.as_str(), spec.address), ("/dev/i2c-1", 0x3c));
+        assert!("hd44780::0x27:16x9".parse::<Spec>().is_err());
+        assert!("st7735".parse::<Spec>().is_err());
+    }
+
+    #[test]
+    fn lays_out_rows() {
+        assert_eq!(
+            layout(&["btc:2545.19 ", "eth:300.1 ", "ltc:40.5 "], 20, 2),
+            vec!["btc:2545.19", "eth:300.1 ltc:40.5"]
+        );
+        assert_eq!(layout(&["btc:2545.19*→"], 8, 1), vec!["btc:2545"]);
+        assert_eq!(layout(&["btc:1→2"], 16, 2), vec!["btc:1?2", ""]);
+    }
+
+    #[test]
+    fn writes_characters_in_nibbles() {
+        let recording = Recording::default();
+        let spec: Spec = "hd44780".parse().unwrap();
+        let mut display = Display::with_bus(spec, Box::new(recording.clone()));
+        display.show(&["A"]).unwrap();
+        let sent = recording.0.lock().unwrap();
+        // after the 4 nibbles and 4 commands of setup: the first row's
+        // address, then 'A' = 0x41 as data, each nibble written with
+        // enable high and then low
+        assert_eq!(
+            sent[24..32].concat(),
+            vec![0x8c, 0x88, 0x0c, 0x08, 0x4d, 0x49, 0x1d, 0x19]
+        );
+    }
+
+    #[test]
+    fn draws_frames() {
+        let frame = frame(&["!".to_string(), String::new()]);
+        assert_eq!(frame[..6], [0x00, 0x00, 0x5f, 0x00, 0x00, 0x00]);
+        assert!(frame[6..].iter().all(|&byte| byte == 0));
+    }
+}
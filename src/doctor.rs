@@ -0,0 +1,76 @@
+use std::path::Path;
+use std::time::Instant;
+
+use clap::ArgMatches;
+
+use config;
+use dirs;
+use errors::Error;
+use history::{self, parse_span};
+use incidents::{self, Log};
+use net::Net;
+
+/// Runs `doctor`: where cryptoticker reads and writes, whether the
+/// ticker API answers, and how often providers have failed lately.
+/// With `--incidents`, every provider's failures are summarized
+/// instead. Returns whether everything checked out.
+pub fn doctor(matches: &ArgMatches, config_path: Option<&Path>, net: &Net) -> Result<bool, Error> {
+    let span = matches.value_of("since").unwrap();
+    let since = history::now() - parse_span(span).map_err(Error::Parse)?;
+    let incidents = Log::open()?.since(since)?;
+    if matches.is_present("incidents") {
+        print!("{}", incidents::report(&incidents, span));
+        return Ok(true);
+    }
+
+    let config = match config_path
+        .map(Path::to_path_buf)
+        .or_else(config::default_path)
+    {
+        Some(ref path) if path.exists() => path.display().to_string(),
+        Some(ref path) => format!("{} (none, using defaults)", path.display()),
+        None => "(no config directory, using defaults)".to_string(),
+    };
+    println!("{:<10}  {}", "config", config);
+    let cache = dirs::cache_dir();
+    match cache {
+        Ok(ref dir) => println!("{:<10}  {}", "cache", dir.display()),
+        Err(ref err) => println!("{:<10}  {}", "cache", err),
+    }
+
+    let url = format!("{}bitcoin/", net.api);
+    let start = Instant::now();
+    let answered = match net.get(&url) {
+        Ok(resp) => {
+            println!(
+                "{:<10}  {} answered {} in {}ms",
+                "ticker API",
+                net.api,
+                resp.status,
+                start.elapsed().as_millis()
+            );
+            resp.status.is_success()
+        }
+        Err(err) => {
+            println!("{:<10}  {}", "ticker API", err);
+            false
+        }
+    };
+
+    let failures = incidents
+        .iter()
+        .filter(|incident| incident.class != incidents::RECOVERED)
+        .count();
+    println!(
+        "{:<10}  {} failed requests in the last {}{}",
+        "incidents",
+        failures,
+        span,
+        if failures > 0 {
+            "; see doctor --incidents"
+        } else {
+            ""
+        }
+    );
+    Ok(cache.is_ok() && answered)
+}
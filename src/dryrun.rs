@@ -0,0 +1,312 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use aggregate::Aggregate;
+use cache::Cache;
+use contract::Contract;
+use fng;
+use gas;
+use history::format_span;
+use incidents;
+use mining;
+use net::Net;
+use news::News;
+use symbols::{self, Symbols};
+
+/// A request a round would send, or the cache answering instead.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    /// the ticker argument it's for
+    pub ticker: String,
+    /// who is asked, like `coinmarketcap`
+    pub provider: String,
+    /// what is sent, or `None` when the cache answers
+    pub request: Option<String>,
+    /// why it is or isn't sent
+    pub cache: String,
+}
+
+impl Step {
+    fn get(ticker: &str, url: String, cache: &str) -> Step {
+        Step {
+            ticker: ticker.to_string(),
+            provider: incidents::provider(&url),
+            request: Some(format!("GET {}", url)),
+            cache: cache.to_string(),
+        }
+    }
+
+    /// The same step, with the request left off unless it is `sent`.
+    fn sent(mut self, sent: bool) -> Step {
+        if !sent {
+            self.request = None;
+        }
+        self
+    }
+
+    /// What a pseudo-ticker with its own cache file would do.
+    fn cached_in(ticker: &str, url: String, file: &str, offline: bool) -> Step {
+        if offline {
+            Step::get(ticker, url, &format!("{}, however old", file)).sent(false)
+        } else {
+            Step::get(ticker, url, &format!("unless {} is recent", file))
+        }
+    }
+}
+
+fn age(age: Duration) -> String {
+    format_span(age.as_secs() as i64)
+}
+
+/// Whether coin `name` would be fetched from `cache`, and why. Interval
+/// mode runs without one.
+fn decide(name: &str, cache: Option<&Cache>, stale: bool) -> (bool, String) {
+    let cache = match cache {
+        Some(cache) => cache,
+        None => return (true, "interval mode fetches every round".to_string()),
+    };
+    let entry = cache.backend.get(name).map(|(_, age)| age);
+    let cold = match (entry, cache.ttl) {
+        (None, _) if cache.offline => {
+            return (false, "not cached, and --offline is set".to_string())
+        }
+        (None, _) => return (true, "not cached".to_string()),
+        (Some(_), None) => return (false, "cached, never goes cold".to_string()),
+        (Some(stored), Some(ttl)) if stored < ttl => {
+            return (false, format!("fresh, {} left", age(ttl - stored)))
+        }
+        (Some(stored), Some(_)) => format!("cold, {} old", age(stored)),
+    };
+    if cache.offline {
+        (false, format!("{}, shown anyway", cold))
+    } else if stale {
+        (
+            true,
+            format!("{}, shown and refreshed in the background", cold),
+        )
+    } else if !cache.backend.validators(name).is_empty() {
+        (true, format!("{}, revalidated", cold))
+    } else {
+        (true, cold)
+    }
+}
+
+/// The steps for coin `name`, priced by the ticker API and, aggregated,
+/// the exchanges too.
+fn coin(
+    name: &str,
+    cache: Option<&Cache>,
+    stale: bool,
+    aggregate: Option<&Aggregate>,
+    symbols: &Symbols,
+    net: &Net,
+) -> Vec<Step> {
+    let (fetched, why) = decide(name, cache, stale);
+    let mut steps = vec![Step::get(name, format!("{}{}", net.api, name), &why).sent(fetched)];
+    let offline = cache.is_some_and(|cache| cache.offline);
+    if let Some(aggregate) = aggregate.filter(|_| !offline) {
+        let symbol = symbols.symbol_of(name).unwrap_or(name).to_uppercase();
+        for exchange in aggregate.exchanges() {
+            steps.push(Step {
+                ticker: name.to_string(),
+                provider: exchange.to_string(),
+                request: Some(format!("{}/USD spot price", symbol)),
+                cache: "never cached, for --aggregate".to_string(),
+            });
+        }
+    }
+    steps
+}
+
+/// Every request one round for the `shown` tickers would send, without
+/// sending any. `stale` is whether `--stale-while-revalidate` is set.
+pub fn plan(
+    shown: &[String],
+    cache: Option<&Cache>,
+    stale: bool,
+    news: &News,
+    aggregate: Option<&Aggregate>,
+    symbols: &Symbols,
+    net: &Net,
+) -> Vec<Step> {
+    let offline = cache.is_some_and(|cache| cache.offline);
+    let why = match symbols::list_age() {
+        Some(stored) => format!("symbols.json, {} old", age(stored)),
+        None => "not downloaded yet".to_string(),
+    };
+    let list = Step::get("coin list", format!("{}?limit=0", net.api), &why);
+    let mut steps = vec![list.sent(!offline && symbols::list_is_stale())];
+    for arg in shown {
+        if let Some(coin) = arg.strip_prefix("news:") {
+            let why = if offline {
+                "--offline is set"
+            } else {
+                "refetched every 15m"
+            };
+            steps.push(Step::get(arg, news.url(coin), why).sent(!offline));
+        } else if let Some(algo) = arg.strip_prefix("mining:") {
+            let (priced, urls) = match mining::requests(algo) {
+                Some(requests) => requests,
+                None => continue,
+            };
+            for url in urls.iter() {
+                steps.push(Step::cached_in(arg, url.clone(), "mining.json", offline));
+            }
+            steps.extend(coin(priced, cache, false, None, symbols, net));
+        } else if arg == "week" {
+            // from the local history, asking nothing
+            continue;
+        } else if arg == "fng" {
+            steps.push(Step::cached_in(
+                arg,
+                fng::URL.to_string(),
+                "fng.json",
+                offline,
+            ));
+        } else if arg == "gas" {
+            steps.push(Step::cached_in(
+                arg,
+                gas::URL.to_string(),
+                "gas.json",
+                offline,
+            ));
+        } else if let Some(spec) = arg.strip_prefix("contract:") {
+            let contract: Contract = match spec.parse() {
+                Ok(contract) => contract,
+                Err(_) => continue,
+            };
+            let (fetched, why) = decide(&contract.id(), cache, false);
+            steps.push(Step::get(arg, contract.url(), &why).sent(fetched));
+        } else {
+            steps.extend(coin(arg, cache, stale, aggregate, symbols, net));
+        }
+    }
+    // a coin `mining:` prices in and shows too is cached after the first
+    if cache.is_some() {
+        let mut seen = HashSet::new();
+        steps.retain(|step| step.request.is_none() || seen.insert(step.request.clone()));
+    }
+    steps
+}
+
+/// The steps as a table, with how many requests they come to. `period`
+/// is the interval mode's, in seconds.
+pub fn report(steps: &[Step], retries: u32, period: Option<u64>) -> String {
+    let mut rows = vec![["TICKER", "PROVIDER", "REQUEST", "CACHE"]];
+    rows.extend(steps.iter().map(|step| {
+        [
+            step.ticker.as_str(),
+            step.provider.as_str(),
+            step.request.as_deref().unwrap_or("-"),
+            step.cache.as_str(),
+        ]
+    }));
+    let mut widths = [0; 3];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let mut out = String::new();
+    for row in &rows {
+        out.push_str(&format!(
+            "{:<a$}  {:<b$}  {:<c$}  {}\n",
+            row[0],
+            row[1],
+            row[2],
+            row[3],
+            a = widths[0],
+            b = widths[1],
+            c = widths[2]
+        ));
+    }
+    let sent = steps.iter().filter(|step| step.request.is_some()).count();
+    out.push_str(&format!(
+        "{} request{} a round, up to {} with {} retries each",
+        sent,
+        if sent == 1 { "" } else { "s" },
+        sent * (retries as usize + 1),
+        retries
+    ));
+    match period {
+        Some(period) if period > 0 => out.push_str(&format!(
+            ", about {} an hour every {}s\n",
+            sent as u64 * 3600 / period,
+            period
+        )),
+        _ => out.push('\n'),
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cache::{MemoryCache, Validators};
+    use serde_json;
+    use ticker::Currency;
+
+    fn cache(ttl: Option<Duration>, offline: bool) -> Cache {
+        let cache = Cache {
+            backend: Box::new(MemoryCache::default()),
+            ttl,
+            offline,
+        };
+        let ticker: Currency = serde_json::from_str(
+            r#"{"id": "bitcoin", "name": "Bitcoin", "symbol": "BTC", "rank": "1",
+                "price_usd": "2545.19"}"#,
+        )
+        .unwrap();
+        let validators = Validators {
+            etag: Some("\"abc\"".to_string()),
+            last_modified: None,
+        };
+        cache.backend.put("bitcoin", &ticker, &validators).unwrap();
+        cache
+    }
+
+    #[test]
+    fn decides_like_the_cache() {
+        let hour = Some(Duration::from_secs(3600));
+        let fresh = cache(hour, false);
+        assert_eq!(
+            decide("bitcoin", Some(&fresh), false),
+            (false, "fresh, 59m left".to_string())
+        );
+        assert_eq!(
+            decide("ethereum", Some(&fresh), false),
+            (true, "not cached".to_string())
+        );
+        let cold = cache(Some(Duration::from_secs(0)), false);
+        assert_eq!(
+            decide("bitcoin", Some(&cold), false),
+            (true, "cold, 0s old, revalidated".to_string())
+        );
+        assert!(decide("bitcoin", Some(&cold), true)
+            .1
+            .ends_with("background"));
+        let offline = cache(Some(Duration::from_secs(0)), true);
+        assert!(!decide("bitcoin", Some(&offline), false).0);
+        assert!(!decide("ethereum", Some(&offline), false).0);
+        assert!(decide("bitcoin", None, false).0);
+    }
+
+    #[test]
+    fn counts_requests() {
+        let steps = vec![
+            Step::get(
+                "bitcoin",
+                "https://api.coinmarketcap.com/v1/ticker/bitcoin".to_string(),
+                "not cached",
+            ),
+            Step::cached_in("fng", fng::URL.to_string(), "fng.json", true),
+        ];
+        assert_eq!(
+            report(&steps, 2, Some(60)),
+            "TICKER   PROVIDER        REQUEST                                              CACHE\n\
+             bitcoin  coinmarketcap   GET https://api.coinmarketcap.com/v1/ticker/bitcoin  not cached\n\
+             fng      alternative.me  -                                                    fng.json, however old\n\
+             1 request a round, up to 3 with 2 retries each, about 60 an hour every 60s\n"
+        );
+    }
+}
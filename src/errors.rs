@@ -1,30 +1,115 @@
 use std;
+use std::fmt;
 use reqwest;
+use rusqlite;
 use serde_json;
 
+/// Everything that can go wrong, told apart so callers can react
+/// differently: fall back to the cache on a network error, suggest
+/// another ID for an invalid ticker, back off when rate limited.
 #[derive(Debug)]
-pub struct StrError(pub String);
+pub enum Error {
+    /// the API or another server couldn't be reached, timed out or failed
+    Network(String),
+    /// the API doesn't know the ticker ID `name`
+    InvalidTicker {
+        name: String,
+    },
+    /// the API asked us to slow down, for `retry_after` seconds if it said
+    RateLimited {
+        retry_after: Option<u64>,
+    },
+    /// the cache couldn't be read or written
+    Cache(String),
+    /// an answer, file or argument didn't look like it should
+    Parse(String),
+    Other(String),
+}
+
+impl Error {
+    /// Whether asking again later might work, which makes a cold cache
+    /// entry a better answer than the error.
+    pub fn is_transient(&self) -> bool {
+        matches!(*self, Error::Network(_) | Error::RateLimited { .. })
+    }
+
+    /// Whether the fetch failed rather than the coin: the network, a rate
+    /// limit, or an answer too garbled to read, like a captive portal's
+    /// page. What the cache has of the coin is still the best answer.
+    pub fn is_failed_fetch(&self) -> bool {
+        self.is_transient() || matches!(*self, Error::Parse(_))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Network(ref message)
+            | Error::Cache(ref message)
+            | Error::Parse(ref message)
+            | Error::Other(ref message) => f.write_str(message),
+            Error::InvalidTicker { ref name } => write!(f, "Ticker ID {} not valid.", name),
+            Error::RateLimited {
+                retry_after: Some(secs),
+            } => write!(f, "Rate limited for {}s.", secs),
+            Error::RateLimited { retry_after: None } => f.write_str("Rate limited."),
+        }
+    }
+}
 
-impl std::convert::From<reqwest::Error> for StrError {
+impl std::error::Error for Error {}
+
+impl std::convert::From<reqwest::Error> for Error {
     fn from(error: reqwest::Error) -> Self {
-        StrError(format!("{:#?}", error))
+        Error::Network(format!("{:#?}", error))
     }
 }
 
-impl std::convert::From<serde_json::Error> for StrError {
+impl std::convert::From<serde_json::Error> for Error {
     fn from(error: serde_json::Error) -> Self {
-        StrError(format!("{:#?}", error))
+        Error::Parse(format!("{:#?}", error))
+    }
+}
+
+impl std::convert::From<rusqlite::Error> for Error {
+    fn from(error: rusqlite::Error) -> Self {
+        Error::Other(format!("{:#?}", error))
     }
 }
 
-impl std::convert::From<std::io::Error> for StrError {
+impl std::convert::From<std::io::Error> for Error {
     fn from(error: std::io::Error) -> Self {
-        StrError(format!("{:#?}", error))
+        Error::Other(format!("{:#?}", error))
     }
 }
 
-impl std::convert::From<String> for StrError {
+impl std::convert::From<String> for Error {
     fn from(error: String) -> Self {
-        StrError(error)
+        Error::Other(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tells_transient_errors_apart() {
+        assert!(Error::Network("down".to_string()).is_transient());
+        assert!(Error::RateLimited { retry_after: None }.is_transient());
+        let invalid = Error::InvalidTicker {
+            name: "ethereem".to_string(),
+        };
+        assert!(!invalid.is_transient());
+        assert!(!invalid.is_failed_fetch());
+        assert!(Error::Parse("<html>".to_string()).is_failed_fetch());
+        assert_eq!(invalid.to_string(), "Ticker ID ethereem not valid.");
+        assert_eq!(
+            Error::RateLimited {
+                retry_after: Some(60)
+            }
+            .to_string(),
+            "Rate limited for 60s."
+        );
     }
 }
@@ -0,0 +1,103 @@
+use time;
+
+use history::parse_span;
+
+/// A market-moving date for a coin, like a halving or a token unlock,
+/// from an `[[event]]` table in the config file.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Event {
+    pub coin: String,
+    pub name: String,
+    /// `2028-04-15`, or `2028-04-15T12:00:00Z` for a time of day (UTC)
+    pub date: String,
+    /// how long beforehand to raise an alert, like `7d`; a day by default
+    pub remind: Option<String>,
+}
+
+impl Event {
+    /// The event's unix time.
+    pub fn timestamp(&self) -> Result<i64, String> {
+        time::strptime(&self.date, "%Y-%m-%dT%H:%M:%SZ")
+            .or_else(|_| time::strptime(&self.date, "%Y-%m-%d"))
+            .map(|tm| tm.to_timespec().sec)
+            .map_err(|_| {
+                format!(
+                    "event date {:?} should look like 2028-04-15 or 2028-04-15T12:00:00Z",
+                    self.date
+                )
+            })
+    }
+
+    /// How many seconds before the event its alert fires.
+    pub fn remind(&self) -> Result<i64, String> {
+        self.remind
+            .as_ref()
+            .map_or(Ok(24 * 60 * 60), |span| parse_span(span))
+    }
+}
+
+/// Checks every event's dates up front, so a typo fails at startup
+/// rather than silently never showing.
+pub fn validate(events: &[Event]) -> Result<(), String> {
+    for event in events {
+        event.timestamp()?;
+        event.remind()?;
+    }
+    Ok(())
+}
+
+/// The soonest event for `coin` that hasn't happened yet, with the
+/// seconds left until it.
+pub fn next<'a>(events: &'a [Event], coin: &str, now: i64) -> Option<(&'a Event, i64)> {
+    events
+        .iter()
+        .filter(|event| event.coin == coin)
+        .filter_map(|event| event.timestamp().ok().map(|at| (event, at - now)))
+        .filter(|&(_, left)| left > 0)
+        .min_by_key(|&(_, left)| left)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(coin: &str, name: &str, date: &str) -> Event {
+        Event {
+            coin: coin.to_string(),
+            name: name.to_string(),
+            date: date.to_string(),
+            remind: None,
+        }
+    }
+
+    #[test]
+    fn parses_dates() {
+        assert_eq!(
+            event("bitcoin", "halving", "1970-01-02").timestamp(),
+            Ok(86_400)
+        );
+        assert_eq!(
+            event("bitcoin", "halving", "1970-01-01T01:00:00Z").timestamp(),
+            Ok(3600)
+        );
+        assert!(event("bitcoin", "halving", "next spring")
+            .timestamp()
+            .is_err());
+        assert!(validate(&[event("bitcoin", "halving", "2028-13-45")]).is_err());
+    }
+
+    #[test]
+    fn finds_the_next_event() {
+        let events = [
+            event("bitcoin", "past", "1970-01-01"),
+            event("bitcoin", "later", "1970-01-10"),
+            event("bitcoin", "sooner", "1970-01-05"),
+            event("ethereum", "fork", "1970-01-02"),
+        ];
+        let (event, left) = next(&events, "bitcoin", 3600).unwrap();
+        assert_eq!(event.name, "sooner");
+        assert_eq!(left, 4 * 86_400 - 3600);
+        assert!(next(&events, "golem", 0).is_none());
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use clap::ArgMatches;
+
+use errors::Error;
+use history;
+use http::{self, Response};
+use net::Net;
+use symbols::{self, Symbols};
+use ticker::{refresh_ticker, to_f64, Currency};
+use trace;
+
+/// A gauge's name, its help text and where its value comes from.
+type Gauge = (&'static str, &'static str, fn(&Currency) -> Option<f64>);
+
+const GAUGES: &[Gauge] = &[
+    ("crypto_price_usd", "Price in USD.", |t| to_f64(t.price_usd)),
+    ("crypto_price_btc", "Price in BTC.", |t| to_f64(t.price_btc)),
+    (
+        "crypto_change_1h",
+        "Price change over the last hour, in percent.",
+        |t| to_f64(t.percent_change_1),
+    ),
+    (
+        "crypto_change_24h",
+        "Price change over the last day, in percent.",
+        |t| to_f64(t.percent_change_24),
+    ),
+    (
+        "crypto_change_7d",
+        "Price change over the last week, in percent.",
+        |t| to_f64(t.percent_change_7),
+    ),
+    (
+        "crypto_volume_24h_usd",
+        "Trading volume over the last day, in USD.",
+        |t| to_f64(t.volume_usd_24h),
+    ),
+    (
+        "crypto_market_cap_usd",
+        "Market capitalization in USD.",
+        |t| to_f64(t.market_cap_usd),
+    ),
+];
+
+/// Label values escape backslashes, quotes and newlines.
+fn label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// The last refresh round's ID and when it started.
+type Refresh = (String, i64);
+
+/// `tickers` in the Prometheus text format, one gauge family at a time,
+/// and the `refresh` round they're from.
+fn metrics(tickers: &[Currency], refresh: Option<&Refresh>) -> String {
+    let mut out = String::new();
+    for &(name, help, value) in GAUGES {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} gauge", name);
+        for ticker in tickers {
+            if let Some(value) = value(ticker) {
+                let _ = writeln!(
+                    out,
+                    "{}{{coin=\"{}\",symbol=\"{}\"}} {}",
+                    name,
+                    label(&ticker.id),
+                    label(&ticker.symbol),
+                    value
+                );
+            }
+        }
+    }
+    if let Some((id, started)) = refresh {
+        out.push_str(
+            "# HELP crypto_last_refresh_timestamp_seconds When the last refresh round started.\n\
+             # TYPE crypto_last_refresh_timestamp_seconds gauge\n",
+        );
+        let _ = writeln!(
+            out,
+            "crypto_last_refresh_timestamp_seconds{{refresh=\"{}\"}} {}",
+            label(id),
+            started
+        );
+    }
+    out
+}
+
+/// Runs `export`, serving `/metrics` for the given coins and refreshing
+/// them every `--interval`. A coin that fails to refresh keeps its last
+/// values; one that has never been fetched is left out.
+pub fn export(
+    matches: &ArgMatches,
+    symbols: &Symbols,
+    overrides: &HashMap<String, String>,
+    net: Net,
+) -> Result<(), Error> {
+    let interval =
+        Duration::from_secs(value_t!(matches, "interval", u64).unwrap_or_else(|e| e.exit()));
+    let listen = matches.value_of("listen").unwrap();
+    let args: Vec<&str> = matches.values_of("TICKER").unwrap().collect();
+    let names = symbols::resolve_all(symbols, &args, overrides, false);
+
+    let latest: Arc<Mutex<HashMap<String, Currency>>> = Arc::default();
+    let refresh: Arc<Mutex<Option<Refresh>>> = Arc::default();
+    {
+        let (latest, refresh) = (latest.clone(), refresh.clone());
+        thread::spawn(move || loop {
+            *refresh.lock().unwrap() = Some((trace::begin(), history::now()));
+            for name in &names {
+                match refresh_ticker(name, None, &net) {
+                    Ok(ticker) => {
+                        latest.lock().unwrap().insert(name.clone(), ticker);
+                    }
+                    Err(err) => warn!("{}", err),
+                }
+            }
+            thread::sleep(interval);
+        });
+    }
+
+    http::serve(listen, move |path| match path {
+        "/metrics" => {
+            let mut tickers: Vec<Currency> = latest.lock().unwrap().values().cloned().collect();
+            tickers.sort_by(|a, b| a.id.cmp(&b.id));
+            let refresh = refresh.lock().unwrap().clone();
+            Response::ok(
+                "text/plain; version=0.0.4",
+                metrics(&tickers, refresh.as_ref()),
+            )
+        }
+        _ => Response::not_found(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn writes_the_text_format() {
+        let tickers: Vec<Currency> =
+            serde_json::from_str(include_str!("../tests/fixtures/ticker.json")).unwrap();
+        let refresh = ("6710f2a0-3".to_string(), 1_729_164_960);
+        let out = metrics(&tickers[..1], Some(&refresh));
+        assert!(out.contains("# TYPE crypto_price_usd gauge\n"));
+        assert!(out.contains("crypto_price_usd{coin=\"bitcoin\",symbol=\"BTC\"} 2545.19\n"));
+        assert!(out.contains("crypto_change_24h{coin=\"bitcoin\",symbol=\"BTC\"} -3.39\n"));
+        assert!(out.ends_with(
+            "crypto_last_refresh_timestamp_seconds{refresh=\"6710f2a0-3\"} 1729164960\n"
+        ));
+        assert_eq!(label("a \"b\"\\"), "a \\\"b\\\"\\\\");
+    }
+}
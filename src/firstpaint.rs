@@ -0,0 +1,122 @@
+use std::str::FromStr;
+
+use cache::Cache;
+use template::Template;
+use ticker::{is_pseudo, short_name};
+
+/// What interval mode's first line shows for the coins that haven't come
+/// in yet, picked with `--first-paint`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FirstPaint {
+    /// `btc:…`
+    Placeholder,
+    /// the last price the cache has, marked `*` like a cold entry, or a
+    /// placeholder for coins it doesn't have
+    Cached,
+    /// nothing, so the line only grows
+    Empty,
+    /// the config's `banner`, until the first coin comes in
+    Banner,
+}
+
+/// The names `--first-paint` accepts.
+pub const FIRST_PAINTS: &[&str] = &["placeholder", "cached", "empty", "banner"];
+
+/// What `Banner` shows when the config has no `banner`.
+pub const DEFAULT_BANNER: &str = "cryptoticker: loading…";
+
+impl FromStr for FirstPaint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<FirstPaint, String> {
+        match s.trim().to_lowercase().as_str() {
+            "placeholder" => Ok(FirstPaint::Placeholder),
+            "cached" => Ok(FirstPaint::Cached),
+            "empty" => Ok(FirstPaint::Empty),
+            "banner" => Ok(FirstPaint::Banner),
+            _ => Err(format!(
+                "{:?} should be one of {}",
+                s,
+                FIRST_PAINTS.join(", ")
+            )),
+        }
+    }
+}
+
+/// Like `btc:… `, standing in for `arg` until its price comes in.
+pub fn placeholder(arg: &str, template: &Template) -> String {
+    let label = template
+        .alias(arg)
+        .map_or_else(|| short_name(arg), String::from);
+    format!("{}:… ", label)
+}
+
+impl FirstPaint {
+    /// What stands in for each of the `shown` coins in the first round,
+    /// read from `cache` without asking the network, so the first line
+    /// is printed before any provider is waited on.
+    pub fn stand_ins(
+        self,
+        shown: &[String],
+        template: &Template,
+        cache: Option<&Cache>,
+    ) -> Vec<String> {
+        shown
+            .iter()
+            .map(|arg| match self {
+                FirstPaint::Empty | FirstPaint::Banner => String::new(),
+                FirstPaint::Cached if !is_pseudo(arg) => {
+                    match cache.and_then(|cache| cache.backend.get(arg)) {
+                        Some((ticker, _)) => format!("{}* ", template.render(arg, &ticker, None)),
+                        None => placeholder(arg, template),
+                    }
+                }
+                _ => placeholder(arg, template),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cache::{MemoryCache, Validators};
+    use serde_json;
+    use ticker::Currency;
+
+    #[test]
+    fn stands_in_for_coins() {
+        let cache = Cache {
+            backend: Box::new(MemoryCache::default()),
+            ttl: None,
+            offline: false,
+        };
+        let ticker: Currency = serde_json::from_str(
+            r#"{"id": "bitcoin", "name": "Bitcoin", "symbol": "BTC", "rank": "1",
+                "price_usd": "2545.19"}"#,
+        )
+        .unwrap();
+        let validators = Validators {
+            etag: None,
+            last_modified: None,
+        };
+        cache.backend.put("bitcoin", &ticker, &validators).unwrap();
+        let shown = vec![
+            "bitcoin".to_string(),
+            "ethereum".to_string(),
+            "fng".to_string(),
+        ];
+        let template = Template::default();
+        let cache = Some(&cache);
+        assert_eq!(
+            FirstPaint::Cached.stand_ins(&shown, &template, cache),
+            ["btc:2545.19* ", "eth:… ", "fng:… "]
+        );
+        assert_eq!(
+            FirstPaint::Placeholder.stand_ins(&shown, &template, None)[0],
+            "btc:… "
+        );
+        assert_eq!(FirstPaint::Empty.stand_ins(&shown, &template, cache)[0], "");
+        assert!("spinner".parse::<FirstPaint>().is_err());
+    }
+}
@@ -0,0 +1,131 @@
+use std::fs;
+
+use serde_json;
+
+use dirs;
+use errors::Error;
+use history;
+use net::Net;
+
+pub const URL: &str = "https://api.alternative.me/fng/?limit=1";
+
+/// The index is published once a day; asking every hour catches a new
+/// one soon enough.
+const REFRESH: i64 = 60 * 60;
+
+/// The Crypto Fear & Greed Index, from 0 for extreme fear to 100 for
+/// extreme greed. Kept in the cache directory as `fng.json`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Index {
+    fetched: i64,
+    pub value: u32,
+    /// like `Greed` or `Extreme Fear`
+    pub classification: String,
+}
+
+#[derive(Deserialize)]
+struct Answer {
+    data: Vec<Reading>,
+}
+
+#[derive(Deserialize)]
+struct Reading {
+    value: String,
+    value_classification: String,
+}
+
+fn parse(content: &str) -> Result<Index, Error> {
+    let answer: Answer = serde_json::from_str(content)?;
+    let reading =
+        answer.data.into_iter().next().ok_or_else(|| {
+            Error::Parse("alternative.me sent no Fear & Greed reading".to_string())
+        })?;
+    let value = reading.value.trim().parse().map_err(|_| {
+        Error::Parse(format!(
+            "alternative.me sent {:?} for the Fear & Greed Index",
+            reading.value
+        ))
+    })?;
+    Ok(Index {
+        fetched: history::now(),
+        value,
+        classification: reading.value_classification,
+    })
+}
+
+fn fetch(net: &Net) -> Result<Index, Error> {
+    debug!("retrieving {}", URL);
+    let resp = net.get(URL)?;
+    if !resp.status.is_success() {
+        return Err(Error::Network(format!("{} answered {}", URL, resp.status)));
+    }
+    parse(&resp.text())
+}
+
+impl Index {
+    /// The index from the cache directory, fetched again when it's an
+    /// hour old. A failed fetch falls back to the older reading, and
+    /// offline there is only ever the older reading.
+    pub fn load(offline: bool, net: &Net) -> Result<Index, Error> {
+        let path = dirs::cache_dir()?.join("fng.json");
+        let cached: Option<Index> = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok());
+        match cached {
+            Some(index) if offline || history::now() - index.fetched < REFRESH => return Ok(index),
+            None if offline => {
+                return Err(Error::Network(
+                    "no Fear & Greed Index is cached, and --offline is set".to_string(),
+                ))
+            }
+            _ => {}
+        }
+        match fetch(net) {
+            Ok(index) => {
+                fs::write(&path, serde_json::to_string(&index)?)?;
+                Ok(index)
+            }
+            Err(err) => match cached {
+                Some(index) => {
+                    debug!("{}, using the older Fear & Greed Index", err);
+                    Ok(index)
+                }
+                None => Err(err),
+            },
+        }
+    }
+
+    /// Like `fng:72(greed)`.
+    pub fn segment(&self) -> String {
+        let class = self.classification.to_lowercase().replace(' ', "-");
+        format!("fng:{}({})", self.value, class)
+    }
+}
+
+/// The segment to print for the `fng` pseudo-ticker.
+pub fn segment(offline: bool, net: &Net) -> Result<String, Error> {
+    Index::load(offline, net).map(|index| index.segment())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_index() {
+        let index = parse(
+            r#"{"name": "Fear and Greed Index", "data": [{"value": "72",
+                "value_classification": "Greed", "timestamp": "1551157200",
+                "time_until_update": "68499"}], "metadata": {"error": null}}"#,
+        )
+        .unwrap();
+        assert_eq!(index.segment(), "fng:72(greed)");
+        let index = Index {
+            classification: "Extreme Fear".to_string(),
+            value: 8,
+            ..index
+        };
+        assert_eq!(index.segment(), "fng:8(extreme-fear)");
+        assert!(parse(r#"{"data": []}"#).is_err());
+    }
+}
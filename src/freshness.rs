@@ -0,0 +1,127 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use ticker::Currency;
+
+/// How a segment shows how old its price is, picked with `--freshness`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Freshness {
+    /// the age after the segment, like `btc:43000(2m)`
+    Age,
+    /// a `*` once the price is past half the cache TTL, as cold cache
+    /// entries already get
+    Mark,
+}
+
+/// The names `--freshness` accepts.
+pub const FRESHNESS: &[&str] = &["age", "mark"];
+
+impl FromStr for Freshness {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Freshness, String> {
+        match s.trim().to_lowercase().as_str() {
+            "age" => Ok(Freshness::Age),
+            "mark" => Ok(Freshness::Mark),
+            _ => Err(format!("{:?} should be one of {}", s, FRESHNESS.join(", "))),
+        }
+    }
+}
+
+/// How old `ticker`'s price is at `now`: since the API last updated it or
+/// since it was `cached`, whichever was longer ago.
+pub fn age(ticker: &Currency, cached: Option<Duration>, now: i64) -> Option<Duration> {
+    let updated = ticker
+        .last_updated
+        .as_ref()
+        .and_then(|updated| updated.trim().parse::<i64>().ok())
+        .map(|updated| Duration::from_secs((now - updated).max(0) as u64));
+    match (updated, cached) {
+        (Some(updated), Some(cached)) => Some(updated.max(cached)),
+        (updated, cached) => updated.or(cached),
+    }
+}
+
+/// An age in its biggest whole unit, like `45s`, `2m`, `5h` or `3d`.
+fn short(age: Duration) -> String {
+    let secs = age.as_secs();
+    match secs {
+        0..=59 => format!("{}s", secs),
+        60..=3599 => format!("{}m", secs / 60),
+        3600..=86_399 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86_400),
+    }
+}
+
+impl Freshness {
+    /// `segment` marked with its price's `age`, judged against `ttl`,
+    /// how long the price is meant to stay fresh.
+    pub fn mark(self, segment: &str, age: Option<Duration>, ttl: Option<Duration>) -> String {
+        let text = segment.trim_end();
+        let age = match age {
+            Some(age) => age,
+            None => return segment.to_string(),
+        };
+        match self {
+            Freshness::Age => format!("{}({}) ", text, short(age)),
+            Freshness::Mark if text.ends_with('*') => segment.to_string(),
+            Freshness::Mark if ttl.is_some_and(|ttl| age > ttl / 2) => format!("{}* ", text),
+            Freshness::Mark => segment.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    fn ticker(last_updated: &str) -> Currency {
+        serde_json::from_str(&format!(
+            r#"{{"id": "bitcoin", "name": "Bitcoin", "symbol": "BTC", "rank": "1",
+                "price_usd": "43000", "last_updated": "{}"}}"#,
+            last_updated
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn measures_age() {
+        let secs = Duration::from_secs;
+        let now = 1_700_000_000;
+        let fresh = ticker("1699999880");
+        assert_eq!(age(&fresh, None, now), Some(secs(120)));
+        // a copy cached an hour ago is an hour old, whatever it says
+        assert_eq!(age(&fresh, Some(secs(3600)), now), Some(secs(3600)));
+        assert_eq!(age(&ticker("soon"), Some(secs(30)), now), Some(secs(30)));
+        assert_eq!(age(&ticker("soon"), None, now), None);
+    }
+
+    #[test]
+    fn marks_segments() {
+        let secs = Duration::from_secs;
+        let ttl = Some(secs(1800));
+        assert_eq!(
+            Freshness::Age.mark("btc:43000 ", Some(secs(125)), ttl),
+            "btc:43000(2m) "
+        );
+        assert_eq!(
+            Freshness::Age.mark("btc:43000 ", Some(secs(90_000)), ttl),
+            "btc:43000(1d) "
+        );
+        assert_eq!(
+            Freshness::Mark.mark("btc:43000 ", Some(secs(600)), ttl),
+            "btc:43000 "
+        );
+        assert_eq!(
+            Freshness::Mark.mark("btc:43000 ", Some(secs(1000)), ttl),
+            "btc:43000* "
+        );
+        assert_eq!(
+            Freshness::Mark.mark("btc:43000* ", Some(secs(2000)), ttl),
+            "btc:43000* "
+        );
+        assert_eq!(Freshness::Age.mark("btc:43000 ", None, ttl), "btc:43000 ");
+        assert!("stale".parse::<Freshness>().is_err());
+    }
+}
@@ -0,0 +1,189 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+
+use rust_decimal::Decimal;
+use serde_json;
+use time;
+
+use dirs;
+use errors::Error;
+use history;
+use net::Net;
+
+/// Every euro foreign exchange reference rate the ECB has published.
+const URL: &str = "https://www.ecb.europa.eu/stats/eurofxref/eurofxref-hist.xml";
+
+/// The ECB publishes once every working day, so twice a day catches a
+/// new one soon enough.
+const REFRESH: i64 = 12 * 60 * 60;
+
+/// The ECB's daily reference rates, kept in the cache directory. Each day
+/// maps a currency to how much of it one euro bought.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Rates {
+    fetched: i64,
+    days: BTreeMap<String, HashMap<String, Decimal>>,
+}
+
+/// The value of `name="..."` in an XML tag.
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let start = tag.find(&format!("{}=", name))? + name.len() + 1;
+    let quote = tag[start..].chars().next()?;
+    let value = &tag[start + 1..];
+    value.find(quote).map(|end| &value[..end])
+}
+
+/// Reads the ECB's `<Cube time="..."><Cube currency="..." rate="..."/>`
+/// layout, which is all of the file that matters.
+fn parse(xml: &str) -> Result<BTreeMap<String, HashMap<String, Decimal>>, Error> {
+    let mut days = BTreeMap::new();
+    let mut day: Option<&str> = None;
+    for tag in xml.split("<Cube").skip(1) {
+        let tag = tag.split('>').next().unwrap_or(tag);
+        if let Some(time) = attribute(tag, "time") {
+            day = Some(time);
+            continue;
+        }
+        if let (Some(day), Some(currency), Some(rate)) =
+            (day, attribute(tag, "currency"), attribute(tag, "rate"))
+        {
+            let rate = rate
+                .parse()
+                .map_err(|_| Error::Parse(format!("the ECB sent {:?} for a rate", rate)))?;
+            days.entry(day.to_string())
+                .or_insert_with(HashMap::new)
+                .insert(currency.to_string(), rate);
+        }
+    }
+    if days.is_empty() {
+        return Err(Error::Parse("the ECB sent no reference rates".to_string()));
+    }
+    Ok(days)
+}
+
+fn fetch(net: &Net) -> Result<Rates, Error> {
+    debug!("retrieving {}", URL);
+    let resp = net.get(URL)?;
+    if !resp.status.is_success() {
+        return Err(Error::Network(format!("{} answered {}", URL, resp.status)));
+    }
+    Ok(Rates {
+        fetched: history::now(),
+        days: parse(&resp.text())?,
+    })
+}
+
+impl Rates {
+    /// The reference rates from the cache directory, fetched again when
+    /// they're half a day old. A failed fetch falls back to the older
+    /// rates, and offline there are only ever the older rates.
+    pub fn load(offline: bool, net: &Net) -> Result<Rates, Error> {
+        let path = dirs::cache_dir()?.join("ecb.json");
+        let cached: Option<Rates> = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok());
+        match cached {
+            Some(rates) if offline || history::now() - rates.fetched < REFRESH => return Ok(rates),
+            None if offline => {
+                return Err(Error::Network(
+                    "no ECB reference rates are cached, and --offline is set".to_string(),
+                ))
+            }
+            _ => {}
+        }
+        match fetch(net) {
+            Ok(rates) => {
+                fs::write(&path, serde_json::to_string(&rates)?)?;
+                Ok(rates)
+            }
+            Err(err) => match cached {
+                Some(rates) => {
+                    debug!("{}, using the older reference rates", err);
+                    Ok(rates)
+                }
+                None => Err(err),
+            },
+        }
+    }
+
+    /// What one USD bought of `currency` on the UTC day of `timestamp`.
+    /// Weekends and holidays have no rates of their own, so they take
+    /// the last one published before them.
+    pub fn usd_to(&self, currency: &str, timestamp: i64) -> Result<Decimal, Error> {
+        let currency = currency.to_uppercase();
+        if currency == "USD" {
+            return Ok(Decimal::ONE);
+        }
+        let tm = time::at_utc(time::Timespec::new(timestamp, 0));
+        let date = time::strftime("%Y-%m-%d", &tm).unwrap_or_default();
+        let (_, day) = self
+            .days
+            .range(..=date.clone())
+            .next_back()
+            .ok_or_else(|| Error::Other(format!("the ECB has no rates as old as {}", date)))?;
+        let rate = |currency: &str| match currency {
+            "EUR" => Some(Decimal::ONE),
+            _ => day.get(currency).cloned(),
+        };
+        match (rate("USD"), rate(&currency)) {
+            (Some(usd), Some(other)) if !usd.is_zero() => Ok(other / usd),
+            _ => Err(Error::Other(format!(
+                "the ECB has no {} reference rate for {}",
+                currency, date
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gesmes:Envelope xmlns:gesmes="http://www.gesmes.org/xml/2002-08-01">
+  <gesmes:subject>Reference rates</gesmes:subject>
+  <Cube>
+    <Cube time="2024-01-05">
+      <Cube currency="USD" rate="1.0921"/>
+      <Cube currency="GBP" rate="0.8603"/>
+    </Cube>
+    <Cube time="2024-01-04">
+      <Cube currency="USD" rate="1.0953"/>
+      <Cube currency="GBP" rate="0.8635"/>
+    </Cube>
+  </Cube>
+</gesmes:Envelope>"#;
+
+    #[test]
+    fn reads_reference_rates() {
+        let days = parse(XML).unwrap();
+        assert_eq!(days.len(), 2);
+        assert_eq!(days["2024-01-04"]["GBP"], Decimal::new(8635, 4));
+        assert!(parse("<html></html>").is_err());
+    }
+
+    #[test]
+    fn converts_at_the_rate_of_the_day() {
+        let rates = Rates {
+            fetched: 0,
+            days: parse(XML).unwrap(),
+        };
+        // 2024-01-04 12:00 UTC
+        let thursday = 1_704_369_600;
+        let usd = Decimal::new(10953, 4);
+        assert_eq!(rates.usd_to("eur", thursday).unwrap(), Decimal::ONE / usd);
+        assert_eq!(
+            rates.usd_to("GBP", thursday).unwrap(),
+            Decimal::new(8635, 4) / usd
+        );
+        // the weekend after still has Friday's
+        let sunday = thursday + 3 * 86_400;
+        assert_eq!(
+            rates.usd_to("EUR", sunday).unwrap(),
+            Decimal::ONE / Decimal::new(10921, 4)
+        );
+        assert_eq!(rates.usd_to("USD", 0).unwrap(), Decimal::ONE);
+        assert!(rates.usd_to("EUR", 0).is_err());
+        assert!(rates.usd_to("XYZ", thursday).is_err());
+    }
+}
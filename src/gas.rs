@@ -0,0 +1,158 @@
+use std::fs;
+
+use serde_json;
+
+use dirs;
+use errors::Error;
+use history;
+use net::Net;
+use template::significant_places;
+
+pub const URL: &str = "https://api.etherscan.io/api?module=gastracker&action=gasoracle";
+
+/// A new block comes every twelve seconds or so, and the oracle follows
+/// them; any longer and the price is one nobody is paying.
+const REFRESH: i64 = 15;
+
+/// Ethereum gas prices in gwei, from Etherscan's gas oracle. Kept in the
+/// cache directory as `gas.json`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Gas {
+    fetched: i64,
+    pub slow: f64,
+    pub standard: f64,
+    pub fast: f64,
+}
+
+#[derive(Deserialize)]
+struct Answer {
+    status: String,
+    result: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Oracle {
+    safe_gas_price: String,
+    propose_gas_price: String,
+    fast_gas_price: String,
+}
+
+fn gwei(price: &str) -> Result<f64, Error> {
+    price
+        .trim()
+        .parse()
+        .map_err(|_| Error::Parse(format!("Etherscan sent {:?} for a gas price", price)))
+}
+
+fn parse(content: &str) -> Result<Gas, Error> {
+    let answer: Answer = serde_json::from_str(content)?;
+    // failures come back as `"status": "0"` with the reason as the result
+    if answer.status != "1" {
+        return Err(Error::Network(format!(
+            "Etherscan's gas oracle answered {}",
+            answer.result
+        )));
+    }
+    let oracle: Oracle = serde_json::from_value(answer.result)?;
+    Ok(Gas {
+        fetched: history::now(),
+        slow: gwei(&oracle.safe_gas_price)?,
+        standard: gwei(&oracle.propose_gas_price)?,
+        fast: gwei(&oracle.fast_gas_price)?,
+    })
+}
+
+fn fetch(key: Option<&str>, net: &Net) -> Result<Gas, Error> {
+    debug!("retrieving {}", URL);
+    let url = match key {
+        Some(key) => format!("{}&apikey={}", URL, key),
+        None => URL.to_string(),
+    };
+    let resp = net.get(&url)?;
+    if !resp.status.is_success() {
+        return Err(Error::Network(format!("{} answered {}", URL, resp.status)));
+    }
+    parse(&resp.text())
+}
+
+impl Gas {
+    /// The gas prices from the cache directory, fetched again when
+    /// they're fifteen seconds old. A failed fetch falls back to the
+    /// older prices, and offline there are only ever the older prices.
+    pub fn load(key: Option<&str>, offline: bool, net: &Net) -> Result<Gas, Error> {
+        let path = dirs::cache_dir()?.join("gas.json");
+        let cached: Option<Gas> = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok());
+        match cached {
+            Some(gas) if offline || history::now() - gas.fetched < REFRESH => return Ok(gas),
+            None if offline => {
+                return Err(Error::Network(
+                    "no gas prices are cached, and --offline is set".to_string(),
+                ))
+            }
+            _ => {}
+        }
+        match fetch(key, net) {
+            Ok(gas) => {
+                fs::write(&path, serde_json::to_string(&gas)?)?;
+                Ok(gas)
+            }
+            Err(err) => match cached {
+                Some(gas) => {
+                    debug!("{}, using the older gas prices", err);
+                    Ok(gas)
+                }
+                None => Err(err),
+            },
+        }
+    }
+
+    /// Like `gas:22gwei`, at the standard price. Prices under a gwei keep
+    /// two significant digits, like `gas:0.61gwei`.
+    pub fn segment(&self) -> String {
+        let places = significant_places(self.standard, 2);
+        format!("gas:{:.*}gwei", places, self.standard)
+    }
+}
+
+/// The segment to print for the `gas` pseudo-ticker.
+pub fn segment(key: Option<&str>, offline: bool, net: &Net) -> Result<String, Error> {
+    Gas::load(key, offline, net).map(|gas| gas.segment())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_oracle() {
+        let gas = parse(
+            r#"{"status": "1", "message": "OK", "result": {"LastBlock": "19000000",
+                "SafeGasPrice": "20", "ProposeGasPrice": "22", "FastGasPrice": "25.5",
+                "suggestBaseFee": "19.8", "gasUsedRatio": "0.4,0.6"}}"#,
+        )
+        .unwrap();
+        assert_eq!((gas.slow, gas.standard, gas.fast), (20.0, 22.0, 25.5));
+        assert_eq!(gas.segment(), "gas:22gwei");
+        let gas = Gas {
+            standard: 0.6123,
+            ..gas
+        };
+        assert_eq!(gas.segment(), "gas:0.61gwei");
+    }
+
+    #[test]
+    fn failures_are_errors() {
+        assert!(parse(
+            r#"{"status": "0", "message": "NOTOK", "result": "Max rate limit reached"}"#
+        )
+        .is_err());
+        assert!(parse(
+            r#"{"status": "1", "result": {"SafeGasPrice": "n/a",
+            "ProposeGasPrice": "1", "FastGasPrice": "2"}}"#
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,149 @@
+use std::fs;
+
+use serde_json::{self, Value};
+
+use dirs;
+use errors::Error;
+use history;
+use net::Net;
+use schema;
+use template::compact;
+
+/// The provider updates its totals every five minutes, like its tickers.
+const REFRESH: i64 = 5 * 60;
+
+/// The whole market, from the provider's global endpoint. Kept in the
+/// cache directory as `global.json`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Global {
+    #[serde(default)]
+    fetched: i64,
+    pub total_market_cap_usd: Option<f64>,
+    pub total_volume_usd_24h: Option<f64>,
+    /// bitcoin's share of the total market cap, in percent
+    pub bitcoin_dominance: Option<f64>,
+}
+
+/// The global endpoint next to the ticker one, like
+/// `https://api.coinmarketcap.com/v1/global/`.
+fn url(net: &Net) -> String {
+    let base = net.api.trim_end_matches('/');
+    let base = base.strip_suffix("ticker").unwrap_or(base);
+    format!("{}/global/", base.trim_end_matches('/'))
+}
+
+fn number(object: &Value, field: &str) -> Option<f64> {
+    match object.get(field)? {
+        Value::Number(number) => number.as_f64(),
+        Value::String(text) => text.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn parse(content: &str) -> Result<Global, Error> {
+    let value = schema::unwrap_data("global", serde_json::from_str(content)?);
+    if !value.is_object() {
+        return Err(Error::Parse(
+            "the global endpoint didn't answer with an object".to_string(),
+        ));
+    }
+    Ok(Global {
+        fetched: history::now(),
+        total_market_cap_usd: number(&value, "total_market_cap_usd"),
+        total_volume_usd_24h: number(&value, "total_24h_volume_usd"),
+        bitcoin_dominance: number(&value, "bitcoin_percentage_of_market_cap"),
+    })
+}
+
+fn fetch(net: &Net) -> Result<Global, Error> {
+    let url = url(net);
+    debug!("retrieving {}", url);
+    let resp = net.get(&url)?;
+    if !resp.status.is_success() {
+        return Err(Error::Network(format!("{} answered {}", url, resp.status)));
+    }
+    parse(&resp.text())
+}
+
+impl Global {
+    /// The market totals from the cache directory, fetched again when
+    /// they're five minutes old. A failed fetch falls back to the older
+    /// totals, and offline there are only ever the older totals.
+    pub fn load(offline: bool, net: &Net) -> Result<Global, Error> {
+        let path = dirs::cache_dir()?.join("global.json");
+        let cached: Option<Global> = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok());
+        match cached {
+            Some(global) if offline || history::now() - global.fetched < REFRESH => {
+                return Ok(global)
+            }
+            None if offline => {
+                return Err(Error::Network(
+                    "no market totals are cached, and --offline is set".to_string(),
+                ))
+            }
+            _ => {}
+        }
+        match fetch(net) {
+            Ok(global) => {
+                fs::write(&path, serde_json::to_string(&global)?)?;
+                Ok(global)
+            }
+            Err(err) => match cached {
+                Some(global) => {
+                    debug!("{}, using the older market totals", err);
+                    Ok(global)
+                }
+                None => Err(err),
+            },
+        }
+    }
+}
+
+/// What `cryptoticker global` prints, like
+/// `market cap $1.71T, 24h volume $89.2B, btc dominance 52.31%`.
+pub fn report(global: &Global) -> String {
+    let usd =
+        |value: Option<f64>| value.map_or("?".to_string(), |value| format!("${}", compact(value)));
+    let dominance = global
+        .bitcoin_dominance
+        .map_or("?".to_string(), |dominance| format!("{:.2}%", dominance));
+    format!(
+        "market cap {}, 24h volume {}, btc dominance {}",
+        usd(global.total_market_cap_usd),
+        usd(global.total_volume_usd_24h),
+        dominance
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_market_totals() {
+        let global = parse(
+            r#"{"total_market_cap_usd": 1712000000000.0, "total_24h_volume_usd": "89200000000",
+                "bitcoin_percentage_of_market_cap": 52.31, "active_currencies": 900}"#,
+        )
+        .unwrap();
+        assert_eq!(global.total_volume_usd_24h, Some(89.2e9));
+        assert_eq!(
+            report(&global),
+            "market cap $1.71T, 24h volume $89.2B, btc dominance 52.31%"
+        );
+        let wrapped = parse(r#"{"data": {"bitcoin_percentage_of_market_cap": 50}}"#).unwrap();
+        assert_eq!(wrapped.bitcoin_dominance, Some(50.0));
+        assert_eq!(wrapped.total_market_cap_usd, None);
+        assert!(parse("[]").is_err());
+    }
+
+    #[test]
+    fn finds_the_endpoint_beside_the_tickers() {
+        let mut net = Net::default();
+        assert_eq!(url(&net), "https://api.coinmarketcap.com/v1/global/");
+        net.api = "http://127.0.0.1:8080/";
+        assert_eq!(url(&net), "http://127.0.0.1:8080/global/");
+    }
+}
@@ -0,0 +1,390 @@
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use clap::ArgMatches;
+use rusqlite::Connection;
+use serde_json;
+use time;
+
+use dirs;
+use errors::Error;
+use symbols::Symbols;
+use ticker::{to_f64, Currency};
+
+/// Every sample fetched in interval mode, kept in a SQLite database in
+/// the user data directory.
+pub struct History {
+    conn: Connection,
+}
+
+impl History {
+    pub fn open() -> Result<History, Error> {
+        History::with_connection(open_database("history.sqlite")?)
+    }
+
+    fn with_connection(mut conn: Connection) -> Result<History, Error> {
+        migrate(&mut conn, MIGRATIONS)?;
+        Ok(History { conn })
+    }
+
+    pub fn record(&self, ticker: &Currency) -> Result<(), Error> {
+        let price = to_f64(ticker.price_usd);
+        let volume = to_f64(ticker.volume_usd_24h);
+        let supply = to_f64(ticker.available_supply);
+        self.conn.execute(
+            "INSERT INTO samples (coin, price_usd, volume_usd_24h, available_supply, timestamp)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            (&ticker.id, price, volume, supply, now()),
+        )?;
+        Ok(())
+    }
+
+    /// Every sample of `coin` taken at or after the unix time `since`,
+    /// oldest first.
+    pub fn samples(&self, coin: &str, since: i64) -> Result<Vec<Sample>, Error> {
+        let mut statement = self.conn.prepare(
+            "SELECT price_usd, volume_usd_24h, available_supply, timestamp FROM samples
+             WHERE coin = ?1 AND timestamp >= ?2 ORDER BY timestamp",
+        )?;
+        let rows = statement.query_map((coin, since), |row| {
+            Ok(Sample {
+                coin: coin.to_string(),
+                price_usd: row.get(0)?,
+                volume_usd_24h: row.get(1)?,
+                available_supply: row.get(2)?,
+                timestamp: row.get(3)?,
+            })
+        })?;
+        let mut samples = Vec::new();
+        for sample in rows {
+            samples.push(sample?);
+        }
+        Ok(samples)
+    }
+
+    /// The last `count` prices recorded for `coin`, oldest first.
+    pub fn recent_prices(&self, coin: &str, count: usize) -> Result<Vec<f64>, Error> {
+        let mut statement = self.conn.prepare(
+            "SELECT price_usd FROM samples WHERE coin = ?1 AND price_usd IS NOT NULL
+             ORDER BY timestamp DESC LIMIT ?2",
+        )?;
+        let rows = statement.query_map((coin, count as i64), |row| row.get(0))?;
+        let mut prices = Vec::new();
+        for price in rows {
+            prices.push(price?);
+        }
+        prices.reverse();
+        Ok(prices)
+    }
+
+    /// When the oldest sample of `coin` was taken, if there is one.
+    pub fn oldest(&self, coin: &str) -> Result<Option<i64>, Error> {
+        Ok(self.conn.query_row(
+            "SELECT MIN(timestamp) FROM samples WHERE coin = ?1",
+            [coin],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// Stores `samples` taken elsewhere, like a provider's past prices,
+    /// all at once.
+    pub fn import(&self, samples: &[Sample]) -> Result<(), Error> {
+        let tx = self.conn.unchecked_transaction()?;
+        for sample in samples {
+            tx.execute(
+                "INSERT INTO samples (coin, price_usd, volume_usd_24h, available_supply, timestamp)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                (
+                    &sample.coin,
+                    sample.price_usd,
+                    sample.volume_usd_24h,
+                    sample.available_supply,
+                    sample.timestamp,
+                ),
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Sample {
+    pub coin: String,
+    pub price_usd: Option<f64>,
+    pub volume_usd_24h: Option<f64>,
+    pub available_supply: Option<f64>,
+    pub timestamp: i64,
+}
+
+/// Parses a span like `90m`, `24h`, `7d` or `2w` into seconds.
+pub fn parse_span(span: &str) -> Result<i64, String> {
+    let span = span.trim();
+    let (digits, scale) = match span.chars().last() {
+        Some('s') => (&span[..span.len() - 1], 1),
+        Some('m') => (&span[..span.len() - 1], 60),
+        Some('h') => (&span[..span.len() - 1], 60 * 60),
+        Some('d') => (&span[..span.len() - 1], 24 * 60 * 60),
+        Some('w') => (&span[..span.len() - 1], 7 * 24 * 60 * 60),
+        _ => (span, 1),
+    };
+    match digits.parse::<i64>() {
+        Ok(n) if n >= 0 => Ok(n * scale),
+        _ => Err(format!("{:?} should be a span like 90m, 24h or 7d", span)),
+    }
+}
+
+/// Formats a number of seconds with its two largest units, like `3d 4h`,
+/// the other way around from `parse_span`.
+pub fn format_span(secs: i64) -> String {
+    let (days, hours, minutes) = (secs / 86_400, secs % 86_400 / 3600, secs % 3600 / 60);
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m", minutes)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+/// Dumps or summarizes the stored samples for one coin.
+pub fn query(
+    matches: &ArgMatches,
+    symbols: &Symbols,
+    overrides: &HashMap<String, String>,
+) -> Result<(), Error> {
+    let coin = symbols.resolve(matches.value_of("TICKER").unwrap(), overrides);
+    let since = match matches.value_of("since") {
+        Some(span) => now() - parse_span(span)?,
+        None => 0,
+    };
+    let samples = History::open()?.samples(coin, since)?;
+    match matches.value_of("output").unwrap() {
+        "csv" => print!("{}", csv(&samples)),
+        "json" => println!("{}", serde_json::to_string_pretty(&samples)?),
+        _ => print!("{}", table(&samples)),
+    }
+    Ok(())
+}
+
+/// `secs` as a UTC time, like `2017-06-15T21:54:15Z`.
+pub fn timestamp(secs: i64) -> String {
+    let tm = time::at_utc(time::Timespec::new(secs, 0));
+    time::strftime("%Y-%m-%dT%H:%M:%SZ", &tm).unwrap_or_default()
+}
+
+fn optional(value: Option<f64>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+fn csv(samples: &[Sample]) -> String {
+    let mut out = "timestamp,coin,price_usd,volume_usd_24h\n".to_string();
+    for sample in samples {
+        out.push_str(&format!(
+            "{},{},{},{}\n",
+            timestamp(sample.timestamp),
+            sample.coin,
+            optional(sample.price_usd),
+            optional(sample.volume_usd_24h)
+        ));
+    }
+    out
+}
+
+#[derive(Debug, PartialEq)]
+struct Summary {
+    count: usize,
+    min: f64,
+    max: f64,
+    avg: f64,
+    first: f64,
+    last: f64,
+}
+
+fn summarize(samples: &[Sample]) -> Option<Summary> {
+    let prices: Vec<f64> = samples.iter().filter_map(|s| s.price_usd).collect();
+    if prices.is_empty() {
+        return None;
+    }
+    Some(Summary {
+        count: prices.len(),
+        min: prices.iter().cloned().fold(f64::MAX, f64::min),
+        max: prices.iter().cloned().fold(f64::MIN, f64::max),
+        avg: prices.iter().sum::<f64>() / prices.len() as f64,
+        first: prices[0],
+        last: prices[prices.len() - 1],
+    })
+}
+
+fn table(samples: &[Sample]) -> String {
+    let mut out = format!(
+        "{:<20}  {:>14}  {:>16}\n",
+        "TIMESTAMP", "PRICE (USD)", "24H VOLUME"
+    );
+    for sample in samples {
+        out.push_str(&format!(
+            "{:<20}  {:>14}  {:>16}\n",
+            timestamp(sample.timestamp),
+            optional(sample.price_usd),
+            optional(sample.volume_usd_24h)
+        ));
+    }
+    match summarize(samples) {
+        Some(summary) => out.push_str(&format!(
+            "\n{} samples  min {:.2}  max {:.2}  avg {:.2}  change {:+.2}%\n",
+            summary.count,
+            summary.min,
+            summary.max,
+            summary.avg,
+            (summary.last - summary.first) / summary.first * 100.0
+        )),
+        None => out.push_str("\nno samples\n"),
+    }
+    out
+}
+
+/// Schema migrations, applied in order. The database's `user_version`
+/// records how many have run, so append new steps and never edit old ones.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE IF NOT EXISTS samples (
+         coin TEXT NOT NULL,
+         price_usd REAL,
+         volume_usd_24h REAL,
+         timestamp INTEGER NOT NULL
+     );
+     CREATE INDEX IF NOT EXISTS samples_by_coin ON samples (coin, timestamp);",
+    "ALTER TABLE samples ADD COLUMN available_supply REAL;",
+];
+
+/// Opens `file` in the user data directory.
+pub fn open_database(file: &str) -> Result<Connection, Error> {
+    Ok(Connection::open(dirs::data_dir()?.join(file))?)
+}
+
+/// Brings `conn` up to date with `migrations`, like `MIGRATIONS` for the
+/// history.
+pub fn migrate(conn: &mut Connection, migrations: &[&str]) -> Result<(), Error> {
+    let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version > migrations.len() {
+        return Err(Error::Other(format!(
+            "database is version {}, newer than this cryptoticker understands",
+            version
+        )));
+    }
+    for (index, migration) in migrations.iter().enumerate().skip(version) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", index + 1)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+pub fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history() -> History {
+        let history = History::with_connection(Connection::open_in_memory().unwrap()).unwrap();
+        for &(price, timestamp) in &[
+            (Some(100.0), 1000),
+            (None, 1500),
+            (Some(120.0), 2000),
+            (Some(80.0), 3000),
+        ] {
+            history
+                .conn
+                .execute(
+                    "INSERT INTO samples (coin, price_usd, volume_usd_24h, timestamp)
+                     VALUES ('bitcoin', ?1, NULL, ?2)",
+                    (price, timestamp),
+                )
+                .unwrap();
+        }
+        history
+    }
+
+    #[test]
+    fn parses_spans() {
+        assert_eq!(parse_span("24h"), Ok(86_400));
+        assert_eq!(parse_span("90m"), Ok(5400));
+        assert_eq!(parse_span("2w"), Ok(1_209_600));
+        assert_eq!(parse_span("300"), Ok(300));
+        assert!(parse_span("yesterday").is_err());
+        assert!(parse_span("-1d").is_err());
+    }
+
+    #[test]
+    fn formats_spans() {
+        assert_eq!(format_span(3 * 86_400 + 4 * 3600 + 59), "3d 4h");
+        assert_eq!(format_span(5 * 3600 + 12 * 60), "5h 12m");
+        assert_eq!(format_span(90), "1m");
+        assert_eq!(format_span(30), "30s");
+    }
+
+    #[test]
+    fn filters_by_coin_and_time() {
+        let history = history();
+        assert_eq!(history.samples("bitcoin", 1500).unwrap().len(), 3);
+        assert!(history.samples("ethereum", 0).unwrap().is_empty());
+        assert_eq!(history.recent_prices("bitcoin", 2).unwrap(), [120.0, 80.0]);
+        assert_eq!(
+            history.recent_prices("bitcoin", 9).unwrap(),
+            [100.0, 120.0, 80.0]
+        );
+    }
+
+    #[test]
+    fn imports_samples() {
+        let history = history();
+        assert_eq!(history.oldest("bitcoin").unwrap(), Some(1000));
+        assert_eq!(history.oldest("ethereum").unwrap(), None);
+        let sample = Sample {
+            coin: "bitcoin".to_string(),
+            price_usd: Some(90.0),
+            volume_usd_24h: Some(1e9),
+            available_supply: None,
+            timestamp: 500,
+        };
+        history.import(std::slice::from_ref(&sample)).unwrap();
+        assert_eq!(history.oldest("bitcoin").unwrap(), Some(500));
+        assert_eq!(history.samples("bitcoin", 0).unwrap()[0], sample);
+    }
+
+    #[test]
+    fn summarizes_prices() {
+        let samples = history().samples("bitcoin", 0).unwrap();
+        assert_eq!(
+            summarize(&samples),
+            Some(Summary {
+                count: 3,
+                min: 80.0,
+                max: 120.0,
+                avg: 100.0,
+                first: 100.0,
+                last: 80.0,
+            })
+        );
+        assert_eq!(summarize(&[]), None);
+    }
+
+    #[test]
+    fn dumps_csv() {
+        let samples = history().samples("bitcoin", 2000).unwrap();
+        assert_eq!(
+            csv(&samples),
+            "timestamp,coin,price_usd,volume_usd_24h\n\
+             1970-01-01T00:33:20Z,bitcoin,120,\n\
+             1970-01-01T00:50:00Z,bitcoin,80,\n"
+        );
+    }
+}
@@ -0,0 +1,133 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+
+use errors::Error;
+
+/// What a handler answers a request with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Response {
+    pub fn ok(content_type: &'static str, body: String) -> Response {
+        Response {
+            status: 200,
+            content_type,
+            body,
+        }
+    }
+
+    pub fn not_found() -> Response {
+        Response {
+            status: 404,
+            content_type: "text/plain",
+            body: "not found\n".to_string(),
+        }
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        500 => "Internal Server Error",
+        502 => "Bad Gateway",
+        _ => "",
+    }
+}
+
+/// The path of a request line like `GET /metrics HTTP/1.1`, or the
+/// response to send if it isn't a GET.
+fn path(request_line: &str) -> Result<&str, Response> {
+    let mut parts = request_line.split_whitespace();
+    match (parts.next(), parts.next()) {
+        (Some("GET"), Some(path)) => Ok(path),
+        (Some(_), Some(_)) => Err(Response {
+            status: 405,
+            content_type: "text/plain",
+            body: "only GET is supported\n".to_string(),
+        }),
+        _ => Err(Response {
+            status: 400,
+            content_type: "text/plain",
+            body: "bad request\n".to_string(),
+        }),
+    }
+}
+
+fn answer<F>(stream: TcpStream, handler: &F) -> Result<(), Error>
+where
+    F: Fn(&str) -> Response,
+{
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // the headers say nothing a handler needs, but have to be read
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+    let response = match path(&request_line) {
+        Ok(path) => handler(path),
+        Err(response) => response,
+    };
+    debug!("{} for {}", response.status, request_line.trim());
+    write!(
+        &stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        reason(response.status),
+        response.content_type,
+        response.body.len(),
+        response.body
+    )?;
+    Ok(())
+}
+
+/// Answers GET requests on `listen`, like `127.0.0.1:9185`, with what
+/// `handler` returns for the path. Each connection gets a thread and a
+/// single response; this is for scrapers and scripts, not browsers.
+pub fn serve<F>(listen: &str, handler: F) -> Result<(), Error>
+where
+    F: Fn(&str) -> Response + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind(listen)
+        .map_err(|err| Error::Other(format!("could not listen on {}: {}", listen, err)))?;
+    info!("listening on {}", listen);
+    let handler = Arc::new(handler);
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                warn!("could not accept a connection: {}", err);
+                continue;
+            }
+        };
+        let handler = handler.clone();
+        thread::spawn(move || {
+            if let Err(err) = answer(stream, &*handler) {
+                debug!("request failed: {}", err);
+            }
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_request_lines() {
+        assert_eq!(path("GET /metrics HTTP/1.1\r\n"), Ok("/metrics"));
+        assert_eq!(path("POST /metrics HTTP/1.1\r\n").unwrap_err().status, 405);
+        assert_eq!(path("\r\n").unwrap_err().status, 400);
+    }
+}
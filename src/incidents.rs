@@ -0,0 +1,268 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use reqwest::StatusCode;
+use rusqlite::Connection;
+
+use errors::Error;
+use history::{self, format_span, migrate, open_database};
+use net::Response;
+
+/// Hosts cryptoticker talks to, by the name a person knows them by.
+const PROVIDERS: &[(&str, &str)] = &[
+    ("api.coinmarketcap.com", "coinmarketcap"),
+    ("api.coingecko.com", "CoinGecko"),
+    ("api.alternative.me", "alternative.me"),
+    ("api.etherscan.io", "Etherscan"),
+    ("api.glassnode.com", "Glassnode"),
+    ("www.ecb.europa.eu", "the ECB"),
+];
+
+/// What a failure is followed by when the provider answers again.
+pub const RECOVERED: &str = "recovered";
+
+/// Failures further apart than this are separate outages, for runs of
+/// one-shot processes that never saw the provider come back.
+const GAP: i64 = 15 * 60;
+
+const MIGRATIONS: &[&str] = &["CREATE TABLE IF NOT EXISTS incidents (
+         provider TEXT NOT NULL,
+         class TEXT NOT NULL,
+         timestamp INTEGER NOT NULL
+     );
+     CREATE INDEX IF NOT EXISTS incidents_by_time ON incidents (timestamp);"];
+
+/// Every failed request, and every recovery after one, kept in
+/// `incidents.sqlite` next to the history.
+pub struct Log {
+    conn: Connection,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Incident {
+    pub provider: String,
+    /// like `timeout` or `rate limited`, or `recovered`
+    pub class: String,
+    pub timestamp: i64,
+}
+
+impl Log {
+    pub fn open() -> Result<Log, Error> {
+        Log::with_connection(open_database("incidents.sqlite")?)
+    }
+
+    fn with_connection(mut conn: Connection) -> Result<Log, Error> {
+        migrate(&mut conn, MIGRATIONS)?;
+        Ok(Log { conn })
+    }
+
+    pub fn record(&self, incident: &Incident) -> Result<(), Error> {
+        self.conn.execute(
+            "INSERT INTO incidents (provider, class, timestamp) VALUES (?1, ?2, ?3)",
+            (&incident.provider, &incident.class, incident.timestamp),
+        )?;
+        Ok(())
+    }
+
+    /// The incidents since `since`, oldest first.
+    pub fn since(&self, since: i64) -> Result<Vec<Incident>, Error> {
+        let mut statement = self.conn.prepare(
+            "SELECT provider, class, timestamp FROM incidents WHERE timestamp >= ?1
+             ORDER BY timestamp, rowid",
+        )?;
+        let rows = statement.query_map([since], |row| {
+            Ok(Incident {
+                provider: row.get(0)?,
+                class: row.get(1)?,
+                timestamp: row.get(2)?,
+            })
+        })?;
+        let mut incidents = Vec::new();
+        for incident in rows {
+            incidents.push(incident?);
+        }
+        Ok(incidents)
+    }
+}
+
+/// Who answers `url`: a provider's name, or the bare host.
+pub fn provider(url: &str) -> String {
+    let rest = url.split("://").nth(1).unwrap_or(url);
+    let host = rest.split(['/', '?']).next().unwrap_or(rest);
+    PROVIDERS
+        .iter()
+        .find(|&&(known, _)| known == host)
+        .map_or(host.to_string(), |&(_, name)| name.to_string())
+}
+
+/// What went wrong with a request, or `None` if nothing did. Answers
+/// like 404 are the request's fault, not the provider's.
+pub fn class(result: &Result<Response, Error>) -> Option<&'static str> {
+    match *result {
+        Ok(ref resp) if resp.status == StatusCode::TooManyRequests => Some("rate limited"),
+        Ok(ref resp) if resp.status.is_server_error() => Some("server error"),
+        Ok(_) => None,
+        Err(Error::Network(ref message)) if message.contains("timed out") => Some("timeout"),
+        Err(Error::Network(_)) => Some("network"),
+        Err(Error::RateLimited { .. }) => Some("rate limited"),
+        Err(_) => Some("error"),
+    }
+}
+
+/// Providers that failed in this process and haven't answered since.
+static FAILING: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Logs a failed request to `url`, or the first good one after a
+/// failure. Logging never gets in the way of the request itself.
+pub fn observe(url: &str, class: Option<&str>) {
+    let provider = provider(url);
+    let mut failing = FAILING
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let class = match class {
+        Some(class) => {
+            if !failing.contains(&provider) {
+                failing.push(provider.clone());
+            }
+            class
+        }
+        None if failing.contains(&provider) => {
+            failing.retain(|known| *known != provider);
+            RECOVERED
+        }
+        None => return,
+    };
+    let incident = Incident {
+        provider,
+        class: class.to_string(),
+        timestamp: history::now(),
+    };
+    if let Err(err) = Log::open().and_then(|log| log.record(&incident)) {
+        debug!("could not log an incident: {}", err);
+    }
+}
+
+/// How one provider fared.
+#[derive(Debug, PartialEq)]
+pub struct Summary {
+    pub failures: usize,
+    /// the longest run of failures, in seconds, from the first to the
+    /// recovery or, without one, the last failure
+    pub longest: i64,
+}
+
+pub fn summarize(incidents: &[Incident]) -> BTreeMap<&str, Summary> {
+    // when each provider's current outage started, and its last failure
+    let mut open: BTreeMap<&str, (i64, i64)> = BTreeMap::new();
+    let mut summaries: BTreeMap<&str, Summary> = BTreeMap::new();
+    for incident in incidents {
+        let provider = incident.provider.as_str();
+        let summary = summaries.entry(provider).or_insert(Summary {
+            failures: 0,
+            longest: 0,
+        });
+        let run = open.get(provider).cloned();
+        if incident.class == RECOVERED {
+            if let Some((start, _)) = run {
+                summary.longest = summary.longest.max(incident.timestamp - start);
+                open.remove(provider);
+            }
+            continue;
+        }
+        summary.failures += 1;
+        let start = match run {
+            Some((start, last)) if incident.timestamp - last <= GAP => start,
+            _ => incident.timestamp,
+        };
+        summary.longest = summary.longest.max(incident.timestamp - start);
+        open.insert(provider, (start, incident.timestamp));
+    }
+    summaries.retain(|_, summary| summary.failures > 0);
+    summaries
+}
+
+/// Like `CoinGecko failed 14 times in the last 24h, longest outage 22m`.
+pub fn report(incidents: &[Incident], span: &str) -> String {
+    let summaries = summarize(incidents);
+    if summaries.is_empty() {
+        return format!("no provider failed in the last {}\n", span);
+    }
+    let mut out = String::new();
+    for (provider, summary) in summaries {
+        let times = match summary.failures {
+            1 => "once".to_string(),
+            n => format!("{} times", n),
+        };
+        let longest = if summary.longest < 60 {
+            "under a minute".to_string()
+        } else {
+            format_span(summary.longest)
+        };
+        out.push_str(&format!(
+            "{} failed {} in the last {}, longest outage {}\n",
+            provider, times, span, longest
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn incident(provider: &str, class: &str, timestamp: i64) -> Incident {
+        Incident {
+            provider: provider.to_string(),
+            class: class.to_string(),
+            timestamp,
+        }
+    }
+
+    #[test]
+    fn names_providers() {
+        assert_eq!(
+            provider("https://api.coingecko.com/api/v3/coins/list"),
+            "CoinGecko"
+        );
+        assert_eq!(provider("http://127.0.0.1:8080/bitcoin/"), "127.0.0.1:8080");
+    }
+
+    #[test]
+    fn measures_outages() {
+        let incidents = vec![
+            incident("CoinGecko", "timeout", 0),
+            incident("CoinGecko", "timeout", 600),
+            incident("CoinGecko", "server error", 1200),
+            incident("CoinGecko", RECOVERED, 1320),
+            // a second outage, never seen to end
+            incident("CoinGecko", "network", 10_000),
+            incident("the ECB", "network", 10_000),
+            incident("CoinGecko", "network", 20_000),
+        ];
+        let summaries = summarize(&incidents);
+        assert_eq!(
+            summaries["CoinGecko"],
+            Summary {
+                failures: 5,
+                longest: 1320,
+            }
+        );
+        assert_eq!(
+            report(&incidents, "24h"),
+            "CoinGecko failed 5 times in the last 24h, longest outage 22m\n\
+             the ECB failed once in the last 24h, longest outage under a minute\n"
+        );
+        assert_eq!(report(&[], "1h"), "no provider failed in the last 1h\n");
+    }
+
+    #[test]
+    fn keeps_incidents() {
+        let log = Log::with_connection(Connection::open_in_memory().unwrap()).unwrap();
+        log.record(&incident("CoinGecko", "timeout", 10)).unwrap();
+        log.record(&incident("CoinGecko", RECOVERED, 20)).unwrap();
+        assert_eq!(
+            log.since(15).unwrap(),
+            vec![incident("CoinGecko", RECOVERED, 20)]
+        );
+    }
+}
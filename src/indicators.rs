@@ -0,0 +1,232 @@
+use std::fmt;
+use std::str::FromStr;
+
+use errors::Error;
+use history::{self, parse_span, History};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Sma,
+    Ema,
+    Rsi,
+}
+
+/// An indicator computed over the samples recorded in the last `span`
+/// seconds, written like `sma_1h` or `rsi_14d`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Indicator {
+    pub kind: Kind,
+    pub span: i64,
+    label: String,
+}
+
+impl FromStr for Indicator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Indicator, String> {
+        let underscore = s
+            .find('_')
+            .ok_or_else(|| format!("{:?} should look like sma_1h, ema_4h or rsi_14d", s))?;
+        let kind = match &s[..underscore] {
+            "sma" => Kind::Sma,
+            "ema" => Kind::Ema,
+            "rsi" => Kind::Rsi,
+            other => return Err(format!("unknown indicator {:?}", other)),
+        };
+        let span = parse_span(&s[underscore + 1..])?;
+        Ok(Indicator {
+            kind,
+            span,
+            label: s.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for Indicator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.label)
+    }
+}
+
+impl Indicator {
+    /// Computes the indicator for `coin` from the local history, or `None`
+    /// if too few samples were recorded in the span.
+    pub fn compute(&self, history: &History, coin: &str) -> Result<Option<f64>, Error> {
+        let prices: Vec<f64> = history
+            .samples(coin, history::now() - self.span)?
+            .iter()
+            .filter_map(|sample| sample.price_usd)
+            .collect();
+        Ok(self.over(&prices))
+    }
+
+    fn over(&self, prices: &[f64]) -> Option<f64> {
+        match self.kind {
+            Kind::Sma => sma(prices),
+            Kind::Ema => ema(prices),
+            Kind::Rsi => rsi(prices),
+        }
+    }
+}
+
+pub fn sma(prices: &[f64]) -> Option<f64> {
+    if prices.is_empty() {
+        return None;
+    }
+    Some(prices.iter().sum::<f64>() / prices.len() as f64)
+}
+
+/// Weights recent samples more, with the usual `2 / (n + 1)` smoothing
+/// over however many samples the span holds.
+pub fn ema(prices: &[f64]) -> Option<f64> {
+    let first = *prices.first()?;
+    let alpha = 2.0 / (prices.len() as f64 + 1.0);
+    Some(
+        prices[1..]
+            .iter()
+            .fold(first, |ema, price| alpha * price + (1.0 - alpha) * ema),
+    )
+}
+
+/// The relative strength index from the average gain and loss between
+/// consecutive samples, from 0 (only losses) to 100 (only gains).
+pub fn rsi(prices: &[f64]) -> Option<f64> {
+    if prices.len() < 2 {
+        return None;
+    }
+    let (mut gains, mut losses) = (0.0, 0.0);
+    for pair in prices.windows(2) {
+        let change = pair[1] - pair[0];
+        if change > 0.0 {
+            gains += change;
+        } else {
+            losses -= change;
+        }
+    }
+    if gains + losses == 0.0 {
+        return Some(50.0);
+    }
+    Some(100.0 * gains / (gains + losses))
+}
+
+/// How far back `{inflation}` looks for the supply to compare against.
+const INFLATION_SPAN: i64 = 365 * 86_400;
+
+/// Supply moves slowly and in steps, so samples closer together than a
+/// day say too little to project a year from.
+const INFLATION_MIN_SPAN: i64 = 86_400;
+
+/// The yearly supply inflation of `coin` in percent, from the circulating
+/// supply recorded over the last year, or `None` until a day of it is.
+pub fn inflation(history: &History, coin: &str) -> Result<Option<f64>, Error> {
+    let supplies: Vec<(i64, f64)> = history
+        .samples(coin, history::now() - INFLATION_SPAN)?
+        .iter()
+        .filter_map(|sample| {
+            sample
+                .available_supply
+                .map(|supply| (sample.timestamp, supply))
+        })
+        .collect();
+    Ok(annualized(&supplies))
+}
+
+/// The growth from the first to the last `(timestamp, supply)`, scaled
+/// to a year.
+fn annualized(supplies: &[(i64, f64)]) -> Option<f64> {
+    let (start, first) = *supplies.first()?;
+    let (end, last) = *supplies.last()?;
+    if end - start < INFLATION_MIN_SPAN || first <= 0.0 {
+        return None;
+    }
+    let year = 365.0 * 86_400.0;
+    Some((last - first) / first * 100.0 * year / (end - start) as f64)
+}
+
+/// How far back `{vs_30d_avg}` averages.
+const AVERAGE_SPAN: i64 = 30 * 86_400;
+
+/// A few hours of samples would make a 30-day average of an afternoon.
+const AVERAGE_MIN_SPAN: i64 = 86_400;
+
+/// How far `price` is above or below `coin`'s average over the last 30
+/// days in percent, from the prices recorded, or `None` until a day of
+/// them is.
+pub fn vs_average(history: &History, coin: &str, price: f64) -> Result<Option<f64>, Error> {
+    let prices: Vec<(i64, f64)> = history
+        .samples(coin, history::now() - AVERAGE_SPAN)?
+        .iter()
+        .filter_map(|sample| sample.price_usd.map(|price| (sample.timestamp, price)))
+        .collect();
+    Ok(against(&prices, price))
+}
+
+fn against(prices: &[(i64, f64)], price: f64) -> Option<f64> {
+    let (start, _) = *prices.first()?;
+    let (end, _) = *prices.last()?;
+    if end - start < AVERAGE_MIN_SPAN {
+        return None;
+    }
+    let prices: Vec<f64> = prices.iter().map(|&(_, price)| price).collect();
+    let average = sma(&prices).filter(|&average| average > 0.0)?;
+    Some((price / average - 1.0) * 100.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_indicators() {
+        let indicator: Indicator = "sma_1h".parse().unwrap();
+        assert_eq!(indicator.kind, Kind::Sma);
+        assert_eq!(indicator.span, 3600);
+        assert_eq!(indicator.to_string(), "sma_1h");
+        assert_eq!("rsi_14d".parse::<Indicator>().unwrap().span, 14 * 86_400);
+        assert!("macd_1h".parse::<Indicator>().is_err());
+        assert!("sma".parse::<Indicator>().is_err());
+        assert!("ema_soon".parse::<Indicator>().is_err());
+    }
+
+    #[test]
+    fn averages() {
+        assert_eq!(sma(&[]), None);
+        assert_eq!(sma(&[1.0, 2.0, 3.0, 6.0]), Some(3.0));
+        assert_eq!(ema(&[]), None);
+        assert_eq!(ema(&[5.0]), Some(5.0));
+        // alpha is 0.5 for three samples
+        assert_eq!(ema(&[2.0, 4.0, 8.0]), Some(5.5));
+    }
+
+    #[test]
+    fn relative_strength() {
+        assert_eq!(rsi(&[1.0]), None);
+        assert_eq!(rsi(&[1.0, 2.0, 3.0]), Some(100.0));
+        assert_eq!(rsi(&[3.0, 2.0, 1.0]), Some(0.0));
+        assert_eq!(rsi(&[1.0, 1.0]), Some(50.0));
+        assert_eq!(rsi(&[10.0, 13.0, 12.0]), Some(75.0));
+    }
+
+    #[test]
+    fn annualizes_supply_growth() {
+        assert_eq!(annualized(&[]), None);
+        // an hour apart is too soon to tell
+        assert_eq!(annualized(&[(0, 100.0), (3600, 101.0)]), None);
+        // 1% in a quarter of a year is 4% a year
+        let quarter = 365 * 86_400 / 4;
+        let rate = annualized(&[(0, 1000.0), (quarter / 2, 1004.0), (quarter, 1010.0)]);
+        assert!((rate.unwrap() - 4.0).abs() < 1e-9);
+        // burning shows as deflation
+        assert!(annualized(&[(0, 100.0), (quarter, 99.0)]).unwrap() < 0.0);
+    }
+
+    #[test]
+    fn compares_to_the_average() {
+        assert_eq!(against(&[], 100.0), None);
+        assert_eq!(against(&[(0, 90.0), (3600, 110.0)], 100.0), None);
+        let prices = [(0, 90.0), (43_200, 100.0), (86_400, 110.0)];
+        // 10% over an average of 100
+        assert!((against(&prices, 110.0).unwrap() - 10.0).abs() < 1e-9);
+        assert!(against(&prices, 80.0).unwrap() < 0.0);
+    }
+}
@@ -0,0 +1,102 @@
+use std::os::unix::net::UnixDatagram;
+
+use errors::Error;
+use ticker::Currency;
+
+/// Where systemd-journald takes entries in its native protocol.
+pub const SOCKET: &str = "/run/systemd/journal/socket";
+
+/// What the entries are tagged with, for `journalctl -t cryptoticker`.
+const IDENTIFIER: &str = "cryptoticker";
+
+/// The fields of `ticker`'s entry: a readable `MESSAGE` and the numbers on
+/// their own, so `journalctl -o json` can be queried without parsing it.
+/// A number the ticker doesn't have is left out rather than sent empty.
+fn fields(ticker: &Currency) -> Vec<(&'static str, String)> {
+    let mut fields = vec![
+        ("SYSLOG_IDENTIFIER", IDENTIFIER.to_string()),
+        // informational, like any other refresh
+        ("PRIORITY", "6".to_string()),
+        ("COIN", ticker.id.clone()),
+        ("SYMBOL", ticker.symbol.clone()),
+    ];
+    let mut message = format!("{} {}", ticker.symbol, ticker.id);
+    if let Some(price) = ticker.price_usd {
+        message.push_str(&format!(" ${}", price));
+        fields.push(("PRICE_USD", price.to_string()));
+    }
+    if let Some(change) = ticker.percent_change_24 {
+        message.push_str(&format!(" {}%", change));
+        fields.push(("CHANGE24", change.to_string()));
+    }
+    fields.insert(0, ("MESSAGE", message));
+    fields
+}
+
+/// Lays `fields` out as one datagram: `KEY=value` lines, except that a
+/// value with a newline in it goes after the key as a little-endian
+/// length, since it can't end the line.
+fn encode(fields: &[(&str, String)]) -> Vec<u8> {
+    let mut datagram = Vec::new();
+    for (key, value) in fields {
+        datagram.extend_from_slice(key.as_bytes());
+        if value.contains('\n') {
+            datagram.push(b'\n');
+            datagram.extend_from_slice(&(value.len() as u64).to_le_bytes());
+        } else {
+            datagram.push(b'=');
+        }
+        datagram.extend_from_slice(value.as_bytes());
+        datagram.push(b'\n');
+    }
+    datagram
+}
+
+/// `--journald`, sending every refreshed ticker to the systemd journal as
+/// an entry of its own.
+pub struct Journal {
+    socket: UnixDatagram,
+}
+
+impl Journal {
+    /// Connects to the journal, failing where there isn't one, so that
+    /// `--journald` on a machine without systemd says so up front.
+    pub fn open() -> Result<Journal, Error> {
+        let socket = UnixDatagram::unbound()?;
+        // said plainly, since not having a journal is the usual way this fails
+        socket
+            .connect(SOCKET)
+            .map_err(|err| Error::Other(err.to_string()))?;
+        Ok(Journal { socket })
+    }
+
+    pub fn log(&self, ticker: &Currency) -> Result<(), Error> {
+        self.socket.send(&encode(&fields(ticker)))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn logs_structured_fields() {
+        let top: Vec<Currency> =
+            serde_json::from_str(include_str!("../tests/fixtures/ticker.json")).unwrap();
+        let datagram = String::from_utf8(encode(&fields(&top[0]))).unwrap();
+        assert_eq!(
+            datagram,
+            "MESSAGE=BTC bitcoin $2545.19 -3.39%\n\
+             SYSLOG_IDENTIFIER=cryptoticker\n\
+             PRIORITY=6\n\
+             COIN=bitcoin\n\
+             SYMBOL=BTC\n\
+             PRICE_USD=2545.19\n\
+             CHANGE24=-3.39\n"
+        );
+        let multiline = encode(&[("MESSAGE", "a\nb".to_string())]);
+        assert_eq!(multiline, b"MESSAGE\n\x03\0\0\0\0\0\0\0a\nb\n".to_vec());
+    }
+}
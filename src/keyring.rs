@@ -0,0 +1,109 @@
+use std::io::{stdin, Write};
+use std::process::{Command, Stdio};
+
+use clap::ArgMatches;
+
+use errors::Error;
+
+/// The config keys that may be kept in the OS keyring instead of the
+/// config file, listed in its `keyring`.
+pub const SECRETS: &[&str] = &["onchain_api_key", "etherscan_api_key", "mqtt_password"];
+
+/// What the secrets are filed under.
+const SERVICE: &str = "cryptoticker";
+
+/// The secret stored for config key `name`, `None` if there isn't one.
+/// It is asked of `secret-tool` on Linux and the BSDs and of `security`
+/// on macOS.
+pub fn get(name: &str) -> Result<Option<String>, Error> {
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = Command::new("security");
+        command.args(["find-generic-password", "-w", "-s", SERVICE, "-a", name]);
+        command
+    } else if cfg!(unix) {
+        let mut command = Command::new("secret-tool");
+        command.args(["lookup", "service", SERVICE, "key", name]);
+        command
+    } else {
+        return Err(no_keyring());
+    };
+    let output = command
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .map_err(|err| Error::Other(format!("Could not ask the keyring: {}", err)))?;
+    // both tools fail when there is nothing stored
+    if !output.status.success() {
+        return Ok(None);
+    }
+    let secret = String::from_utf8_lossy(&output.stdout)
+        .trim_end_matches(['\r', '\n'])
+        .to_string();
+    Ok(Some(secret).filter(|secret| !secret.is_empty()))
+}
+
+/// Stores `secret` for config key `name`, replacing what was there.
+pub fn set(name: &str, secret: &str) -> Result<(), Error> {
+    let status = if cfg!(target_os = "macos") {
+        // security only takes the password as an argument
+        Command::new("security")
+            .args([
+                "add-generic-password",
+                "-U",
+                "-s",
+                SERVICE,
+                "-a",
+                name,
+                "-w",
+                secret,
+            ])
+            .stdin(Stdio::null())
+            .status()
+    } else if cfg!(unix) {
+        let label = format!("{} {}", SERVICE, name);
+        Command::new("secret-tool")
+            .args(["store", "--label", &label, "service", SERVICE, "key", name])
+            .stdin(Stdio::piped())
+            .spawn()
+            .and_then(|mut child| {
+                if let Some(mut stdin) = child.stdin.take() {
+                    stdin.write_all(secret.as_bytes())?;
+                }
+                child.wait()
+            })
+    } else {
+        return Err(no_keyring());
+    };
+    let status =
+        status.map_err(|err| Error::Other(format!("Could not reach the keyring: {}", err)))?;
+    if !status.success() {
+        return Err(Error::Other(format!(
+            "The keyring would not store {}: {}",
+            name, status
+        )));
+    }
+    Ok(())
+}
+
+/// Runs `keyring set NAME`, storing the secret read from stdin, so it
+/// stays out of the shell's history.
+pub fn keyring(matches: &ArgMatches) -> Result<(), Error> {
+    let matches = matches.subcommand_matches("set").unwrap();
+    let name = matches.value_of("NAME").unwrap();
+    let mut secret = String::new();
+    stdin().read_line(&mut secret)?;
+    let secret = secret.trim();
+    if secret.is_empty() {
+        return Err(Error::Other(format!("No {} was given on stdin.", name)));
+    }
+    set(name, secret)?;
+    println!(
+        "stored {}; add it to `keyring` in the config to use it",
+        name
+    );
+    Ok(())
+}
+
+fn no_keyring() -> Error {
+    Error::Other("There is no keyring cryptoticker knows on this system.".to_string())
+}
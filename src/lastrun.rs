@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use rust_decimal::Decimal;
+use serde_json;
+
+use dirs;
+use errors::Error;
+use ticker::parse_price;
+
+/// The price each coin was last shown at, by ID, kept in `last.json` so
+/// a status bar running one-shot still knows what the run before it
+/// printed.
+#[derive(Debug, Default)]
+pub struct LastRun {
+    prices: HashMap<String, String>,
+}
+
+fn path() -> Result<PathBuf, Error> {
+    Ok(dirs::cache_dir()?.join("last.json"))
+}
+
+/// How much `price` moved from `before`, like `+15` or `-0.2`.
+fn delta(before: Decimal, price: Decimal) -> String {
+    let delta = (price - before).normalize();
+    if delta.is_sign_negative() && !delta.is_zero() {
+        delta.to_string()
+    } else {
+        format!("+{}", delta.abs())
+    }
+}
+
+impl LastRun {
+    /// The prices the last run showed, none if it never ran.
+    pub fn load() -> LastRun {
+        let prices = path()
+            .ok()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        LastRun { prices }
+    }
+
+    /// `segment` with how far coin `id`'s `price` moved since it was last
+    /// shown after it, like `btc:43010 (+15) `, remembering the price for
+    /// next time. A coin shown for the first time is left as it is.
+    pub fn mark(&mut self, segment: &str, id: &str, price: Option<Decimal>) -> String {
+        let price = match price {
+            Some(price) => price,
+            None => return segment.to_string(),
+        };
+        let before = self
+            .prices
+            .insert(id.to_string(), price.to_string())
+            .and_then(|before| parse_price(&before));
+        match before {
+            Some(before) => format!("{} ({}) ", segment.trim_end(), delta(before, price)),
+            None => segment.to_string(),
+        }
+    }
+
+    /// Keeps the prices for the next run.
+    pub fn save(&self) -> Result<(), Error> {
+        fs::write(path()?, serde_json::to_string(&self.prices)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn marks_the_move_since_last_time() {
+        let mut last = LastRun::default();
+        let price = |price: &str| parse_price(price);
+        assert_eq!(
+            last.mark("btc:42995 ", "bitcoin", price("42995")),
+            "btc:42995 "
+        );
+        assert_eq!(
+            last.mark("btc:43010 ", "bitcoin", price("43010")),
+            "btc:43010 (+15) "
+        );
+        assert_eq!(
+            last.mark("btc:43009.80 ", "bitcoin", price("43009.80")),
+            "btc:43009.80 (-0.2) "
+        );
+        assert_eq!(
+            last.mark("btc:43009.8 ", "bitcoin", price("43009.8")),
+            "btc:43009.8 (+0) "
+        );
+        // a price that failed to come leaves the last one standing
+        assert_eq!(last.mark("btc:null ", "bitcoin", None), "btc:null ");
+        assert_eq!(last.prices["bitcoin"], "43009.8");
+    }
+}
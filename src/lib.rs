@@ -0,0 +1,127 @@
+//! Fetching, caching and rendering crypto prices, the data layer of the
+//! `cryptoticker` command. Other programs can use it directly instead of
+//! shelling out: `ticker::refresh_ticker` fetches a `Currency` through a
+//! `Cache`, and a `Template` renders it.
+
+extern crate app_dirs;
+extern crate arboard;
+extern crate crossterm;
+extern crate fs2;
+#[cfg(feature = "display")]
+extern crate i2cdev;
+extern crate native_tls;
+extern crate reqwest;
+extern crate rusqlite;
+extern crate rust_decimal;
+extern crate serde;
+extern crate serde_json;
+extern crate signal_hook;
+
+#[macro_use]
+extern crate clap;
+#[macro_use]
+extern crate log;
+#[macro_use]
+extern crate serde_derive;
+
+extern crate time;
+extern crate toml;
+
+#[cfg(test)]
+extern crate insta;
+#[cfg(test)]
+extern crate proptest;
+
+use app_dirs::*;
+const APP_INFO: AppInfo = AppInfo {
+    name: "cryptoticker",
+    author: "Josh Leverette",
+};
+
+pub mod aggregate;
+pub mod alerts;
+pub mod anomaly;
+pub mod backfill;
+pub mod cache;
+pub mod calc;
+pub mod chaos;
+pub mod chart;
+pub mod clock;
+pub mod completions;
+pub mod condition;
+pub mod config;
+pub mod contract;
+pub mod convert;
+pub mod csvlog;
+pub mod display;
+pub mod doctor;
+pub mod dryrun;
+#[cfg(unix)]
+pub mod daemon;
+pub mod dirs;
+pub mod errors;
+pub mod events;
+pub mod exporter;
+pub mod firstpaint;
+pub mod fng;
+pub mod freshness;
+pub mod fx;
+pub mod gas;
+pub mod global;
+pub mod history;
+pub mod http;
+pub mod incidents;
+pub mod indicators;
+#[cfg(unix)]
+pub mod journald;
+pub mod keyring;
+pub mod lastrun;
+pub mod liquidations;
+pub mod listings;
+pub mod logo;
+pub mod memcache;
+pub mod mining;
+pub mod motd;
+pub mod missing;
+pub mod mqtt;
+pub mod net;
+pub mod news;
+pub mod obs;
+pub mod onchain;
+pub mod output;
+pub mod padding;
+pub mod pages;
+pub mod pairs;
+pub mod paper;
+pub mod period;
+pub mod peg;
+pub mod rates;
+pub mod ratio;
+pub mod reminders;
+pub mod returns;
+pub mod schedule;
+pub mod schema;
+pub mod server;
+pub mod session;
+pub mod sources;
+pub mod signals;
+pub mod spread;
+pub mod stream;
+pub mod supervise;
+pub mod symbols;
+pub mod template;
+pub mod termux;
+pub mod throttle;
+pub mod ticker;
+pub mod trace;
+pub mod tui;
+pub mod unlocks;
+pub mod wait;
+pub mod watchlist;
+pub mod weekly;
+
+pub use cache::{Cache, CacheBackend};
+pub use errors::Error;
+pub use net::Net;
+pub use template::Template;
+pub use ticker::Currency;
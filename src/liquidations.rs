@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use serde_json;
+
+use dirs;
+use errors::Error;
+use history;
+use net::Net;
+use symbols::Symbols;
+
+/// How long fetched totals are used before asking again. Adding up a day
+/// of liquidations takes several requests, too many for every tmux
+/// refresh.
+const REFRESH: i64 = 15 * 60;
+
+const DAY: i64 = 24 * 60 * 60;
+
+/// OKX returns 100 liquidations a page; a wild day can have thousands, so
+/// stop after this many pages rather than hammer the API.
+const MAX_PAGES: u32 = 30;
+
+/// What was liquidated on one coin's USDT perpetual over the last day,
+/// in USD. Longs are liquidated when the price falls, shorts when it rises.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Liquidations {
+    pub long_usd: f64,
+    pub short_usd: f64,
+}
+
+#[derive(Deserialize)]
+struct OkxResponse<T> {
+    data: Vec<T>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Instrument {
+    ct_val: String,
+}
+
+#[derive(Deserialize)]
+struct Orders {
+    details: Vec<Order>,
+}
+
+/// One liquidation: `sz` contracts of a `posSide` position, closed at the
+/// bankruptcy price `bkPx`, at `ts` milliseconds.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Order {
+    pos_side: String,
+    sz: String,
+    bk_px: String,
+    ts: String,
+}
+
+/// Fetched totals, kept in the cache directory by symbol.
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    fetched: i64,
+    liquidations: Liquidations,
+}
+
+fn get(url: &str, net: &Net) -> Result<String, Error> {
+    debug!("retrieving {}", url);
+    let resp = net.get(url)?;
+    if !resp.status.is_success() {
+        return Err(Error::Network(format!("{} answered {}", url, resp.status)));
+    }
+    Ok(resp.text())
+}
+
+fn number(value: &str) -> Result<f64, Error> {
+    value
+        .parse()
+        .map_err(|_| Error::Parse(format!("OKX sent {:?} for a number", value)))
+}
+
+/// Adds up the orders at or after `since`, worth `contract` coins per
+/// contract, returning the totals and the oldest timestamp seen.
+fn add(
+    totals: &mut Liquidations,
+    orders: &[Order],
+    contract: f64,
+    since: i64,
+) -> Result<Option<i64>, Error> {
+    let mut oldest = None;
+    for order in orders {
+        let ts = number(&order.ts)? as i64;
+        oldest = Some(oldest.map_or(ts, |oldest: i64| oldest.min(ts)));
+        if ts < since * 1000 {
+            continue;
+        }
+        let usd = number(&order.sz)? * contract * number(&order.bk_px)?;
+        match order.pos_side.as_str() {
+            "long" => totals.long_usd += usd,
+            "short" => totals.short_usd += usd,
+            _ => {}
+        }
+    }
+    Ok(oldest)
+}
+
+/// Adds up the last day of liquidations on OKX's `SYMBOL-USDT-SWAP`,
+/// paging back until a day is covered.
+fn fetch(symbol: &str, net: &Net) -> Result<Liquidations, Error> {
+    let url = format!(
+        "https://www.okx.com/api/v5/public/instruments?instType=SWAP&instId={}-USDT-SWAP",
+        symbol
+    );
+    let instruments: OkxResponse<Instrument> = serde_json::from_str(&get(&url, net)?)?;
+    let contract = match instruments.data.first() {
+        Some(instrument) => number(&instrument.ct_val)?,
+        None => return Err(Error::Other(format!("OKX has no {} perpetual", symbol))),
+    };
+    let since = history::now() - DAY;
+    let mut totals = Liquidations {
+        long_usd: 0.0,
+        short_usd: 0.0,
+    };
+    let mut after = String::new();
+    for _ in 0..MAX_PAGES {
+        let url = format!(
+            "https://www.okx.com/api/v5/public/liquidation-orders?instType=SWAP&uly={}-USDT\
+             &state=filled&limit=100{}",
+            symbol, after
+        );
+        let page: OkxResponse<Orders> = serde_json::from_str(&get(&url, net)?)?;
+        let orders: Vec<Order> = page
+            .data
+            .into_iter()
+            .flat_map(|orders| orders.details)
+            .collect();
+        match add(&mut totals, &orders, contract, since)? {
+            Some(oldest) if oldest >= since * 1000 => after = format!("&after={}", oldest),
+            _ => return Ok(totals),
+        }
+    }
+    debug!(
+        "stopped adding up {} liquidations after {} pages",
+        symbol, MAX_PAGES
+    );
+    Ok(totals)
+}
+
+fn path() -> Result<PathBuf, Error> {
+    Ok(dirs::cache_dir()?.join("liquidations.json"))
+}
+
+/// The last day of liquidations for `symbol`, from the cache directory
+/// if they were added up recently. A failed fetch falls back to older
+/// totals, and offline there are only ever the older totals.
+pub fn load(symbol: &str, offline: bool, net: &Net) -> Result<Liquidations, Error> {
+    let path = path()?;
+    let mut entries: HashMap<String, Entry> = fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    match entries.get(symbol) {
+        Some(entry) if offline || history::now() - entry.fetched < REFRESH => {
+            return Ok(entry.liquidations)
+        }
+        None if offline => {
+            return Err(Error::Network(format!(
+                "no liquidations for {} are cached, and --offline is set",
+                symbol
+            )))
+        }
+        _ => {}
+    }
+    match fetch(symbol, net) {
+        Ok(liquidations) => {
+            entries.insert(
+                symbol.to_string(),
+                Entry {
+                    fetched: history::now(),
+                    liquidations,
+                },
+            );
+            fs::write(&path, serde_json::to_string(&entries)?)?;
+            Ok(liquidations)
+        }
+        Err(err) => match entries.get(symbol) {
+            Some(entry) => {
+                debug!("{}, using older liquidations for {}", err, symbol);
+                Ok(entry.liquidations)
+            }
+            None => Err(err),
+        },
+    }
+}
+
+/// Liquidations for each coin ID in `ids` that has a perpetual, for the
+/// `{liq_long_24h}` and `{liq_short_24h}` template fields.
+pub fn load_all(
+    ids: &[String],
+    symbols: &Symbols,
+    offline: bool,
+    net: &Net,
+) -> HashMap<String, Liquidations> {
+    let mut found = HashMap::new();
+    for id in ids {
+        let symbol = match symbols.symbol_of(id) {
+            Some(symbol) => symbol.to_uppercase(),
+            None => continue,
+        };
+        match load(&symbol, offline, net) {
+            Ok(liquidations) => {
+                found.insert(id.clone(), liquidations);
+            }
+            Err(err) => {
+                debug!("no liquidations for {}: {}", id, err);
+            }
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adds_up_a_day() {
+        let page: OkxResponse<Orders> = serde_json::from_str(
+            r#"{"code": "0", "msg": "", "data": [{"instId": "BTC-USDT-SWAP", "details": [
+                {"posSide": "long", "side": "sell", "sz": "10", "bkPx": "50000", "ts": "1700000300000"},
+                {"posSide": "short", "side": "buy", "sz": "2", "bkPx": "51000", "ts": "1700000200000"},
+                {"posSide": "long", "side": "sell", "sz": "99", "bkPx": "49000", "ts": "1699990000000"}
+            ]}]}"#,
+        )
+        .unwrap();
+        let mut totals = Liquidations {
+            long_usd: 0.0,
+            short_usd: 0.0,
+        };
+        let orders: Vec<Order> = page.data.into_iter().flat_map(|o| o.details).collect();
+        // BTC contracts are worth 0.01 BTC
+        let oldest = add(&mut totals, &orders, 0.01, 1_700_000_000).unwrap();
+        assert_eq!(oldest, Some(1_699_990_000_000));
+        assert_eq!(
+            totals,
+            Liquidations {
+                long_usd: 5000.0,
+                short_usd: 1020.0,
+            }
+        );
+        assert_eq!(add(&mut totals, &[], 0.01, 0).unwrap(), None);
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use std::thread::sleep;
+use std::time::Duration;
+
+use clap::ArgMatches;
+use serde_json;
+
+use alerts;
+use net::Net;
+use config::Config;
+use dirs;
+use errors::Error;
+
+/// A coin as CoinGecko lists it. The ticker API has no notion of
+/// exchanges or categories, so listings come from CoinGecko like charts.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Listing {
+    pub id: String,
+    pub symbol: String,
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+struct ExchangeTickers {
+    tickers: Vec<ExchangeTicker>,
+}
+
+#[derive(Deserialize)]
+struct ExchangeTicker {
+    base: String,
+    coin_id: String,
+}
+
+/// CoinGecko pages category and exchange listings; stop after this many
+/// pages rather than hammer the API.
+const MAX_PAGES: u32 = 20;
+
+fn get(url: &str, net: &Net) -> Result<String, Error> {
+    debug!("retrieving {}", url);
+    let resp = net.get(url)?;
+    if !resp.status.is_success() {
+        return Err(Error::Network(format!("{} answered {}", url, resp.status)));
+    }
+    Ok(resp.text())
+}
+
+fn all_coins(net: &Net) -> Result<Vec<Listing>, Error> {
+    Ok(serde_json::from_str(&get(
+        "https://api.coingecko.com/api/v3/coins/list",
+        net,
+    )?)?)
+}
+
+fn category(category: &str, net: &Net) -> Result<Vec<Listing>, Error> {
+    let mut listings = Vec::new();
+    for page in 1..MAX_PAGES + 1 {
+        let url = format!(
+            "https://api.coingecko.com/api/v3/coins/markets?vs_currency=usd&category={}\
+             &per_page=250&page={}",
+            category, page
+        );
+        let batch: Vec<Listing> = serde_json::from_str(&get(&url, net)?)?;
+        if batch.is_empty() {
+            break;
+        }
+        listings.extend(batch);
+    }
+    Ok(listings)
+}
+
+fn exchange(exchange: &str, net: &Net) -> Result<Vec<Listing>, Error> {
+    let mut listings: Vec<Listing> = Vec::new();
+    for page in 1..MAX_PAGES + 1 {
+        let url = format!(
+            "https://api.coingecko.com/api/v3/exchanges/{}/tickers?page={}",
+            exchange, page
+        );
+        let batch: ExchangeTickers = serde_json::from_str(&get(&url, net)?)?;
+        if batch.tickers.is_empty() {
+            break;
+        }
+        for ticker in batch.tickers {
+            if !listings.iter().any(|listing| listing.id == ticker.coin_id) {
+                listings.push(Listing {
+                    id: ticker.coin_id,
+                    symbol: ticker.base.to_lowercase(),
+                    name: ticker.base,
+                });
+            }
+        }
+    }
+    Ok(listings)
+}
+
+/// Fetches the current coin list, narrowed to the coins matching every
+/// filter given.
+fn fetch(
+    category_filter: Option<&str>,
+    exchange_filter: Option<&str>,
+    net: &Net,
+) -> Result<Vec<Listing>, Error> {
+    net.chaos.inject("listings")?;
+    let mut listings = match category_filter {
+        Some(filter) => category(filter, net)?,
+        None if exchange_filter.is_none() => all_coins(net)?,
+        None => vec![],
+    };
+    if let Some(filter) = exchange_filter {
+        let listed = exchange(filter, net)?;
+        if category_filter.is_some() {
+            listings.retain(|listing| listed.iter().any(|l| l.id == listing.id));
+        } else {
+            listings = listed;
+        }
+    }
+    Ok(listings)
+}
+
+/// The listings in `current` that aren't in `known`.
+fn new_listings<'a>(known: &HashSet<String>, current: &'a [Listing]) -> Vec<&'a Listing> {
+    current
+        .iter()
+        .filter(|listing| !known.contains(&listing.id))
+        .collect()
+}
+
+/// Where the coins seen so far are remembered, so listings that appear
+/// while cryptoticker isn't running are still reported. Each filter gets
+/// its own file.
+fn known_path(category: Option<&str>, exchange: Option<&str>) -> Result<PathBuf, Error> {
+    Ok(dirs::cache_dir()?.join(format!(
+        "listings-{}-{}.json",
+        category.unwrap_or("all"),
+        exchange.unwrap_or("all")
+    )))
+}
+
+/// Polls the coin list forever, announcing every coin that wasn't listed
+/// the last time. The very first run only records the baseline.
+pub fn listings(matches: &ArgMatches, config: &Config, net: &Net) -> Result<(), Error> {
+    let interval =
+        Duration::from_secs(value_t!(matches, "interval", u64).unwrap_or_else(|e| e.exit()));
+    let category = matches.value_of("category");
+    let exchange = matches.value_of("exchange");
+    let path = known_path(category, exchange)?;
+    let mut known: Option<HashSet<String>> = fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok());
+
+    loop {
+        match fetch(category, exchange, net) {
+            Ok(current) => {
+                if let Some(ref known) = known {
+                    for listing in new_listings(known, &current) {
+                        println!(
+                            "{} {} ({})",
+                            listing.id,
+                            listing.name,
+                            listing.symbol.to_uppercase()
+                        );
+                        alerts::deliver(
+                            &format!(
+                                "new listing: {} ({})",
+                                listing.name,
+                                listing.symbol.to_uppercase()
+                            ),
+                            config.alert_command.as_deref(),
+                        );
+                    }
+                } else {
+                    info!("recorded {} listings as the baseline", current.len());
+                }
+                // an empty answer is more likely an API hiccup than every
+                // coin being delisted, so don't forget what we've seen
+                if !current.is_empty() {
+                    let ids: HashSet<String> = current.into_iter().map(|l| l.id).collect();
+                    fs::write(&path, serde_json::to_string(&ids)?)?;
+                    known = Some(ids);
+                }
+            }
+            Err(err) => {
+                debug!("{}", err);
+            }
+        }
+        sleep(interval);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listing(id: &str) -> Listing {
+        Listing {
+            id: id.to_string(),
+            symbol: id[..3].to_string(),
+            name: id.to_string(),
+        }
+    }
+
+    #[test]
+    fn finds_new_listings() {
+        let known: HashSet<String> = vec!["bitcoin".to_string()].into_iter().collect();
+        let current = vec![listing("bitcoin"), listing("ethereum")];
+        assert_eq!(new_listings(&known, &current), vec![&current[1]]);
+    }
+
+    #[test]
+    fn reads_exchange_tickers() {
+        let content = r#"{"name": "Binance", "tickers": [
+            {"base": "BTC", "target": "USDT", "coin_id": "bitcoin"},
+            {"base": "ETH", "target": "BTC", "coin_id": "ethereum"}]}"#;
+        let tickers: ExchangeTickers = serde_json::from_str(content).unwrap();
+        assert_eq!(tickers.tickers[1].coin_id, "ethereum");
+        assert_eq!(tickers.tickers[0].base, "BTC");
+    }
+}
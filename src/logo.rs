@@ -0,0 +1,192 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use serde_json;
+
+use dirs;
+use errors::Error;
+use net::Net;
+
+/// How a logo gets onto the screen. Sixel-only terminals get the text
+/// fallback, since sixel needs the PNG decoded first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Protocol {
+    Kitty,
+    Iterm,
+    Text,
+}
+
+impl Protocol {
+    /// Parses a `--logos` value, detecting the terminal for `auto`.
+    pub fn parse(value: &str) -> Protocol {
+        match value {
+            "kitty" => Protocol::Kitty,
+            "iterm" => Protocol::Iterm,
+            "text" => Protocol::Text,
+            _ => Protocol::detect(
+                env::var("TERM").ok().as_deref(),
+                env::var("TERM_PROGRAM").ok().as_deref(),
+                env::var_os("KITTY_WINDOW_ID").is_some(),
+            ),
+        }
+    }
+
+    fn detect(term: Option<&str>, term_program: Option<&str>, kitty_window: bool) -> Protocol {
+        if kitty_window || term == Some("xterm-kitty") {
+            return Protocol::Kitty;
+        }
+        match term_program {
+            Some("iTerm.app") | Some("WezTerm") => Protocol::Iterm,
+            _ => Protocol::Text,
+        }
+    }
+
+    /// The escape sequence drawing `png` two cells wide at the cursor, or
+    /// the first letter of `symbol` when there's no image to draw.
+    pub fn render(self, png: Option<&[u8]>, symbol: &str) -> String {
+        let png = match png {
+            Some(png) if self != Protocol::Text => png,
+            _ => return format!("{:<2}", symbol.chars().next().unwrap_or(' ')),
+        };
+        let data = base64(png);
+        match self {
+            Protocol::Kitty => kitty(&data),
+            Protocol::Iterm => format!(
+                "\x1b]1337;File=inline=1;width=2;height=1;preserveAspectRatio=1:{}\x07",
+                data
+            ),
+            Protocol::Text => unreachable!(),
+        }
+    }
+}
+
+/// The kitty protocol limits each escape to 4096 bytes of payload, with
+/// `m=1` on every chunk but the last.
+fn kitty(data: &str) -> String {
+    let chunks: Vec<&[u8]> = data.as_bytes().chunks(4096).collect();
+    let mut out = String::new();
+    for (index, chunk) in chunks.iter().enumerate() {
+        let more = if index + 1 < chunks.len() { 1 } else { 0 };
+        let chunk = String::from_utf8_lossy(chunk);
+        if index == 0 {
+            out.push_str(&format!(
+                "\x1b_Gf=100,a=T,c=2,r=1,q=2,m={};{}\x1b\\",
+                more, chunk
+            ));
+        } else {
+            out.push_str(&format!("\x1b_Gm={};{}\x1b\\", more, chunk));
+        }
+    }
+    out
+}
+
+pub fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).cloned().unwrap_or(0),
+            chunk.get(2).cloned().unwrap_or(0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Returns the PNG logo for `name`, downloading it into the cache
+/// directory the first time. Logos rarely change, so cached ones never
+/// expire; `--clear-cache` removes them along with everything else.
+pub fn load(name: &str, net: &Net) -> Result<Vec<u8>, Error> {
+    let path = logo_dir()?.join(format!("{}.png", name));
+    if let Ok(png) = fs::read(&path) {
+        return Ok(png);
+    }
+    let png = fetch(name, net)?;
+    fs::write(&path, &png)?;
+    Ok(png)
+}
+
+fn logo_dir() -> Result<PathBuf, Error> {
+    let dir = dirs::cache_dir()?.join("logos");
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// coinmarketcap's ticker API has no images, so logos come from CoinGecko,
+/// which uses the same coin IDs for most coins.
+fn fetch(name: &str, net: &Net) -> Result<Vec<u8>, Error> {
+    let url = format!(
+        "https://api.coingecko.com/api/v3/coins/{}?localization=false&tickers=false\
+         &market_data=false&community_data=false&developer_data=false",
+        name
+    );
+    let resp = net.get(url.as_str())?;
+    if !resp.status.is_success() {
+        return Err(Error::InvalidTicker {
+            name: name.to_string(),
+        });
+    }
+    let coin: serde_json::Value = serde_json::from_str(&resp.text())?;
+    let url = coin["image"]["thumb"]
+        .as_str()
+        .ok_or_else(|| format!("No logo for ticker ID {}.", name))?;
+
+    Ok(net.get(url)?.body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn detects_terminals() {
+        assert_eq!(
+            Protocol::detect(Some("xterm-kitty"), None, false),
+            Protocol::Kitty
+        );
+        assert_eq!(
+            Protocol::detect(Some("xterm-256color"), None, true),
+            Protocol::Kitty
+        );
+        assert_eq!(
+            Protocol::detect(Some("xterm-256color"), Some("iTerm.app"), false),
+            Protocol::Iterm
+        );
+        assert_eq!(
+            Protocol::detect(Some("screen"), Some("tmux"), false),
+            Protocol::Text
+        );
+    }
+
+    #[test]
+    fn chunks_kitty_payloads() {
+        let escape = kitty(&"A".repeat(5000));
+        assert!(escape.starts_with("\x1b_Gf=100,a=T,c=2,r=1,q=2,m=1;"));
+        assert!(escape.contains("\x1b_Gm=0;"));
+        assert_eq!(escape.matches("\x1b_G").count(), 2);
+    }
+
+    #[test]
+    fn falls_back_to_text() {
+        assert_eq!(Protocol::Text.render(Some(b"png"), "BTC"), "B ");
+        assert_eq!(Protocol::Kitty.render(None, "ETH"), "E ");
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use ticker::Currency;
+
+struct Entry {
+    ticker: Currency,
+    stored: Instant,
+    last_used: u64,
+}
+
+/// Counters for judging whether the cache is pulling its weight.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Stats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+/// An in-memory LRU of recent quotes, for long-running modes that would
+/// otherwise go to disk or the network on every lookup. Holds at most
+/// `capacity` coins, each for at most `ttl`.
+pub struct QuoteCache {
+    capacity: usize,
+    ttl: Duration,
+    entries: HashMap<String, Entry>,
+    clock: u64,
+    stats: Stats,
+}
+
+impl QuoteCache {
+    pub fn new(capacity: usize, ttl: Duration) -> QuoteCache {
+        QuoteCache {
+            capacity,
+            ttl,
+            entries: HashMap::new(),
+            clock: 0,
+            stats: Stats::default(),
+        }
+    }
+
+    pub fn get(&mut self, name: &str) -> Option<Currency> {
+        self.clock += 1;
+        let ttl = self.ttl;
+        let expired = match self.entries.get_mut(name) {
+            Some(ref entry) if entry.stored.elapsed() >= ttl => true,
+            Some(entry) => {
+                entry.last_used = self.clock;
+                self.stats.hits += 1;
+                return Some(entry.ticker.clone());
+            }
+            None => false,
+        };
+        if expired {
+            self.entries.remove(name);
+        }
+        self.stats.misses += 1;
+        None
+    }
+
+    pub fn insert(&mut self, name: &str, ticker: Currency) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.clock += 1;
+        if !self.entries.contains_key(name) && self.entries.len() >= self.capacity {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|&(_, entry)| entry.last_used)
+                .map(|(name, _)| name.clone());
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+                self.stats.evictions += 1;
+            }
+        }
+        self.entries.insert(
+            name.to_string(),
+            Entry {
+                ticker,
+                stored: Instant::now(),
+                last_used: self.clock,
+            },
+        );
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    fn ticker(id: &str) -> Currency {
+        serde_json::from_str(&format!(
+            r#"{{"id": "{}", "name": "", "symbol": "", "rank": "1"}}"#,
+            id
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let mut cache = QuoteCache::new(2, Duration::from_secs(60));
+        cache.insert("bitcoin", ticker("bitcoin"));
+        cache.insert("ethereum", ticker("ethereum"));
+        assert!(cache.get("bitcoin").is_some());
+        cache.insert("litecoin", ticker("litecoin"));
+
+        assert_eq!(cache.entries.len(), 2);
+        assert!(cache.get("ethereum").is_none());
+        assert!(cache.get("bitcoin").is_some());
+        assert!(cache.get("litecoin").is_some());
+        assert_eq!(
+            cache.stats(),
+            Stats {
+                hits: 3,
+                misses: 1,
+                evictions: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn expires_entries() {
+        let mut cache = QuoteCache::new(2, Duration::from_secs(0));
+        cache.insert("bitcoin", ticker("bitcoin"));
+        assert!(cache.get("bitcoin").is_none());
+        assert_eq!(cache.entries.len(), 0);
+    }
+}
@@ -0,0 +1,374 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use rust_decimal::prelude::ToPrimitive;
+use serde_json;
+
+use cache::Cache;
+use dirs;
+use errors::Error;
+use history;
+use net::Net;
+use ticker::usd_price;
+
+/// Difficulty only changes every couple of weeks, so the network is
+/// asked once an hour at most.
+const REFRESH: i64 = 60 * 60;
+
+/// A proof-of-work algorithm `mining:<algo>` knows, and the coin mined
+/// with it. Both chains run a mempool.space explorer, which has the
+/// difficulty and the height the block reward halves with.
+struct Algo {
+    name: &'static str,
+    coin: &'static str,
+    api: &'static str,
+    /// the reward of the first blocks, in coins
+    subsidy: f64,
+    /// how many blocks go by between halvings
+    halving: u64,
+}
+
+const ALGOS: &[Algo] = &[
+    Algo {
+        name: "sha256",
+        coin: "bitcoin",
+        api: "https://mempool.space/api",
+        subsidy: 50.0,
+        halving: 210_000,
+    },
+    Algo {
+        name: "scrypt",
+        coin: "litecoin",
+        api: "https://litecoinspace.org/api",
+        subsidy: 50.0,
+        halving: 840_000,
+    },
+];
+
+/// A home miner, configured per algorithm in a `[mining.<algo>]` table.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Miner {
+    /// like `110TH/s` or `9.5GH/s`
+    pub hashrate: String,
+    /// what the rig draws from the wall
+    pub watts: f64,
+    /// the price of electricity, in USD per kWh; with `power_price_url`,
+    /// what is paid on top of the spot price, like grid fees
+    pub power_cost: f64,
+    /// an aWATTar-style API of hourly spot prices, whose current price
+    /// is added to `power_cost`
+    #[serde(default)]
+    pub power_price_url: Option<String>,
+    /// what a spot price is multiplied by for USD per kWh; the default
+    /// turns a price per MWh into one per kWh
+    #[serde(default = "default_power_price_scale")]
+    pub power_price_scale: f64,
+}
+
+fn default_power_price_scale() -> f64 {
+    0.001
+}
+
+/// One hour of spot prices, from `start_timestamp` to `end_timestamp` in
+/// milliseconds.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SpotPrice {
+    start_timestamp: i64,
+    end_timestamp: i64,
+    marketprice: f64,
+}
+
+#[derive(Deserialize)]
+struct SpotPrices {
+    data: Vec<SpotPrice>,
+}
+
+/// The network's state, kept in the cache directory by algorithm.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct Network {
+    fetched: i64,
+    difficulty: f64,
+    /// the block reward right now, in coins
+    reward: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Hashrate {
+    current_difficulty: f64,
+}
+
+/// Parses a hashrate like `110TH/s`, `9.5 GH` or `500M` into hashes per
+/// second.
+pub fn parse_hashrate(hashrate: &str) -> Result<f64, String> {
+    let trimmed = hashrate.trim();
+    let trimmed = trimmed.strip_suffix("/s").unwrap_or(trimmed);
+    let trimmed = trimmed
+        .strip_suffix('H')
+        .or_else(|| trimmed.strip_suffix('h'))
+        .unwrap_or(trimmed)
+        .trim_end();
+    let (digits, scale) = match trimmed.chars().last() {
+        Some('k') | Some('K') => (&trimmed[..trimmed.len() - 1], 1e3),
+        Some('M') => (&trimmed[..trimmed.len() - 1], 1e6),
+        Some('G') => (&trimmed[..trimmed.len() - 1], 1e9),
+        Some('T') => (&trimmed[..trimmed.len() - 1], 1e12),
+        Some('P') => (&trimmed[..trimmed.len() - 1], 1e15),
+        _ => (trimmed, 1.0),
+    };
+    match digits.trim().parse::<f64>() {
+        Ok(value) if value > 0.0 => Ok(value * scale),
+        _ => Err(format!(
+            "{:?} should be a hashrate like 110TH/s or 9.5GH/s",
+            hashrate
+        )),
+    }
+}
+
+/// The reward for a block at `height`, halving every `halving` blocks.
+fn reward(algo: &Algo, height: u64) -> f64 {
+    match height / algo.halving {
+        halvings if halvings >= 64 => 0.0,
+        halvings => algo.subsidy / (1u64 << halvings) as f64,
+    }
+}
+
+/// How many coins `hashrate` finds a day at `difficulty`, on average.
+/// Each unit of difficulty is 2^32 hashes per block found.
+fn coins_per_day(hashrate: f64, difficulty: f64, reward: f64) -> f64 {
+    hashrate * 86_400.0 * reward / (difficulty * 4_294_967_296.0)
+}
+
+/// The estimated USD a day `miner` makes at `price` per coin, after
+/// paying `power_cost` per kWh.
+fn profit(miner: &Miner, hashrate: f64, network: &Network, price: f64, power_cost: f64) -> f64 {
+    let revenue = coins_per_day(hashrate, network.difficulty, network.reward) * price;
+    let power = miner.watts / 1000.0 * 24.0 * power_cost;
+    revenue - power
+}
+
+/// The spot price for the hour that contains `now`, in milliseconds.
+fn current(prices: &[SpotPrice], now: i64) -> Option<f64> {
+    prices
+        .iter()
+        .find(|price| price.start_timestamp <= now && now < price.end_timestamp)
+        .map(|price| price.marketprice)
+}
+
+/// The current hour's spot price from `url`. Day-ahead prices are known
+/// well before they apply, so the API is only asked again once the kept
+/// prices run out.
+fn spot_price(url: &str, offline: bool, net: &Net) -> Result<f64, Error> {
+    let path = dirs::cache_dir()?.join("power.json");
+    let mut cached: HashMap<String, Vec<SpotPrice>> = fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    let now = history::now() * 1000;
+    if let Some(price) = cached.get(url).and_then(|prices| current(prices, now)) {
+        return Ok(price);
+    }
+    if offline {
+        return Err(Error::Network(
+            "no power price for this hour is cached, and --offline is set".to_string(),
+        ));
+    }
+    let prices: SpotPrices = serde_json::from_str(&get(url, net)?)?;
+    let price = current(&prices.data, now);
+    cached.insert(url.to_string(), prices.data);
+    fs::write(&path, serde_json::to_string(&cached)?)?;
+    price.ok_or_else(|| Error::Other(format!("{} has no price for this hour", url)))
+}
+
+fn get(url: &str, net: &Net) -> Result<String, Error> {
+    debug!("retrieving {}", url);
+    let resp = net.get(url)?;
+    if !resp.status.is_success() {
+        return Err(Error::Network(format!("{} answered {}", url, resp.status)));
+    }
+    Ok(resp.text())
+}
+
+/// The explorer's difficulty and height, the two things asked of it.
+fn urls(algo: &Algo) -> [String; 2] {
+    [
+        format!("{}/v1/mining/hashrate/3d", algo.api),
+        format!("{}/blocks/tip/height", algo.api),
+    ]
+}
+
+/// The coin `mining:<name>` is priced in and what its explorer is asked,
+/// if `name` is an algorithm it knows.
+pub fn requests(name: &str) -> Option<(&'static str, [String; 2])> {
+    let algo = ALGOS.iter().find(|algo| algo.name == name)?;
+    Some((algo.coin, urls(algo)))
+}
+
+fn fetch(algo: &Algo, net: &Net) -> Result<Network, Error> {
+    let [hashrate, height] = urls(algo);
+    let hashrate: Hashrate = serde_json::from_str(&get(&hashrate, net)?)?;
+    let height = get(&height, net)?;
+    let height: u64 = height
+        .trim()
+        .parse()
+        .map_err(|_| Error::Parse(format!("{} sent {:?} for a height", algo.api, height)))?;
+    Ok(Network {
+        fetched: history::now(),
+        difficulty: hashrate.current_difficulty,
+        reward: reward(algo, height),
+    })
+}
+
+fn path() -> Result<PathBuf, Error> {
+    Ok(dirs::cache_dir()?.join("mining.json"))
+}
+
+/// The network's state for `algo`, from the cache directory if it was
+/// fetched recently. A failed fetch falls back to the older state, and
+/// offline there is only ever the older state.
+fn network(algo: &Algo, offline: bool, net: &Net) -> Result<Network, Error> {
+    let path = path()?;
+    let mut networks: HashMap<String, Network> = fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    match networks.get(algo.name) {
+        Some(network) if offline || history::now() - network.fetched < REFRESH => {
+            return Ok(*network)
+        }
+        None if offline => {
+            return Err(Error::Network(format!(
+                "no {} difficulty is cached, and --offline is set",
+                algo.name
+            )))
+        }
+        _ => {}
+    }
+    match fetch(algo, net) {
+        Ok(network) => {
+            networks.insert(algo.name.to_string(), network);
+            fs::write(&path, serde_json::to_string(&networks)?)?;
+            Ok(network)
+        }
+        Err(err) => match networks.get(algo.name) {
+            Some(network) => {
+                debug!("{}, using the older {} difficulty", err, algo.name);
+                Ok(*network)
+            }
+            None => Err(err),
+        },
+    }
+}
+
+/// The segment to print for `mining:<algo>`, like `sha256:$4.12/day`,
+/// with the estimated daily profit of the miner configured for it.
+pub fn segment(
+    name: &str,
+    miners: &HashMap<String, Miner>,
+    cache: Option<&Cache>,
+    offline: bool,
+    net: &Net,
+) -> Result<String, Error> {
+    let algo = ALGOS.iter().find(|algo| algo.name == name).ok_or_else(|| {
+        let known: Vec<&str> = ALGOS.iter().map(|algo| algo.name).collect();
+        Error::Parse(format!(
+            "unknown mining algorithm {:?}, try {}",
+            name,
+            known.join(" or ")
+        ))
+    })?;
+    let miner = miners.get(name).ok_or_else(|| {
+        Error::Other(format!(
+            "mining:{} needs a [mining.{}] table in the config file",
+            name, name
+        ))
+    })?;
+    let hashrate = parse_hashrate(&miner.hashrate).map_err(Error::Parse)?;
+    let network = network(algo, offline, net)?;
+    let price = usd_price(algo.coin, cache, net)?.to_f64().unwrap_or(0.0);
+    let power_cost = match miner.power_price_url {
+        Some(ref url) => {
+            miner.power_cost + spot_price(url, offline, net)? * miner.power_price_scale
+        }
+        None => miner.power_cost,
+    };
+    Ok(format!(
+        "{}:{}",
+        name,
+        dollars(profit(miner, hashrate, &network, price, power_cost))
+    ))
+}
+
+fn dollars(amount: f64) -> String {
+    if amount < 0.0 {
+        format!("-${:.2}/day", -amount)
+    } else {
+        format!("${:.2}/day", amount)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_hashrates() {
+        assert_eq!(parse_hashrate("110TH/s"), Ok(110e12));
+        assert_eq!(parse_hashrate("9.5 GH"), Ok(9.5e9));
+        assert_eq!(parse_hashrate("500M"), Ok(500e6));
+        assert_eq!(parse_hashrate("1200"), Ok(1200.0));
+        assert!(parse_hashrate("fast").is_err());
+        assert!(parse_hashrate("-5TH/s").is_err());
+    }
+
+    #[test]
+    fn halves_the_reward() {
+        assert_eq!(reward(&ALGOS[0], 0), 50.0);
+        assert_eq!(reward(&ALGOS[0], 840_000), 3.125);
+        assert_eq!(reward(&ALGOS[1], 840_000), 25.0);
+        assert_eq!(reward(&ALGOS[0], 100_000_000), 0.0);
+    }
+
+    #[test]
+    fn estimates_daily_profit() {
+        let miner = Miner {
+            hashrate: "100TH/s".to_string(),
+            watts: 3000.0,
+            power_cost: 0.10,
+            power_price_url: None,
+            power_price_scale: 0.001,
+        };
+        // the whole network's 600 EH/s at 10-minute blocks
+        let network = Network {
+            fetched: 0,
+            difficulty: 600e18 * 600.0 / 4_294_967_296.0,
+            reward: 3.125,
+        };
+        // 100 TH/s of 600 EH/s finds 1/6,000,000 of 144 blocks a day
+        let coins = coins_per_day(100e12, network.difficulty, network.reward);
+        assert!((coins - 144.0 * 3.125 / 6e6).abs() < 1e-12);
+        // 7.2 kWh a day costs $7.20
+        let profit = profit(&miner, 100e12, &network, 100_000.0, miner.power_cost);
+        assert!((profit - (coins * 100_000.0 - 7.2)).abs() < 1e-9);
+        assert_eq!(dollars(profit), "$0.30/day");
+        assert_eq!(dollars(-1.5), "-$1.50/day");
+        assert_eq!(dollars(4.123), "$4.12/day");
+    }
+
+    #[test]
+    fn finds_this_hours_power_price() {
+        let prices: SpotPrices = serde_json::from_str(
+            r#"{"object": "list", "data": [
+                {"start_timestamp": 1700000000000, "end_timestamp": 1700003600000,
+                 "marketprice": 95.1, "unit": "Eur/MWh"},
+                {"start_timestamp": 1700003600000, "end_timestamp": 1700007200000,
+                 "marketprice": -2.5, "unit": "Eur/MWh"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(current(&prices.data, 1700000000000), Some(95.1));
+        assert_eq!(current(&prices.data, 1700005000000), Some(-2.5));
+        assert_eq!(current(&prices.data, 1700007200000), None);
+    }
+}
@@ -0,0 +1,259 @@
+use std::str::FromStr;
+
+use rust_decimal::Decimal;
+use serde_json::{self, Value};
+
+use errors::Error;
+use history::{self, History, Sample};
+use net::Net;
+use ticker::{parse_price, to_f64, Currency};
+
+/// What stands in for a field the provider left out, tried in the order
+/// `[missing]` lists them. Without one the field renders as `–`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fallback {
+    /// CoinGecko's figure, asked for when the ticker API sent none
+    Provider,
+    /// worked out from the samples the local history recorded
+    History,
+    /// `–`
+    Dash,
+    /// `null`, for scripts written against what was shown before `–`
+    Null,
+}
+
+/// The names `[missing]` accepts.
+pub const FALLBACKS: &[&str] = &["provider", "history", "dash", "null"];
+
+/// What `Fallback::Dash` renders, and a field with nothing to fall back
+/// on.
+pub const DASH: &str = "–";
+
+/// The fields CoinGecko's markets fill in.
+const PROVIDED: &[&str] = &[
+    "rank",
+    "price",
+    "change_1h",
+    "change_24h",
+    "change_7d",
+    "volume_24h",
+    "market_cap",
+];
+
+/// The fields the local history can work out.
+const RECORDED: &[&str] = &[
+    "price",
+    "change_1h",
+    "change_24h",
+    "change_7d",
+    "volume_24h",
+];
+
+impl FromStr for Fallback {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Fallback, String> {
+        match s.trim().to_lowercase().as_str() {
+            "provider" => Ok(Fallback::Provider),
+            "history" => Ok(Fallback::History),
+            "dash" => Ok(Fallback::Dash),
+            "null" => Ok(Fallback::Null),
+            _ => Err(format!("{:?} should be one of {}", s, FALLBACKS.join(", "))),
+        }
+    }
+}
+
+impl Fallback {
+    /// Whether this can stand in for `field` at all.
+    pub fn covers(self, field: &str) -> bool {
+        match self {
+            Fallback::Provider => PROVIDED.contains(&field),
+            Fallback::History => RECORDED.contains(&field),
+            Fallback::Dash | Fallback::Null => true,
+        }
+    }
+}
+
+/// Whether `field` has no value in `ticker`, for the fields CoinGecko
+/// fills in.
+pub fn lacks(ticker: &Currency, field: &str) -> bool {
+    match field {
+        "rank" => ticker.rank.trim().is_empty(),
+        "price" => ticker.price_usd.is_none(),
+        "change_1h" => ticker.percent_change_1.is_none(),
+        "change_24h" => ticker.percent_change_24.is_none(),
+        "change_7d" => ticker.percent_change_7.is_none(),
+        "volume_24h" => ticker.volume_usd_24h.is_none(),
+        "market_cap" => ticker.market_cap_usd.is_none(),
+        _ => false,
+    }
+}
+
+fn decimal(value: &Value) -> Option<Decimal> {
+    match *value {
+        Value::Number(ref number) => parse_price(&number.to_string()),
+        Value::String(ref text) => parse_price(text),
+        _ => None,
+    }
+}
+
+/// Fills in what `ticker` lacks from CoinGecko's `market`, which uses the
+/// same coin IDs for most coins. What the ticker API did send is kept.
+fn merge(ticker: &mut Currency, market: &Value) {
+    if ticker.rank.trim().is_empty() {
+        if let Some(rank) = market["market_cap_rank"].as_u64() {
+            ticker.rank = rank.to_string();
+        }
+    }
+    let fields = [
+        (&mut ticker.price_usd, "current_price"),
+        (
+            &mut ticker.percent_change_1,
+            "price_change_percentage_1h_in_currency",
+        ),
+        (
+            &mut ticker.percent_change_24,
+            "price_change_percentage_24h_in_currency",
+        ),
+        (
+            &mut ticker.percent_change_7,
+            "price_change_percentage_7d_in_currency",
+        ),
+        (&mut ticker.volume_usd_24h, "total_volume"),
+        (&mut ticker.market_cap_usd, "market_cap"),
+    ];
+    for (value, key) in fields {
+        if value.is_none() {
+            *value = decimal(&market[key]);
+        }
+    }
+}
+
+/// Asks CoinGecko for what `ticker` lacks.
+pub fn fill(ticker: &mut Currency, net: &Net) -> Result<(), Error> {
+    let url = format!(
+        "https://api.coingecko.com/api/v3/coins/markets?vs_currency=usd&ids={}\
+         &price_change_percentage=1h,24h,7d",
+        ticker.id
+    );
+    debug!("retrieving {}", url);
+    let resp = net.get(url.as_str())?;
+    if !resp.status.is_success() {
+        return Err(Error::Network(format!("{} answered {}", url, resp.status)));
+    }
+    let markets: Vec<Value> = serde_json::from_str(&resp.text())?;
+    match markets.first() {
+        Some(market) => {
+            merge(ticker, market);
+            Ok(())
+        }
+        None => Err(Error::InvalidTicker {
+            name: ticker.id.clone(),
+        }),
+    }
+}
+
+/// How far back each change field looks.
+fn span(field: &str) -> Option<i64> {
+    match field {
+        "change_1h" => Some(3600),
+        "change_24h" => Some(86_400),
+        "change_7d" => Some(7 * 86_400),
+        _ => None,
+    }
+}
+
+/// The change in percent from the first price in `samples` to `latest`,
+/// or `None` unless the first was taken near the start of the `span`
+/// before `now`.
+fn change(samples: &[Sample], latest: f64, span: i64, now: i64) -> Option<f64> {
+    let (then, price) = samples
+        .iter()
+        .find_map(|sample| sample.price_usd.map(|price| (sample.timestamp, price)))?;
+    // a tenth of the span off is close enough to call it the same change
+    if then > now - span + span / 10 || price <= 0.0 {
+        return None;
+    }
+    Some((latest / price - 1.0) * 100.0)
+}
+
+/// `field` for `ticker`, from the samples `history` recorded, or `None`
+/// without enough of them.
+pub fn recorded(field: &str, ticker: &Currency, history: &History) -> Option<String> {
+    let now = history::now();
+    let since = now - span(field).unwrap_or(86_400);
+    let samples = history
+        .samples(&ticker.id, since)
+        .map_err(|err| debug!("no history for {}: {}", ticker.id, err))
+        .ok()?;
+    let last_price = samples.iter().rev().find_map(|sample| sample.price_usd);
+    match field {
+        "price" => last_price.map(|price| price.to_string()),
+        "volume_24h" => samples
+            .iter()
+            .rev()
+            .find_map(|sample| sample.volume_usd_24h)
+            .map(|volume| format!("{:.0}", volume)),
+        _ => {
+            let latest = to_f64(ticker.price_usd).or(last_price)?;
+            let change = change(&samples, latest, span(field)?, now)?;
+            Some(format!("{:.2}", change))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(price: Option<f64>, timestamp: i64) -> Sample {
+        Sample {
+            coin: "bitcoin".to_string(),
+            price_usd: price,
+            volume_usd_24h: None,
+            available_supply: None,
+            timestamp,
+        }
+    }
+
+    #[test]
+    fn works_out_changes() {
+        let now = 1_000_000;
+        let day = 86_400;
+        let samples = vec![
+            sample(None, now - day),
+            sample(Some(100.0), now - day + 600),
+            sample(Some(105.0), now - 60),
+        ];
+        let rose = change(&samples, 110.0, day, now).unwrap();
+        assert!((rose - 10.0).abs() < 1e-9);
+        // a minute of samples says nothing about a whole day
+        let recent = &samples[2..];
+        assert_eq!(change(recent, 110.0, day, now), None);
+    }
+
+    #[test]
+    fn fills_what_is_missing() {
+        let mut ticker: Currency = serde_json::from_str(
+            r#"{"id": "bitcoin", "name": "bitcoin", "symbol": "BTC", "rank": "",
+                "price_usd": "64250.5"}"#,
+        )
+        .unwrap();
+        assert!(lacks(&ticker, "change_7d"));
+        assert!(!lacks(&ticker, "price"));
+        let market = serde_json::json!({
+            "current_price": 64100,
+            "market_cap_rank": 1,
+            "price_change_percentage_7d_in_currency": 3.25,
+            "total_volume": null,
+        });
+        merge(&mut ticker, &market);
+        assert_eq!(ticker.rank, "1");
+        assert_eq!(ticker.price_usd, parse_price("64250.5"));
+        assert_eq!(ticker.percent_change_7, parse_price("3.25"));
+        assert_eq!(ticker.volume_usd_24h, None);
+        assert!(Fallback::History.covers("change_7d"));
+        assert!(!Fallback::History.covers("market_cap"));
+        assert!("zero".parse::<Fallback>().is_err());
+    }
+}
@@ -0,0 +1,173 @@
+use clap::ArgMatches;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+
+use alerts::Holding;
+use cache::Cache;
+use errors::Error;
+use fng::Index;
+use global::{self, Global};
+use net::Net;
+use template::{compact, significant_places};
+use ticker::{fetch_top, to_f64, usd_price, Currency};
+
+/// What the holdings were worth and cost, in USD.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Worth {
+    value: Decimal,
+    cost: Decimal,
+}
+
+/// Values `holdings` at their live prices, `None` without any, or if one
+/// can't be priced, since a total missing a coin would mislead.
+fn worth(holdings: &[Holding], cache: Option<&Cache>, net: &Net) -> Option<Worth> {
+    if holdings.is_empty() {
+        return None;
+    }
+    let mut worth = Worth {
+        value: Decimal::ZERO,
+        cost: Decimal::ZERO,
+    };
+    for holding in holdings {
+        match usd_price(&holding.coin, cache, net) {
+            Ok(price) => worth.value += price * holding.amount,
+            Err(err) => {
+                debug!("can't value the holdings: {}", err);
+                return None;
+            }
+        }
+        worth.cost += holding.cost.unwrap_or_default();
+    }
+    Some(worth)
+}
+
+fn price(price: Option<Decimal>) -> String {
+    match to_f64(price) {
+        Some(price) if price >= 1_000_000.0 => format!("${}", compact(price)),
+        Some(price) if price >= 1.0 => format!("${:.2}", price),
+        Some(price) => format!("${:.*}", significant_places(price, 4), price),
+        None => "?".to_string(),
+    }
+}
+
+/// The summary, a line per coin like `#1  btc  $64250.10  +1.23%` and
+/// then whatever else there is, in plain ASCII so any terminal shows it
+/// at login.
+fn report(
+    top: &[Currency],
+    worth: Option<Worth>,
+    fear_greed: Option<&Index>,
+    global: Option<&Global>,
+) -> String {
+    let rows: Vec<[String; 4]> = top
+        .iter()
+        .map(|ticker| {
+            [
+                format!("#{}", ticker.rank.trim()),
+                ticker.symbol.to_lowercase(),
+                price(ticker.price_usd),
+                to_f64(ticker.percent_change_24)
+                    .map_or("?".to_string(), |change| format!("{:+.2}%", change)),
+            ]
+        })
+        .collect();
+    let mut widths = [0; 4];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    let mut out = String::new();
+    for row in &rows {
+        out.push_str(&format!(
+            "{:<w0$}  {:<w1$}  {:>w2$}  {:>w3$}\n",
+            row[0],
+            row[1],
+            row[2],
+            row[3],
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+            w3 = widths[3]
+        ));
+    }
+    if let Some(global) = global {
+        out.push_str(&format!("{}\n", global::report(global)));
+    }
+    if let Some(worth) = worth {
+        let value = worth.value.to_f64().unwrap_or_default();
+        out.push_str(&format!("holdings ${}", compact(value)));
+        if let Some(gain) = ((worth.value - worth.cost) * Decimal::ONE_HUNDRED)
+            .checked_div(worth.cost)
+            .and_then(|gain| gain.to_f64())
+        {
+            out.push_str(&format!(", {:+.2}% on cost", gain));
+        }
+        out.push('\n');
+    }
+    if let Some(index) = fear_greed {
+        out.push_str(&format!(
+            "fear & greed {}, {}\n",
+            index.value,
+            index.classification.to_lowercase()
+        ));
+    }
+    out
+}
+
+/// Runs `motd`, printing the top `--top` coins, the market totals, what
+/// the `[[holding]]` tables are worth and the Fear & Greed Index for a
+/// script in `/etc/update-motd.d/`. Only the top list has to be fetched;
+/// the rest is left out when it can't be.
+pub fn motd(
+    matches: &ArgMatches,
+    holdings: &[Holding],
+    cache: Option<&Cache>,
+    offline: bool,
+    net: &Net,
+) -> Result<(), Error> {
+    let limit = value_t!(matches, "top", u32).unwrap_or_else(|e| e.exit());
+    let top = fetch_top(limit, net)?;
+    let global = Global::load(offline, net)
+        .map_err(|err| debug!("no market totals: {}", err))
+        .ok();
+    let fear_greed = Index::load(offline, net)
+        .map_err(|err| debug!("no Fear & Greed Index: {}", err))
+        .ok();
+    let worth = worth(holdings, cache, net);
+    print!(
+        "{}",
+        report(&top, worth, fear_greed.as_ref(), global.as_ref())
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn summarizes_the_market() {
+        let top: Vec<Currency> =
+            serde_json::from_str(include_str!("../tests/fixtures/ticker.json")).unwrap();
+        let worth = Worth {
+            value: Decimal::new(12_345, 0),
+            cost: Decimal::new(10_000, 0),
+        };
+        let report = report(&top[..1], Some(worth), None, None);
+        assert!(report.starts_with("#1  btc  $2545.19  -3.39%\n"));
+        assert!(report.ends_with("holdings $12.35k, +23.45% on cost\n"));
+        assert!(report.is_ascii());
+        let free = Worth {
+            cost: Decimal::ZERO,
+            ..worth
+        };
+        assert_eq!(
+            super::report(&[], Some(free), None, None),
+            "holdings $12.35k\n"
+        );
+        assert_eq!(price(Some(Decimal::new(35, 8))), "$0.0000003500");
+        assert_eq!(price(None), "?");
+    }
+}
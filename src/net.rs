@@ -0,0 +1,582 @@
+use std::io::Read;
+use std::sync::{mpsc, Mutex};
+use std::thread::{self, sleep};
+use std::time::{Duration, Instant};
+
+use reqwest::header::Headers;
+use reqwest::{self, StatusCode};
+
+use chaos::{roll, Chaos};
+use errors::Error;
+use history;
+use incidents;
+use sources::Source;
+use time;
+
+/// Where tickers come from, with the coin's ID appended.
+pub const DEFAULT_API: &str = "https://api.coinmarketcap.com/v1/ticker/";
+/// Overrides `DEFAULT_API`. `--api-url` sets it too, so background
+/// refreshes fetch from the same place.
+pub const API_URL_VAR: &str = "CRYPTOTICKER_API_URL";
+
+/// How fetches reach the API, handed to everything that touches the
+/// network.
+#[derive(Debug, Clone, Copy)]
+pub struct Net {
+    pub chaos: Chaos,
+    pub retry: Retry,
+    /// how long a request may take in all; `None` waits as long as the
+    /// system does
+    pub timeout: Option<Duration>,
+    /// the ticker API, ending in a `/`, so tests can point it at a mock
+    /// server
+    pub api: &'static str,
+    /// where tickers are fetched from, tried in order until one answers;
+    /// the ticker API alone when empty
+    pub sources: &'static [Source],
+    /// whether a rate limit is waited out, as in interval mode, where the
+    /// next round can wait; a one-shot run falls back to the cache at
+    /// once instead of holding up a status bar
+    pub hold_off: bool,
+    /// whether failed requests go in the incident log, which tests
+    /// shouldn't fill
+    pub incidents: bool,
+}
+
+impl Default for Net {
+    fn default() -> Net {
+        Net {
+            chaos: Chaos::default(),
+            retry: Retry::default(),
+            timeout: None,
+            api: DEFAULT_API,
+            sources: &[],
+            hold_off: false,
+            incidents: false,
+        }
+    }
+}
+
+/// What came back from a GET, read in full.
+pub struct Response {
+    pub status: StatusCode,
+    pub headers: Headers,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    /// The first value of header `name`, if it is there and is text.
+    pub fn header(&self, name: &str) -> Option<String> {
+        let raw = self.headers.get_raw(name)?.first()?;
+        String::from_utf8(raw.clone()).ok()
+    }
+}
+
+/// Retries for transient failures, like a DNS hiccup or a 5xx.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Retry {
+    /// retries after the first attempt; 0 never retries
+    pub attempts: u32,
+    /// the wait before the first retry, doubled before each one after
+    pub backoff: Duration,
+}
+
+impl Default for Retry {
+    fn default() -> Retry {
+        Retry {
+            attempts: 2,
+            backoff: Duration::from_millis(250),
+        }
+    }
+}
+
+impl Retry {
+    /// The wait before retry number `retry` (counting from 0): the backoff
+    /// doubled `retry` times, give or take half, so a fleet of status bars
+    /// doesn't retry in lockstep. `jitter` is a percentage from 0 to 99.
+    fn delay(&self, retry: u32, jitter: u32) -> Duration {
+        let base = self.backoff * 2u32.saturating_pow(retry.min(16));
+        base * (50 + jitter) / 100
+    }
+}
+
+/// What a provider has said about how hard it may be asked, shared by
+/// every fetch in the process so a 429 for one coin holds back the rest
+/// too.
+#[derive(Debug, PartialEq)]
+struct Throttle {
+    /// nothing is sent before this, after a 429 or a spent quota
+    until: Option<Instant>,
+    /// the gap left between requests while the quota is running low
+    spacing: Duration,
+    last: Option<Instant>,
+}
+
+/// Below this many requests left in the window, requests are spread out
+/// over what remains of it instead of sent as fast as they come.
+const LOW_QUOTA: u64 = 10;
+/// How long to hold off after a 429 that doesn't say.
+const DEFAULT_RETRY_AFTER: u64 = 60;
+/// The longest a request is held back, whatever the provider asks for;
+/// a `Retry-After` of an hour would otherwise stall interval mode for one.
+const MAX_PAUSE: Duration = Duration::from_secs(5 * 60);
+
+/// A throttle per provider, by `incidents::provider`, so one that is
+/// rate limited doesn't hold up a `source` to fall back to.
+static THROTTLES: Mutex<Vec<(String, Throttle)>> = Mutex::new(Vec::new());
+
+impl Throttle {
+    const fn new() -> Throttle {
+        Throttle {
+            until: None,
+            spacing: Duration::from_secs(0),
+            last: None,
+        }
+    }
+
+    /// How long to wait at `now` before the next request may go out.
+    fn pause(&self, now: Instant) -> Duration {
+        let until = self.until.map_or(now, |until| until.max(now));
+        let spaced = self.last.map_or(now, |last| (last + self.spacing).max(now));
+        until.max(spaced) - now
+    }
+
+    /// The seconds left at `now` of a hold-off after a 429 or a spent
+    /// quota, if there is one.
+    fn held_off(&self, now: Instant) -> Option<u64> {
+        self.until
+            .filter(|&until| until > now)
+            .map(|until| (until - now).as_secs())
+    }
+
+    /// Learns from a response: `retry_after` and `reset` are seconds from
+    /// now, `remaining` the requests left before the quota resets.
+    fn observe(
+        &mut self,
+        now: Instant,
+        limited: bool,
+        retry_after: Option<u64>,
+        remaining: Option<u64>,
+        reset: Option<u64>,
+    ) {
+        self.last = Some(now);
+        self.until = if limited {
+            let wait = retry_after.or(reset).unwrap_or(DEFAULT_RETRY_AFTER);
+            Some(now + Duration::from_secs(wait))
+        } else if remaining == Some(0) {
+            reset.map(|reset| now + Duration::from_secs(reset))
+        } else {
+            None
+        };
+        self.spacing = match (remaining, reset) {
+            (Some(remaining), Some(reset)) if remaining <= LOW_QUOTA => {
+                Duration::from_secs(reset) / (remaining as u32 + 1)
+            }
+            _ => Duration::from_secs(0),
+        };
+    }
+
+    fn describe(&self, now: Instant) -> Option<String> {
+        match self.until {
+            Some(until) if until > now => {
+                Some(format!("holding off for {}s", (until - now).as_secs()))
+            }
+            _ if self.spacing > Duration::from_secs(0) => Some(format!(
+                "spacing requests {}ms apart",
+                self.spacing.as_millis()
+            )),
+            _ => None,
+        }
+    }
+}
+
+fn throttles() -> ::std::sync::MutexGuard<'static, Vec<(String, Throttle)>> {
+    THROTTLES
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Runs `f` with the throttle of `url`'s provider.
+fn throttle<T, F: FnOnce(&mut Throttle) -> T>(url: &str, f: F) -> T {
+    let provider = incidents::provider(url);
+    let mut throttles = throttles();
+    let index = match throttles.iter().position(|(name, _)| *name == provider) {
+        Some(index) => index,
+        None => {
+            throttles.push((provider, Throttle::new()));
+            throttles.len() - 1
+        }
+    };
+    f(&mut throttles[index].1)
+}
+
+/// What the rate limiter is doing right now, for debug output, like
+/// `coinmarketcap: holding off for 42s`; `None` when requests go out
+/// unhindered.
+pub fn throttle_state() -> Option<String> {
+    let now = Instant::now();
+    let states: Vec<String> = throttles()
+        .iter()
+        .filter_map(|(provider, throttle)| {
+            throttle
+                .describe(now)
+                .map(|state| format!("{}: {}", provider, state))
+        })
+        .collect();
+    Some(states.join(", ")).filter(|states| !states.is_empty())
+}
+
+/// `Retry-After` is either a number of seconds or an HTTP date.
+fn parse_retry_after(value: &str, now: i64) -> Option<u64> {
+    if let Ok(secs) = value.trim().parse() {
+        return Some(secs);
+    }
+    let tm = time::strptime(value.trim(), "%a, %d %b %Y %H:%M:%S GMT").ok()?;
+    Some((tm.to_timespec().sec - now).max(0) as u64)
+}
+
+/// `X-RateLimit-Reset` is seconds from now for some APIs and a unix time
+/// for others; nobody waits thirty years for a quota, so big numbers are
+/// taken as times.
+fn parse_reset(value: &str, now: i64) -> Option<u64> {
+    let reset: i64 = value.trim().parse().ok()?;
+    if reset > 1_000_000_000 {
+        Some((reset - now).max(0) as u64)
+    } else {
+        Some(reset.max(0) as u64)
+    }
+}
+
+impl Net {
+    /// GETs `url`, reading the whole body. hyper can only time out reads
+    /// and writes, not connecting, so with a timeout set the request runs
+    /// on its own thread and is abandoned if it takes too long.
+    pub fn get(&self, url: &str) -> Result<Response, Error> {
+        self.get_with(url, Headers::new())
+    }
+
+    /// Like `get`, sending `headers` along with the request.
+    /// With `hold_off`, waits out any rate limiting first, up to
+    /// `MAX_PAUSE`; without, a provider that is holding requests off
+    /// isn't asked at all. A 429 is `Error::RateLimited` either way.
+    pub fn get_with(&self, url: &str, headers: Headers) -> Result<Response, Error> {
+        let now = Instant::now();
+        if self.hold_off {
+            sleep(throttle(url, |throttle| throttle.pause(now)).min(MAX_PAUSE));
+        } else if let Some(left) = throttle(url, |throttle| throttle.held_off(now)) {
+            return Err(Error::RateLimited {
+                retry_after: Some(left),
+            });
+        }
+        let resp = self.send(url, headers);
+        if self.incidents {
+            incidents::observe(url, incidents::class(&resp));
+        }
+        let resp = resp?;
+        let now = history::now();
+        let limited = resp.status == StatusCode::TooManyRequests;
+        let retry_after = resp
+            .header("Retry-After")
+            .and_then(|value| parse_retry_after(&value, now));
+        let remaining = resp
+            .header("X-RateLimit-Remaining")
+            .and_then(|value| value.trim().parse().ok());
+        let reset = resp
+            .header("X-RateLimit-Reset")
+            .and_then(|value| parse_reset(&value, now));
+        throttle(url, |throttle| {
+            throttle.observe(Instant::now(), limited, retry_after, remaining, reset)
+        });
+        if limited {
+            return Err(Error::RateLimited { retry_after });
+        }
+        Ok(resp)
+    }
+
+    fn send(&self, url: &str, headers: Headers) -> Result<Response, Error> {
+        let timeout = match self.timeout {
+            Some(timeout) => timeout,
+            None => return get(url, headers, None),
+        };
+        let (tx, rx) = mpsc::channel();
+        let owned = url.to_string();
+        thread::spawn(move || {
+            let _ = tx.send(get(&owned, headers, Some(timeout)));
+        });
+        rx.recv_timeout(timeout).unwrap_or_else(|_| {
+            Err(Error::Network(format!(
+                "{} timed out after {}s",
+                url,
+                timeout.as_secs()
+            )))
+        })
+    }
+
+    fn retries(&self, err: &Error) -> bool {
+        match *err {
+            Error::RateLimited { .. } => self.hold_off,
+            _ => err.is_transient(),
+        }
+    }
+
+    /// Runs `fetch` until it succeeds or the retries run out. Only network
+    /// errors are retried, and rate limiting with `hold_off`; asking again
+    /// won't make a ticker ID valid.
+    pub fn retry<T, F>(&self, name: &str, mut fetch: F) -> Result<T, Error>
+    where
+        F: FnMut() -> Result<T, Error>,
+    {
+        let mut retry = 0;
+        loop {
+            match fetch() {
+                Err(ref err) if self.retries(err) && retry < self.retry.attempts => {
+                    let delay = self.retry.delay(retry, roll());
+                    info!(
+                        "{} failed ({}), retrying in {}ms",
+                        name,
+                        err,
+                        delay.as_millis()
+                    );
+                    sleep(delay);
+                    retry += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+fn get(url: &str, headers: Headers, timeout: Option<Duration>) -> Result<Response, Error> {
+    let mut client = reqwest::Client::new()?;
+    if let Some(timeout) = timeout {
+        client.timeout(timeout);
+    }
+    let mut resp = client.get(url).headers(headers).send()?;
+    let mut body = Vec::new();
+    resp.read_to_end(&mut body)?;
+    Ok(Response {
+        status: *resp.status(),
+        headers: resp.headers().clone(),
+        body,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn quick(attempts: u32) -> Net {
+        Net {
+            chaos: Chaos::default(),
+            retry: Retry {
+                attempts,
+                backoff: Duration::from_millis(1),
+            },
+            timeout: None,
+            api: DEFAULT_API,
+            sources: &[],
+            hold_off: true,
+            incidents: false,
+        }
+    }
+
+    #[test]
+    fn backs_off_exponentially() {
+        let retry = Retry::default();
+        assert_eq!(retry.delay(0, 50), Duration::from_millis(250));
+        assert_eq!(retry.delay(2, 50), Duration::from_millis(1000));
+        assert_eq!(retry.delay(2, 0), Duration::from_millis(500));
+        assert!(retry.delay(2, 99) < Duration::from_millis(1500));
+    }
+
+    #[test]
+    fn retries_network_errors() {
+        let calls = Cell::new(0);
+        let result = quick(2).retry("bitcoin", || {
+            calls.set(calls.get() + 1);
+            if calls.get() < 3 {
+                Err(Error::Network("down".to_string()))
+            } else {
+                Ok(calls.get())
+            }
+        });
+        assert_eq!(result.unwrap(), 3);
+
+        calls.set(0);
+        let result: Result<(), _> = quick(2).retry("bitcoin", || {
+            calls.set(calls.get() + 1);
+            Err(Error::Network("down".to_string()))
+        });
+        assert!(result.is_err());
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[test]
+    fn times_out_hung_requests() {
+        // accepts the connection but never answers
+        let listener = ::std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let net = Net {
+            timeout: Some(Duration::from_secs(1)),
+            ..quick(0)
+        };
+        let err = net.get(&url).err().unwrap();
+        match err {
+            Error::Network(ref message) => assert!(message.contains("timed out"), "{}", err),
+            _ => panic!("{:?}", err),
+        }
+    }
+
+    #[test]
+    fn sends_and_reads_headers() {
+        use std::io::{BufRead, BufReader, Write};
+
+        let listener = ::std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                request.push(line.trim().to_string());
+            }
+            (&stream)
+                .write_all(
+                    b"HTTP/1.1 304 Not Modified\r\nETag: \"v2\"\r\nContent-Length: 0\r\n\r\n",
+                )
+                .unwrap();
+            request
+        });
+
+        let mut headers = Headers::new();
+        headers.set_raw("If-None-Match", vec![b"\"v1\"".to_vec()]);
+        let resp = quick(0).get_with(&url, headers).unwrap();
+        assert_eq!(resp.status, StatusCode::NotModified);
+        assert_eq!(resp.header("ETag").as_deref(), Some("\"v2\""));
+        assert!(server
+            .join()
+            .unwrap()
+            .contains(&"If-None-Match: \"v1\"".to_string()));
+    }
+
+    #[test]
+    fn holds_off_after_429() {
+        let now = Instant::now();
+        let mut throttle = Throttle::new();
+        assert_eq!(throttle.pause(now), Duration::from_secs(0));
+
+        throttle.observe(now, true, Some(30), None, None);
+        assert_eq!(throttle.pause(now), Duration::from_secs(30));
+        assert_eq!(
+            throttle.describe(now).as_deref(),
+            Some("holding off for 30s")
+        );
+        assert_eq!(
+            throttle.pause(now + Duration::from_secs(40)),
+            Duration::from_secs(0)
+        );
+
+        throttle.observe(now, true, None, None, None);
+        assert_eq!(
+            throttle.pause(now),
+            Duration::from_secs(DEFAULT_RETRY_AFTER)
+        );
+
+        throttle.observe(now, false, None, Some(50), Some(60));
+        assert_eq!(
+            throttle,
+            Throttle {
+                last: Some(now),
+                ..Throttle::new()
+            }
+        );
+    }
+
+    #[test]
+    fn spreads_out_a_low_quota() {
+        let now = Instant::now();
+        let mut throttle = Throttle::new();
+        throttle.observe(now, false, None, Some(3), Some(60));
+        assert_eq!(throttle.pause(now), Duration::from_secs(15));
+        assert_eq!(
+            throttle.pause(now + Duration::from_secs(5)),
+            Duration::from_secs(10)
+        );
+
+        throttle.observe(now, false, None, Some(0), Some(60));
+        assert_eq!(throttle.pause(now), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn parses_rate_limit_headers() {
+        assert_eq!(parse_retry_after("120", 0), Some(120));
+        // 2017-11-11T00:00:00Z
+        assert_eq!(
+            parse_retry_after("Sat, 11 Nov 2017 00:01:00 GMT", 1_510_358_400),
+            Some(60)
+        );
+        assert_eq!(parse_retry_after("soon", 0), None);
+        assert_eq!(parse_reset("45", 1_510_358_400), Some(45));
+        assert_eq!(parse_reset("1510358500", 1_510_358_400), Some(100));
+    }
+
+    #[test]
+    fn falls_back_when_rate_limited_once() {
+        let limited = || Err(Error::RateLimited { retry_after: None });
+        let calls = Cell::new(0);
+        let result: Result<(), _> = quick(2).retry("bitcoin", || {
+            calls.set(calls.get() + 1);
+            limited()
+        });
+        assert!(result.is_err());
+        assert_eq!(calls.get(), 3);
+
+        calls.set(0);
+        let once = Net {
+            hold_off: false,
+            ..quick(2)
+        };
+        let result: Result<(), _> = once.retry("bitcoin", || {
+            calls.set(calls.get() + 1);
+            limited()
+        });
+        assert!(result.is_err());
+        assert_eq!(calls.get(), 1);
+
+        let url = "http://held-off.invalid/ticker/";
+        throttle(url, |throttle| {
+            throttle.observe(Instant::now(), true, Some(3600), None, None)
+        });
+        let started = Instant::now();
+        match once.get(url) {
+            Err(Error::RateLimited {
+                retry_after: Some(left),
+            }) => assert!(left > 3500, "{}", left),
+            other => panic!("{:?}", other.err()),
+        }
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn gives_up_on_other_errors() {
+        let calls = Cell::new(0);
+        let result: Result<(), _> = quick(5).retry("bitcoin", || {
+            calls.set(calls.get() + 1);
+            Err(Error::InvalidTicker {
+                name: "bitcoin".to_string(),
+            })
+        });
+        assert!(result.is_err());
+        assert_eq!(calls.get(), 1);
+    }
+}
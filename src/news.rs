@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use net::Net;
+use errors::Error;
+use ticker::short_name;
+
+/// Where headlines come from unless `news_url` says otherwise. `{coin}`
+/// is replaced with the coin's name.
+pub const DEFAULT_URL: &str = "https://cointelegraph.com/rss/tag/{coin}";
+/// Headlines longer than this many characters are cut short.
+pub const DEFAULT_WIDTH: usize = 60;
+/// Feeds change far less often than prices, so they're only refetched
+/// this often.
+const REFRESH: Duration = Duration::from_secs(15 * 60);
+
+/// Serves `news:<coin>` pseudo-tickers, rotating through the latest
+/// headlines one per round.
+pub struct News {
+    url: String,
+    width: usize,
+    /// only rotate through headlines already fetched
+    offline: bool,
+    feeds: HashMap<String, Feed>,
+}
+
+struct Feed {
+    headlines: Vec<String>,
+    fetched: Instant,
+    next: usize,
+}
+
+impl News {
+    pub fn new(url: Option<&str>, width: Option<usize>, offline: bool) -> News {
+        News {
+            url: url.unwrap_or(DEFAULT_URL).to_string(),
+            width: width.unwrap_or(DEFAULT_WIDTH),
+            offline,
+            feeds: HashMap::new(),
+        }
+    }
+
+    /// The feed `coin`'s headlines come from.
+    pub fn url(&self, coin: &str) -> String {
+        self.url.replace("{coin}", coin)
+    }
+
+    /// The segment to print for `coin`, like `btc: Bitcoin tops…`, moving
+    /// on to the next headline every call.
+    pub fn segment(&mut self, coin: &str, net: &Net) -> Result<String, Error> {
+        let stale = self
+            .feeds
+            .get(coin)
+            .is_none_or(|feed| feed.fetched.elapsed() >= REFRESH);
+        if stale && !self.offline {
+            let headlines = fetch(&self.url(coin), net)?;
+            let next = self.feeds.get(coin).map_or(0, |feed| feed.next);
+            self.feeds.insert(
+                coin.to_string(),
+                Feed {
+                    headlines,
+                    fetched: Instant::now(),
+                    next,
+                },
+            );
+        }
+        let feed = self.feeds.get_mut(coin).ok_or_else(|| {
+            Error::Network(format!("no headlines for {}, and --offline is set", coin))
+        })?;
+        if feed.headlines.is_empty() {
+            return Err(Error::Other(format!("no headlines for {}", coin)));
+        }
+        let headline = &feed.headlines[feed.next % feed.headlines.len()];
+        feed.next = (feed.next + 1) % feed.headlines.len();
+        Ok(format!(
+            "{}: {}",
+            short_name(coin),
+            truncate(headline, self.width)
+        ))
+    }
+}
+
+fn fetch(url: &str, net: &Net) -> Result<Vec<String>, Error> {
+    debug!("retrieving headlines from {}", url);
+    net.chaos.inject("news")?;
+    let resp = net.get(url)?;
+    if !resp.status.is_success() {
+        return Err(Error::Network(format!("{} answered {}", url, resp.status)));
+    }
+    Ok(titles(&resp.text()))
+}
+
+/// Pulls the item titles out of an RSS or Atom feed, newest first as
+/// feeds list them. This is nowhere near a full XML parser, but feeds are
+/// regular enough for it.
+fn titles(feed: &str) -> Vec<String> {
+    let mut titles = Vec::new();
+    let mut rest = feed;
+    while let Some(start) = rest.find("<item").or_else(|| rest.find("<entry")) {
+        rest = &rest[start..];
+        let title = rest.find("<title").and_then(|open| {
+            let body = &rest[open..];
+            let body = &body[body.find('>')? + 1..];
+            Some(&body[..body.find("</title>")?])
+        });
+        if let Some(title) = title {
+            let title = unescape(title.trim());
+            if !title.is_empty() {
+                titles.push(title);
+            }
+        }
+        rest = &rest[1..];
+    }
+    titles
+}
+
+fn unescape(text: &str) -> String {
+    let text = text
+        .trim_start_matches("<![CDATA[")
+        .trim_end_matches("]]>")
+        .trim();
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let mut cut: String = text.chars().take(width.saturating_sub(1)).collect();
+    cut.push('…');
+    cut
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_rss_titles() {
+        let feed = r#"<?xml version="1.0"?><rss><channel><title>Feed</title>
+            <item><title><![CDATA[Bitcoin tops $100k]]></title></item>
+            <item><title>Fees &amp; forks</title><link>x</link></item>
+            </channel></rss>"#;
+        assert_eq!(titles(feed), vec!["Bitcoin tops $100k", "Fees & forks"]);
+    }
+
+    #[test]
+    fn reads_atom_titles() {
+        let feed = r#"<feed><title>Feed</title>
+            <entry><title type="text">Ethereum upgrade lands</title></entry></feed>"#;
+        assert_eq!(titles(feed), vec!["Ethereum upgrade lands"]);
+    }
+
+    #[test]
+    fn truncates_long_headlines() {
+        assert_eq!(truncate("short", 10), "short");
+        assert_eq!(truncate("a much longer headline", 10), "a much lo…");
+    }
+}
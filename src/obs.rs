@@ -0,0 +1,48 @@
+use std::fs;
+use std::path::Path;
+
+use errors::Error;
+use ticker::{to_f64, Currency};
+
+/// What a coin's file says, like `BTC $2545.19 ▼3.39%`: one line, since
+/// OBS shows a text source's file as it is.
+pub fn text(ticker: &Currency) -> String {
+    let price = ticker
+        .price_usd
+        .map_or("?".to_string(), |price| format!("${}", price));
+    match to_f64(ticker.percent_change_24) {
+        Some(change) if change < 0.0 => format!("{} {} ▼{:.2}%", ticker.symbol, price, -change),
+        Some(change) => format!("{} {} ▲{:.2}%", ticker.symbol, price, change),
+        None => format!("{} {}", ticker.symbol, price),
+    }
+}
+
+/// Writes `ticker` to `<dir>/<coin ID>.txt` for an OBS "read from file"
+/// text source. The file is replaced in one rename, so OBS never reads
+/// it half written.
+pub fn write(dir: &Path, ticker: &Currency) -> Result<(), Error> {
+    let path = dir.join(format!("{}.txt", ticker.id));
+    let partial = dir.join(format!(".{}.txt.tmp", ticker.id));
+    fs::write(&partial, text(ticker))?;
+    fs::rename(&partial, &path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+    use std::{env, process};
+
+    #[test]
+    fn writes_a_file_per_coin() {
+        let tickers: Vec<Currency> =
+            serde_json::from_str(include_str!("../tests/fixtures/ticker.json")).unwrap();
+        let dir = env::temp_dir().join(format!("cryptoticker-test-obs-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        write(&dir, &tickers[0]).unwrap();
+        let text = fs::read_to_string(dir.join("bitcoin.txt")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(text, "BTC $2545.19 ▼3.39%");
+    }
+}
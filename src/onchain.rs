@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use serde_json;
+
+use dirs;
+use errors::Error;
+use history;
+use net::Net;
+use symbols::Symbols;
+
+/// Where the metrics come from unless `onchain_url` says otherwise.
+pub const DEFAULT_URL: &str = "https://api.glassnode.com";
+
+/// The metrics are daily, so there is no point asking more than hourly.
+const REFRESH: i64 = 60 * 60;
+
+/// On-chain metrics for one coin, each `None` if the provider doesn't
+/// have it for that coin or the key's plan doesn't cover it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Metrics {
+    /// every coin valued at the price it last moved at, in USD
+    pub realized_cap: Option<f64>,
+    /// addresses that sent or received a transaction in the last day
+    pub active_addresses: Option<f64>,
+    /// coins that moved onto exchanges minus those that moved off, over
+    /// the last day
+    pub exchange_netflow: Option<f64>,
+}
+
+/// A Glassnode-compatible provider, called with the user's API key.
+#[derive(Debug, Clone)]
+pub struct Provider {
+    pub url: String,
+    pub key: String,
+}
+
+/// One point of a metric's series.
+#[derive(Deserialize)]
+struct Point {
+    v: Option<f64>,
+}
+
+/// Fetched metrics, kept in the cache directory by symbol.
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    fetched: i64,
+    metrics: Metrics,
+}
+
+fn get(url: &str, net: &Net) -> Result<String, Error> {
+    // the URL carries the API key, so only the path is logged
+    debug!("retrieving {}", url.split('?').next().unwrap_or(url));
+    let resp = net.get(url)?;
+    if !resp.status.is_success() {
+        return Err(Error::Network(format!(
+            "the on-chain provider answered {}",
+            resp.status
+        )));
+    }
+    Ok(resp.text())
+}
+
+/// The latest value of the daily series a list of points makes up.
+fn latest(content: &str) -> Result<Option<f64>, Error> {
+    let points: Vec<Point> = serde_json::from_str(content)?;
+    Ok(points.last().and_then(|point| point.v))
+}
+
+impl Provider {
+    fn metric(&self, path: &str, symbol: &str, net: &Net) -> Result<Option<f64>, Error> {
+        let url = format!(
+            "{}/v1/metrics/{}?a={}&i=24h&api_key={}",
+            self.url.trim_end_matches('/'),
+            path,
+            symbol,
+            self.key
+        );
+        latest(&get(&url, net)?)
+    }
+
+    /// Asks for each metric of `symbol`. One the provider won't give is
+    /// left out, but if none come back that is an error.
+    fn fetch(&self, symbol: &str, net: &Net) -> Result<Metrics, Error> {
+        let mut last_err = None;
+        let mut ask = |path: &str| match self.metric(path, symbol, net) {
+            Ok(value) => value,
+            Err(err) => {
+                debug!("no {} for {}: {}", path, symbol, err);
+                last_err = Some(err);
+                None
+            }
+        };
+        let metrics = Metrics {
+            realized_cap: ask("market/marketcap_realized_usd"),
+            active_addresses: ask("addresses/active_count"),
+            exchange_netflow: ask("transactions/transfers_volume_exchanges_net"),
+        };
+        match last_err {
+            Some(err) if metrics == Metrics::default() => Err(err),
+            _ => Ok(metrics),
+        }
+    }
+}
+
+fn path() -> Result<PathBuf, Error> {
+    Ok(dirs::cache_dir()?.join("onchain.json"))
+}
+
+/// The metrics for `symbol`, from the cache directory if they were
+/// fetched recently. A failed fetch falls back to older metrics, and
+/// offline there are only ever the older metrics.
+pub fn load(provider: &Provider, symbol: &str, offline: bool, net: &Net) -> Result<Metrics, Error> {
+    let path = path()?;
+    let mut entries: HashMap<String, Entry> = fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    match entries.get(symbol) {
+        Some(entry) if offline || history::now() - entry.fetched < REFRESH => {
+            return Ok(entry.metrics)
+        }
+        None if offline => {
+            return Err(Error::Network(format!(
+                "no on-chain metrics for {} are cached, and --offline is set",
+                symbol
+            )))
+        }
+        _ => {}
+    }
+    match provider.fetch(symbol, net) {
+        Ok(metrics) => {
+            entries.insert(
+                symbol.to_string(),
+                Entry {
+                    fetched: history::now(),
+                    metrics,
+                },
+            );
+            fs::write(&path, serde_json::to_string(&entries)?)?;
+            Ok(metrics)
+        }
+        Err(err) => match entries.get(symbol) {
+            Some(entry) => {
+                debug!("{}, using older on-chain metrics for {}", err, symbol);
+                Ok(entry.metrics)
+            }
+            None => Err(err),
+        },
+    }
+}
+
+/// Metrics for each coin ID in `ids` the provider knows, for the
+/// `{realized_cap}`, `{active_addresses}` and `{exchange_netflow}`
+/// template fields.
+pub fn load_all(
+    provider: &Provider,
+    ids: &[String],
+    symbols: &Symbols,
+    offline: bool,
+    net: &Net,
+) -> HashMap<String, Metrics> {
+    let mut found = HashMap::new();
+    for id in ids {
+        let symbol = match symbols.symbol_of(id) {
+            Some(symbol) => symbol.to_uppercase(),
+            None => continue,
+        };
+        match load(provider, &symbol, offline, net) {
+            Ok(metrics) => {
+                found.insert(id.clone(), metrics);
+            }
+            Err(err) => {
+                debug!("no on-chain metrics for {}: {}", id, err);
+            }
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_latest_point() {
+        let content = r#"[{"t": 1699920000, "v": 401234567890.5},
+                          {"t": 1700006400, "v": 402000000000.0}]"#;
+        assert_eq!(latest(content).unwrap(), Some(402000000000.0));
+        assert_eq!(latest("[]").unwrap(), None);
+        assert_eq!(latest(r#"[{"t": 1700006400, "v": null}]"#).unwrap(), None);
+        assert!(latest(r#"{"error": "unauthorized"}"#).is_err());
+    }
+}
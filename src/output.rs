@@ -0,0 +1,646 @@
+use std::io::{stdin, BufRead};
+use std::str::FromStr;
+use std::thread;
+
+use clap::ArgMatches;
+use serde_json::{self, Value};
+
+use period::Period;
+use signals::Signals;
+use template::{compact, significant_places};
+use ticker::{to_f64, Currency};
+
+/// Colors for coins up or down over the last day.
+pub const UP: &str = "#00ff00";
+pub const DOWN: &str = "#ff0000";
+
+/// How each round is printed, picked with `--output`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Output {
+    /// the segments on one line, as tmux and most status bars take it
+    Text,
+    /// i3bar's JSON protocol, a block per coin
+    I3bar,
+    /// the JSON object a Waybar custom module reads, a line per round
+    Waybar,
+    /// a line with Polybar's formatting tags, coloring each coin
+    Polybar,
+    /// an xbar or SwiftBar plugin's menu bar line and dropdown
+    Xbar,
+    /// one coin and nothing else, for a shell prompt
+    Prompt,
+}
+
+impl FromStr for Output {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Output, String> {
+        match s {
+            "text" => Ok(Output::Text),
+            "i3bar" => Ok(Output::I3bar),
+            "waybar" => Ok(Output::Waybar),
+            "polybar" => Ok(Output::Polybar),
+            "xbar" => Ok(Output::Xbar),
+            "prompt" => Ok(Output::Prompt),
+            _ => Err(format!(
+                "{:?} should be text, i3bar, waybar, polybar, xbar or prompt",
+                s
+            )),
+        }
+    }
+}
+
+/// One argument's share of the line, kept apart for bars that draw each
+/// coin on its own.
+#[derive(Debug, Clone)]
+pub struct Block {
+    /// the argument it's for, like `bitcoin` or `news:bitcoin`
+    pub name: String,
+    /// the rendered segment
+    pub full_text: String,
+    /// what to show when the bar runs out of room, like the coin's alias
+    pub short_text: Option<String>,
+    /// the data it was rendered from, for coins
+    pub ticker: Option<Currency>,
+    /// a color that wins over the change's, like a broken peg's
+    pub highlight: Option<&'static str>,
+    /// the change that colors it up or down
+    pub period: Period,
+}
+
+impl Block {
+    pub fn new(name: &str, full_text: &str) -> Block {
+        Block {
+            name: name.to_string(),
+            full_text: full_text.trim_end().to_string(),
+            short_text: None,
+            ticker: None,
+            highlight: None,
+            period: Period::default(),
+        }
+    }
+
+    /// The highlight, or else `UP` or `DOWN` by the change over the
+    /// block's period, if it's known.
+    pub fn color(&self) -> Option<&'static str> {
+        self.highlight.or_else(|| {
+            self.ticker
+                .as_ref()
+                .and_then(|ticker| change_color(ticker, self.period))
+        })
+    }
+}
+
+/// How the text output puts its segments on a line: `--separator`
+/// between them, and `--prefix` and `--suffix` around it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub separator: String,
+    pub prefix: String,
+    pub suffix: String,
+}
+
+impl Default for Frame {
+    fn default() -> Frame {
+        Frame {
+            separator: " ".to_string(),
+            prefix: String::new(),
+            suffix: String::new(),
+        }
+    }
+}
+
+impl Frame {
+    /// What `--separator`, `--prefix` and `--suffix` ask for.
+    pub fn of(matches: &ArgMatches) -> Frame {
+        let given = |name| matches.value_of(name).map(unescape);
+        let default = Frame::default();
+        Frame {
+            separator: given("separator").unwrap_or(default.separator),
+            prefix: given("prefix").unwrap_or(default.prefix),
+            suffix: given("suffix").unwrap_or(default.suffix),
+        }
+    }
+
+    /// `segments` between separators, each without the space it was
+    /// rendered with. Padding stays, and empty ones, like a first paint
+    /// that shows nothing, are left out.
+    pub fn join<S: AsRef<str>>(&self, segments: &[S]) -> String {
+        let segments: Vec<&str> = segments
+            .iter()
+            .map(|segment| {
+                let segment = segment.as_ref();
+                segment.strip_suffix(' ').unwrap_or(segment)
+            })
+            .filter(|segment| !segment.is_empty())
+            .collect();
+        segments.join(&self.separator)
+    }
+
+    /// `line` between the prefix and the suffix.
+    pub fn wrap(&self, line: &str) -> String {
+        format!("{}{}{}", self.prefix, line, self.suffix)
+    }
+}
+
+/// `text` with `\n`, `\t` and `\\` turned into what they stand for, since
+/// a shell passes them on as they were typed.
+pub fn unescape(text: &str) -> String {
+    let mut out = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek().cloned()) {
+            ('\\', Some(next @ ('n' | 't' | '\\'))) => {
+                chars.next();
+                out.push(match next {
+                    'n' => '\n',
+                    't' => '\t',
+                    _ => '\\',
+                });
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// `UP` or `DOWN` by `ticker`'s change over `period`, if it's known.
+fn change_color(ticker: &Currency, period: Period) -> Option<&'static str> {
+    let change = to_f64(period.change(ticker))?;
+    Some(if change < 0.0 { DOWN } else { UP })
+}
+
+#[derive(Serialize)]
+struct I3Block<'a> {
+    name: &'static str,
+    instance: &'a str,
+    full_text: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    short_text: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    color: Option<&'static str>,
+}
+
+/// What starts an i3bar stream: the header, asking for clicks, and the
+/// opening of the endless array of status lines.
+pub const I3BAR_HEADER: &str = "{\"version\":1,\"click_events\":true}\n[";
+
+/// One i3bar status line, a block per coin. Blocks are told apart in
+/// click events by their `instance`, the argument they're for.
+pub fn i3bar(blocks: &[Block]) -> String {
+    let blocks: Vec<I3Block> = blocks
+        .iter()
+        .map(|block| I3Block {
+            name: "cryptoticker",
+            instance: &block.name,
+            full_text: &block.full_text,
+            short_text: block.short_text.as_deref(),
+            color: block.color(),
+        })
+        .collect();
+    serde_json::to_string(&blocks).unwrap_or_default()
+}
+
+#[derive(Serialize)]
+struct WaybarModule {
+    text: String,
+    tooltip: String,
+    class: &'static str,
+}
+
+/// Waybar renders text and tooltips as Pango markup.
+fn markup(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// A tooltip line with what doesn't fit in the bar, like
+/// `Bitcoin (BTC) #1, market cap $42.1B, 24h volume $1.2B`.
+fn details(ticker: &Currency) -> String {
+    let usd = |value| to_f64(value).map_or("?".to_string(), |value| format!("${}", compact(value)));
+    format!(
+        "{} ({}) #{}, market cap {}, 24h volume {}",
+        ticker.name,
+        ticker.symbol,
+        ticker.rank,
+        usd(ticker.market_cap_usd),
+        usd(ticker.volume_usd_24h)
+    )
+}
+
+/// One round as a Waybar module: the whole line as its text, a line of
+/// details per coin as its tooltip, and `up` or `down` as its class when
+/// every coin moved the same way over the last day, `mixed` otherwise.
+pub fn waybar(blocks: &[Block]) -> String {
+    let text: Vec<&str> = blocks
+        .iter()
+        .map(|block| block.full_text.as_str())
+        .collect();
+    let tooltip: Vec<String> = blocks
+        .iter()
+        .map(|block| match block.ticker {
+            Some(ref ticker) => details(ticker),
+            None => block.full_text.clone(),
+        })
+        .collect();
+    let colors: Vec<&str> = blocks.iter().filter_map(Block::color).collect();
+    let class = if colors.is_empty() {
+        ""
+    } else if colors.iter().all(|&color| color == UP) {
+        "up"
+    } else if colors.iter().all(|&color| color == DOWN) {
+        "down"
+    } else {
+        "mixed"
+    };
+    let module = WaybarModule {
+        text: markup(&text.join(" ")),
+        tooltip: markup(&tooltip.join("\n")),
+        class,
+    };
+    serde_json::to_string(&module).unwrap_or_default()
+}
+
+/// One round as a line of Polybar formatting tags, each coin in the
+/// color of its 24h change. With `click`, a left click on a coin runs it
+/// with `{coin}` replaced by the coin's ID.
+pub fn polybar(blocks: &[Block], click: Option<&str>) -> String {
+    let segments: Vec<String> = blocks
+        .iter()
+        .map(|block| {
+            let text = match block.color() {
+                Some(color) => format!("%{{F{}}}{}%{{F-}}", color, block.full_text),
+                None => block.full_text.clone(),
+            };
+            match (click, block.ticker.as_ref()) {
+                (Some(command), Some(ticker)) => {
+                    // a colon would end the action early
+                    let command = command.replace("{coin}", &ticker.id).replace(':', "\\:");
+                    format!("%{{A1:{}:}}{}%{{A}}", command, text)
+                }
+                _ => text,
+            }
+        })
+        .collect();
+    segments.join(" ")
+}
+
+/// One round as an xbar plugin's output: the line for the menu bar, then
+/// a dropdown with a line of details per coin, linking to its page.
+pub fn xbar(blocks: &[Block]) -> String {
+    let text: Vec<&str> = blocks
+        .iter()
+        .map(|block| block.full_text.as_str())
+        .collect();
+    // a `|` would start the line's parameters
+    let mut out = format!("{}\n---\n", text.join(" ").replace('|', "/"));
+    for block in blocks {
+        match block.ticker {
+            Some(ref ticker) => {
+                out.push_str(&format!(
+                    "{} | href=https://coinmarketcap.com/currencies/{}/",
+                    details(ticker).replace('|', "/"),
+                    ticker.id
+                ));
+                if let Some(color) = block.color() {
+                    out.push_str(&format!(" color={}", color));
+                }
+                out.push('\n');
+            }
+            None => out.push_str(&format!("{}\n", block.full_text.replace('|', "/"))),
+        }
+    }
+    out
+}
+
+/// A shell whose prompt `--output prompt` colors, marking the escape
+/// codes as taking no room so the line editor measures the prompt right.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+}
+
+impl FromStr for Shell {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Shell, String> {
+        match s {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            _ => Err(format!("{:?} should be bash or zsh", s)),
+        }
+    }
+}
+
+/// The first coin alone, without the trailing space, for a prompt to
+/// embed. With `shell`, it's green or red by its 24h change.
+pub fn prompt(blocks: &[Block], shell: Option<Shell>) -> String {
+    let block = match blocks.first() {
+        Some(block) => block,
+        None => return String::new(),
+    };
+    let code = match block.color() {
+        Some(color) if color == UP => "32",
+        Some(_) => "31",
+        None => return block.full_text.clone(),
+    };
+    // readline skips what's between \x01 and \x02; zsh what's in %{ %}
+    let (start, end) = match shell {
+        Some(Shell::Bash) => ("\x01", "\x02"),
+        Some(Shell::Zsh) => ("%{", "%}"),
+        None => return block.full_text.clone(),
+    };
+    format!(
+        "{}\x1b[{}m{}{}{}\x1b[0m{}",
+        start, code, end, block.full_text, start, end
+    )
+}
+
+/// The block a line of i3bar's click event stream was for. The stream is
+/// an endless array, so every event after the first starts with a comma.
+fn clicked(line: &str) -> Option<String> {
+    let line = line.trim().trim_start_matches(',');
+    let event: Value = serde_json::from_str(line).ok()?;
+    event.get("instance")?.as_str().map(String::from)
+}
+
+/// One Stream Deck key's worth of a coin, for plugins that show a field
+/// of a polled JSON document, like `title`, and tint the key with
+/// another, like `color`.
+#[derive(Debug, Serialize)]
+pub struct StreamDeckKey {
+    pub id: String,
+    /// the symbol over the price, like `BTC\n2.55k`, sized for a key
+    pub title: String,
+    pub price: Option<String>,
+    pub change_24h: Option<String>,
+    pub color: Option<&'static str>,
+}
+
+/// A price short enough for a key: compacted from a thousand up, and to
+/// four significant digits below.
+fn key_price(price: f64) -> String {
+    if price >= 1000.0 {
+        compact(price)
+    } else {
+        format!("{:.*}", significant_places(price, 4), price)
+    }
+}
+
+pub fn streamdeck(ticker: &Currency) -> StreamDeckKey {
+    let price = to_f64(ticker.price_usd).map(key_price);
+    StreamDeckKey {
+        id: ticker.id.clone(),
+        title: format!("{}\n{}", ticker.symbol, price.as_deref().unwrap_or("?")),
+        price,
+        change_24h: to_f64(ticker.percent_change_24).map(|change| format!("{:+.2}%", change)),
+        color: change_color(ticker, Period::Day),
+    }
+}
+
+/// Clears the terminal and puts the cursor top left.
+pub const CLEAR: &str = "\x1b[H\x1b[2J";
+
+/// One round for `--watch`: the screen cleared and a row per argument,
+/// its columns lined up, then when it was `refreshed`. Pseudo-tickers
+/// take the first column with their whole segment.
+pub fn watch(blocks: &[Block], refreshed: &str) -> String {
+    let change = |value| to_f64(value).map_or("?".to_string(), |value| format!("{:+.2}%", value));
+    let mut rows = vec![[
+        "COIN".to_string(),
+        "PRICE".to_string(),
+        "1H".to_string(),
+        "24H".to_string(),
+        "7D".to_string(),
+        "MARKET CAP".to_string(),
+    ]];
+    for block in blocks {
+        rows.push(match block.ticker {
+            Some(ref ticker) => [
+                block
+                    .short_text
+                    .clone()
+                    .unwrap_or_else(|| ticker.symbol.to_lowercase()),
+                ticker
+                    .price_usd
+                    .map_or("?".to_string(), |price| price.to_string()),
+                change(ticker.percent_change_1),
+                change(ticker.percent_change_24),
+                change(ticker.percent_change_7),
+                to_f64(ticker.market_cap_usd)
+                    .map_or("?".to_string(), |cap| format!("${}", compact(cap))),
+            ],
+            None => [
+                block.full_text.clone(),
+                String::new(),
+                String::new(),
+                String::new(),
+                String::new(),
+                String::new(),
+            ],
+        });
+    }
+    let mut widths = [0; 6];
+    for row in rows.iter().filter(|row| !row[1].is_empty()) {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let mut out = CLEAR.to_string();
+    for row in &rows {
+        if row[1].is_empty() {
+            out.push_str(&format!("{}\n", row[0]));
+            continue;
+        }
+        let mut line = format!("{:<width$}", row[0], width = widths[0]);
+        for (cell, width) in row.iter().zip(&widths).skip(1) {
+            line.push_str(&format!("  {:>width$}", cell, width = width));
+        }
+        out.push_str(&format!("{}\n", line));
+    }
+    out.push_str(&format!("\nrefreshed {}\n", refreshed));
+    out
+}
+
+/// Refreshes right away whenever a block is clicked.
+pub fn refresh_on_click(signals: Signals) {
+    thread::spawn(move || {
+        let stdin = stdin();
+        for line in stdin.lock().lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => return,
+            };
+            if let Some(instance) = clicked(&line) {
+                debug!("{} was clicked", instance);
+                signals.refresh();
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn joins_text_segments() {
+        let segments = ["btc:2545.19 ", "eth:348.46   ", "", "doge:error "];
+        assert_eq!(
+            Frame::default().join(&segments),
+            "btc:2545.19 eth:348.46   doge:error"
+        );
+        let frame = Frame {
+            separator: " | ".to_string(),
+            prefix: "[".to_string(),
+            suffix: unescape("]\\n"),
+        };
+        assert_eq!(
+            frame.wrap(&frame.join(&segments)),
+            "[btc:2545.19 | eth:348.46   | doge:error]\n"
+        );
+        assert_eq!(frame.wrap(&frame.join::<&str>(&[])), "[]\n");
+        assert_eq!(unescape("a\\tb\\\\n\\x"), "a\tb\\n\\x");
+    }
+
+    #[test]
+    fn writes_i3bar_blocks() {
+        let tickers: Vec<Currency> =
+            serde_json::from_str(include_str!("../tests/fixtures/ticker.json")).unwrap();
+        let mut coin = Block::new("bitcoin", "btc:2545.19 ");
+        coin.short_text = Some("BTC".to_string());
+        coin.ticker = Some(tickers[0].clone());
+        let error = Block::new("news:bitcoin", "news:bitcoin:error ");
+        assert_eq!(
+            i3bar(&[coin, error]),
+            "[{\"name\":\"cryptoticker\",\"instance\":\"bitcoin\",\"full_text\":\"btc:2545.19\",\
+             \"short_text\":\"BTC\",\"color\":\"#ff0000\"},\
+             {\"name\":\"cryptoticker\",\"instance\":\"news:bitcoin\",\
+             \"full_text\":\"news:bitcoin:error\"}]"
+        );
+    }
+
+    #[test]
+    fn writes_waybar_modules() {
+        let tickers: Vec<Currency> =
+            serde_json::from_str(include_str!("../tests/fixtures/ticker.json")).unwrap();
+        let mut coin = Block::new("bitcoin", "btc:2545.19 ");
+        coin.ticker = Some(tickers[0].clone());
+        let news = Block::new("news:bitcoin", "Fees & forks ");
+        let module: Value = serde_json::from_str(&waybar(&[coin, news])).unwrap();
+        assert_eq!(module["text"], "btc:2545.19 Fees &amp; forks");
+        assert_eq!(module["class"], "down");
+        let tooltip = module["tooltip"].as_str().unwrap();
+        assert!(tooltip.starts_with("Bitcoin (BTC) #1, market cap $"));
+        assert!(tooltip.ends_with("\nFees &amp; forks"));
+    }
+
+    #[test]
+    fn writes_polybar_tags() {
+        let tickers: Vec<Currency> =
+            serde_json::from_str(include_str!("../tests/fixtures/ticker.json")).unwrap();
+        let mut coin = Block::new("bitcoin", "btc:2545.19 ");
+        coin.ticker = Some(tickers[0].clone());
+        let news = Block::new("news:bitcoin", "news:bitcoin:error ");
+        let blocks = [coin, news];
+        assert_eq!(
+            polybar(&blocks, None),
+            "%{F#ff0000}btc:2545.19%{F-} news:bitcoin:error"
+        );
+        assert_eq!(
+            polybar(
+                &blocks[..1],
+                Some("xdg-open https://coinmarketcap.com/{coin}")
+            ),
+            "%{A1:xdg-open https\\://coinmarketcap.com/bitcoin:}%{F#ff0000}btc:2545.19%{F-}%{A}"
+        );
+    }
+
+    #[test]
+    fn writes_xbar_plugins() {
+        let tickers: Vec<Currency> =
+            serde_json::from_str(include_str!("../tests/fixtures/ticker.json")).unwrap();
+        let mut coin = Block::new("bitcoin", "btc:2545.19 ");
+        coin.ticker = Some(tickers[0].clone());
+        let news = Block::new("news:bitcoin", "Fees | forks ");
+        let out = xbar(&[coin, news]);
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[0], "btc:2545.19 Fees / forks");
+        assert_eq!(lines[1], "---");
+        assert!(lines[2].starts_with("Bitcoin (BTC) #1, market cap $"));
+        assert!(lines[2]
+            .ends_with(" | href=https://coinmarketcap.com/currencies/bitcoin/ color=#ff0000"));
+        assert_eq!(lines[3], "Fees / forks");
+    }
+
+    #[test]
+    fn writes_prompt_segments() {
+        let tickers: Vec<Currency> =
+            serde_json::from_str(include_str!("../tests/fixtures/ticker.json")).unwrap();
+        let mut coin = Block::new("bitcoin", "btc:2545.19 ");
+        coin.ticker = Some(tickers[0].clone());
+        let blocks = [coin];
+        assert_eq!(prompt(&blocks, None), "btc:2545.19");
+        assert_eq!(
+            prompt(&blocks, Some(Shell::Bash)),
+            "\x01\x1b[31m\x02btc:2545.19\x01\x1b[0m\x02"
+        );
+        assert_eq!(
+            prompt(&blocks, Some(Shell::Zsh)),
+            "%{\x1b[31m%}btc:2545.19%{\x1b[0m%}"
+        );
+        assert_eq!(prompt(&[], Some(Shell::Zsh)), "");
+    }
+
+    #[test]
+    fn reads_click_events() {
+        assert_eq!(clicked("["), None);
+        assert_eq!(
+            clicked("{\"name\":\"cryptoticker\",\"instance\":\"bitcoin\",\"button\":1}"),
+            Some("bitcoin".to_string())
+        );
+        assert_eq!(
+            clicked(",{\"name\":\"cryptoticker\",\"instance\":\"ethereum\",\"button\":3}"),
+            Some("ethereum".to_string())
+        );
+    }
+
+    #[test]
+    fn shapes_stream_deck_keys() {
+        let tickers: Vec<Currency> =
+            serde_json::from_str(include_str!("../tests/fixtures/ticker.json")).unwrap();
+        let key = streamdeck(&tickers[0]);
+        assert_eq!(key.title, "BTC\n2.55k");
+        assert_eq!(key.change_24h.as_deref(), Some("-3.39%"));
+        assert_eq!(key.color, Some(DOWN));
+        assert_eq!(streamdeck(&tickers[1]).price.as_deref(), Some("348.5"));
+    }
+
+    #[test]
+    fn lines_up_the_watch_table() {
+        let tickers: Vec<Currency> =
+            serde_json::from_str(include_str!("../tests/fixtures/ticker.json")).unwrap();
+        let mut btc = Block::new("bitcoin", "btc:2545.19 ");
+        btc.ticker = Some(tickers[0].clone());
+        let mut eth = Block::new("ethereum", "eth:348.5 ");
+        eth.short_text = Some("ether".to_string());
+        eth.ticker = Some(tickers[1].clone());
+        let fng = Block::new("fng", "fng:72(greed) ");
+        let screen = watch(&[btc, eth, fng], "14:03:22");
+        assert!(screen.starts_with(CLEAR));
+        let lines: Vec<&str> = screen[CLEAR.len()..].lines().collect();
+        assert_eq!(
+            lines[0],
+            "COIN     PRICE      1H     24H       7D  MARKET CAP"
+        );
+        assert_eq!(
+            lines[1],
+            "btc    2545.19  +0.26%  -3.39%   +2.15%      $41.7B"
+        );
+        assert!(lines[2].starts_with("ether  348.463  +1.05%  -6.54%  +27.21%"));
+        assert_eq!(lines[3], "fng:72(greed)");
+        assert_eq!(lines[5], "refreshed 14:03:22");
+    }
+}
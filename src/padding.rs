@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use serde_json;
+
+use dirs;
+use errors::Error;
+
+/// How wide each segment is kept, so prices gaining or losing a digit
+/// don't shift the rest of the status line.
+pub enum Padding {
+    /// every segment exactly this many characters, cut short with `…`
+    Fixed(usize),
+    /// every segment as wide as it's ever been, by argument, kept in
+    /// `widths.json` so one-shot runs from a status bar remember too
+    Widest(HashMap<String, usize>),
+}
+
+fn path() -> Result<PathBuf, Error> {
+    Ok(dirs::cache_dir()?.join("widths.json"))
+}
+
+/// How many characters `text` takes on screen, leaving out tmux style
+/// codes like `#[fg=red]`.
+fn visible(text: &str) -> usize {
+    let mut width = 0;
+    let mut styling = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if styling {
+            styling = c != ']';
+        } else if c == '#' && chars.peek() == Some(&'[') {
+            styling = true;
+        } else {
+            width += 1;
+        }
+    }
+    width
+}
+
+/// The first `width` visible characters of `text`, the last of them a
+/// `…`, closing any style left open.
+fn truncate(text: &str, width: usize) -> String {
+    let mut out = String::new();
+    let mut shown = 0;
+    let mut styling = false;
+    let mut styled = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if styling {
+            styling = c != ']';
+        } else if c == '#' && chars.peek() == Some(&'[') {
+            styling = true;
+            styled = true;
+        } else if shown + 1 >= width {
+            break;
+        } else {
+            shown += 1;
+        }
+        out.push(c);
+    }
+    if width > 0 {
+        out.push('…');
+    }
+    if styled {
+        out.push_str("#[default]");
+    }
+    out
+}
+
+impl Padding {
+    /// `Widest`, with the widths earlier runs saw.
+    pub fn load() -> Padding {
+        let widths = path()
+            .ok()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Padding::Widest(widths)
+    }
+
+    /// `segment` for argument `name`, padded on the right, or cut down
+    /// to a fixed width. The trailing space stays on the end.
+    pub fn fit(&mut self, name: &str, segment: &str) -> String {
+        let text = segment.trim_end();
+        let shown = visible(text);
+        let width = match *self {
+            Padding::Fixed(width) if shown > width => {
+                return format!("{} ", truncate(text, width));
+            }
+            Padding::Fixed(width) => width,
+            Padding::Widest(ref mut widths) => {
+                let widest = widths.entry(name.to_string()).or_insert(0);
+                *widest = (*widest).max(shown);
+                *widest
+            }
+        };
+        format!("{}{} ", text, " ".repeat(width - shown))
+    }
+
+    /// Keeps the widths for the next run, if they're remembered at all.
+    pub fn save(&self) -> Result<(), Error> {
+        if let Padding::Widest(ref widths) = *self {
+            fs::write(path()?, serde_json::to_string(widths)?)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_widest() {
+        let mut padding = Padding::Widest(HashMap::new());
+        assert_eq!(padding.fit("bitcoin", "btc:64250.1 "), "btc:64250.1 ");
+        assert_eq!(padding.fit("bitcoin", "btc:9999.9 "), "btc:9999.9  ");
+        assert_eq!(padding.fit("ethereum", "eth:3400 "), "eth:3400 ");
+        // tmux styles take no room
+        assert_eq!(
+            padding.fit("bitcoin", "btc:#[fg=red]-3#[default] "),
+            "btc:#[fg=red]-3#[default]      "
+        );
+    }
+
+    #[test]
+    fn fixes_the_width() {
+        let mut padding = Padding::Fixed(10);
+        assert_eq!(padding.fit("bitcoin", "btc:2545 "), "btc:2545   ");
+        assert_eq!(padding.fit("bitcoin", "btc:64250.123 "), "btc:64250… ");
+        assert_eq!(
+            padding.fit("bitcoin", "btc:#[fg=red]-3.3912#[default] "),
+            "btc:#[fg=red]-3.39…#[default] "
+        );
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+use std::env;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+
+use clap::ArgMatches;
+
+use errors::Error;
+use symbols::Symbols;
+
+/// Which page `open` shows for a coin.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Page {
+    /// the coin's page on coinmarketcap, where its prices come from
+    Provider,
+    /// a block explorer for the coin's chain
+    Explorer,
+    /// the coin's USD market on an exchange from `TRADE_URLS`
+    Exchange(&'static str),
+}
+
+/// The names `--page` and `open_page` accept.
+pub const PAGES: &[&str] = &[
+    "provider", "explorer", "binance", "bitstamp", "coinbase", "kraken",
+];
+
+/// Each exchange's trade view, `{symbol}` and `{SYMBOL}` being the coin's
+/// symbol in lower and upper case. Binance has no USD markets, so it
+/// shows USDT.
+const TRADE_URLS: &[(&str, &str)] = &[
+    ("binance", "https://www.binance.com/en/trade/{SYMBOL}_USDT"),
+    ("bitstamp", "https://www.bitstamp.net/markets/{symbol}/usd/"),
+    (
+        "coinbase",
+        "https://www.coinbase.com/advanced-trade/spot/{SYMBOL}-USD",
+    ),
+    ("kraken", "https://pro.kraken.com/app/trade/{symbol}-usd"),
+];
+
+/// Block explorers by coin ID. Chains not listed here have no explorer
+/// page.
+const EXPLORERS: &[(&str, &str)] = &[
+    ("bitcoin", "https://mempool.space/"),
+    ("ethereum", "https://etherscan.io/"),
+    ("litecoin", "https://litecoinspace.org/"),
+    ("bitcoin-cash", "https://blockchair.com/bitcoin-cash"),
+    ("dogecoin", "https://blockchair.com/dogecoin"),
+    ("dash", "https://blockchair.com/dash"),
+    ("zcash", "https://blockchair.com/zcash"),
+    ("cardano", "https://cardanoscan.io/"),
+    ("ripple", "https://xrpscan.com/"),
+    ("stellar", "https://stellar.expert/explorer/public"),
+    ("monero", "https://xmrchain.net/"),
+];
+
+impl FromStr for Page {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Page, String> {
+        let name = s.trim().to_lowercase();
+        match name.as_str() {
+            "provider" => Ok(Page::Provider),
+            "explorer" => Ok(Page::Explorer),
+            _ => TRADE_URLS
+                .iter()
+                .find(|&&(exchange, _)| exchange == name)
+                .map(|&(exchange, _)| Page::Exchange(exchange))
+                .ok_or_else(|| format!("{:?} should be one of {}", s, PAGES.join(", "))),
+        }
+    }
+}
+
+impl Page {
+    /// The page for coin `id`, whose symbol is `symbol`.
+    pub fn url(self, id: &str, symbol: &str) -> Result<String, Error> {
+        match self {
+            Page::Provider => Ok(format!("https://coinmarketcap.com/currencies/{}/", id)),
+            Page::Explorer => EXPLORERS
+                .iter()
+                .find(|&&(coin, _)| coin == id)
+                .map(|&(_, url)| url.to_string())
+                .ok_or_else(|| Error::Other(format!("No explorer known for ticker ID {}.", id))),
+            Page::Exchange(exchange) => {
+                let &(_, url) = TRADE_URLS
+                    .iter()
+                    .find(|&&(known, _)| known == exchange)
+                    .unwrap();
+                Ok(url
+                    .replace("{symbol}", &symbol.to_lowercase())
+                    .replace("{SYMBOL}", &symbol.to_uppercase()))
+            }
+        }
+    }
+}
+
+/// Opens `url` in `$BROWSER`, or the desktop's default browser.
+pub fn browse(url: &str) -> Result<(), Error> {
+    let mut command = match env::var("BROWSER") {
+        Ok(ref browser) if !browser.is_empty() => Command::new(browser),
+        _ if cfg!(target_os = "macos") => Command::new("open"),
+        _ if cfg!(windows) => {
+            let mut command = Command::new("cmd");
+            command.args(["/C", "start", ""]);
+            command
+        }
+        _ => Command::new("xdg-open"),
+    };
+    let status = command
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map_err(|err| Error::Other(format!("Could not start a browser: {}", err)))?;
+    if !status.success() {
+        return Err(Error::Other(format!("The browser exited with {}", status)));
+    }
+    Ok(())
+}
+
+/// `cryptoticker open`: opens a coin's page, `default` being the
+/// config's `open_page`.
+pub fn open(
+    matches: &ArgMatches,
+    symbols: &Symbols,
+    overrides: &HashMap<String, String>,
+    default: Option<&str>,
+) -> Result<(), Error> {
+    let arg = matches.value_of("TICKER").unwrap();
+    let id = symbols.resolve(arg, overrides);
+    let symbol = symbols.symbol_of(id).unwrap_or(arg);
+    // the config was checked when it loaded, and the flags by clap
+    let page: Page = matches
+        .value_of("exchange")
+        .or(matches.value_of("page"))
+        .or(default)
+        .unwrap_or("provider")
+        .parse()
+        .unwrap();
+    let url = page.url(id, symbol)?;
+    if matches.is_present("print") {
+        println!("{}", url);
+        return Ok(());
+    }
+    browse(&url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spread::EXCHANGES;
+
+    #[test]
+    fn links_pages() {
+        let page = |name: &str| name.parse::<Page>().unwrap();
+        assert_eq!(
+            page("provider").url("bitcoin", "BTC").unwrap(),
+            "https://coinmarketcap.com/currencies/bitcoin/"
+        );
+        assert_eq!(
+            page("explorer").url("ethereum", "ETH").unwrap(),
+            "https://etherscan.io/"
+        );
+        assert!(page("explorer").url("tether", "USDT").is_err());
+        assert_eq!(
+            page("Kraken").url("bitcoin", "BTC").unwrap(),
+            "https://pro.kraken.com/app/trade/btc-usd"
+        );
+        assert!("ftx".parse::<Page>().is_err());
+        // every exchange prices come from has a trade view
+        for &(exchange, _) in EXCHANGES {
+            assert!(PAGES.contains(&exchange));
+            assert!(exchange.parse::<Page>().is_ok());
+        }
+    }
+}
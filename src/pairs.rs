@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+
+use clap::ArgMatches;
+use serde_json::{self, Value};
+
+use errors::Error;
+use net::Net;
+use symbols::Symbols;
+
+fn get(url: &str, net: &Net) -> Result<String, Error> {
+    debug!("retrieving {}", url);
+    let resp = net.get(url)?;
+    if !resp.status.is_success() {
+        return Err(Error::Network(format!("{} answered {}", url, resp.status)));
+    }
+    Ok(resp.text())
+}
+
+/// What each exchange trades `symbol` against, read from its list of
+/// every pair it has.
+type Lister = fn(&str, &str) -> Result<Vec<String>, Error>;
+
+fn binance(content: &str, symbol: &str) -> Result<Vec<String>, Error> {
+    let answer: Value = serde_json::from_str(content)?;
+    let symbols = answer["symbols"]
+        .as_array()
+        .ok_or_else(|| Error::Parse("binance sent no symbols".to_string()))?;
+    Ok(symbols
+        .iter()
+        .filter(|pair| pair["baseAsset"] == symbol && pair["status"] == "TRADING")
+        .filter_map(|pair| pair["quoteAsset"].as_str().map(String::from))
+        .collect())
+}
+
+fn bitstamp(content: &str, symbol: &str) -> Result<Vec<String>, Error> {
+    let answer: Vec<Value> = serde_json::from_str(content)?;
+    Ok(answer
+        .iter()
+        .filter(|pair| pair["trading"] == "Enabled")
+        .filter_map(|pair| {
+            let (base, quote) = pair["name"].as_str()?.split_once('/')?;
+            Some(quote.to_string()).filter(|_| base == symbol)
+        })
+        .collect())
+}
+
+fn coinbase(content: &str, symbol: &str) -> Result<Vec<String>, Error> {
+    let answer: Vec<Value> = serde_json::from_str(content)?;
+    Ok(answer
+        .iter()
+        .filter(|pair| pair["base_currency"] == symbol && pair["status"] == "online")
+        .filter_map(|pair| pair["quote_currency"].as_str().map(String::from))
+        .collect())
+}
+
+fn kraken(content: &str, symbol: &str) -> Result<Vec<String>, Error> {
+    let answer: Value = serde_json::from_str(content)?;
+    if let Some(error) = answer["error"].as_array().and_then(|errors| errors.first()) {
+        return Err(Error::Network(format!("kraken answered {}", error)));
+    }
+    let pairs = answer["result"]
+        .as_object()
+        .ok_or_else(|| Error::Parse("kraken sent no pairs".to_string()))?;
+    // Kraken still calls bitcoin XBT
+    let renamed = |name: &str| {
+        if name == "XBT" {
+            "BTC".to_string()
+        } else {
+            name.to_string()
+        }
+    };
+    Ok(pairs
+        .values()
+        .filter_map(|pair| {
+            let (base, quote) = pair["wsname"].as_str()?.split_once('/')?;
+            Some(renamed(quote)).filter(|_| renamed(base) == symbol)
+        })
+        .collect())
+}
+
+/// Where each exchange lists its pairs, by the names `spread` takes.
+const LISTS: &[(&str, &str, Lister)] = &[
+    (
+        "binance",
+        "https://api.binance.com/api/v3/exchangeInfo",
+        binance,
+    ),
+    (
+        "bitstamp",
+        "https://www.bitstamp.net/api/v2/trading-pairs-info/",
+        bitstamp,
+    ),
+    (
+        "coinbase",
+        "https://api.exchange.coinbase.com/products",
+        coinbase,
+    ),
+    (
+        "kraken",
+        "https://api.kraken.com/0/public/AssetPairs",
+        kraken,
+    ),
+];
+
+/// What `exchange` trades `symbol` against, sorted, like `["EUR", "USD"]`.
+fn quotes(exchange: &str, symbol: &str, net: &Net) -> Result<Vec<String>, Error> {
+    let &(_, url, lister) = LISTS
+        .iter()
+        .find(|&&(name, _, _)| name == exchange)
+        .ok_or_else(|| Error::Parse(format!("no pairs are known for {}", exchange)))?;
+    let mut quotes = lister(&get(url, net)?, symbol)?;
+    quotes.sort();
+    quotes.dedup();
+    Ok(quotes)
+}
+
+/// Runs `pairs`, printing the pairs an exchange has for a coin, like
+/// `BTC/USD`, one a line.
+pub fn pairs(
+    matches: &ArgMatches,
+    symbols: &Symbols,
+    overrides: &HashMap<String, String>,
+    net: &Net,
+) -> Result<(), Error> {
+    let exchange = matches.value_of("EXCHANGE").unwrap();
+    let arg = matches.value_of("TICKER").unwrap();
+    let id = symbols.resolve(arg, overrides);
+    let symbol = symbols.symbol_of(id).unwrap_or(arg).to_uppercase();
+    let quotes = quotes(exchange, &symbol, net)?;
+    if quotes.is_empty() {
+        return Err(Error::Other(format!(
+            "{} has no pairs for {}",
+            exchange, symbol
+        )));
+    }
+    for quote in quotes {
+        println!("{}/{}", symbol, quote);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spread::EXCHANGES;
+
+    #[test]
+    fn lists_pairs() {
+        let content = r#"{"error": [], "result": {
+            "XXBTZUSD": {"altname": "XBTUSD", "wsname": "XBT/USD"},
+            "XXBTZEUR": {"altname": "XBTEUR", "wsname": "XBT/EUR"},
+            "XETHXXBT": {"altname": "ETHXBT", "wsname": "ETH/XBT"}}}"#;
+        let mut quotes = kraken(content, "BTC").unwrap();
+        quotes.sort();
+        assert_eq!(quotes, ["EUR", "USD"]);
+        assert_eq!(kraken(content, "ETH").unwrap(), ["BTC"]);
+        assert!(kraken(r#"{"error": ["EGeneral:Too many requests"]}"#, "BTC").is_err());
+        let content = r#"[
+            {"name": "BTC/USD", "trading": "Enabled"},
+            {"name": "BTC/GBP", "trading": "Disabled"},
+            {"name": "ETH/BTC", "trading": "Enabled"}]"#;
+        assert_eq!(bitstamp(content, "BTC").unwrap(), ["USD"]);
+        let content = r#"{"symbols": [
+            {"baseAsset": "BTC", "quoteAsset": "USDT", "status": "TRADING"},
+            {"baseAsset": "BTC", "quoteAsset": "BUSD", "status": "BREAK"}]}"#;
+        assert_eq!(binance(content, "BTC").unwrap(), ["USDT"]);
+        let content = r#"[{"base_currency": "BTC", "quote_currency": "USD", "status": "online"}]"#;
+        assert_eq!(coinbase(content, "BTC").unwrap(), ["USD"]);
+        // every exchange prices come from can list its pairs
+        for &(exchange, _) in EXCHANGES {
+            assert!(LISTS.iter().any(|&(name, _, _)| name == exchange));
+        }
+    }
+}
@@ -0,0 +1,473 @@
+use std::collections::{BTreeMap, HashMap};
+
+use clap::ArgMatches;
+use rusqlite::Connection;
+use rust_decimal::prelude::FromPrimitive;
+use rust_decimal::Decimal;
+
+use errors::Error;
+use fx::Rates;
+use history::{self, migrate, open_database, History};
+use net::Net;
+use returns::{self, Prices};
+use symbols::{self, Symbols};
+use template::rounded;
+use ticker::{parse_price, refresh_ticker};
+
+/// Trades made with `paper buy` and `paper sell`, kept in their own
+/// database next to the history. Nothing about them touches real funds.
+pub struct Ledger {
+    conn: Connection,
+}
+
+/// One trade: a positive `amount` was bought, a negative one sold.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trade {
+    pub coin: String,
+    pub amount: Decimal,
+    pub price_usd: Decimal,
+    pub timestamp: i64,
+}
+
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE IF NOT EXISTS trades (
+         coin TEXT NOT NULL,
+         amount REAL NOT NULL,
+         price_usd REAL NOT NULL,
+         timestamp INTEGER NOT NULL
+     );",
+    // decimals are kept as text, so no trade is rounded to a float
+    "CREATE TABLE exact_trades (
+         coin TEXT NOT NULL,
+         amount TEXT NOT NULL,
+         price_usd TEXT NOT NULL,
+         timestamp INTEGER NOT NULL
+     );
+     INSERT INTO exact_trades
+         SELECT coin, CAST(amount AS TEXT), CAST(price_usd AS TEXT), timestamp
+         FROM trades ORDER BY timestamp, rowid;
+     DROP TABLE trades;
+     ALTER TABLE exact_trades RENAME TO trades;",
+];
+
+fn decimal(column: &str, text: &str) -> Result<Decimal, Error> {
+    parse_price(text).ok_or_else(|| {
+        Error::Parse(format!(
+            "the paper ledger has {:?} for a trade's {}",
+            text, column
+        ))
+    })
+}
+
+impl Ledger {
+    pub fn open() -> Result<Ledger, Error> {
+        Ledger::with_connection(open_database("paper.sqlite")?)
+    }
+
+    fn with_connection(mut conn: Connection) -> Result<Ledger, Error> {
+        migrate(&mut conn, MIGRATIONS)?;
+        Ok(Ledger { conn })
+    }
+
+    pub fn record(&self, trade: &Trade) -> Result<(), Error> {
+        self.conn.execute(
+            "INSERT INTO trades (coin, amount, price_usd, timestamp) VALUES (?1, ?2, ?3, ?4)",
+            (
+                &trade.coin,
+                trade.amount.to_string(),
+                trade.price_usd.to_string(),
+                trade.timestamp,
+            ),
+        )?;
+        Ok(())
+    }
+
+    /// Every trade, oldest first.
+    pub fn trades(&self) -> Result<Vec<Trade>, Error> {
+        let mut statement = self.conn.prepare(
+            "SELECT coin, amount, price_usd, timestamp FROM trades ORDER BY timestamp, rowid",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, i64>(3)?,
+            ))
+        })?;
+        let mut trades = Vec::new();
+        for row in rows {
+            let (coin, amount, price_usd, timestamp) = row?;
+            trades.push(Trade {
+                coin,
+                amount: decimal("amount", &amount)?,
+                price_usd: decimal("price", &price_usd)?,
+                timestamp,
+            });
+        }
+        Ok(trades)
+    }
+}
+
+/// What is held of one coin after replaying its trades. Sales are booked
+/// against the average cost of what was held.
+#[derive(Debug, Default, PartialEq)]
+struct Position {
+    amount: Decimal,
+    /// what the coins still held cost in all
+    cost: Decimal,
+    realized: Decimal,
+}
+
+impl Position {
+    /// What each coin still held cost on average.
+    fn average(&self) -> Decimal {
+        self.cost.checked_div(self.amount).unwrap_or_default()
+    }
+}
+
+fn positions(trades: &[Trade]) -> BTreeMap<&str, Position> {
+    let mut positions: BTreeMap<&str, Position> = BTreeMap::new();
+    for trade in trades {
+        let position = positions.entry(&trade.coin).or_default();
+        if !trade.amount.is_sign_negative() {
+            position.amount += trade.amount;
+            position.cost += trade.amount * trade.price_usd;
+        } else {
+            let sold = -trade.amount;
+            let average = position.average();
+            position.realized += sold * (trade.price_usd - average);
+            position.cost -= sold * average;
+            position.amount -= sold;
+        }
+    }
+    positions
+}
+
+fn price(coin: &str, net: &Net) -> Result<Decimal, Error> {
+    let ticker = refresh_ticker(coin, None, net)?;
+    ticker
+        .price_usd
+        .ok_or_else(|| Error::Other(format!("{} has no price", coin)))
+}
+
+/// Buys or sells at the live price, refusing to sell more than is held.
+fn trade(
+    matches: &ArgMatches,
+    sign: Decimal,
+    ledger: &Ledger,
+    symbols: &Symbols,
+    overrides: &HashMap<String, String>,
+    net: &Net,
+) -> Result<(), Error> {
+    let amount = value_t!(matches, "AMOUNT", Decimal).unwrap_or_else(|e| e.exit());
+    if amount <= Decimal::ZERO {
+        return Err(Error::Other("AMOUNT has to be more than 0".to_string()));
+    }
+    let arg = matches.value_of("TICKER").unwrap();
+    let coin = symbols::resolve_all(symbols, &[arg], overrides, false).remove(0);
+    if sign.is_sign_negative() {
+        let trades = ledger.trades()?;
+        let held = positions(&trades)
+            .get(coin.as_str())
+            .map_or(Decimal::ZERO, |position| position.amount);
+        if amount > held {
+            return Err(Error::Other(format!("only {} {} is held", held, coin)));
+        }
+    }
+    let price_usd = price(&coin, net)?;
+    ledger.record(&Trade {
+        coin: coin.clone(),
+        amount: sign * amount,
+        price_usd,
+        timestamp: history::now(),
+    })?;
+    println!(
+        "{} {} {} at {:.2} for {:.2} USD",
+        if sign.is_sign_negative() {
+            "sold"
+        } else {
+            "bought"
+        },
+        amount,
+        coin,
+        rounded(price_usd, 2),
+        rounded(amount * price_usd, 2)
+    );
+    Ok(())
+}
+
+fn report(
+    positions: &BTreeMap<&str, Position>,
+    prices: &BTreeMap<&str, Option<Decimal>>,
+    currency: &str,
+) -> String {
+    let mut out = format!(
+        "{:<14}  {:>12}  {:>12}  {:>12}  {:>14}  {:>12}  {:>12}\n",
+        "COIN", "AMOUNT", "AVG COST", "PRICE", "VALUE", "UNREALIZED", "REALIZED"
+    );
+    let (mut value, mut unrealized, mut realized) = (Decimal::ZERO, Decimal::ZERO, Decimal::ZERO);
+    for (coin, position) in positions {
+        let price = prices.get(coin).cloned().unwrap_or(None);
+        // a closed position is worth nothing whatever the price
+        let worth = match price {
+            _ if position.amount <= Decimal::ZERO => Some(Decimal::ZERO),
+            price => price.map(|price| price * position.amount),
+        };
+        let gain = worth.map(|worth| worth - position.cost);
+        out.push_str(&format!(
+            "{:<14}  {:>12}  {:>12.2}  {:>12}  {:>14}  {:>12}  {:>12.2}\n",
+            coin,
+            position.amount,
+            rounded(position.average(), 2),
+            price.map_or("?".to_string(), |p| format!("{:.2}", rounded(p, 2))),
+            worth.map_or("?".to_string(), |w| format!("{:.2}", rounded(w, 2))),
+            gain.map_or("?".to_string(), |g| format!("{:+.2}", rounded(g, 2))),
+            rounded(position.realized, 2)
+        ));
+        value += worth.unwrap_or_default();
+        unrealized += gain.unwrap_or_default();
+        realized += position.realized;
+    }
+    out.push_str(&format!(
+        "\nvalue {:.2} {}  unrealized {:+.2}  realized {:+.2}  total {:+.2}\n",
+        rounded(value, 2),
+        currency,
+        rounded(unrealized, 2),
+        rounded(realized, 2),
+        rounded(unrealized + realized, 2)
+    ));
+    out
+}
+
+/// `trades` priced in `currency`, each at the ECB reference rate of the
+/// day it was made, so sales are booked the way a tax return wants them.
+fn in_currency(trades: Vec<Trade>, currency: &str, rates: &Rates) -> Result<Vec<Trade>, Error> {
+    trades
+        .into_iter()
+        .map(|trade| {
+            let rate = rates.usd_to(currency, trade.timestamp)?;
+            Ok(Trade {
+                price_usd: trade.price_usd * rate,
+                ..trade
+            })
+        })
+        .collect()
+}
+
+/// How `trades` did, valuing what was held between them at the prices the
+/// history has, converted with `rates` when they are in another currency.
+fn performance(
+    trades: &[Trade],
+    positions: &BTreeMap<&str, Position>,
+    live: &BTreeMap<&str, Option<Decimal>>,
+    rates: Option<(&Rates, &str)>,
+) -> Result<String, Error> {
+    let mut prices = Prices::of(trades);
+    let since = trades.first().map_or(0, |trade| trade.timestamp);
+    match History::open() {
+        Ok(history) => {
+            for &coin in positions.keys() {
+                for sample in history.samples(coin, since)? {
+                    let price = sample.price_usd.and_then(Decimal::from_f64);
+                    let rate = match rates {
+                        Some((rates, currency)) => rates.usd_to(currency, sample.timestamp).ok(),
+                        None => Some(Decimal::ONE),
+                    };
+                    if let (Some(price), Some(rate)) = (price, rate) {
+                        prices.sample(coin, sample.timestamp, price * rate);
+                    }
+                }
+            }
+        }
+        // the trades' own prices will do
+        Err(err) => debug!("no history to value the holdings with: {}", err),
+    }
+    let worth: Option<Decimal> = positions
+        .iter()
+        .filter(|&(_, position)| position.amount > Decimal::ZERO)
+        .map(|(coin, position)| {
+            let price = live.get(coin).cloned().unwrap_or(None);
+            price.map(|price| price * position.amount)
+        })
+        .sum();
+    let returns = worth.and_then(|worth| returns::returns(trades, &prices, worth, history::now()));
+    match returns {
+        Some(returns) => Ok(returns::report(&returns)),
+        None => Err(Error::Other(
+            "the returns need a price for everything held".to_string(),
+        )),
+    }
+}
+
+/// Runs `paper buy`, `paper sell` and `paper show`.
+pub fn paper(
+    matches: &ArgMatches,
+    symbols: &Symbols,
+    overrides: &HashMap<String, String>,
+    offline: bool,
+    net: &Net,
+) -> Result<(), Error> {
+    let ledger = Ledger::open()?;
+    match matches.subcommand() {
+        ("buy", Some(matches)) => trade(matches, Decimal::ONE, &ledger, symbols, overrides, net),
+        ("sell", Some(matches)) => trade(
+            matches,
+            Decimal::NEGATIVE_ONE,
+            &ledger,
+            symbols,
+            overrides,
+            net,
+        ),
+        (_, show) => {
+            let currency = show
+                .and_then(|matches| matches.value_of("currency"))
+                .unwrap_or("USD")
+                .to_uppercase();
+            let (trades, rates) = if currency == "USD" {
+                (ledger.trades()?, None)
+            } else {
+                let rates = Rates::load(offline, net)?;
+                (
+                    in_currency(ledger.trades()?, &currency, &rates)?,
+                    Some(rates),
+                )
+            };
+            let rate = match rates {
+                Some(ref rates) => rates.usd_to(&currency, history::now())?,
+                None => Decimal::ONE,
+            };
+            let positions = positions(&trades);
+            if positions.is_empty() {
+                println!("no paper trades yet");
+                return Ok(());
+            }
+            let prices = positions
+                .iter()
+                .filter(|&(_, position)| position.amount > Decimal::ZERO)
+                .map(|(&coin, _)| {
+                    let price = price(coin, net).map_err(|err| {
+                        debug!("{}", err);
+                    });
+                    (coin, price.ok().map(|price| price * rate))
+                })
+                .collect();
+            print!("{}", report(&positions, &prices, &currency));
+            if show.is_some_and(|matches| matches.is_present("twr")) {
+                let rates = rates.as_ref().map(|rates| (rates, currency.as_str()));
+                print!("{}", performance(&trades, &positions, &prices, rates)?);
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    fn number(text: &str) -> Decimal {
+        text.parse().unwrap()
+    }
+
+    fn trade(coin: &str, amount: &str, price_usd: &str) -> Trade {
+        Trade {
+            coin: coin.to_string(),
+            amount: number(amount),
+            price_usd: number(price_usd),
+            timestamp: 0,
+        }
+    }
+
+    fn trade_at(coin: &str, amount: &str, price_usd: &str, timestamp: i64) -> Trade {
+        Trade {
+            timestamp,
+            ..trade(coin, amount, price_usd)
+        }
+    }
+
+    #[test]
+    fn books_sales_against_average_cost() {
+        let trades = vec![
+            trade("bitcoin", "1", "100"),
+            trade("bitcoin", "1", "200"),
+            trade("bitcoin", "-1.5", "300"),
+            trade("ethereum", "2", "10"),
+        ];
+        let positions = positions(&trades);
+        assert_eq!(
+            positions["bitcoin"],
+            Position {
+                amount: number("0.5"),
+                cost: number("75"),
+                realized: number("225"),
+            }
+        );
+        assert_eq!(positions["ethereum"].cost, number("20"));
+    }
+
+    #[test]
+    fn sums_without_float_error() {
+        // ten buys of 0.1 at 0.1 are 1 coin for 0.1, which floats miss
+        let mut trades = vec![trade("bitcoin", "0.1", "0.1"); 10];
+        trades.push(trade("bitcoin", "-1", "0.3"));
+        let positions = positions(&trades);
+        assert_eq!(positions["bitcoin"].amount, Decimal::ZERO);
+        assert_eq!(positions["bitcoin"].cost, Decimal::ZERO);
+        assert_eq!(positions["bitcoin"].realized, number("0.2"));
+    }
+
+    #[test]
+    fn reports_profit_and_loss() {
+        let trades = vec![trade("bitcoin", "2", "100"), trade("bitcoin", "-1", "150")];
+        let positions = positions(&trades);
+        let prices = vec![("bitcoin", Some(number("120")))].into_iter().collect();
+        let report = report(&positions, &prices, "USD");
+        assert!(report.contains("unrealized +20.00  realized +50.00  total +70.00"));
+    }
+
+    #[test]
+    fn books_each_trade_at_its_own_rate() {
+        let rates: Rates = serde_json::from_str(
+            r#"{"fetched": 0, "days": {
+                "1970-01-01": {"USD": "1.0"},
+                "1970-01-02": {"USD": "2.0"}
+            }}"#,
+        )
+        .unwrap();
+        let mut sale = trade("bitcoin", "-1", "150");
+        sale.timestamp = 86_400;
+        let trades = vec![trade("bitcoin", "1", "100"), sale];
+        let trades = in_currency(trades, "EUR", &rates).unwrap();
+        // bought for 100 EUR, sold for 75 when the dollar was worth half
+        assert_eq!(positions(&trades)["bitcoin"].realized, number("-25"));
+    }
+
+    #[test]
+    fn keeps_trades_in_order() {
+        let ledger = Ledger::with_connection(Connection::open_in_memory().unwrap()).unwrap();
+        ledger.record(&trade("bitcoin", "1", "100")).unwrap();
+        ledger
+            .record(&trade("bitcoin", "-1", "90.123456789012345678"))
+            .unwrap();
+        let trades = ledger.trades().unwrap();
+        assert_eq!(trades.len(), 2);
+        assert_eq!(trades[1].amount, number("-1"));
+        assert_eq!(trades[1].price_usd, number("90.123456789012345678"));
+    }
+
+    #[test]
+    fn moves_old_ledgers_to_decimals() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn, &MIGRATIONS[..1]).unwrap();
+        conn.execute_batch(
+            "INSERT INTO trades VALUES ('bitcoin', 0.1, 2545.19, 1);
+             INSERT INTO trades VALUES ('bitcoin', -0.05, 2600.0, 2);",
+        )
+        .unwrap();
+        let trades = Ledger::with_connection(conn).unwrap().trades().unwrap();
+        assert_eq!(trades[0], trade_at("bitcoin", "0.1", "2545.19", 1));
+        assert_eq!(trades[1].amount, number("-0.05"));
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use rust_decimal::Decimal;
+
+use output::{DOWN, UP};
+use template::rounded;
+use ticker::Currency;
+
+/// How far off its peg a stablecoin may trade before it counts as
+/// broken, in basis points, when `--peg-threshold` isn't given.
+pub const DEFAULT_THRESHOLD: u32 = 50;
+
+/// A stablecoin and the dollar price it should hold, written
+/// `COIN=PRICE` like `tether=1.00`. A bare `COIN` is pegged to $1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Peg {
+    pub coin: String,
+    pub target: Decimal,
+}
+
+impl FromStr for Peg {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Peg, String> {
+        let mut parts = s.trim().splitn(2, '=');
+        let coin = parts.next().unwrap_or("").trim();
+        if coin.is_empty() {
+            return Err(format!("{:?} should be COIN=PRICE, like tether=1.00", s));
+        }
+        let target = match parts.next() {
+            Some(price) => match price.trim().trim_start_matches('$').parse::<Decimal>() {
+                Ok(target) if target > Decimal::ZERO => target,
+                _ => return Err(format!("{:?} isn't a price to peg {} to", price, coin)),
+            },
+            None => Decimal::ONE,
+        };
+        Ok(Peg {
+            coin: coin.to_string(),
+            target,
+        })
+    }
+}
+
+impl Peg {
+    /// How far `price` is off the peg in basis points, to the nearest
+    /// one, negative below it.
+    pub fn deviation(&self, price: Decimal) -> Decimal {
+        rounded(
+            (price - self.target) / self.target * Decimal::from(10_000),
+            0,
+        )
+    }
+}
+
+/// A pegged coin's quote, held up against its peg.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reading {
+    /// basis points off the peg
+    pub bps: Decimal,
+    /// whether that's past the threshold
+    pub broken: bool,
+    /// what to tell the user, when the peg just broke or came back
+    pub alert: Option<String>,
+}
+
+impl Reading {
+    /// Like `usdt:-12bps`.
+    pub fn segment(&self, label: &str) -> String {
+        let sign = if self.bps > Decimal::ZERO { "+" } else { "" };
+        format!("{}:{}{}bps", label, sign, self.bps)
+    }
+
+    /// `DOWN` off the peg and `UP` on it, since a stablecoin's 24h
+    /// change says nothing.
+    pub fn color(&self) -> &'static str {
+        if self.broken {
+            DOWN
+        } else {
+            UP
+        }
+    }
+}
+
+/// The `--peg` coins, and which of them are off their peg, so each
+/// break and recovery is alerted on once.
+pub struct Watch {
+    pegs: Vec<Peg>,
+    threshold: Decimal,
+    broken: HashSet<String>,
+}
+
+impl Watch {
+    /// `pegs` should name coins by ID, as they're looked up by the
+    /// quote's.
+    pub fn new(pegs: Vec<Peg>, threshold: u32) -> Watch {
+        Watch {
+            pegs,
+            threshold: Decimal::from(threshold),
+            broken: HashSet::new(),
+        }
+    }
+
+    /// How `ticker` is holding its peg, if it has one and a price.
+    pub fn read(&mut self, ticker: &Currency) -> Option<Reading> {
+        let peg = self.pegs.iter().find(|peg| peg.coin == ticker.id)?;
+        let price = ticker.price_usd?;
+        let bps = peg.deviation(price);
+        let broken = bps.abs() > self.threshold;
+        let alert = match (broken, self.broken.contains(&ticker.id)) {
+            (true, false) => {
+                self.broken.insert(ticker.id.clone());
+                Some(format!(
+                    "{} is {}bps off its ${} peg, at ${}",
+                    ticker.id,
+                    bps.abs(),
+                    peg.target,
+                    price
+                ))
+            }
+            (false, true) => {
+                self.broken.remove(&ticker.id);
+                Some(format!(
+                    "{} is back within {}bps of its ${} peg, at ${}",
+                    ticker.id, self.threshold, peg.target, price
+                ))
+            }
+            _ => None,
+        };
+        Some(Reading { bps, broken, alert })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    fn quote(price: &str) -> Currency {
+        serde_json::from_str(&format!(
+            r#"{{"id": "tether", "name": "Tether", "symbol": "USDT", "rank": "3",
+                "price_usd": "{}"}}"#,
+            price
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn reads_pegs() {
+        assert_eq!(
+            "tether=1.00".parse(),
+            Ok(Peg {
+                coin: "tether".to_string(),
+                target: Decimal::ONE,
+            })
+        );
+        let peg: Peg = "eurc=$1.08".parse().unwrap();
+        assert_eq!(peg.target, Decimal::new(108, 2));
+        assert_eq!("dai".parse::<Peg>().unwrap().target, Decimal::ONE);
+        assert!("tether=par".parse::<Peg>().is_err());
+        assert!("tether=0".parse::<Peg>().is_err());
+        assert!("=1".parse::<Peg>().is_err());
+    }
+
+    #[test]
+    fn measures_in_basis_points() {
+        let peg: Peg = "tether=1.00".parse().unwrap();
+        assert_eq!(peg.deviation(Decimal::new(9988, 4)), Decimal::from(-12));
+        assert_eq!(peg.deviation(Decimal::new(100005, 5)), Decimal::from(1));
+        let mut watch = Watch::new(vec![peg], 50);
+        let reading = watch.read(&quote("1.0003")).unwrap();
+        assert_eq!(reading.segment("usdt"), "usdt:+3bps");
+        assert_eq!(reading.color(), UP);
+        assert_eq!(
+            watch.read(&quote("1")).unwrap().segment("usdt"),
+            "usdt:0bps"
+        );
+    }
+
+    #[test]
+    fn alerts_once_per_break() {
+        let mut watch = Watch::new(vec!["tether".parse().unwrap()], 50);
+        assert_eq!(watch.read(&quote("0.998")).unwrap().alert, None);
+        let reading = watch.read(&quote("0.9938")).unwrap();
+        assert!(reading.broken);
+        assert_eq!(reading.color(), DOWN);
+        assert_eq!(
+            reading.alert.as_deref(),
+            Some("tether is 62bps off its $1 peg, at $0.9938")
+        );
+        assert_eq!(watch.read(&quote("0.97")).unwrap().alert, None);
+        assert_eq!(
+            watch.read(&quote("0.9999")).unwrap().alert.as_deref(),
+            Some("tether is back within 50bps of its $1 peg, at $0.9999")
+        );
+        let mut other = quote("0.5");
+        other.id = "bitcoin".to_string();
+        assert_eq!(watch.read(&other), None);
+    }
+}
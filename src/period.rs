@@ -0,0 +1,57 @@
+use std::str::FromStr;
+
+use rust_decimal::Decimal;
+
+use ticker::Currency;
+
+/// Which of the provider's changes `{change}` and the bars' up and down
+/// colors follow, picked with `--change-period`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Period {
+    Hour,
+    #[default]
+    Day,
+    Week,
+}
+
+/// The periods `--change-period` accepts.
+pub const CHANGE_PERIODS: &[&str] = &["1h", "24h", "7d"];
+
+impl FromStr for Period {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Period, String> {
+        match s.trim().to_lowercase().as_str() {
+            "1h" => Ok(Period::Hour),
+            "24h" => Ok(Period::Day),
+            "7d" => Ok(Period::Week),
+            _ => Err(format!(
+                "{:?} should be one of {}",
+                s,
+                CHANGE_PERIODS.join(", ")
+            )),
+        }
+    }
+}
+
+impl Period {
+    /// The template field with the change over this period, like
+    /// `change_24h`.
+    pub fn field(self) -> &'static str {
+        match self {
+            Period::Hour => "change_1h",
+            Period::Day => "change_24h",
+            Period::Week => "change_7d",
+        }
+    }
+
+    /// `ticker`'s change in percent over this period, if the provider
+    /// sent it.
+    pub fn change(self, ticker: &Currency) -> Option<Decimal> {
+        match self {
+            Period::Hour => ticker.percent_change_1,
+            Period::Day => ticker.percent_change_24,
+            Period::Week => ticker.percent_change_7,
+        }
+    }
+}
@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+
+use clap::ArgMatches;
+use serde_json;
+
+use errors::Error;
+use net::Net;
+use symbols::Symbols;
+
+/// Perpetual funding is settled every 8 hours on the venues asked.
+const FUNDINGS_PER_YEAR: f64 = 3.0 * 365.0;
+
+/// How many lending markets are shown, biggest first.
+const MARKETS: usize = 5;
+
+/// One row of the table: a perpetual's funding or a lending market's
+/// rates, all as yearly percentages so they line up.
+#[derive(Debug, Clone, PartialEq)]
+struct Rate {
+    source: String,
+    market: String,
+    funding: Option<f64>,
+    supply: Option<f64>,
+    borrow: Option<f64>,
+    tvl: Option<f64>,
+}
+
+impl Rate {
+    fn funding(source: &str, market: &str, per_funding: f64) -> Rate {
+        Rate {
+            source: source.to_string(),
+            market: market.to_string(),
+            funding: Some(per_funding * 100.0 * FUNDINGS_PER_YEAR),
+            supply: None,
+            borrow: None,
+            tvl: None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BinancePremium {
+    symbol: String,
+    last_funding_rate: String,
+}
+
+#[derive(Deserialize)]
+struct BybitTickers {
+    result: BybitList,
+}
+
+#[derive(Deserialize)]
+struct BybitList {
+    list: Vec<BybitTicker>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BybitTicker {
+    symbol: String,
+    funding_rate: String,
+}
+
+#[derive(Deserialize)]
+struct LlamaPools {
+    data: Vec<LlamaPool>,
+}
+
+/// A DeFi Llama yield pool. `apyBase` is what suppliers earn before
+/// token rewards.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LlamaPool {
+    pool: String,
+    chain: String,
+    project: String,
+    symbol: String,
+    tvl_usd: Option<f64>,
+    apy_base: Option<f64>,
+}
+
+/// The borrow side of a DeFi Llama lending pool.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LlamaBorrow {
+    pool: String,
+    apy_base_borrow: Option<f64>,
+}
+
+fn get(url: &str, net: &Net) -> Result<String, Error> {
+    debug!("retrieving {}", url);
+    let resp = net.get(url)?;
+    if !resp.status.is_success() {
+        return Err(Error::Network(format!("{} answered {}", url, resp.status)));
+    }
+    Ok(resp.text())
+}
+
+fn parse_rate(rate: &str) -> Result<f64, Error> {
+    rate.parse()
+        .map_err(|_| Error::Parse(format!("funding rate {:?} is not a number", rate)))
+}
+
+fn binance(symbol: &str, net: &Net) -> Result<Vec<Rate>, Error> {
+    let url = format!(
+        "https://fapi.binance.com/fapi/v1/premiumIndex?symbol={}USDT",
+        symbol
+    );
+    let premium: BinancePremium = serde_json::from_str(&get(&url, net)?)?;
+    let rate = parse_rate(&premium.last_funding_rate)?;
+    Ok(vec![Rate::funding("binance", &premium.symbol, rate)])
+}
+
+fn bybit(symbol: &str, net: &Net) -> Result<Vec<Rate>, Error> {
+    let url = format!(
+        "https://api.bybit.com/v5/market/tickers?category=linear&symbol={}USDT",
+        symbol
+    );
+    let tickers: BybitTickers = serde_json::from_str(&get(&url, net)?)?;
+    tickers
+        .result
+        .list
+        .iter()
+        .map(|ticker| {
+            let rate = parse_rate(&ticker.funding_rate)?;
+            Ok(Rate::funding("bybit", &ticker.symbol, rate))
+        })
+        .collect()
+}
+
+/// The biggest lending markets for `symbol` or its wrapped version, like
+/// WBTC for BTC.
+fn lending(pools: &[LlamaPool], borrows: &[LlamaBorrow], symbol: &str) -> Vec<Rate> {
+    let borrows: HashMap<&str, Option<f64>> = borrows
+        .iter()
+        .map(|borrow| (borrow.pool.as_str(), borrow.apy_base_borrow))
+        .collect();
+    let wrapped = format!("W{}", symbol);
+    let mut markets: Vec<&LlamaPool> = pools
+        .iter()
+        .filter(|pool| borrows.contains_key(pool.pool.as_str()))
+        .filter(|pool| {
+            let asset = pool.symbol.to_uppercase();
+            asset == symbol || asset == wrapped
+        })
+        .collect();
+    markets.sort_by(|a, b| {
+        b.tvl_usd
+            .unwrap_or(0.0)
+            .partial_cmp(&a.tvl_usd.unwrap_or(0.0))
+            .unwrap()
+    });
+    markets
+        .into_iter()
+        .take(MARKETS)
+        .map(|pool| Rate {
+            source: pool.project.clone(),
+            market: format!("{} on {}", pool.symbol, pool.chain),
+            funding: None,
+            supply: pool.apy_base,
+            borrow: borrows[pool.pool.as_str()],
+            tvl: pool.tvl_usd,
+        })
+        .collect()
+}
+
+fn defi(symbol: &str, net: &Net) -> Result<Vec<Rate>, Error> {
+    let pools: LlamaPools = serde_json::from_str(&get("https://yields.llama.fi/pools", net)?)?;
+    let borrows: Vec<LlamaBorrow> =
+        serde_json::from_str(&get("https://yields.llama.fi/lendBorrow", net)?)?;
+    Ok(lending(&pools.data, &borrows, symbol))
+}
+
+fn report(rates: &[Rate]) -> String {
+    let percent = |rate: Option<f64>| rate.map_or("-".to_string(), |r| format!("{:.2}%", r));
+    let mut out = format!(
+        "{:<14}  {:<22}  {:>12}  {:>11}  {:>11}  {:>10}\n",
+        "SOURCE", "MARKET", "FUNDING APR", "SUPPLY APY", "BORROW APY", "TVL (M)"
+    );
+    for rate in rates {
+        out.push_str(&format!(
+            "{:<14}  {:<22}  {:>12}  {:>11}  {:>11}  {:>10}\n",
+            rate.source,
+            rate.market,
+            percent(rate.funding),
+            percent(rate.supply),
+            percent(rate.borrow),
+            rate.tvl
+                .map_or("-".to_string(), |tvl| format!("{:.1}", tvl / 1e6))
+        ));
+    }
+    out
+}
+
+/// Runs `rates`, putting perpetual funding on the big exchanges next to
+/// DeFi lending rates for the same coin. A source that fails is left out.
+pub fn rates(
+    matches: &ArgMatches,
+    symbols: &Symbols,
+    overrides: &HashMap<String, String>,
+    net: &Net,
+) -> Result<(), Error> {
+    let arg = matches.value_of("TICKER").unwrap();
+    let id = symbols.resolve(arg, overrides);
+    let symbol = symbols.symbol_of(id).unwrap_or(arg).to_uppercase();
+    type Source = fn(&str, &Net) -> Result<Vec<Rate>, Error>;
+    let sources: [(&str, Source); 3] = [("binance", binance), ("bybit", bybit), ("defi", defi)];
+    let mut rates = Vec::new();
+    for &(name, source) in &sources {
+        match source(&symbol, net) {
+            Ok(found) => rates.extend(found),
+            Err(err) => {
+                debug!("{} rates unavailable: {}", name, err);
+            }
+        }
+    }
+    if rates.is_empty() {
+        return Err(Error::Other(format!("no rates found for {}", symbol)));
+    }
+    print!("{}", report(&rates));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn annualizes_funding() {
+        let content = r#"{"symbol": "BTCUSDT", "markPrice": "50000.0",
+            "lastFundingRate": "0.00010000", "nextFundingTime": 1700000000000}"#;
+        let premium: BinancePremium = serde_json::from_str(content).unwrap();
+        let rate = Rate::funding(
+            "binance",
+            &premium.symbol,
+            parse_rate(&premium.last_funding_rate).unwrap(),
+        );
+        assert!((rate.funding.unwrap() - 10.95).abs() < 1e-9);
+        assert!(parse_rate("soon").is_err());
+    }
+
+    #[test]
+    fn picks_the_biggest_lending_markets() {
+        let pools: LlamaPools = serde_json::from_str(
+            r#"{"status": "success", "data": [
+                {"pool": "a", "chain": "Ethereum", "project": "aave-v3", "symbol": "WBTC",
+                 "tvlUsd": 2000000000.0, "apyBase": 0.02},
+                {"pool": "b", "chain": "Ethereum", "project": "compound-v3", "symbol": "WBTC",
+                 "tvlUsd": 500000000.0, "apyBase": null},
+                {"pool": "c", "chain": "Ethereum", "project": "uniswap-v3", "symbol": "WBTC-WETH",
+                 "tvlUsd": 9000000000.0, "apyBase": 5.0},
+                {"pool": "d", "chain": "Ethereum", "project": "lido", "symbol": "WBTC",
+                 "tvlUsd": 9000000000.0, "apyBase": 3.0}]}"#,
+        )
+        .unwrap();
+        let borrows: Vec<LlamaBorrow> = serde_json::from_str(
+            r#"[{"pool": "a", "apyBaseBorrow": 0.3}, {"pool": "b", "apyBaseBorrow": 1.1},
+                {"pool": "c", "apyBaseBorrow": null}]"#,
+        )
+        .unwrap();
+        let rates = lending(&pools.data, &borrows, "BTC");
+        assert_eq!(rates.len(), 2);
+        assert_eq!(rates[0].source, "aave-v3");
+        assert_eq!(rates[0].market, "WBTC on Ethereum");
+        assert_eq!(rates[0].borrow, Some(0.3));
+        assert_eq!(rates[1].supply, None);
+
+        let report = report(&rates);
+        assert!(report.contains("0.02%"));
+        assert!(report.contains("2000.0"));
+    }
+}
@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+use std::io::{stdout, Write};
+use std::thread::sleep;
+use std::time::Duration;
+
+use clap::ArgMatches;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+
+use cache::Cache;
+use errors::Error;
+use net::Net;
+use symbols::{self, Symbols};
+use template::{rounded, significant_places};
+use ticker::{short_name, usd_price};
+
+/// What the pair is called on the line, like `eth/btc`.
+fn label(symbols: &Symbols, base: &str, quote: &str) -> String {
+    let short = |id: &str| match symbols.symbol_of(id) {
+        Some(symbol) => symbol.to_lowercase(),
+        None => short_name(id),
+    };
+    format!("{}/{}", short(base), short(quote))
+}
+
+/// `ratio` to four significant digits, like `0.05214`.
+fn format_ratio(ratio: Decimal) -> String {
+    let places = significant_places(ratio.to_f64().unwrap_or(0.0), 4);
+    format!("{:.*}", places, rounded(ratio, places as u32))
+}
+
+fn segment(
+    label: &str,
+    base: &str,
+    quote: &str,
+    cache: Option<&Cache>,
+    net: &Net,
+) -> Result<String, Error> {
+    let quoted = usd_price(quote, cache, net)?;
+    let ratio = usd_price(base, cache, net)?
+        .checked_div(quoted)
+        .ok_or_else(|| Error::Other(format!("{} is priced at {} USD", quote, quoted)))?;
+    Ok(format!("{}:{}", label, format_ratio(ratio)))
+}
+
+/// Runs `ratio`, printing what BASE is worth in QUOTE, like
+/// `eth/btc:0.05214`. With `--interval`, the ratio is fetched fresh and
+/// printed over itself every SECS seconds, riding out network errors.
+pub fn ratio(
+    matches: &ArgMatches,
+    cache: Option<&Cache>,
+    symbols: &Symbols,
+    overrides: &HashMap<String, String>,
+    net: &Net,
+) -> Result<(), Error> {
+    let args = [
+        matches.value_of("BASE").unwrap(),
+        matches.value_of("QUOTE").unwrap(),
+    ];
+    let coins = symbols::resolve_all(symbols, &args, overrides, false);
+    let (base, quote) = (&coins[0], &coins[1]);
+    let label = label(symbols, base, quote);
+    if !matches.is_present("interval") {
+        println!("{}", segment(&label, base, quote, cache, net)?);
+        return Ok(());
+    }
+    let interval =
+        Duration::from_secs(value_t!(matches, "interval", u64).unwrap_or_else(|e| e.exit()));
+    loop {
+        let line = match segment(&label, base, quote, None, net) {
+            Ok(line) => line,
+            Err(ref err) if err.is_transient() => {
+                debug!("{}", err);
+                format!("{}:error", label)
+            }
+            Err(err) => return Err(err),
+        };
+        print!("\r{}\x1b[K", line);
+        stdout().flush()?;
+        sleep(interval);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_ratios() {
+        let (btc, eth) = (Decimal::new(254519, 2), Decimal::new(348463, 3));
+        assert_eq!(format_ratio(eth / btc), "0.1369");
+        assert_eq!(format_ratio(btc / eth), "7.304");
+        assert_eq!(format_ratio(Decimal::new(2, 1) / btc), "0.00007858");
+        assert_eq!(label(&Symbols::default(), "ethereum", "bitcoin"), "eth/btc");
+    }
+}
@@ -0,0 +1,164 @@
+use rust_decimal::Decimal;
+use time::{self, Tm};
+
+use template::rounded;
+
+const DAYS: &[&str] = &[
+    "sunday",
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+];
+
+/// A recurring nudge, like a dollar-cost-averaging day, from a
+/// `[[reminder]]` table in the config file.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Reminder {
+    pub message: String,
+    /// `day` for every day, or a weekday like `friday`
+    pub every: String,
+    /// the local time of day it's due, like `09:00`; midnight by default
+    pub at: Option<String>,
+    /// a coin whose price goes in the message
+    pub coin: Option<String>,
+    /// USD to spend, so the message can say how much `coin` it buys
+    pub budget: Option<Decimal>,
+}
+
+impl Reminder {
+    /// The weekday it recurs on, 0 for Sunday, or `None` for every day.
+    fn weekday(&self) -> Result<Option<i32>, String> {
+        let every = self.every.to_lowercase();
+        if every == "day" {
+            return Ok(None);
+        }
+        DAYS.iter()
+            .position(|day| *day == every)
+            .map(|day| Some(day as i32))
+            .ok_or_else(|| {
+                format!(
+                    "reminder every {:?} should be day or a weekday like friday",
+                    self.every
+                )
+            })
+    }
+
+    /// The hour and minute it's due.
+    fn time_of_day(&self) -> Result<(i32, i32), String> {
+        let at = match self.at {
+            Some(ref at) => at,
+            None => return Ok((0, 0)),
+        };
+        time::strptime(at, "%H:%M")
+            .map(|tm| (tm.tm_hour, tm.tm_min))
+            .map_err(|_| format!("reminder at {:?} should look like 09:00", at))
+    }
+
+    /// Which day `now` is, as a number that changes once a day, if the
+    /// reminder is due by then on that day.
+    pub fn due(&self, now: &Tm) -> Option<i32> {
+        let (weekday, (hour, minute)) = match (self.weekday(), self.time_of_day()) {
+            (Ok(weekday), Ok(at)) => (weekday, at),
+            _ => return None,
+        };
+        if weekday.is_some_and(|day| day != now.tm_wday) {
+            return None;
+        }
+        if (now.tm_hour, now.tm_min) < (hour, minute) {
+            return None;
+        }
+        Some(now.tm_year * 1000 + now.tm_yday)
+    }
+
+    /// The message, with the price and what the budget buys when there
+    /// is a price to go on.
+    pub fn text(&self, price: Option<Decimal>) -> String {
+        match (self.coin.as_ref(), price) {
+            (Some(coin), Some(price)) => match self.budget {
+                Some(budget) if !price.is_zero() => format!(
+                    "{}: {} at {:.2}, {} USD buys {:.6}",
+                    self.message,
+                    coin,
+                    rounded(price, 2),
+                    budget,
+                    rounded(budget / price, 6)
+                ),
+                _ => format!("{}: {} at {:.2}", self.message, coin, rounded(price, 2)),
+            },
+            _ => self.message.clone(),
+        }
+    }
+}
+
+/// Checks every reminder's schedule up front, so a typo fails at startup
+/// rather than silently never reminding.
+pub fn validate(reminders: &[Reminder]) -> Result<(), String> {
+    for reminder in reminders {
+        reminder.weekday()?;
+        reminder.time_of_day()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reminder(every: &str, at: Option<&str>) -> Reminder {
+        Reminder {
+            message: "DCA day".to_string(),
+            every: every.to_string(),
+            at: at.map(|at| at.to_string()),
+            coin: None,
+            budget: None,
+        }
+    }
+
+    fn tm(date: &str) -> Tm {
+        // strptime leaves the weekday alone, so go through a timestamp
+        time::at_utc(
+            time::strptime(date, "%Y-%m-%d %H:%M")
+                .unwrap()
+                .to_timespec(),
+        )
+    }
+
+    #[test]
+    fn comes_due_on_its_day() {
+        let friday = reminder("Friday", Some("09:00"));
+        // 2017-11-10 was a Friday
+        assert_eq!(friday.due(&tm("2017-11-10 08:59")), None);
+        let due = friday.due(&tm("2017-11-10 09:00"));
+        assert!(due.is_some());
+        assert_eq!(friday.due(&tm("2017-11-10 23:00")), due);
+        assert_eq!(friday.due(&tm("2017-11-11 09:00")), None);
+
+        let daily = reminder("day", None);
+        assert_ne!(daily.due(&tm("2017-11-11 00:00")), due);
+    }
+
+    #[test]
+    fn prices_the_budget() {
+        let mut dca = reminder("friday", None);
+        let price = Decimal::new(50000, 0);
+        assert_eq!(dca.text(Some(price)), "DCA day");
+        dca.coin = Some("bitcoin".to_string());
+        dca.budget = Some(Decimal::new(100, 0));
+        assert_eq!(
+            dca.text(Some(price)),
+            "DCA day: bitcoin at 50000.00, 100 USD buys 0.002000"
+        );
+        assert_eq!(dca.text(None), "DCA day");
+    }
+
+    #[test]
+    fn rejects_bad_schedules() {
+        assert!(validate(&[reminder("fortnight", None)]).is_err());
+        assert!(validate(&[reminder("monday", Some("9am"))]).is_err());
+        assert!(validate(&[reminder("monday", Some("9:30"))]).is_ok());
+    }
+}
@@ -0,0 +1,234 @@
+use std::collections::{BTreeMap, HashMap};
+
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+
+use history::format_span;
+use paper::Trade;
+
+const YEAR: f64 = 365.25 * 86_400.0;
+
+/// What each coin was worth over time, to value what was held between
+/// trades: the trades' own prices, and the history's samples in between.
+#[derive(Default)]
+pub struct Prices {
+    coins: HashMap<String, BTreeMap<i64, Decimal>>,
+}
+
+impl Prices {
+    pub fn of(trades: &[Trade]) -> Prices {
+        let mut prices = Prices::default();
+        for trade in trades {
+            prices
+                .coins
+                .entry(trade.coin.clone())
+                .or_default()
+                .insert(trade.timestamp, trade.price_usd);
+        }
+        prices
+    }
+
+    /// Adds a sample of `coin`, unless a trade was made at that second,
+    /// whose price is the one that was paid.
+    pub fn sample(&mut self, coin: &str, timestamp: i64, price: Decimal) {
+        self.coins
+            .entry(coin.to_string())
+            .or_default()
+            .entry(timestamp)
+            .or_insert(price);
+    }
+
+    /// The last price of `coin` known at `timestamp`.
+    fn at(&self, coin: &str, timestamp: i64) -> Option<Decimal> {
+        let (_, &price) = self.coins.get(coin)?.range(..=timestamp).next_back()?;
+        Some(price)
+    }
+}
+
+/// How a run of trades did from the first until now, as fractions, so
+/// `0.1` is 10%.
+#[derive(Debug, PartialEq)]
+pub struct Returns {
+    /// the growth of what was held, chained from one trade to the next,
+    /// so money put in or taken out doesn't count as gain or loss
+    pub time_weighted: f64,
+    /// the one rate the money put in would have had to grow at to be worth
+    /// what it is, so it weighs each period by how much was in it
+    pub money_weighted: Option<f64>,
+    /// seconds since the first trade
+    pub span: i64,
+}
+
+fn value(held: &BTreeMap<&str, Decimal>, prices: &Prices, timestamp: i64) -> Option<Decimal> {
+    held.iter()
+        .filter(|&(_, amount)| !amount.is_zero())
+        .map(|(coin, &amount)| prices.at(coin, timestamp).map(|price| price * amount))
+        .sum()
+}
+
+fn growth(to: Decimal, from: Decimal) -> Option<f64> {
+    to.checked_div(from)?.to_f64()
+}
+
+/// The returns of `trades`, oldest first, which are worth `worth` at
+/// `now`. A buy is money put in and a sale money taken out. `None` if
+/// there are no trades, or something held has no price.
+pub fn returns(trades: &[Trade], prices: &Prices, worth: Decimal, now: i64) -> Option<Returns> {
+    let start = trades.first()?.timestamp;
+    let mut held: BTreeMap<&str, Decimal> = BTreeMap::new();
+    // what was held just after the last trades
+    let mut after = Decimal::ZERO;
+    let mut chained = 1.0;
+    let mut flows = Vec::new();
+    let mut rest = trades;
+    while let Some(first) = rest.first() {
+        let timestamp = first.timestamp;
+        let count = rest
+            .iter()
+            .take_while(|trade| trade.timestamp == timestamp)
+            .count();
+        let (now_made, later) = rest.split_at(count);
+        rest = later;
+        // nothing held is no period to measure
+        if after > Decimal::ZERO {
+            chained *= growth(value(&held, prices, timestamp)?, after)?;
+        }
+        let mut flow = Decimal::ZERO;
+        for trade in now_made {
+            *held.entry(&trade.coin).or_default() += trade.amount;
+            flow += trade.amount * trade.price_usd;
+        }
+        flows.push((timestamp, flow.to_f64()?));
+        after = value(&held, prices, timestamp)?;
+    }
+    if after > Decimal::ZERO {
+        chained *= growth(worth, after)?;
+    }
+    let span = now - start;
+    Some(Returns {
+        time_weighted: chained - 1.0,
+        money_weighted: money_weighted(&flows, worth.to_f64()?, start, span),
+        span,
+    })
+}
+
+/// Solves for the growth over `span` that makes `flows`, put in at their
+/// times, worth `worth` at its end, halving a range of growths until it
+/// is found.
+fn money_weighted(flows: &[(i64, f64)], worth: f64, start: i64, span: i64) -> Option<f64> {
+    if span <= 0 {
+        return None;
+    }
+    // what the flows would be worth at a growth of e^ln over the span,
+    // less what they are
+    let gap = |ln: f64| {
+        flows
+            .iter()
+            .map(|&(timestamp, flow)| {
+                let left = 1.0 - (timestamp - start) as f64 / span as f64;
+                flow * (ln * left).exp()
+            })
+            .sum::<f64>()
+            - worth
+    };
+    let (mut low, mut high) = (-20.0, 20.0);
+    if gap(low).signum() == gap(high).signum() {
+        return None;
+    }
+    for _ in 0..200 {
+        let middle = (low + high) / 2.0;
+        if gap(middle).signum() == gap(low).signum() {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    Some(((low + high) / 2.0).exp() - 1.0)
+}
+
+/// `rate` over `span` as a yearly one. Returns over less than a year
+/// aren't annualized, since a good week would come out as thousands of
+/// percent.
+fn annualized(rate: f64, span: i64) -> Option<f64> {
+    Some((1.0 + rate).powf(YEAR / span as f64) - 1.0).filter(|_| span as f64 >= YEAR)
+}
+
+/// `rate` in percent to two places, so a solved rate a hair under zero
+/// shows as `+0.00` and not `-0.00`.
+fn hundredths(rate: f64) -> String {
+    format!("{:+.2}", (rate * 10_000.0).round() / 100.0 + 0.0)
+}
+
+fn percent(rate: Option<f64>, span: i64) -> String {
+    match rate {
+        Some(rate) => match annualized(rate, span) {
+            Some(yearly) => format!("{}% ({}% a year)", hundredths(rate), hundredths(yearly)),
+            None => format!("{}%", hundredths(rate)),
+        },
+        None => "?".to_string(),
+    }
+}
+
+/// Like `time-weighted +12.34%  money-weighted +8.10%  over 143d 2h`.
+pub fn report(returns: &Returns) -> String {
+    format!(
+        "time-weighted {}  money-weighted {}  over {}\n",
+        percent(Some(returns.time_weighted), returns.span),
+        percent(returns.money_weighted, returns.span),
+        format_span(returns.span)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade(coin: &str, amount: i64, price_usd: i64, timestamp: i64) -> Trade {
+        Trade {
+            coin: coin.to_string(),
+            amount: Decimal::from(amount),
+            price_usd: Decimal::from(price_usd),
+            timestamp,
+        }
+    }
+
+    #[test]
+    fn weighs_returns_by_time_and_money() {
+        // bought at 100, doubled, bought again at 200, and halved
+        let trades = vec![trade("bitcoin", 1, 100, 0), trade("bitcoin", 1, 200, 50)];
+        let mut prices = Prices::of(&trades);
+        // a sample can't say otherwise about what was paid
+        prices.sample("bitcoin", 50, Decimal::from(150));
+        let returns = returns(&trades, &prices, Decimal::from(200), 100).unwrap();
+        // the coin is back where it started
+        assert!(returns.time_weighted.abs() < 1e-9);
+        // but most of the money went in at the top: 100x + 200√x = 200
+        let money = returns.money_weighted.unwrap();
+        assert!((money - ((3f64.sqrt() - 1.0).powi(2) - 1.0)).abs() < 1e-9);
+        assert_eq!(
+            report(&returns),
+            "time-weighted +0.00%  money-weighted -46.41%  over 1m\n"
+        );
+    }
+
+    #[test]
+    fn values_holdings_between_trades() {
+        let trades = vec![
+            trade("bitcoin", 1, 100, 0),
+            trade("ethereum", 10, 10, 100),
+            trade("bitcoin", -1, 150, 200),
+        ];
+        let mut prices = Prices::of(&trades);
+        prices.sample("bitcoin", 90, Decimal::from(120));
+        // sold out, and bought back into a loss
+        let returns = returns(&trades, &prices, Decimal::from(50), 300).unwrap();
+        // 100 to 120, 220 to 250 at the sale, 100 to 50
+        let expected = 1.2 * (250.0 / 220.0) * 0.5 - 1.0;
+        assert!((returns.time_weighted - expected).abs() < 1e-9);
+        assert!(Prices::default().at("bitcoin", 0).is_none());
+        assert_eq!(super::returns(&[], &prices, Decimal::ZERO, 0), None);
+        assert_eq!(annualized(0.1, 86_400), None);
+        assert_eq!(hundredths(-1e-12), "+0.00");
+        assert!((annualized(0.21, 2 * YEAR as i64).unwrap() - 0.1).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,72 @@
+use std::time::{Duration, Instant};
+
+use chaos::roll;
+
+/// When interval mode's rounds start: on ticks a fixed period apart,
+/// counted from the first round, so the time a round spends fetching
+/// comes out of the wait instead of pushing every later round back.
+pub struct Schedule {
+    period: Duration,
+    /// the most each tick is put off by, so a crowd of tickers started
+    /// on the minute doesn't hit the API at once
+    jitter: Duration,
+    next: Instant,
+}
+
+impl Schedule {
+    pub fn new(period: Duration, jitter: Duration, start: Instant) -> Schedule {
+        Schedule {
+            period,
+            jitter,
+            next: start + period,
+        }
+    }
+
+    /// How long to wait from `now` until the next round. Ticks a slow
+    /// round ran past are skipped rather than run back to back.
+    pub fn wait(&mut self, now: Instant) -> Duration {
+        self.wait_with(now, roll())
+    }
+
+    /// `wait`, putting the tick off by `roll` percent of the jitter.
+    fn wait_with(&mut self, now: Instant, roll: u32) -> Duration {
+        if self.period.is_zero() {
+            self.next = now;
+        }
+        while !self.period.is_zero() && self.next <= now {
+            self.next += self.period;
+        }
+        self.next.saturating_duration_since(now) + self.jitter * roll / 100
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_to_the_ticks() {
+        let start = Instant::now();
+        let secs = Duration::from_secs;
+        let mut schedule = Schedule::new(secs(60), secs(0), start);
+        // a round that took 5s waits 55s, not 60s
+        assert_eq!(schedule.wait(start + secs(5)), secs(55));
+        assert_eq!(schedule.wait(start + secs(62)), secs(58));
+        // a round that ran 150s late skips the ticks it missed
+        assert_eq!(schedule.wait(start + secs(330)), secs(30));
+        // a wait cut short still ends on the same tick
+        assert_eq!(schedule.wait(start + secs(340)), secs(20));
+    }
+
+    #[test]
+    fn spreads_ticks_by_the_jitter() {
+        let start = Instant::now();
+        let secs = Duration::from_secs;
+        let mut schedule = Schedule::new(secs(60), secs(10), start);
+        assert_eq!(schedule.wait_with(start, 50), secs(65));
+        // the jitter doesn't pile up
+        assert_eq!(schedule.wait_with(start + secs(65), 0), secs(55));
+        let mut schedule = Schedule::new(secs(0), secs(10), start);
+        assert_eq!(schedule.wait_with(start, 20), secs(2));
+    }
+}
@@ -0,0 +1,113 @@
+use serde_json::{Map, Value};
+
+/// What a provider's field is expected to hold.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    /// text, required unless it can be filled in
+    Text,
+    /// text that may be left out
+    OptionalText,
+    /// a number, or a string holding one; anything else is left out
+    Number,
+}
+
+/// Unwraps the `{"data": ...}` envelope newer API versions put around
+/// their answers.
+pub fn unwrap_data(what: &str, value: Value) -> Value {
+    match value {
+        Value::Object(mut object) if object.contains_key("data") => {
+            info!("{}: reading the answer's \"data\"", what);
+            object.remove("data").unwrap_or(Value::Null)
+        }
+        value => value,
+    }
+}
+
+fn describe(value: &Value) -> &'static str {
+    match *value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+/// Bends `object` into the shape `fields` describe, so a provider that
+/// changed its schema still deserializes: numbers and booleans become
+/// text where text is expected, text fields that went missing become
+/// empty, and a field of the wrong kind entirely is left out. Each
+/// change is logged rather than failing the whole answer.
+pub fn tolerate(what: &str, object: &mut Map<String, Value>, fields: &[(&str, Kind)]) {
+    for &(field, kind) in fields {
+        let fixed = match (kind, object.get(field)) {
+            (Kind::Text, None | Some(Value::Null)) => {
+                info!("{}: {} is missing", what, field);
+                Some(Value::String(String::new()))
+            }
+            (_, None | Some(Value::Null)) => None,
+            (Kind::Text | Kind::OptionalText, Some(Value::String(_))) => None,
+            (Kind::Text | Kind::OptionalText, Some(value))
+                if value.is_number() || value.is_boolean() =>
+            {
+                info!("{}: {} is {}, not text", what, field, describe(value));
+                Some(Value::String(value.to_string()))
+            }
+            (Kind::Number, Some(Value::Number(_))) => None,
+            (Kind::Number, Some(Value::String(text))) if text.trim().parse::<f64>().is_ok() => None,
+            (_, Some(value)) => {
+                info!("{}: {} is {}, ignoring it", what, field, describe(value));
+                Some(Value::Null)
+            }
+        };
+        if let Some(fixed) = fixed {
+            object.insert(field.to_string(), fixed);
+        }
+    }
+    for key in object.keys() {
+        if !fields.iter().any(|&(field, _)| field == key) {
+            debug!("{}: ignoring the unknown field {}", what, key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    const FIELDS: &[(&str, Kind)] = &[
+        ("id", Kind::Text),
+        ("rank", Kind::Text),
+        ("updated", Kind::OptionalText),
+        ("price", Kind::Number),
+        ("volume", Kind::Number),
+        ("supply", Kind::Number),
+    ];
+
+    #[test]
+    fn bends_drifted_fields() {
+        let mut value: Value = serde_json::from_str(
+            r#"{"rank": 1, "updated": 1497225555, "price": "2545.19",
+                "volume": "n/a", "supply": {"circulating": 1}, "new": true}"#,
+        )
+        .unwrap();
+        tolerate("bitcoin", value.as_object_mut().unwrap(), FIELDS);
+        assert_eq!(value["id"], "");
+        assert_eq!(value["rank"], "1");
+        assert_eq!(value["updated"], "1497225555");
+        assert_eq!(value["price"], "2545.19");
+        assert_eq!(value["volume"], Value::Null);
+        assert_eq!(value["supply"], Value::Null);
+        assert_eq!(value["new"], true);
+    }
+
+    #[test]
+    fn unwraps_data() {
+        let wrapped: Value = serde_json::from_str(r#"{"data": [1], "status": {}}"#).unwrap();
+        assert_eq!(unwrap_data("top list", wrapped), serde_json::json!([1]));
+        let bare: Value = serde_json::from_str("[1]").unwrap();
+        assert_eq!(unwrap_data("top list", bare.clone()), bare);
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+
+use serde_json::{self, Value};
+
+use cache::Cache;
+use errors::Error;
+use http::{self, Response};
+use net::Net;
+use output::{self, StreamDeckKey};
+use symbols::Symbols;
+use ticker::{load_ticker, Currency};
+
+const JSON: &str = "application/json";
+
+fn json_error(status: u16, err: &Error) -> Response {
+    Response {
+        status,
+        content_type: JSON,
+        body: format!("{{\"error\": {}}}", Value::String(err.to_string())),
+    }
+}
+
+fn json<T: ::serde::Serialize>(value: &T) -> Response {
+    match serde_json::to_string(value) {
+        Ok(body) => Response::ok(JSON, body),
+        Err(err) => json_error(500, &err.into()),
+    }
+}
+
+/// What `--serve` answers `path` with: `/tickers` for every coin given on
+/// the command line, `/tickers/<coin>` for any one coin. `/streamdeck`
+/// and `/streamdeck/<coin>` answer the same in the shape Stream Deck
+/// plugins read, a key's title and color.
+fn route(
+    path: &str,
+    names: &[String],
+    cache: &Cache,
+    symbols: &Symbols,
+    overrides: &HashMap<String, String>,
+    net: &Net,
+) -> Response {
+    let path = path.split('?').next().unwrap_or(path).trim_end_matches('/');
+    let (rest, keys) = match (
+        path.strip_prefix("/tickers"),
+        path.strip_prefix("/streamdeck"),
+    ) {
+        (Some(rest), _) => (rest, false),
+        (_, Some(rest)) => (rest, true),
+        _ => return Response::not_found(),
+    };
+    if rest.is_empty() {
+        let tickers: Vec<Currency> = names
+            .iter()
+            .filter_map(|name| {
+                load_ticker(name, Some(cache), net)
+                    .map_err(|err| warn!("{}", err))
+                    .ok()
+            })
+            .collect();
+        if keys {
+            let keys: Vec<StreamDeckKey> = tickers.iter().map(output::streamdeck).collect();
+            return json(&keys);
+        }
+        return json(&tickers);
+    }
+    match rest.strip_prefix('/') {
+        Some(coin) if !coin.is_empty() && !coin.contains('/') => {
+            let id = symbols.resolve(coin, overrides);
+            match load_ticker(id, Some(cache), net) {
+                Ok(ticker) if keys => json(&output::streamdeck(&ticker)),
+                Ok(ticker) => json(&ticker),
+                Err(err @ Error::InvalidTicker { .. }) => json_error(404, &err),
+                Err(err) => json_error(502, &err),
+            }
+        }
+        _ => Response::not_found(),
+    }
+}
+
+/// Runs `--serve`, answering with tickers as JSON, fetched through
+/// `cache` so repeated requests don't hit the API.
+pub fn serve(
+    listen: &str,
+    names: Vec<String>,
+    cache: Cache,
+    symbols: Symbols,
+    overrides: HashMap<String, String>,
+    net: Net,
+) -> Result<(), Error> {
+    http::serve(listen, move |path| {
+        route(path, &names, &cache, &symbols, &overrides, &net)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cache::{CacheBackend, MemoryCache, Validators};
+    use std::time::Duration;
+
+    #[test]
+    fn routes_to_cached_tickers() {
+        let tickers: Vec<Currency> =
+            serde_json::from_str(include_str!("../tests/fixtures/ticker.json")).unwrap();
+        let backend = MemoryCache::default();
+        backend
+            .put("bitcoin", &tickers[0], &Validators::default())
+            .unwrap();
+        let cache = Cache {
+            backend: Box::new(backend),
+            ttl: Some(Duration::from_secs(3600)),
+            offline: true,
+        };
+        let mut overrides = HashMap::new();
+        overrides.insert("btc".to_string(), "bitcoin".to_string());
+        let route = |path| {
+            route(
+                path,
+                &["bitcoin".to_string()],
+                &cache,
+                &Symbols::default(),
+                &overrides,
+                &Net::default(),
+            )
+        };
+
+        let all = route("/tickers");
+        assert_eq!(all.status, 200);
+        let all: Vec<Currency> = serde_json::from_str(&all.body).unwrap();
+        assert_eq!(all[0].id, "bitcoin");
+
+        let one = route("/tickers/btc/");
+        let one: Currency = serde_json::from_str(&one.body).unwrap();
+        assert_eq!(one.price_usd, tickers[0].price_usd);
+
+        // offline, an uncached coin can't be fetched
+        assert_eq!(route("/tickers/ethereum").status, 502);
+        assert_eq!(route("/prices").status, 404);
+        assert_eq!(route("/tickersbitcoin").status, 404);
+
+        let key: Value = serde_json::from_str(&route("/streamdeck/btc").body).unwrap();
+        assert_eq!(key["title"], "BTC\n2.55k");
+        assert_eq!(key["color"], "#ff0000");
+        let keys: Vec<Value> = serde_json::from_str(&route("/streamdeck").body).unwrap();
+        assert_eq!(keys[0]["id"], "bitcoin");
+    }
+}
@@ -0,0 +1,150 @@
+use std::fs::{File, OpenOptions};
+use std::io::{stdout, BufRead, BufReader, Write};
+use std::path::Path;
+use std::thread::sleep;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde_json;
+
+use errors::Error;
+
+/// One rendered update: exactly what went to stdout, and when.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Frame {
+    /// milliseconds since the epoch
+    pub time: u64,
+    pub text: String,
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Appends a frame per rendered update to a JSON Lines file, for
+/// `--record-session`.
+pub struct Recorder {
+    file: File,
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> Result<Recorder, Error> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Recorder { file })
+    }
+
+    pub fn record(&mut self, text: &str) -> Result<(), Error> {
+        let frame = Frame {
+            time: now_ms(),
+            text: text.to_string(),
+        };
+        writeln!(self.file, "{}", serde_json::to_string(&frame)?)?;
+        Ok(())
+    }
+}
+
+/// Reads `--speed` like `10x`, `10` or `0.5x`.
+pub fn parse_speed(speed: &str) -> Result<f64, String> {
+    let digits = speed.trim().trim_end_matches(['x', 'X']);
+    match digits.parse::<f64>() {
+        Ok(speed) if speed > 0.0 && speed.is_finite() => Ok(speed),
+        _ => Err(format!("{:?} should be a speed like 10x", speed)),
+    }
+}
+
+/// The frames of a recorded session, in order. Blank lines are skipped
+/// so a hand-trimmed file still plays.
+pub fn read(path: &Path) -> Result<Vec<Frame>, Error> {
+    let file = File::open(path)
+        .map_err(|err| Error::Other(format!("could not open {}: {}", path.display(), err)))?;
+    let mut frames = vec![];
+    for (number, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let frame = serde_json::from_str(&line).map_err(|err| {
+            Error::Parse(format!("{} line {}: {}", path.display(), number + 1, err))
+        })?;
+        frames.push(frame);
+    }
+    Ok(frames)
+}
+
+/// How long to wait before each frame, the recorded gaps divided by
+/// `speed`. The first frame plays at once.
+pub fn delays(frames: &[Frame], speed: f64) -> Vec<Duration> {
+    let mut previous = frames.first().map_or(0, |frame| frame.time);
+    frames
+        .iter()
+        .map(|frame| {
+            let gap = frame.time.saturating_sub(previous);
+            previous = previous.max(frame.time);
+            Duration::from_millis((gap as f64 / speed) as u64)
+        })
+        .collect()
+}
+
+/// Prints the recorded frames again with their original pacing, sped
+/// up by `speed`.
+pub fn replay(path: &Path, speed: f64) -> Result<(), Error> {
+    let frames = read(path)?;
+    let out = stdout();
+    for (frame, delay) in frames.iter().zip(delays(&frames, speed)) {
+        sleep(delay);
+        let mut out = out.lock();
+        out.write_all(frame.text.as_bytes())?;
+        out.flush()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, process};
+
+    #[test]
+    fn reads_speeds() {
+        assert_eq!(parse_speed("10x"), Ok(10.0));
+        assert_eq!(parse_speed("0.5X"), Ok(0.5));
+        assert_eq!(parse_speed("3"), Ok(3.0));
+        assert!(parse_speed("0x").is_err());
+        assert!(parse_speed("fast").is_err());
+    }
+
+    #[test]
+    fn round_trips_a_session() {
+        let path =
+            env::temp_dir().join(format!("cryptoticker-test-session-{}.jsonl", process::id()));
+        let _ = fs::remove_file(&path);
+        let mut recorder = Recorder::create(&path).unwrap();
+        recorder.record("btc:2545.19 \x08").unwrap();
+        recorder.record("\rbtc:2550 \x1b[K\x08").unwrap();
+        let frames = read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[1].text, "\rbtc:2550 \x1b[K\x08");
+        assert!(frames[0].time <= frames[1].time);
+    }
+
+    #[test]
+    fn scales_the_gaps() {
+        let frame = |time| Frame {
+            time,
+            text: String::new(),
+        };
+        let frames = [frame(1_000), frame(11_000), frame(31_000), frame(30_000)];
+        assert_eq!(
+            delays(&frames, 10.0),
+            vec![
+                Duration::from_millis(0),
+                Duration::from_millis(1_000),
+                Duration::from_millis(2_000),
+                Duration::from_millis(0),
+            ]
+        );
+    }
+}
@@ -0,0 +1,93 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use signal_hook::consts::{SIGINT, SIGTERM};
+use signal_hook::flag;
+
+use errors::Error;
+
+/// How often a sleeping interval mode looks for signals.
+const POLL: Duration = Duration::from_millis(100);
+
+/// Signals interval mode answers to, so `tmux bind-key` or a cron job can
+/// poke a running ticker: SIGHUP rereads the config and watchlist, SIGUSR1
+/// refreshes right away, and SIGTERM or SIGINT stop it after the round.
+#[derive(Debug, Default, Clone)]
+pub struct Signals {
+    reload: Arc<AtomicBool>,
+    refresh: Arc<AtomicBool>,
+    stop: Arc<AtomicBool>,
+}
+
+impl Signals {
+    pub fn register() -> Result<Signals, Error> {
+        let signals = Signals::default();
+        for &signal in &[SIGINT, SIGTERM] {
+            // a second one while the first is still being handled, like
+            // pressing Ctrl-C again during a slow fetch, exits at once
+            flag::register_conditional_shutdown(signal, 1, signals.stop.clone())?;
+            flag::register(signal, signals.stop.clone())?;
+        }
+        #[cfg(unix)]
+        {
+            use signal_hook::consts::{SIGHUP, SIGUSR1};
+            flag::register(SIGHUP, signals.reload.clone())?;
+            flag::register(SIGUSR1, signals.refresh.clone())?;
+        }
+        Ok(signals)
+    }
+
+    /// Whether SIGHUP arrived since this was last asked.
+    pub fn take_reload(&self) -> bool {
+        self.reload.swap(false, Ordering::Relaxed)
+    }
+
+    /// Cuts the current sleep short, as SIGUSR1 does.
+    pub fn refresh(&self) {
+        self.refresh.store(true, Ordering::Relaxed);
+    }
+
+    pub fn stopping(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    /// Sleeps for `duration`, or until one of the signals arrives.
+    pub fn sleep(&self, duration: Duration) {
+        let start = Instant::now();
+        while start.elapsed() < duration {
+            if self.refresh.swap(false, Ordering::Relaxed)
+                || self.reload.load(Ordering::Relaxed)
+                || self.stopping()
+            {
+                return;
+            }
+            sleep(POLL.min(duration.saturating_sub(start.elapsed())));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wakes_up_for_signals() {
+        let signals = Signals::default();
+        signals.refresh.store(true, Ordering::Relaxed);
+        let start = Instant::now();
+        signals.sleep(Duration::from_secs(10));
+        assert!(start.elapsed() < Duration::from_secs(1));
+        // a refresh is used up by the sleep it cut short
+        assert!(!signals.refresh.load(Ordering::Relaxed));
+
+        signals.reload.store(true, Ordering::Relaxed);
+        signals.sleep(Duration::from_secs(10));
+        assert!(signals.take_reload());
+        assert!(!signals.take_reload());
+
+        signals.sleep(Duration::from_millis(150));
+        assert!(start.elapsed() >= Duration::from_millis(150));
+    }
+}
@@ -0,0 +1,186 @@
+use std::str::FromStr;
+
+use serde_json::{self, json, Value};
+use time;
+
+use errors::Error;
+use net::Net;
+use spread::EXCHANGES;
+use ticker::Currency;
+
+/// Where a ticker can be fetched from, named in the config's `source`
+/// list, which is tried in order.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source {
+    /// the ticker API, coinmarketcap's unless `--api-url` says otherwise
+    Api,
+    /// CoinGecko's market data, which has everything the API has
+    CoinGecko,
+    /// an exchange `spread` asks, which only has a price, and only for a
+    /// coin whose symbol is known
+    Exchange(&'static str),
+}
+
+impl FromStr for Source {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Source, String> {
+        match s.trim().to_lowercase().as_str() {
+            "coinmarketcap" => Ok(Source::Api),
+            "coingecko" => Ok(Source::CoinGecko),
+            name => EXCHANGES
+                .iter()
+                .find(|&&(exchange, _)| exchange == name)
+                .map(|&(exchange, _)| Source::Exchange(exchange))
+                .ok_or_else(|| {
+                    let exchanges: Vec<&str> = EXCHANGES.iter().map(|&(name, _)| name).collect();
+                    format!(
+                        "source {:?} should be coinmarketcap, coingecko or one of {}",
+                        s,
+                        exchanges.join(", ")
+                    )
+                }),
+        }
+    }
+}
+
+impl Source {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Source::Api => "coinmarketcap",
+            Source::CoinGecko => "coingecko",
+            Source::Exchange(name) => name,
+        }
+    }
+}
+
+/// Reads `names` from the config's `source` list.
+pub fn parse(names: &[String]) -> Result<Vec<Source>, String> {
+    names.iter().map(|name| name.parse()).collect()
+}
+
+/// CoinGecko's `2024-06-10T12:34:56.789Z` as the unix seconds the API
+/// sends.
+fn unix(timestamp: &str) -> Option<String> {
+    let seconds = timestamp.get(..19)?;
+    let tm = time::strptime(seconds, "%Y-%m-%dT%H:%M:%S").ok()?;
+    Some(tm.to_timespec().sec.to_string())
+}
+
+/// Reads CoinGecko's market data for `id` into the shape the API has.
+fn coingecko_ticker(id: &str, content: &str) -> Result<Currency, Error> {
+    let markets: Vec<Value> = serde_json::from_str(content)?;
+    let market = match markets.into_iter().next() {
+        Some(market) => market,
+        None => {
+            return Err(Error::InvalidTicker {
+                name: id.to_string(),
+            })
+        }
+    };
+    let text = |field: &str| market[field].as_str().unwrap_or_default().to_string();
+    Ok(serde_json::from_value(json!({
+        "id": id,
+        "name": text("name"),
+        "symbol": text("symbol").to_uppercase(),
+        "rank": market["market_cap_rank"].as_u64().map_or_else(String::new, |rank| rank.to_string()),
+        "price_usd": market["current_price"],
+        "24h_volume_usd": market["total_volume"],
+        "market_cap_usd": market["market_cap"],
+        "available_supply": market["circulating_supply"],
+        "total_supply": market["total_supply"],
+        "percent_change_1h": market["price_change_percentage_1h_in_currency"],
+        "percent_change_24h": market["price_change_percentage_24h_in_currency"],
+        "percent_change_7d": market["price_change_percentage_7d_in_currency"],
+        "last_updated": market["last_updated"].as_str().and_then(unix),
+    }))?)
+}
+
+fn coingecko(id: &str, net: &Net) -> Result<Currency, Error> {
+    let url = format!(
+        "https://api.coingecko.com/api/v3/coins/markets?vs_currency=usd&ids={}\
+         &price_change_percentage=1h,24h,7d",
+        id
+    );
+    debug!("retrieving {}", url);
+    let resp = net.get(&url)?;
+    if !resp.status.is_success() {
+        return Err(Error::Network(format!("{} answered {}", url, resp.status)));
+    }
+    coingecko_ticker(id, &resp.text())
+}
+
+/// Fetches `id` from `source`, other than the API, which `ticker` asks
+/// itself. An exchange needs the coin's `symbol`, from a ticker fetched
+/// before; what it gives is a ticker with only a price.
+pub fn fetch(source: Source, id: &str, symbol: Option<&str>, net: &Net) -> Result<Currency, Error> {
+    match source {
+        Source::Api => Err(Error::Other(
+            "the ticker API is asked by the ticker itself".to_string(),
+        )),
+        Source::CoinGecko => coingecko(id, net),
+        Source::Exchange(name) => {
+            let symbol = symbol.ok_or_else(|| {
+                Error::Other(format!(
+                    "{} is asked by symbol, and none is known for {} yet",
+                    name, id
+                ))
+            })?;
+            let &(_, price) = EXCHANGES
+                .iter()
+                .find(|&&(exchange, _)| exchange == name)
+                .unwrap();
+            let price = price(&symbol.to_uppercase(), net)?;
+            Ok(serde_json::from_value(json!({
+                "id": id,
+                "name": id,
+                "symbol": symbol.to_uppercase(),
+                "rank": "",
+                "price_usd": price.to_string(),
+            }))?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+
+    #[test]
+    fn reads_sources() {
+        let names: Vec<String> = vec!["coingecko".into(), "CoinMarketCap".into(), "binance".into()];
+        assert_eq!(
+            parse(&names),
+            Ok(vec![
+                Source::CoinGecko,
+                Source::Api,
+                Source::Exchange("binance")
+            ])
+        );
+        assert!(parse(&["coinpaprika".to_string()]).is_err());
+        assert_eq!(Source::Exchange("kraken").name(), "kraken");
+    }
+
+    #[test]
+    fn normalizes_coingecko_markets() {
+        let ticker = coingecko_ticker(
+            "bitcoin",
+            r#"[{"id": "bitcoin", "symbol": "btc", "name": "Bitcoin", "current_price": 67000.5,
+                "market_cap": 1320000000000, "market_cap_rank": 1, "total_volume": 25000000000,
+                "circulating_supply": 19700000.0, "total_supply": 21000000.0,
+                "last_updated": "1970-01-02T00:00:10.123Z",
+                "price_change_percentage_1h_in_currency": 0.12,
+                "price_change_percentage_24h_in_currency": -1.5,
+                "price_change_percentage_7d_in_currency": null}]"#,
+        )
+        .unwrap();
+        assert_eq!(ticker.symbol, "BTC");
+        assert_eq!(ticker.rank, "1");
+        assert_eq!(ticker.price_usd, Some(Decimal::new(670005, 1)));
+        assert_eq!(ticker.percent_change_24, Some(Decimal::new(-15, 1)));
+        assert_eq!(ticker.percent_change_7, None);
+        assert_eq!(ticker.last_updated.as_deref(), Some("86410"));
+        assert!(coingecko_ticker("nothing", "[]").is_err());
+    }
+}
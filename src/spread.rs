@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+
+use clap::ArgMatches;
+use rust_decimal::Decimal;
+use serde_json::{self, Value};
+
+use errors::Error;
+use incidents;
+use net::Net;
+use symbols::Symbols;
+use template::rounded;
+use ticker::{parse_price, refresh_ticker};
+
+/// One venue's price for the coin, in dollars or the stablecoin it
+/// trades against.
+#[derive(Debug, Clone, PartialEq)]
+struct Quote {
+    source: String,
+    price: Decimal,
+}
+
+fn get(url: &str, net: &Net) -> Result<String, Error> {
+    debug!("retrieving {}", url);
+    let resp = net.get(url)?;
+    if !resp.status.is_success() {
+        return Err(Error::Network(format!("{} answered {}", url, resp.status)));
+    }
+    Ok(resp.text())
+}
+
+/// Reads a price the exchanges send as a string, naming `exchange` if
+/// it isn't one.
+fn price(value: &Value, exchange: &str) -> Result<Decimal, Error> {
+    value
+        .as_str()
+        .and_then(parse_price)
+        .ok_or_else(|| Error::Parse(format!("{} sent {} for a price", exchange, value)))
+}
+
+fn binance(symbol: &str, net: &Net) -> Result<Decimal, Error> {
+    let url = format!(
+        "https://api.binance.com/api/v3/ticker/price?symbol={}USDT",
+        symbol
+    );
+    let answer: Value = serde_json::from_str(&get(&url, net)?)?;
+    price(&answer["price"], "binance")
+}
+
+fn coinbase(symbol: &str, net: &Net) -> Result<Decimal, Error> {
+    let url = format!("https://api.coinbase.com/v2/prices/{}-USD/spot", symbol);
+    let answer: Value = serde_json::from_str(&get(&url, net)?)?;
+    price(&answer["data"]["amount"], "coinbase")
+}
+
+fn kraken_price(content: &str) -> Result<Decimal, Error> {
+    let answer: Value = serde_json::from_str(content)?;
+    if let Some(error) = answer["error"].as_array().and_then(|errors| errors.first()) {
+        return Err(Error::Network(format!("kraken answered {}", error)));
+    }
+    // the one pair asked for, under Kraken's own name for it
+    let pair = answer["result"]
+        .as_object()
+        .and_then(|result| result.values().next())
+        .ok_or_else(|| Error::Parse("kraken sent no pairs".to_string()))?;
+    price(&pair["c"][0], "kraken")
+}
+
+fn kraken(symbol: &str, net: &Net) -> Result<Decimal, Error> {
+    // Kraken still calls bitcoin XBT
+    let symbol = if symbol == "BTC" { "XBT" } else { symbol };
+    let url = format!("https://api.kraken.com/0/public/Ticker?pair={}USD", symbol);
+    kraken_price(&get(&url, net)?)
+}
+
+fn bitstamp(symbol: &str, net: &Net) -> Result<Decimal, Error> {
+    let url = format!(
+        "https://www.bitstamp.net/api/v2/ticker/{}usd/",
+        symbol.to_lowercase()
+    );
+    let answer: Value = serde_json::from_str(&get(&url, net)?)?;
+    price(&answer["last"], "bitstamp")
+}
+
+type Source = fn(&str, &Net) -> Result<Decimal, Error>;
+
+/// The exchanges `spread` asks, by the names `--exchange` takes.
+pub const EXCHANGES: &[(&str, Source)] = &[
+    ("binance", binance),
+    ("bitstamp", bitstamp),
+    ("coinbase", coinbase),
+    ("kraken", kraken),
+];
+
+/// The widest gap between two quotes, as a percentage of the lower,
+/// with the cheapest and dearest quote.
+fn widest(quotes: &[Quote]) -> Option<(Decimal, &Quote, &Quote)> {
+    let low = quotes.iter().min_by_key(|quote| quote.price)?;
+    let high = quotes.iter().max_by_key(|quote| quote.price)?;
+    let spread = (high.price - low.price)
+        .checked_div(low.price)?
+        .checked_mul(Decimal::from(100))?;
+    Some((spread, low, high))
+}
+
+fn report(quotes: &[Quote]) -> String {
+    let mut out = format!("{:<16}  {:>14}\n", "SOURCE", "PRICE");
+    for quote in quotes {
+        out.push_str(&format!("{:<16}  {:>14}\n", quote.source, quote.price));
+    }
+    if let Some((spread, low, high)) = widest(quotes).filter(|_| quotes.len() > 1) {
+        out.push_str(&format!(
+            "max spread {:.2}%, {} to {}\n",
+            rounded(spread, 2),
+            low.source,
+            high.source
+        ));
+    }
+    out
+}
+
+/// Runs `spread`, asking the ticker API and each exchange for the coin's
+/// price and showing how far apart they are. A source that fails is
+/// left out, as long as one answers.
+pub fn spread(
+    matches: &ArgMatches,
+    symbols: &Symbols,
+    overrides: &HashMap<String, String>,
+    net: &Net,
+) -> Result<(), Error> {
+    let arg = matches.value_of("TICKER").unwrap();
+    let id = symbols.resolve(arg, overrides);
+    let symbol = symbols.symbol_of(id).unwrap_or(arg).to_uppercase();
+    let only: Option<Vec<&str>> = matches.values_of("exchange").map(Iterator::collect);
+    if let Some(ref only) = only {
+        for name in only {
+            if !EXCHANGES.iter().any(|&(known, _)| known == *name) {
+                let known: Vec<&str> = EXCHANGES.iter().map(|&(name, _)| name).collect();
+                return Err(Error::Parse(format!(
+                    "{:?} should be one of {}",
+                    name,
+                    known.join(", ")
+                )));
+            }
+        }
+    }
+
+    let mut quotes = Vec::new();
+    match refresh_ticker(id, None, net) {
+        Ok(ticker) => {
+            if let Some(price) = ticker.price_usd {
+                quotes.push(Quote {
+                    source: incidents::provider(net.api),
+                    price,
+                });
+            }
+        }
+        Err(err) => debug!("the ticker API has no price: {}", err),
+    }
+    for &(name, source) in EXCHANGES {
+        if only.as_ref().is_some_and(|only| !only.contains(&name)) {
+            continue;
+        }
+        match source(&symbol, net) {
+            Ok(price) => quotes.push(Quote {
+                source: name.to_string(),
+                price,
+            }),
+            Err(err) => debug!("no {} price: {}", name, err),
+        }
+    }
+    if quotes.is_empty() {
+        return Err(Error::Other(format!("no prices found for {}", symbol)));
+    }
+    print!("{}", report(&quotes));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quote(source: &str, price: &str) -> Quote {
+        Quote {
+            source: source.to_string(),
+            price: price.parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn reads_kraken() {
+        let price = kraken_price(
+            r#"{"error": [], "result": {"XXBTZUSD": {"a": ["64215.0", "1", "1.000"],
+                "c": ["64210.10000", "0.00100000"]}}}"#,
+        )
+        .unwrap();
+        assert_eq!(price, "64210.1".parse().unwrap());
+        assert!(kraken_price(r#"{"error": ["EQuery:Unknown asset pair"]}"#).is_err());
+    }
+
+    #[test]
+    fn finds_the_widest_gap() {
+        let quotes = vec![
+            quote("coinmarketcap", "64250"),
+            quote("binance", "64287.00"),
+            quote("kraken", "64210.10"),
+        ];
+        assert_eq!(
+            report(&quotes),
+            "SOURCE                     PRICE\n\
+             coinmarketcap              64250\n\
+             binance                 64287.00\n\
+             kraken                  64210.10\n\
+             max spread 0.12%, kraken to binance\n"
+        );
+        assert!(!report(&quotes[..1]).contains("spread"));
+    }
+}
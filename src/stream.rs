@@ -0,0 +1,352 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::thread::sleep;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use clap::ArgMatches;
+use native_tls::{TlsConnector, TlsStream};
+use rust_decimal::Decimal;
+use serde_json::{self, Value};
+
+use chaos::roll;
+use errors::Error;
+use logo::base64;
+use ticker::parse_price;
+
+/// The exchanges `stream --source` can subscribe to.
+pub const SOURCES: &[&str] = &["binance"];
+
+const HOST: &str = "stream.binance.com";
+const PORT: u16 = 9443;
+
+/// The wait before the first reconnect, doubled after each one that
+/// fails, up to `MAX_BACKOFF`.
+const BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// A connection that sends nothing for this long is taken for dead.
+/// Binance pings every few minutes, so a quiet market still talks.
+const SILENCE: Duration = Duration::from_secs(600);
+
+/// Binance's combined stream of each pair's 24h mini ticker, which it
+/// sends about once a second when the pair trades.
+fn path(pairs: &[String]) -> String {
+    let streams: Vec<String> = pairs
+        .iter()
+        .map(|pair| format!("{}@miniTicker", pair))
+        .collect();
+    format!("/stream?streams={}", streams.join("/"))
+}
+
+/// Bytes for the handshake key and frame masks. They only have to differ
+/// from one connection to the next, so the clock will do.
+fn noise(len: usize) -> Vec<u8> {
+    let mut x = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+        | 1;
+    (0..len)
+        .map(|_| {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            x as u8
+        })
+        .collect()
+}
+
+/// A frame from the server, the ones the stream cares about.
+#[derive(Debug, PartialEq)]
+enum Frame {
+    Text(String),
+    Ping(Vec<u8>),
+    Close,
+    /// a pong, or a binary frame, neither of which Binance sends
+    Other,
+}
+
+const CONTINUATION: u8 = 0x0;
+const TEXT: u8 = 0x1;
+const CLOSE: u8 = 0x8;
+const PING: u8 = 0x9;
+const PONG: u8 = 0xa;
+
+/// The most a message may take, fragments and all. Binance's mini
+/// tickers are a few hundred bytes, so anything near this is a broken
+/// server, and a length read off the wire can't be trusted with memory.
+const MAX_MESSAGE: u64 = 1 << 20;
+
+/// Reads one message, joining a fragmented one back together.
+fn read_frame<R: Read>(reader: &mut R) -> io::Result<Frame> {
+    let mut message = Vec::new();
+    loop {
+        let mut head = [0; 2];
+        reader.read_exact(&mut head)?;
+        let (fin, opcode) = (head[0] & 0x80 != 0, head[0] & 0x0f);
+        let len = match head[1] & 0x7f {
+            126 => {
+                let mut len = [0; 2];
+                reader.read_exact(&mut len)?;
+                u16::from_be_bytes(len) as u64
+            }
+            127 => {
+                let mut len = [0; 8];
+                reader.read_exact(&mut len)?;
+                u64::from_be_bytes(len)
+            }
+            len => len as u64,
+        };
+        if len > MAX_MESSAGE - message.len() as u64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("a {}-byte frame is more than a ticker needs", len),
+            ));
+        }
+        // servers don't mask their frames, but a mask is easy to undo
+        let mask = if head[1] & 0x80 != 0 {
+            let mut mask = [0; 4];
+            reader.read_exact(&mut mask)?;
+            Some(mask)
+        } else {
+            None
+        };
+        let mut payload = vec![0; len as usize];
+        reader.read_exact(&mut payload)?;
+        if let Some(mask) = mask {
+            for (i, byte) in payload.iter_mut().enumerate() {
+                *byte ^= mask[i % 4];
+            }
+        }
+        match opcode {
+            TEXT | CONTINUATION => {
+                message.extend(payload);
+                if fin {
+                    let text = String::from_utf8(message)
+                        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+                    return Ok(Frame::Text(text));
+                }
+            }
+            PING => return Ok(Frame::Ping(payload)),
+            CLOSE => return Ok(Frame::Close),
+            _ => return Ok(Frame::Other),
+        }
+    }
+}
+
+/// Writes one frame, masked as a client's must be.
+fn write_frame<W: Write>(
+    writer: &mut W,
+    opcode: u8,
+    payload: &[u8],
+    mask: &[u8],
+) -> io::Result<()> {
+    let mut frame = vec![0x80 | opcode];
+    match payload.len() {
+        len if len < 126 => frame.push(0x80 | len as u8),
+        len if len <= u16::MAX as usize => {
+            frame.push(0x80 | 126);
+            frame.extend(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(0x80 | 127);
+            frame.extend(&(len as u64).to_be_bytes());
+        }
+    }
+    frame.extend(&mask[..4]);
+    frame.extend(
+        payload
+            .iter()
+            .enumerate()
+            .map(|(i, byte)| byte ^ mask[i % 4]),
+    );
+    writer.write_all(&frame)
+}
+
+/// The pair and its last price from one mini ticker message, like
+/// `("btcusdt", 64210.1)`.
+fn update(message: &str) -> Option<(String, Decimal)> {
+    let message: Value = serde_json::from_str(message).ok()?;
+    let data = &message["data"];
+    let pair = data["s"].as_str()?.to_lowercase();
+    let price = parse_price(data["c"].as_str()?)?;
+    Some((pair, price.normalize()))
+}
+
+/// The line for `pairs`, in the order they were asked for, like
+/// `btcusdt:64210.1 ethusdt:…`.
+fn line(pairs: &[String], prices: &HashMap<String, Decimal>) -> String {
+    let segments: Vec<String> = pairs
+        .iter()
+        .map(|pair| match prices.get(pair) {
+            Some(price) => format!("{}:{}", pair, price),
+            None => format!("{}:…", pair),
+        })
+        .collect();
+    segments.join(" ")
+}
+
+/// Opens the stream for `pairs`, already past the handshake. Frames
+/// are read through the buffer the handshake was, which may hold the
+/// start of the first one.
+fn connect(pairs: &[String]) -> Result<BufReader<TlsStream<TcpStream>>, Error> {
+    let tcp = TcpStream::connect((HOST, PORT))?;
+    tcp.set_read_timeout(Some(SILENCE))?;
+    let connector = TlsConnector::builder()
+        .and_then(|builder| builder.build())
+        .map_err(|err| Error::Network(format!("Could not set up TLS: {}", err)))?;
+    let mut tls = connector
+        .connect(HOST, tcp)
+        .map_err(|err| Error::Network(format!("TLS with {} failed: {}", HOST, err)))?;
+    write!(
+        tls,
+        "GET {} HTTP/1.1\r\n\
+         Host: {}:{}\r\n\
+         Upgrade: websocket\r\n\
+         Connection: Upgrade\r\n\
+         Sec-WebSocket-Key: {}\r\n\
+         Sec-WebSocket-Version: 13\r\n\r\n",
+        path(pairs),
+        HOST,
+        PORT,
+        base64(&noise(16))
+    )?;
+    let mut reader = BufReader::new(tls);
+    let mut status = String::new();
+    reader.read_line(&mut status)?;
+    if status.split_whitespace().nth(1) != Some("101") {
+        return Err(Error::Network(format!(
+            "{} would not stream: {}",
+            HOST,
+            status.trim()
+        )));
+    }
+    // the rest of the answer's headers
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+    Ok(reader)
+}
+
+/// Streams until the connection drops, rewriting the line on every
+/// update. Returns whether any update came, so a stream that was working
+/// reconnects without waiting.
+fn follow(pairs: &[String], prices: &mut HashMap<String, Decimal>) -> Result<bool, Error> {
+    let mut stream = connect(pairs)?;
+    let mut updated = false;
+    loop {
+        match read_frame(&mut stream)? {
+            Frame::Text(message) => {
+                if let Some((pair, price)) = update(&message) {
+                    prices.insert(pair, price);
+                    updated = true;
+                    print!("\r{}\x1b[K", line(pairs, prices));
+                    io::stdout().flush()?;
+                }
+            }
+            Frame::Ping(payload) => write_frame(stream.get_mut(), PONG, &payload, &noise(4))?,
+            Frame::Close => {
+                let _ = write_frame(stream.get_mut(), CLOSE, &[], &noise(4));
+                return Ok(updated);
+            }
+            Frame::Other => {}
+        }
+    }
+}
+
+/// Runs `stream`: subscribes to the exchange's ticker stream for the
+/// pairs, like `btcusdt`, and rewrites the line on every update instead
+/// of polling. A dropped connection is reopened, backing off while it
+/// keeps failing; the last prices stay on the line meanwhile.
+pub fn stream(matches: &ArgMatches) -> Result<(), Error> {
+    let pairs: Vec<String> = matches
+        .values_of("PAIR")
+        .unwrap()
+        .map(|pair| pair.to_lowercase())
+        .collect();
+    let mut prices = HashMap::new();
+    let mut backoff = BACKOFF;
+    print!("{}", line(&pairs, &prices));
+    io::stdout().flush()?;
+    loop {
+        match follow(&pairs, &mut prices) {
+            Ok(true) => {
+                debug!("{} closed the stream, reconnecting", HOST);
+                backoff = BACKOFF;
+                continue;
+            }
+            Ok(false) => debug!("{} closed the stream before any update", HOST),
+            Err(err) => warn!("the stream broke: {}", err),
+        }
+        // give or take half, so a fleet of tickers doesn't come back at once
+        sleep(backoff * (50 + roll()) / 100);
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_and_writes_frames() {
+        let mut frame = Vec::new();
+        write_frame(&mut frame, TEXT, b"hello", &[1, 2, 3, 4]).unwrap();
+        assert_eq!(frame[..2], [0x81, 0x85]);
+        // a masked frame reads back as it was written
+        assert_eq!(
+            read_frame(&mut &frame[..]).unwrap(),
+            Frame::Text("hello".to_string())
+        );
+        // "hel" and "lo", unmasked as servers send them
+        let fragments = [
+            &[0x01, 0x03][..],
+            b"hel",
+            &[0x80, 0x02],
+            b"lo",
+            &[0x89, 0x01], // and then a ping
+            b"!",
+        ]
+        .concat();
+        let mut reader = &fragments[..];
+        assert_eq!(
+            read_frame(&mut reader).unwrap(),
+            Frame::Text("hello".to_string())
+        );
+        assert_eq!(read_frame(&mut reader).unwrap(), Frame::Ping(b"!".to_vec()));
+        let long = vec![b'x'; 300];
+        let mut frame = Vec::new();
+        write_frame(&mut frame, TEXT, &long, &noise(4)).unwrap();
+        assert_eq!(frame[1], 0x80 | 126);
+        assert_eq!(
+            read_frame(&mut &frame[..]).unwrap(),
+            Frame::Text(String::from_utf8(long).unwrap())
+        );
+        // an 8-byte length of 2^63, which isn't worth allocating for
+        let huge = [&[0x81, 127][..], &(1u64 << 63).to_be_bytes()].concat();
+        let err = read_frame(&mut &huge[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn follows_mini_tickers() {
+        let pairs = vec!["btcusdt".to_string(), "ethusdt".to_string()];
+        assert_eq!(
+            path(&pairs),
+            "/stream?streams=btcusdt@miniTicker/ethusdt@miniTicker"
+        );
+        let message = r#"{"stream": "btcusdt@miniTicker", "data": {"e": "24hrMiniTicker",
+            "E": 1718000000000, "s": "BTCUSDT", "c": "64210.10000000", "o": "63900.00000000"}}"#;
+        let (pair, price) = update(message).unwrap();
+        assert_eq!(
+            (pair.as_str(), price.to_string().as_str()),
+            ("btcusdt", "64210.1")
+        );
+        assert_eq!(update(r#"{"result": null, "id": 1}"#), None);
+        let mut prices = HashMap::new();
+        prices.insert(pair, price);
+        assert_eq!(line(&pairs, &prices), "btcusdt:64210.1 ethusdt:…");
+    }
+}
@@ -0,0 +1,84 @@
+use std::any::Any;
+use std::cell::Cell;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+
+/// A panic `run` caught, with what was running and what it said.
+#[derive(Debug, PartialEq)]
+pub struct Panicked {
+    what: String,
+    message: String,
+}
+
+impl fmt::Display for Panicked {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} panicked: {}", self.what, self.message)
+    }
+}
+
+fn message(payload: &(dyn Any + Send)) -> String {
+    match (
+        payload.downcast_ref::<&str>(),
+        payload.downcast_ref::<String>(),
+    ) {
+        (Some(message), _) => message.to_string(),
+        (_, Some(message)) => message.clone(),
+        _ => "no message".to_string(),
+    }
+}
+
+thread_local! {
+    /// whether this thread is inside `run`, which catches what panics
+    static SUPERVISED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Sends the panics `run` catches to the debug log, where they say where
+/// they happened, instead of the default hook's stderr; whoever called
+/// `run` warns about them. Any other panic still goes to the hook there
+/// was before.
+pub fn install() {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if SUPERVISED.with(Cell::get) {
+            debug!("{}", info);
+        } else {
+            previous(info);
+        }
+    }));
+}
+
+/// Runs `f` for `what`, like a coin, and with `supervise`, catches a panic
+/// in it, so one provider or format that breaks can't take the whole
+/// status bar down. Without it a panic goes on as usual.
+pub fn run<T, F: FnOnce() -> T>(supervise: bool, what: &str, f: F) -> Result<T, Panicked> {
+    if !supervise {
+        return Ok(f());
+    }
+    let outer = SUPERVISED.with(|supervised| supervised.replace(true));
+    // whatever `f` left half done is thrown away with its result
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    SUPERVISED.with(|supervised| supervised.set(outer));
+    result.map_err(|payload| Panicked {
+        what: what.to_string(),
+        message: message(payload.as_ref()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn catches_panics() {
+        assert_eq!(run(true, "bitcoin", || 1), Ok(1));
+        let panicked = run(true, "bitcoin", || -> u32 { panic!("no price") }).unwrap_err();
+        assert_eq!(panicked.to_string(), "bitcoin panicked: no price");
+        let coin = "ethereum".to_string();
+        let panicked = run(true, "ethereum", || -> u32 {
+            panic!("{} has no price", coin)
+        });
+        assert_eq!(panicked.unwrap_err().message, "ethereum has no price");
+        assert_eq!(run(false, "bitcoin", || 2), Ok(2));
+        assert!(!SUPERVISED.with(Cell::get));
+    }
+}
@@ -0,0 +1,462 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+use serde_json;
+
+use dirs;
+use errors::Error;
+use net::Net;
+use ticker::{fetch_top, PSEUDO};
+
+/// How long the downloaded coin list is trusted before it is fetched
+/// again. New coins are rare enough that a day is plenty.
+const REFRESH: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// What resolving symbols needs to know about a coin.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Coin {
+    id: String,
+    symbol: String,
+    rank: u32,
+}
+
+/// Maps ticker symbols like `btc` to the API's IDs, from the provider's
+/// coin list kept in the cache directory.
+#[derive(Default)]
+pub struct Symbols {
+    coins: Vec<Coin>,
+    /// which coin a shared symbol was settled on, by lowercase symbol
+    choices: HashMap<String, String>,
+}
+
+/// What happens to a symbol several coins share, like `uni`. Pins in
+/// the config's `[symbols]` table always win.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Collisions {
+    /// the highest-ranked coin, the one with the biggest market cap
+    Rank,
+    /// asks which one in a terminal, once ever per symbol
+    Ask,
+}
+
+/// The names `collisions` in the config file accepts.
+pub const COLLISIONS: &[&str] = &["rank", "ask"];
+
+impl FromStr for Collisions {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Collisions, String> {
+        match s.trim().to_lowercase().as_str() {
+            "rank" => Ok(Collisions::Rank),
+            "ask" => Ok(Collisions::Ask),
+            _ => Err(format!(
+                "{:?} should be one of {}",
+                s,
+                COLLISIONS.join(", ")
+            )),
+        }
+    }
+}
+
+impl Symbols {
+    /// Like `load`, with an empty list if the cache directory is unusable.
+    pub fn load_or_empty(offline: bool, net: &Net) -> Symbols {
+        Symbols::load(offline, net).unwrap_or_else(|err| {
+            debug!("{}", err);
+            Symbols::default()
+        })
+    }
+
+    /// Loads the cached coin list, downloading it first if it is missing
+    /// or a day old, and the collisions settled so far. A failed download
+    /// falls back to the old list, and offline there is only ever the old
+    /// list.
+    pub fn load(offline: bool, net: &Net) -> Result<Symbols, Error> {
+        let coins = load_coins(offline, net)?;
+        let choices = choices_path()
+            .ok()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Ok(Symbols { coins, choices })
+    }
+
+    /// The ID `arg` stands for. IDs are left alone, `overrides` win over
+    /// the coin list, and a symbol several coins share goes to the coin
+    /// it was settled on, or else the highest-ranked one. Anything unknown
+    /// is passed through, so the API can say it isn't valid.
+    pub fn resolve<'a>(&'a self, arg: &'a str, overrides: &'a HashMap<String, String>) -> &'a str {
+        let lower = arg.to_lowercase();
+        if let Some((_, id)) = overrides
+            .iter()
+            .find(|(symbol, _)| symbol.to_lowercase() == lower)
+        {
+            return id;
+        }
+        if self.coins.iter().any(|coin| coin.id == arg) {
+            return arg;
+        }
+        if let Some(id) = self.choices.get(&lower) {
+            return id;
+        }
+        self.coins
+            .iter()
+            .filter(|coin| coin.symbol.to_lowercase() == lower)
+            .min_by_key(|coin| coin.rank)
+            .map_or(arg, |coin| coin.id.as_str())
+    }
+
+    /// The coins sharing `arg` as their symbol, highest-ranked first, if
+    /// there's more than one and nothing settles which it means.
+    fn collision(&self, arg: &str, overrides: &HashMap<String, String>) -> Vec<&Coin> {
+        let lower = arg.to_lowercase();
+        let settled = overrides
+            .keys()
+            .any(|symbol| symbol.to_lowercase() == lower)
+            || self.choices.contains_key(&lower)
+            || self.coins.iter().any(|coin| coin.id == arg);
+        if settled {
+            return vec![];
+        }
+        let mut coins: Vec<&Coin> = self
+            .coins
+            .iter()
+            .filter(|coin| coin.symbol.to_lowercase() == lower)
+            .collect();
+        coins.sort_by_key(|coin| coin.rank);
+        if coins.len() < 2 {
+            coins.clear();
+        }
+        coins
+    }
+
+    /// Asks on `output` which coin each shared symbol in `args` means,
+    /// reading the answers from `input`, and keeps them so no symbol is
+    /// asked about twice. An empty answer takes the highest-ranked coin.
+    pub fn settle<R: BufRead, W: Write>(
+        &mut self,
+        args: &[&str],
+        overrides: &HashMap<String, String>,
+        mut input: R,
+        mut output: W,
+    ) -> Result<(), Error> {
+        for arg in args {
+            let ids: Vec<String> = self
+                .collision(arg, overrides)
+                .iter()
+                .map(|coin| coin.id.clone())
+                .collect();
+            if ids.is_empty() {
+                continue;
+            }
+            writeln!(output, "{} is the symbol of {} coins:", arg, ids.len())?;
+            for (number, id) in ids.iter().enumerate() {
+                writeln!(output, "  {}) {}", number + 1, id)?;
+            }
+            let choice = loop {
+                write!(output, "which one? [1] ")?;
+                output.flush()?;
+                let mut answer = String::new();
+                // out of input, the usual pick
+                if input.read_line(&mut answer)? == 0 {
+                    break 0;
+                }
+                match answer.trim() {
+                    "" => break 0,
+                    answer => match answer.parse::<usize>() {
+                        Ok(number) if (1..=ids.len()).contains(&number) => break number - 1,
+                        _ => writeln!(output, "pick a number from 1 to {}", ids.len())?,
+                    },
+                }
+            };
+            self.choices.insert(arg.to_lowercase(), ids[choice].clone());
+        }
+        Ok(())
+    }
+
+    /// Writes the settled collisions out for the next run.
+    pub fn save_choices(&self) -> Result<(), Error> {
+        fs::write(choices_path()?, serde_json::to_string(&self.choices)?)?;
+        Ok(())
+    }
+
+    /// Whether any of `args` is a shared symbol nothing settles yet.
+    pub fn collides(&self, args: &[&str], overrides: &HashMap<String, String>) -> bool {
+        args.iter()
+            .any(|arg| !self.collision(arg, overrides).is_empty())
+    }
+
+    /// The symbol of the coin with ID `id`, if the coin list has it.
+    pub fn symbol_of(&self, id: &str) -> Option<&str> {
+        self.coins
+            .iter()
+            .find(|coin| coin.id == id)
+            .map(|coin| coin.symbol.as_str())
+    }
+
+    /// Every ID in the coin list, highest-ranked first, for completing
+    /// coin names in the shell.
+    pub fn ids(&self) -> Vec<&str> {
+        let mut coins: Vec<&Coin> = self.coins.iter().collect();
+        coins.sort_by_key(|coin| coin.rank);
+        coins.into_iter().map(|coin| coin.id.as_str()).collect()
+    }
+
+    /// Whether `arg` is an ID or symbol in the coin list.
+    fn knows(&self, arg: &str) -> bool {
+        let lower = arg.to_lowercase();
+        self.coins
+            .iter()
+            .any(|coin| coin.id == arg || coin.symbol.to_lowercase() == lower)
+    }
+
+    /// Up to three IDs close to `arg`, closest first, for when it isn't
+    /// valid. Longer names tolerate more typos.
+    pub fn suggest(&self, arg: &str) -> Vec<&str> {
+        let lower = arg.to_lowercase();
+        let limit = (lower.chars().count() / 3).max(1);
+        let mut close: Vec<(usize, &Coin)> = self
+            .coins
+            .iter()
+            .map(|coin| (distance(&lower, &coin.id), coin))
+            .filter(|&(distance, _)| distance <= limit)
+            .collect();
+        close.sort_by_key(|&(distance, coin)| (distance, coin.rank));
+        close
+            .into_iter()
+            .take(3)
+            .map(|(_, coin)| coin.id.as_str())
+            .collect()
+    }
+}
+
+fn path() -> Result<PathBuf, Error> {
+    Ok(dirs::cache_dir()?.join("symbols.json"))
+}
+
+/// Where settled collisions are kept: with the history rather than the
+/// cache, so clearing the cache doesn't ask them all again.
+fn choices_path() -> Result<PathBuf, Error> {
+    Ok(dirs::data_dir()?.join("symbol-choices.json"))
+}
+
+/// How long ago the coin list was downloaded, if it ever was.
+pub fn list_age() -> Option<Duration> {
+    fs::metadata(path().ok()?)
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+}
+
+/// Whether loading the coin list would download it again.
+pub fn list_is_stale() -> bool {
+    list_age().is_none_or(|age| age >= REFRESH)
+}
+
+fn load_coins(offline: bool, net: &Net) -> Result<Vec<Coin>, Error> {
+    let path = path()?;
+    let cached = fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok());
+    let age = list_age();
+    match cached {
+        Some(coins) if offline || age.is_some_and(|age| age < REFRESH) => return Ok(coins),
+        None if offline => return Ok(vec![]),
+        _ => {}
+    }
+    match download(net) {
+        Ok(coins) => {
+            fs::write(&path, serde_json::to_string(&coins)?)?;
+            Ok(coins)
+        }
+        Err(err) => {
+            info!("could not refresh the coin list: {}", err);
+            Ok(cached.unwrap_or_default())
+        }
+    }
+}
+
+fn download(net: &Net) -> Result<Vec<Coin>, Error> {
+    // a limit of 0 asks for every coin the API knows
+    let coins = fetch_top(0, net)?
+        .into_iter()
+        .map(|ticker| Coin {
+            rank: ticker.rank.parse().unwrap_or(u32::MAX),
+            id: ticker.id,
+            symbol: ticker.symbol,
+        })
+        .collect();
+    Ok(coins)
+}
+
+/// Resolves every ticker argument, `news:` ones included and `mining:`
+/// ones left alone, printing what changed when debugging. With `fuzzy`,
+/// arguments nothing matches are replaced with the closest coin.
+pub fn resolve_all(
+    symbols: &Symbols,
+    args: &[&str],
+    overrides: &HashMap<String, String>,
+    fuzzy: bool,
+) -> Vec<String> {
+    args.iter()
+        .map(|arg| {
+            // an algorithm, a token or an index, not a coin
+            if arg.starts_with("mining:") || arg.starts_with("contract:") || PSEUDO.contains(arg) {
+                return arg.to_string();
+            }
+            let (prefix, name) = match arg.strip_prefix("news:") {
+                Some(coin) => ("news:", coin),
+                None => ("", *arg),
+            };
+            let mut id = symbols.resolve(name, overrides);
+            if fuzzy && id == name && !symbols.knows(name) {
+                id = symbols.suggest(name).first().cloned().unwrap_or(id);
+            }
+            if id != name {
+                debug!("resolved {} to {}", name, id);
+            }
+            format!("{}{}", prefix, id)
+        })
+        .collect()
+}
+
+/// The edit distance between `a` and `b`: how many characters have to be
+/// inserted, removed or swapped to turn one into the other.
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..b.len() + 1).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                diagonal
+            } else {
+                1 + diagonal.min(above).min(row[j])
+            };
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbols() -> Symbols {
+        let coin = |id: &str, symbol: &str, rank| Coin {
+            id: id.to_string(),
+            symbol: symbol.to_string(),
+            rank,
+        };
+        Symbols {
+            coins: vec![
+                coin("bitcoin", "BTC", 1),
+                coin("ethereum", "ETH", 2),
+                coin("batcoin", "BTC", 900),
+                coin("eth", "ETHS", 1200),
+            ],
+            choices: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn resolves_symbols_to_ids() {
+        let none = HashMap::new();
+        assert_eq!(symbols().resolve("btc", &none), "bitcoin");
+        assert_eq!(symbols().resolve("ETH", &none), "ethereum");
+        assert_eq!(symbols().resolve("ethereum", &none), "ethereum");
+        assert_eq!(symbols().resolve("nosuchcoin", &none), "nosuchcoin");
+        assert_eq!(symbols().symbol_of("ethereum"), Some("ETH"));
+        assert_eq!(
+            symbols().ids(),
+            vec!["bitcoin", "ethereum", "batcoin", "eth"]
+        );
+    }
+
+    #[test]
+    fn prefers_ids_and_overrides() {
+        let mut overrides = HashMap::new();
+        // an ID that is also someone else's symbol stays the ID
+        assert_eq!(symbols().resolve("eth", &overrides), "eth");
+        overrides.insert("btc".to_string(), "batcoin".to_string());
+        overrides.insert("eth".to_string(), "ethereum".to_string());
+        assert_eq!(symbols().resolve("BTC", &overrides), "batcoin");
+        assert_eq!(symbols().resolve("eth", &overrides), "ethereum");
+    }
+
+    #[test]
+    fn settles_collisions_once() {
+        let none = HashMap::new();
+        let mut settled = symbols();
+        assert!(settled.collides(&["btc", "eth"], &none));
+        let mut asked = Vec::new();
+        settled
+            .settle(&["btc", "eth"], &none, &b"3\n2\n"[..], &mut asked)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(asked).unwrap(),
+            "btc is the symbol of 2 coins:\n  1) bitcoin\n  2) batcoin\n\
+             which one? [1] pick a number from 1 to 2\nwhich one? [1] "
+        );
+        assert_eq!(settled.resolve("BTC", &none), "batcoin");
+        assert!(!settled.collides(&["btc"], &none));
+
+        // a pin in the config is never asked about
+        let mut pinned = HashMap::new();
+        pinned.insert("BTC".to_string(), "bitcoin".to_string());
+        assert!(!symbols().collides(&["btc"], &pinned));
+        assert_eq!("Ask".parse(), Ok(Collisions::Ask));
+        assert!("vote".parse::<Collisions>().is_err());
+    }
+
+    #[test]
+    fn measures_edit_distance() {
+        assert_eq!(distance("ethereem", "ethereum"), 1);
+        assert_eq!(distance("bitcion", "bitcoin"), 2);
+        assert_eq!(distance("", "eth"), 3);
+        assert_eq!(distance("litecoin", "litecoin"), 0);
+    }
+
+    #[test]
+    fn suggests_close_ids() {
+        assert_eq!(symbols().suggest("ethereem"), vec!["ethereum"]);
+        // bitcoin and batcoin are both one edit away; rank breaks the tie
+        assert_eq!(symbols().suggest("bntcoin"), vec!["bitcoin", "batcoin"]);
+        assert!(symbols().suggest("dogecoin").is_empty());
+
+        let none = HashMap::new();
+        let fixed = resolve_all(
+            &symbols(),
+            &[
+                "ethereem",
+                "news:btc",
+                "mining:sha256",
+                "fng",
+                "gas",
+                "contract:0x1:eth",
+            ],
+            &none,
+            true,
+        );
+        assert_eq!(
+            fixed,
+            vec![
+                "ethereum",
+                "news:bitcoin",
+                "mining:sha256",
+                "fng",
+                "gas",
+                "contract:0x1:eth"
+            ]
+        );
+        let kept = resolve_all(&symbols(), &["ethereem"], &none, false);
+        assert_eq!(kept, vec!["ethereem"]);
+    }
+}
@@ -0,0 +1,1327 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::{Decimal, RoundingStrategy};
+
+use clock::Clock;
+use condition::Comparison;
+use events::{self, Event};
+use global::Global;
+use history::{self, History};
+use indicators::{self, Indicator};
+use liquidations::Liquidations;
+use missing::{self, Fallback, DASH};
+use onchain::Metrics;
+use period::Period;
+use ticker::{short_name, to_f64, Currency};
+use unlocks::{self, Unlock};
+
+/// The segment printed for each coin when no `--format` is given.
+pub const DEFAULT: &str = "{short}:{price}";
+
+/// What `{price}` is shown in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Denomination {
+    Usd,
+    Btc,
+    /// hundred-millionths of a bitcoin
+    Sats,
+}
+
+/// The names `--in` takes.
+pub const DENOMINATIONS: &[&str] = &["usd", "btc", "sats"];
+
+impl FromStr for Denomination {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Denomination, String> {
+        match s {
+            "usd" => Ok(Denomination::Usd),
+            "btc" => Ok(Denomination::Btc),
+            "sats" => Ok(Denomination::Sats),
+            _ => Err(format!("{:?} should be usd, btc or sats", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Literal(String),
+    Field(String),
+    Indicator(Indicator),
+    /// a field or indicator run through functions, like
+    /// `{price | round(0)}`
+    Piped(Box<Segment>, Vec<Filter>),
+}
+
+impl Segment {
+    /// The field or indicator under any functions.
+    fn placeholder(&self) -> &Segment {
+        match *self {
+            Segment::Piped(ref inner, _) => inner,
+            ref segment => segment,
+        }
+    }
+}
+
+/// A function a placeholder's value goes through, left to right. The
+/// ones on numbers leave anything else, like `null` or `–`, as it is.
+#[derive(Debug, Clone, PartialEq)]
+enum Filter {
+    /// `round(N)`: to N decimal places
+    Round(u32),
+    /// `pad(N)`: right-aligned in N columns, for a line that doesn't
+    /// jump around
+    Pad(usize),
+    /// `abs`: without its sign
+    Abs,
+    /// `humanize`: like `1.5M`
+    Humanize,
+    /// `upper`
+    Upper,
+    /// `color_if(<0, red)`: in a tmux color when the comparison holds
+    ColorIf(Comparison, f64, String),
+}
+
+/// The functions placeholders can be piped through.
+const FILTERS: &[&str] = &["round", "pad", "abs", "humanize", "upper", "color_if"];
+
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Filter, String> {
+        let s = s.trim();
+        let (name, args) = match s.find('(') {
+            Some(open) if s.ends_with(')') => (s[..open].trim(), &s[open + 1..s.len() - 1]),
+            Some(_) => return Err(format!("unclosed ( in {:?}", s)),
+            None => (s, ""),
+        };
+        let args: Vec<&str> = args
+            .split(',')
+            .map(str::trim)
+            .filter(|arg| !arg.is_empty())
+            .collect();
+        let count = |wanted: usize| {
+            if args.len() == wanted {
+                Ok(())
+            } else {
+                Err(format!(
+                    "{} takes {} arguments, not {}",
+                    name,
+                    wanted,
+                    args.len()
+                ))
+            }
+        };
+        let number = |arg: &str| {
+            arg.parse::<usize>()
+                .map_err(|_| format!("{}({}) needs a whole number", name, arg))
+        };
+        match name {
+            "round" => {
+                count(1)?;
+                // a decimal holds no more than 28 places
+                Ok(Filter::Round(number(args[0])?.min(28) as u32))
+            }
+            "pad" => {
+                count(1)?;
+                Ok(Filter::Pad(number(args[0])?))
+            }
+            "abs" => count(0).map(|_| Filter::Abs),
+            "humanize" => count(0).map(|_| Filter::Humanize),
+            "upper" => count(0).map(|_| Filter::Upper),
+            "color_if" => {
+                count(2)?;
+                let test = args[0];
+                let split = test.find(|c| !"<>=".contains(c)).unwrap_or(test.len());
+                let comparison = Comparison::parse(&test[..split]).ok_or_else(|| {
+                    format!("color_if({}) should compare, like color_if(<0, red)", test)
+                })?;
+                let threshold = test[split..].trim().parse().map_err(|_| {
+                    format!("color_if({}) should compare, like color_if(<0, red)", test)
+                })?;
+                Ok(Filter::ColorIf(comparison, threshold, args[1].to_string()))
+            }
+            _ => Err(format!(
+                "unknown function {:?}, should be one of {}",
+                name,
+                FILTERS.join(", ")
+            )),
+        }
+    }
+}
+
+/// The number in a rendered value, and the `%` or nothing after it.
+fn number(value: &str) -> Option<(Decimal, &str)> {
+    let (digits, suffix) = match value.strip_suffix('%') {
+        Some(digits) => (digits, "%"),
+        None => (value, ""),
+    };
+    digits.parse().ok().map(|number| (number, suffix))
+}
+
+impl Filter {
+    fn apply(&self, value: String) -> String {
+        match *self {
+            Filter::Round(places) => match number(&value) {
+                Some((number, suffix)) => {
+                    format!("{:.*}{}", places as usize, rounded(number, places), suffix)
+                }
+                None => value,
+            },
+            Filter::Pad(width) => format!("{:>1$}", value, width),
+            Filter::Abs => match number(&value) {
+                Some((number, suffix)) => format!("{}{}", number.abs(), suffix),
+                None => value,
+            },
+            Filter::Humanize => match number(&value).and_then(|(number, _)| number.to_f64()) {
+                Some(number) if number < 0.0 => format!("-{}", compact(-number)),
+                Some(number) => compact(number),
+                None => value,
+            },
+            Filter::Upper => value.to_uppercase(),
+            Filter::ColorIf(comparison, threshold, ref color) => {
+                let holds = number(&value)
+                    .and_then(|(number, _)| number.to_f64())
+                    .is_some_and(|number| comparison.holds(number, threshold));
+                if holds {
+                    format!("#[fg={}]{}#[default]", color, value)
+                } else {
+                    value
+                }
+            }
+        }
+    }
+}
+
+/// A parsed `--format` template. Placeholders are ticker fields like
+/// `{price}` or indicators over the local history like `{sma_1h}`;
+/// `{{` and `}}` are literal braces.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    segments: Vec<Segment>,
+    /// the configured events `{event}` and `{countdown}` look at
+    events: Vec<Event>,
+    /// what `{short}` says for coins given an alias, by ID
+    aliases: HashMap<String, String>,
+    /// the price targets `{target}` and `{target_bar}` measure, by ID
+    targets: HashMap<String, f64>,
+    /// what `{liq_long_24h}` and `{liq_short_24h}` show, by ID
+    liquidations: HashMap<String, Liquidations>,
+    /// the token unlocks `{next_unlock}` counts down to, by ID
+    unlocks: HashMap<String, Vec<Unlock>>,
+    /// what the on-chain fields like `{realized_cap}` show, by ID
+    onchain: HashMap<String, Metrics>,
+    /// what the market-wide fields like `{btc_dominance}` show
+    global: Option<Global>,
+    /// what `{price}` is in
+    denomination: Denomination,
+    /// how many decimal places `{price}` gets, if not the default
+    precision: Option<usize>,
+    /// how `{last_updated}` is shown
+    clock: Clock,
+    /// which change `{change}` is
+    period: Period,
+    /// how many of the latest prices `{sparkline}` draws
+    sparkline: usize,
+    /// what stands in for each field the provider left out, with `*`
+    /// for the fields not named
+    fallbacks: HashMap<String, Vec<Fallback>>,
+    /// the formats that replace this one for coins that meet a
+    /// condition, first match first
+    rules: Vec<(When, Vec<Segment>)>,
+}
+
+/// A `[[format_rule]]` table: a whole other format for coins that meet
+/// `when`, like `change_24h < -5`, checked every round.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FormatRule {
+    pub when: String,
+    pub format: String,
+}
+
+/// The fields a `when` can compare.
+const NUMERIC_FIELDS: &[&str] = &[
+    "rank",
+    "price",
+    "price_btc",
+    "change",
+    "change_1h",
+    "change_24h",
+    "change_7d",
+    "volume_24h",
+    "market_cap",
+];
+
+/// A parsed `when`, like `change_24h < -5`.
+#[derive(Debug, Clone, PartialEq)]
+struct When {
+    field: String,
+    comparison: Comparison,
+    threshold: f64,
+}
+
+impl FromStr for When {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<When, String> {
+        let example = || format!("{:?} should compare a field, like change_24h < -5", s);
+        let start = s.find(|c| "<>=".contains(c)).ok_or_else(example)?;
+        let field = s[..start].trim();
+        let rest = &s[start..];
+        let split = rest.find(|c| !"<>=".contains(c)).unwrap_or(rest.len());
+        let comparison = Comparison::parse(&rest[..split]).ok_or_else(example)?;
+        let threshold = rest[split..].trim().parse().map_err(|_| example())?;
+        if !NUMERIC_FIELDS.contains(&field) {
+            return Err(format!(
+                "{:?} can't be compared, use one of {}",
+                field,
+                NUMERIC_FIELDS.join(", ")
+            ));
+        }
+        Ok(When {
+            field: field.to_string(),
+            comparison,
+            threshold,
+        })
+    }
+}
+
+impl When {
+    /// Whether `ticker` meets the condition, `change` being the change
+    /// over `period`. A missing value never does.
+    fn holds(&self, name: &str, ticker: &Currency, period: Period) -> bool {
+        let field = match self.field.as_str() {
+            "change" => period.field(),
+            field => field,
+        };
+        field_value(field, name, ticker)
+            .and_then(|value| value.trim().parse::<f64>().ok())
+            .is_some_and(|value| self.comparison.holds(value, self.threshold))
+    }
+}
+
+const FIELDS: &[&str] = &[
+    "id",
+    "name",
+    "symbol",
+    "short",
+    "rank",
+    "price",
+    "price_btc",
+    "change",
+    "change_1h",
+    "change_24h",
+    "change_7d",
+    "volume_24h",
+    "market_cap",
+    "event",
+    "countdown",
+    "next_unlock",
+    "target",
+    "target_bar",
+    "liq_long_24h",
+    "liq_short_24h",
+    "inflation",
+    "vs_30d_avg",
+    "sparkline",
+    "realized_cap",
+    "active_addresses",
+    "exchange_netflow",
+    "total_market_cap",
+    "total_volume_24h",
+    "btc_dominance",
+    "last_updated",
+];
+
+/// The fields `with_onchain` fills in.
+const ONCHAIN_FIELDS: &[&str] = &["realized_cap", "active_addresses", "exchange_netflow"];
+
+/// The fields worked out from the local history.
+const HISTORY_FIELDS: &[&str] = &["inflation", "vs_30d_avg", "sparkline"];
+
+/// The fields `with_global` fills in.
+const GLOBAL_FIELDS: &[&str] = &["total_market_cap", "total_volume_24h", "btc_dominance"];
+
+/// How many prices `{sparkline}` draws unless `--sparkline` says.
+pub const SPARKLINE_SAMPLES: usize = 24;
+
+/// The blocks `{sparkline}` is drawn with, lowest first.
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// How many cells `{target_bar}` fills.
+const BAR_WIDTH: usize = 6;
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Template, String> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    let mut closed = false;
+                    for c in chars.by_ref() {
+                        if c == '}' {
+                            closed = true;
+                            break;
+                        }
+                        name.push(c);
+                    }
+                    if !closed {
+                        return Err(format!("unclosed {{ in {:?}", s));
+                    }
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(literal.clone()));
+                        literal.clear();
+                    }
+                    let mut pipes = name.split('|');
+                    let name = pipes.next().unwrap_or("").trim().to_string();
+                    let placeholder =
+                        if FIELDS.contains(&name.as_str()) {
+                            Segment::Field(name)
+                        } else {
+                            Segment::Indicator(name.parse().map_err(|_| {
+                                format!("unknown placeholder {{{}}} in {:?}", name, s)
+                            })?)
+                        };
+                    let filters = pipes
+                        .map(str::parse)
+                        .collect::<Result<Vec<Filter>, String>>()
+                        .map_err(|err| format!("{} in {:?}", err, s))?;
+                    segments.push(if filters.is_empty() {
+                        placeholder
+                    } else {
+                        Segment::Piped(Box::new(placeholder), filters)
+                    });
+                }
+                '}' => return Err(format!("unmatched }} in {:?}, write }}}} for a brace", s)),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+        Ok(Template {
+            segments,
+            events: vec![],
+            aliases: HashMap::new(),
+            targets: HashMap::new(),
+            liquidations: HashMap::new(),
+            unlocks: HashMap::new(),
+            onchain: HashMap::new(),
+            global: None,
+            denomination: Denomination::Usd,
+            precision: None,
+            clock: Clock::default(),
+            period: Period::default(),
+            sparkline: SPARKLINE_SAMPLES,
+            fallbacks: HashMap::new(),
+            rules: vec![],
+        })
+    }
+}
+
+impl Default for Template {
+    fn default() -> Template {
+        DEFAULT.parse().unwrap()
+    }
+}
+
+impl Template {
+    /// What `{short}` prints for `id` instead of its usual short name.
+    pub fn alias(&self, id: &str) -> Option<&str> {
+        self.aliases.get(id).map(String::as_str)
+    }
+
+    /// Renders coins that meet a rule's condition in the rule's format
+    /// instead, with everything else this template was given.
+    pub fn with_rules(mut self, rules: &[FormatRule]) -> Result<Template, String> {
+        self.rules = rules
+            .iter()
+            .map(|rule| {
+                let when = rule.when.parse()?;
+                let format: Template = rule.format.parse()?;
+                Ok((when, format.segments))
+            })
+            .collect::<Result<_, String>>()
+            .map_err(|err| format!("[[format_rule]]: {}", err))?;
+        Ok(self)
+    }
+
+    /// Has `{sparkline}` draw the last `samples` prices.
+    pub fn with_sparkline(mut self, samples: usize) -> Template {
+        self.sparkline = samples;
+        self
+    }
+
+    /// Has fields the provider left out fall back on `table`'s
+    /// fallbacks, tried in order, by field name or `*` for the rest.
+    pub fn with_fallbacks(
+        mut self,
+        table: &HashMap<String, Vec<String>>,
+    ) -> Result<Template, String> {
+        for (field, names) in table {
+            if field != "*" && !FIELDS.contains(&field.as_str()) {
+                return Err(format!("[missing]: unknown field {:?}", field));
+            }
+            let fallbacks = names
+                .iter()
+                .map(|name| {
+                    let fallback: Fallback = name.parse()?;
+                    if field != "*" && !fallback.covers(field) {
+                        return Err(format!("{} can't stand in for {}", name.trim(), field));
+                    }
+                    Ok(fallback)
+                })
+                .collect::<Result<_, String>>()
+                .map_err(|err| format!("[missing] {}: {}", field, err))?;
+            self.fallbacks.insert(field.clone(), fallbacks);
+        }
+        Ok(self)
+    }
+
+    fn fallbacks(&self, field: &str) -> &[Fallback] {
+        self.fallbacks
+            .get(field)
+            .or_else(|| self.fallbacks.get("*"))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// The segments of this format and every rule's.
+    fn all_segments(&self) -> impl Iterator<Item = &Segment> {
+        self.segments
+            .iter()
+            .chain(self.rules.iter().flat_map(|(_, segments)| segments))
+    }
+
+    /// Gives `{event}` and `{countdown}` the events to count down to.
+    pub fn with_events(mut self, events: &[Event]) -> Template {
+        self.events = events.to_vec();
+        self
+    }
+
+    /// Has `{short}` print `aliases`, by coin ID, instead of the usual
+    /// short names.
+    pub fn with_aliases(mut self, aliases: HashMap<String, String>) -> Template {
+        self.aliases = aliases;
+        self
+    }
+
+    /// Gives `{target}` and `{target_bar}` a price target per coin ID.
+    pub fn with_targets(mut self, targets: HashMap<String, f64>) -> Template {
+        self.targets = targets;
+        self
+    }
+
+    /// Gives `{liq_long_24h}` and `{liq_short_24h}` the last day of
+    /// liquidations per coin ID.
+    pub fn with_liquidations(mut self, liquidations: HashMap<String, Liquidations>) -> Template {
+        self.liquidations = liquidations;
+        self
+    }
+
+    /// Gives `{next_unlock}` each coin's token unlocks, by ID.
+    pub fn with_unlocks(mut self, unlocks: HashMap<String, Vec<Unlock>>) -> Template {
+        self.unlocks = unlocks;
+        self
+    }
+
+    /// Gives `{realized_cap}`, `{active_addresses}` and
+    /// `{exchange_netflow}` on-chain metrics per coin ID.
+    pub fn with_onchain(mut self, onchain: HashMap<String, Metrics>) -> Template {
+        self.onchain = onchain;
+        self
+    }
+
+    /// Gives `{total_market_cap}`, `{total_volume_24h}` and
+    /// `{btc_dominance}` the market's totals.
+    pub fn with_global(mut self, global: Global) -> Template {
+        self.global = Some(global);
+        self
+    }
+
+    /// Shows `{price}` in `denomination`, from the API's BTC price for
+    /// BTC and sats.
+    pub fn with_denomination(mut self, denomination: Denomination) -> Template {
+        self.denomination = denomination;
+        self
+    }
+
+    /// Rounds `{price}` to `places` decimal places. Without it, USD
+    /// prices are shown as the API sent them and BTC and sats prices to
+    /// four significant digits, however small.
+    pub fn with_precision(mut self, places: usize) -> Template {
+        self.precision = Some(places);
+        self
+    }
+
+    /// Shows `{last_updated}` on `clock` rather than in local time.
+    pub fn with_clock(mut self, clock: Clock) -> Template {
+        self.clock = clock;
+        self
+    }
+
+    /// Has `{change}` show the change over `period` rather than the
+    /// last day.
+    pub fn with_change_period(mut self, period: Period) -> Template {
+        self.period = period;
+        self
+    }
+
+    pub fn change_period(&self) -> Period {
+        self.period
+    }
+
+    /// `{price}` in the template's denomination and precision.
+    fn price(&self, ticker: &Currency) -> Option<String> {
+        let price = match self.denomination {
+            Denomination::Usd => ticker.price_usd?,
+            Denomination::Btc => ticker.price_btc?,
+            Denomination::Sats => ticker.price_btc? * Decimal::new(100_000_000, 0),
+        };
+        let places = match (self.precision, self.denomination) {
+            (Some(places), _) => places,
+            (None, Denomination::Usd) => return Some(price.to_string()),
+            (None, _) => significant_places(to_f64(Some(price)).unwrap_or(0.0), 4),
+        };
+        Some(format!("{:.*}", places, rounded(price, places as u32)))
+    }
+
+    /// Whether rendering needs on-chain metrics fetched.
+    pub fn needs_onchain(&self) -> bool {
+        self.all_segments()
+            .any(|segment| match *segment.placeholder() {
+                Segment::Field(ref field) => ONCHAIN_FIELDS.contains(&field.as_str()),
+                _ => false,
+            })
+    }
+
+    /// Whether rendering needs the market's totals fetched.
+    pub fn needs_global(&self) -> bool {
+        self.all_segments()
+            .any(|segment| match *segment.placeholder() {
+                Segment::Field(ref field) => GLOBAL_FIELDS.contains(&field.as_str()),
+                _ => false,
+            })
+    }
+
+    /// Whether rendering needs liquidations fetched.
+    pub fn needs_liquidations(&self) -> bool {
+        self.all_segments()
+            .any(|segment| match *segment.placeholder() {
+                Segment::Field(ref field) => field.starts_with("liq_"),
+                _ => false,
+            })
+    }
+
+    /// Whether rendering needs token unlocks fetched.
+    pub fn needs_unlocks(&self) -> bool {
+        self.all_segments()
+            .any(|segment| match *segment.placeholder() {
+                Segment::Field(ref field) => field == "next_unlock",
+                _ => false,
+            })
+    }
+
+    /// Whether rendering reads the local history.
+    pub fn needs_history(&self) -> bool {
+        self.all_segments()
+            .any(|segment| match *segment.placeholder() {
+                Segment::Indicator(_) => true,
+                Segment::Field(ref field) => {
+                    HISTORY_FIELDS.contains(&field.as_str())
+                        || self.fallbacks(field).contains(&Fallback::History)
+                }
+                _ => false,
+            })
+    }
+
+    /// Whether rendering `ticker` needs fields it lacks asked of
+    /// CoinGecko first.
+    pub fn needs_provider(&self, ticker: &Currency) -> bool {
+        self.all_segments()
+            .any(|segment| match *segment.placeholder() {
+                Segment::Field(ref field) => {
+                    self.fallbacks(field).contains(&Fallback::Provider)
+                        && missing::lacks(ticker, field)
+                }
+                _ => false,
+            })
+    }
+
+    /// What `field` shows when `ticker` has no value for it: the first of
+    /// its fallbacks with something to show, or `–`.
+    fn missing(&self, field: &str, ticker: &Currency, history: Option<&History>) -> String {
+        for &fallback in self.fallbacks(field) {
+            match fallback {
+                // the history only knows prices in dollars
+                Fallback::History
+                    if fallback.covers(field)
+                        && (field != "price" || self.denomination == Denomination::Usd) =>
+                {
+                    let value =
+                        history.and_then(|history| missing::recorded(field, ticker, history));
+                    if let Some(value) = value {
+                        return value;
+                    }
+                }
+                Fallback::Dash => return DASH.to_string(),
+                Fallback::Null => return "null".to_string(),
+                // asked for before rendering, see `needs_provider`
+                _ => {}
+            }
+        }
+        DASH.to_string()
+    }
+
+    /// Fills in the template for one coin. Missing values, including
+    /// indicators without enough history, render as their fallbacks say,
+    /// or `–`.
+    pub fn render(&self, name: &str, ticker: &Currency, history: Option<&History>) -> String {
+        let mut out = String::new();
+        let segments = self
+            .rules
+            .iter()
+            .find(|(when, _)| when.holds(name, ticker, self.period))
+            .map_or(&self.segments, |(_, segments)| segments);
+        for segment in segments {
+            self.render_segment(segment, name, ticker, history, &mut out);
+        }
+        out
+    }
+
+    fn render_segment(
+        &self,
+        segment: &Segment,
+        name: &str,
+        ticker: &Currency,
+        history: Option<&History>,
+        out: &mut String,
+    ) {
+        match *segment {
+            Segment::Literal(ref text) => out.push_str(text),
+            Segment::Piped(ref inner, ref filters) => {
+                let mut value = String::new();
+                self.render_segment(inner, name, ticker, history, &mut value);
+                for filter in filters {
+                    value = filter.apply(value);
+                }
+                out.push_str(&value);
+            }
+            Segment::Field(ref field) if field == "event" || field == "countdown" => {
+                match events::next(&self.events, &ticker.id, history::now()) {
+                    Some((event, _)) if field == "event" => out.push_str(&event.name),
+                    Some((_, left)) => out.push_str(&history::format_span(left)),
+                    None => out.push_str(&self.missing(field, ticker, history)),
+                }
+            }
+            Segment::Field(ref field) if field == "next_unlock" => {
+                match unlocks::next(&self.unlocks, &ticker.id, history::now()) {
+                    Some((_, left)) => out.push_str(&history::format_span(left)),
+                    None => out.push_str(&self.missing(field, ticker, history)),
+                }
+            }
+            Segment::Field(ref field) if field == "target" || field == "target_bar" => {
+                let price = to_f64(ticker.price_usd);
+                match (price, self.targets.get(&ticker.id)) {
+                    (Some(price), Some(&target)) if field == "target" => {
+                        out.push_str(&progress(price, target))
+                    }
+                    (Some(price), Some(&target)) => out.push_str(&progress_bar(price, target)),
+                    _ => out.push_str(&self.missing(field, ticker, history)),
+                }
+            }
+            Segment::Field(ref field) if field.starts_with("liq_") => {
+                match self.liquidations.get(&ticker.id) {
+                    Some(liquidations) if field == "liq_long_24h" => {
+                        out.push_str(&compact(liquidations.long_usd))
+                    }
+                    Some(liquidations) => out.push_str(&compact(liquidations.short_usd)),
+                    None => out.push_str(&self.missing(field, ticker, history)),
+                }
+            }
+            Segment::Field(ref field) if ONCHAIN_FIELDS.contains(&field.as_str()) => {
+                let metrics = self.onchain.get(&ticker.id);
+                let value = match field.as_str() {
+                    "realized_cap" => metrics.and_then(|metrics| metrics.realized_cap),
+                    "active_addresses" => metrics.and_then(|metrics| metrics.active_addresses),
+                    _ => metrics.and_then(|metrics| metrics.exchange_netflow),
+                };
+                match value {
+                    Some(value) if value < 0.0 => out.push_str(&format!("-{}", compact(-value))),
+                    Some(value) => out.push_str(&compact(value)),
+                    None => out.push_str(&self.missing(field, ticker, history)),
+                }
+            }
+            Segment::Field(ref field) if GLOBAL_FIELDS.contains(&field.as_str()) => {
+                let global = self.global.as_ref();
+                let value = match field.as_str() {
+                    "total_market_cap" => global.and_then(|global| global.total_market_cap_usd),
+                    "total_volume_24h" => global.and_then(|global| global.total_volume_usd_24h),
+                    _ => global.and_then(|global| global.bitcoin_dominance),
+                };
+                match value {
+                    Some(value) if field == "btc_dominance" => {
+                        out.push_str(&format!("{:.2}%", value))
+                    }
+                    Some(value) => out.push_str(&compact(value)),
+                    None => out.push_str(&self.missing(field, ticker, history)),
+                }
+            }
+            Segment::Field(ref field) if field == "inflation" => {
+                let rate = history
+                    .and_then(|history| indicators::inflation(history, &ticker.id).ok())
+                    .and_then(|rate| rate);
+                match rate {
+                    Some(rate) => out.push_str(&format!("{:.2}%", rate)),
+                    None => out.push_str(&self.missing(field, ticker, history)),
+                }
+            }
+            Segment::Field(ref field) if field == "sparkline" => {
+                let prices = history
+                    .and_then(|history| history.recent_prices(&ticker.id, self.sparkline).ok())
+                    .unwrap_or_default();
+                match sparkline(&prices) {
+                    Some(line) => out.push_str(&line),
+                    None => out.push_str(&self.missing(field, ticker, history)),
+                }
+            }
+            Segment::Field(ref field) if field == "vs_30d_avg" => {
+                let price = to_f64(ticker.price_usd);
+                let gap = history
+                    .zip(price)
+                    .and_then(|(history, price)| {
+                        indicators::vs_average(history, &ticker.id, price).ok()
+                    })
+                    .and_then(|gap| gap);
+                match gap {
+                    Some(gap) => out.push_str(&format!("{:+.2}%", gap)),
+                    None => out.push_str(&self.missing(field, ticker, history)),
+                }
+            }
+            Segment::Field(ref field) if field == "last_updated" => {
+                let updated = ticker
+                    .last_updated
+                    .as_ref()
+                    .and_then(|updated| updated.trim().parse::<i64>().ok());
+                match updated {
+                    Some(updated) => out.push_str(&self.clock.show(updated)),
+                    None => out.push_str(&self.missing(field, ticker, history)),
+                }
+            }
+            Segment::Field(ref field) if field == "price" => match self.price(ticker) {
+                Some(price) => out.push_str(&price),
+                None => out.push_str(&self.missing(field, ticker, history)),
+            },
+            Segment::Field(ref field)
+                if field == "short" && self.aliases.contains_key(&ticker.id) =>
+            {
+                out.push_str(&self.aliases[&ticker.id])
+            }
+            Segment::Field(ref field) if field == "change" => {
+                let field = self.period.field();
+                match field_value(field, name, ticker) {
+                    Some(value) => out.push_str(value.trim()),
+                    None => out.push_str(&self.missing(field, ticker, history)),
+                }
+            }
+            Segment::Field(ref field) => match field_value(field, name, ticker) {
+                Some(value) => out.push_str(value.trim()),
+                None => out.push_str(&self.missing(field, ticker, history)),
+            },
+            Segment::Indicator(ref indicator) => {
+                let value = history
+                    .and_then(|history| indicator.compute(history, &ticker.id).ok())
+                    .and_then(|value| value);
+                match value {
+                    Some(value) => out.push_str(&format!("{:.2}", value)),
+                    None => out.push_str(&self.missing(&indicator.to_string(), ticker, history)),
+                }
+            }
+        }
+    }
+}
+
+/// `value` rounded to `places` decimal places, halves away from zero.
+/// Formatting a decimal with a precision cuts the digits off rather than
+/// rounding, so money is passed through this first.
+pub fn rounded(value: Decimal, places: u32) -> Decimal {
+    value.round_dp_with_strategy(places, RoundingStrategy::MidpointAwayFromZero)
+}
+
+/// How many decimal places show `digits` significant digits of `value`,
+/// so `0.00000035` keeps its `35` rather than rounding to nothing.
+pub fn significant_places(value: f64, digits: i32) -> usize {
+    if value == 0.0 || !value.is_finite() {
+        return 0;
+    }
+    let magnitude = value.abs().log10().floor() as i32;
+    // a decimal holds no more than 28 places
+    (digits - 1 - magnitude).clamp(0, 28) as usize
+}
+
+/// How far `price` has come towards `target`, never more than all the way.
+fn fraction(price: f64, target: f64) -> f64 {
+    if target > 0.0 {
+        (price / target).clamp(0.0, 1.0)
+    } else {
+        1.0
+    }
+}
+
+/// Like `▁▂▅▇`, from the lowest of `prices` to the highest, or nothing
+/// for fewer than two prices, which make no line. Prices that never
+/// moved lie flat along the bottom.
+fn sparkline(prices: &[f64]) -> Option<String> {
+    if prices.len() < 2 {
+        return None;
+    }
+    let low = prices.iter().cloned().fold(f64::INFINITY, f64::min);
+    let high = prices.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let top = (SPARKS.len() - 1) as f64;
+    Some(
+        prices
+            .iter()
+            .map(|&price| {
+                let level = if high > low {
+                    ((price - low) / (high - low) * top).round() as usize
+                } else {
+                    0
+                };
+                SPARKS[level]
+            })
+            .collect(),
+    )
+}
+
+/// Like `[87%→50k]`.
+fn progress(price: f64, target: f64) -> String {
+    format!(
+        "[{:.0}%→{}]",
+        (fraction(price, target) * 100.0).floor(),
+        compact(target)
+    )
+}
+
+/// Like `[█████░]→50k`.
+fn progress_bar(price: f64, target: f64) -> String {
+    let filled = (fraction(price, target) * BAR_WIDTH as f64).floor() as usize;
+    format!(
+        "[{}{}]→{}",
+        "█".repeat(filled),
+        "░".repeat(BAR_WIDTH - filled),
+        compact(target)
+    )
+}
+
+/// A price with a k, M, B or T in place of trailing zeros, like `50k` or
+/// `1.5M`.
+pub fn compact(price: f64) -> String {
+    let (value, suffix) = if price >= 1e12 {
+        (price / 1e12, "T")
+    } else if price >= 1e9 {
+        (price / 1e9, "B")
+    } else if price >= 1e6 {
+        (price / 1e6, "M")
+    } else if price >= 1e3 {
+        (price / 1e3, "k")
+    } else {
+        (price, "")
+    };
+    let digits = format!("{:.2}", value);
+    let digits = digits.trim_end_matches('0').trim_end_matches('.');
+    format!("{}{}", digits, suffix)
+}
+
+fn field_value(field: &str, name: &str, ticker: &Currency) -> Option<String> {
+    let value = match field {
+        "id" => return Some(ticker.id.clone()),
+        "name" => return Some(ticker.name.clone()),
+        "symbol" => return Some(ticker.symbol.clone()),
+        "rank" => return Some(ticker.rank.clone()),
+        "short" => return Some(short_name(name)),
+        "price" => ticker.price_usd,
+        "price_btc" => ticker.price_btc,
+        "change_1h" => ticker.percent_change_1,
+        "change_24h" => ticker.percent_change_24,
+        "change_7d" => ticker.percent_change_7,
+        "volume_24h" => ticker.volume_usd_24h,
+        "market_cap" => ticker.market_cap_usd,
+        _ => return None,
+    };
+    value.map(|value| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    fn bitcoin() -> Currency {
+        let tickers: Vec<Currency> =
+            serde_json::from_str(include_str!("../tests/fixtures/ticker.json")).unwrap();
+        tickers[0].clone()
+    }
+
+    #[test]
+    fn default_matches_the_plain_ticker() {
+        assert_eq!(
+            Template::default().render("bitcoin", &bitcoin(), None),
+            "btc:2545.19"
+        );
+    }
+
+    #[test]
+    fn shows_when_the_quote_was_taken() {
+        let template: Template = "{short} {last_updated}".parse().unwrap();
+        let clock = Clock::new(Some("utc"), Some("%Y-%m-%d %H:%M")).unwrap();
+        assert_eq!(
+            template
+                .with_clock(clock)
+                .render("bitcoin", &bitcoin(), None),
+            "btc 2017-06-15 21:54"
+        );
+        let mut ticker = bitcoin();
+        ticker.last_updated = None;
+        let template: Template = "{last_updated}".parse().unwrap();
+        assert_eq!(template.render("bitcoin", &ticker, None), "–");
+    }
+
+    #[test]
+    fn follows_the_change_period() {
+        let template: Template = "{short} {change}%".parse().unwrap();
+        assert_eq!(
+            template.clone().render("bitcoin", &bitcoin(), None),
+            "btc -3.39%"
+        );
+        let template = template.with_change_period(Period::Week);
+        assert_eq!(template.render("bitcoin", &bitcoin(), None), "btc 2.15%");
+        let rules = [FormatRule {
+            when: "change > 1".to_string(),
+            format: "{short}↑".to_string(),
+        }];
+        let template = Template::default()
+            .with_rules(&rules)
+            .unwrap()
+            .with_change_period(Period::Hour);
+        assert_eq!(template.render("bitcoin", &bitcoin(), None), "btc:2545.19");
+        let template = template.with_change_period(Period::Week);
+        assert_eq!(template.render("bitcoin", &bitcoin(), None), "btc↑");
+        assert!("30d".parse::<Period>().is_err());
+    }
+
+    #[test]
+    fn shows_aliases() {
+        let aliases = vec![("bitcoin".to_string(), "BTC".to_string())]
+            .into_iter()
+            .collect();
+        let template = Template::default().with_aliases(aliases);
+        assert_eq!(template.render("bitcoin", &bitcoin(), None), "BTC:2545.19");
+    }
+
+    #[test]
+    fn shows_progress_towards_targets() {
+        let targets = vec![("bitcoin".to_string(), 3000.0)].into_iter().collect();
+        let template: Template = "{short}:{price} {target} {target_bar}".parse().unwrap();
+        assert_eq!(
+            template
+                .with_targets(targets)
+                .render("bitcoin", &bitcoin(), None),
+            "btc:2545.19 [84%→3k] [█████░]→3k"
+        );
+        assert_eq!(progress(60000.0, 50000.0), "[100%→50k]");
+        assert_eq!(compact(1_500_000.0), "1.5M");
+        assert_eq!(compact(1.71e12), "1.71T");
+        assert_eq!(compact(0.25), "0.25");
+
+        let template: Template = "{target}".parse().unwrap();
+        assert_eq!(template.render("bitcoin", &bitcoin(), None), "–");
+    }
+
+    #[test]
+    fn falls_back_on_missing_fields() {
+        let table: HashMap<String, Vec<String>> = vec![
+            (
+                "change_7d".to_string(),
+                vec!["provider".to_string(), "dash".to_string()],
+            ),
+            ("*".to_string(), vec!["null".to_string()]),
+        ]
+        .into_iter()
+        .collect();
+        let template: Template = "{short} {change_7d} {target}".parse().unwrap();
+        let template = template.with_fallbacks(&table).unwrap();
+        let mut ticker = bitcoin();
+        assert!(!template.needs_provider(&ticker));
+        ticker.percent_change_7 = None;
+        assert!(template.needs_provider(&ticker));
+        assert_eq!(template.render("bitcoin", &ticker, None), "btc – null");
+
+        let table = vec![("market_cap".to_string(), vec!["history".to_string()])]
+            .into_iter()
+            .collect();
+        assert!(Template::default().with_fallbacks(&table).is_err());
+    }
+
+    #[test]
+    fn shows_liquidations() {
+        let template: Template = "{short} {liq_long_24h}/{liq_short_24h}".parse().unwrap();
+        assert!(template.needs_liquidations());
+        assert_eq!(template.render("bitcoin", &bitcoin(), None), "btc –/–");
+        let liquidations = vec![(
+            "bitcoin".to_string(),
+            Liquidations {
+                long_usd: 12_500_000.0,
+                short_usd: 800_000.0,
+            },
+        )]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            template
+                .with_liquidations(liquidations)
+                .render("bitcoin", &bitcoin(), None),
+            "btc 12.5M/800k"
+        );
+        assert!(!Template::default().needs_liquidations());
+    }
+
+    #[test]
+    fn counts_down_to_unlocks() {
+        let template: Template = "{short} unlock {next_unlock}".parse().unwrap();
+        assert!(template.needs_unlocks());
+        assert_eq!(template.render("bitcoin", &bitcoin(), None), "btc unlock –");
+        let unlock = Unlock {
+            timestamp: history::now() + 3 * 86_400 + 7200 + 30,
+            amount: None,
+        };
+        let unlocks = vec![("bitcoin".to_string(), vec![unlock])]
+            .into_iter()
+            .collect();
+        assert_eq!(
+            template
+                .with_unlocks(unlocks)
+                .render("bitcoin", &bitcoin(), None),
+            "btc unlock 3d 2h"
+        );
+        assert!(!Template::default().needs_unlocks());
+    }
+
+    #[test]
+    fn shows_onchain_metrics() {
+        let template: Template = "{realized_cap} {active_addresses} {exchange_netflow}"
+            .parse()
+            .unwrap();
+        assert!(template.needs_onchain());
+        assert_eq!(template.render("bitcoin", &bitcoin(), None), "– – –");
+        let onchain = vec![(
+            "bitcoin".to_string(),
+            Metrics {
+                realized_cap: Some(402_000_000_000.0),
+                active_addresses: Some(812_345.0),
+                exchange_netflow: Some(-1_250.0),
+            },
+        )]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            template
+                .with_onchain(onchain)
+                .render("bitcoin", &bitcoin(), None),
+            "402B 812.35k -1.25k"
+        );
+        assert!(!Template::default().needs_onchain());
+    }
+
+    #[test]
+    fn renders_fields_and_braces() {
+        let template: Template = "{{{symbol}}} {price} ({change_24h}%) {price_btc}"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            template.render("bitcoin", &bitcoin(), None),
+            "{BTC} 2545.19 (-3.39%) 1.0"
+        );
+        assert!(!template.needs_history());
+    }
+
+    #[test]
+    fn shows_market_totals() {
+        let template: Template = "{short}:{price} dom {btc_dominance} cap {total_market_cap}"
+            .parse()
+            .unwrap();
+        assert!(template.needs_global());
+        assert_eq!(
+            template.render("bitcoin", &bitcoin(), None),
+            "btc:2545.19 dom – cap –"
+        );
+        let global: Global = serde_json::from_str(
+            r#"{"total_market_cap_usd": 1.71e12, "bitcoin_dominance": 52.314}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            template
+                .with_global(global)
+                .render("bitcoin", &bitcoin(), None),
+            "btc:2545.19 dom 52.31% cap 1.71T"
+        );
+    }
+
+    #[test]
+    fn denominates_prices() {
+        let tickers: Vec<Currency> =
+            serde_json::from_str(include_str!("../tests/fixtures/ticker.json")).unwrap();
+        let mut tiny = tickers[1].clone();
+        tiny.price_btc = Some(Decimal::new(35, 11));
+        let template = Template::default().with_denomination(Denomination::Btc);
+        assert_eq!(template.render("eth", &tickers[1], None), "eth:0.1376");
+        tiny.price_btc = Some(Decimal::new(13_765, 9));
+        assert_eq!(template.render("shib", &tiny, None), "shib:0.00001377");
+        tiny.price_btc = Some(Decimal::new(35, 11));
+        assert_eq!(template.render("shib", &tiny, None), "shib:0.0000000003500");
+        let template = template.with_denomination(Denomination::Sats);
+        assert_eq!(template.render("eth", &tickers[1], None), "eth:13764700");
+        assert_eq!(template.render("shib", &tiny, None), "shib:0.03500");
+        let template = template.with_precision(1);
+        assert_eq!(template.render("shib", &tiny, None), "shib:0.0");
+        let template = Template::default().with_precision(1);
+        assert_eq!(template.render("bitcoin", &bitcoin(), None), "btc:2545.2");
+        tiny.price_btc = None;
+        let template = template.with_denomination(Denomination::Btc);
+        assert_eq!(template.render("shib", &tiny, None), "shib:–");
+    }
+
+    #[test]
+    fn indicators_need_history() {
+        let template: Template = "{short} {sma_1h}".parse().unwrap();
+        assert!(template.needs_history());
+        assert_eq!(template.render("bitcoin", &bitcoin(), None), "btc –");
+
+        let template: Template = "{short} {inflation}".parse().unwrap();
+        assert!(template.needs_history());
+        assert_eq!(template.render("bitcoin", &bitcoin(), None), "btc –");
+
+        let template: Template = "{short} {vs_30d_avg}".parse().unwrap();
+        assert!(template.needs_history());
+        assert_eq!(template.render("bitcoin", &bitcoin(), None), "btc –");
+
+        let template: Template = "{short} {sparkline}".parse().unwrap();
+        assert!(template.needs_history());
+        assert_eq!(template.render("bitcoin", &bitcoin(), None), "btc –");
+    }
+
+    #[test]
+    fn draws_sparklines() {
+        assert_eq!(
+            sparkline(&[100.0, 101.0, 104.0, 107.0, 108.0]).as_deref(),
+            Some("▁▂▅▇█")
+        );
+        assert_eq!(sparkline(&[5.0, 5.0, 5.0]).as_deref(), Some("▁▁▁"));
+        assert_eq!(sparkline(&[5.0]), None);
+    }
+
+    #[test]
+    fn counts_down_to_events() {
+        let template: Template = "{short} {event} in {countdown}".parse().unwrap();
+        assert_eq!(template.render("bitcoin", &bitcoin(), None), "btc – in –");
+        let halving = Event {
+            coin: "bitcoin".to_string(),
+            name: "halving".to_string(),
+            date: "2999-01-01".to_string(),
+            remind: None,
+        };
+        let rendered = template
+            .with_events(&[halving])
+            .render("bitcoin", &bitcoin(), None);
+        assert!(rendered.starts_with("btc halving in "));
+        assert!(rendered.ends_with('h'));
+    }
+
+    #[test]
+    fn pipes_through_functions() {
+        let template: Template = "{short | upper}:{price | round(0) | pad(7)} \
+                                  {change_24h | abs}% {market_cap|humanize}"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            template.render("bitcoin", &bitcoin(), None),
+            "BTC:   2545 3.39% 41.7B"
+        );
+        let template: Template =
+            "{change_24h | color_if(<0, red)} {change_24h | color_if(>0, green)}"
+                .parse()
+                .unwrap();
+        assert_eq!(
+            template.render("bitcoin", &bitcoin(), None),
+            "#[fg=red]-3.39#[default] -3.39"
+        );
+        // nothing to round, and `%` kept
+        assert_eq!(Filter::Round(1).apply("null".to_string()), "null");
+        assert_eq!(Filter::Round(1).apply("52.314%".to_string()), "52.3%");
+        assert_eq!(Filter::Humanize.apply("-1250".to_string()), "-1.25k");
+        let template: Template = "{sma_1h | round(0)}".parse().unwrap();
+        assert!(template.needs_history());
+    }
+
+    #[test]
+    fn switches_formats_by_rule() {
+        let rules = [
+            FormatRule {
+                when: "change_24h < -3".to_string(),
+                format: "💀{short}:{price} {change_24h}%".to_string(),
+            },
+            FormatRule {
+                when: "change_24h<0".to_string(),
+                format: "{short}:{price}↓".to_string(),
+            },
+        ];
+        let template = Template::default().with_rules(&rules).unwrap();
+        assert_eq!(
+            template.render("bitcoin", &bitcoin(), None),
+            "💀btc:2545.19 -3.39%"
+        );
+        let mut calm = bitcoin();
+        calm.percent_change_24 = Some(Decimal::new(-1, 0));
+        assert_eq!(template.render("bitcoin", &calm, None), "btc:2545.19↓");
+        calm.percent_change_24 = None;
+        assert_eq!(template.render("bitcoin", &calm, None), "btc:2545.19");
+
+        let rule = |when: &str, format: &str| {
+            Template::default().with_rules(&[FormatRule {
+                when: when.to_string(),
+                format: format.to_string(),
+            }])
+        };
+        assert!(rule("change_24h < -5", "{short} {sma_1h}")
+            .unwrap()
+            .needs_history());
+        assert!(rule("name < 5", "{short}").is_err());
+        assert!(rule("change_24h", "{short}").is_err());
+        assert!(rule("change_24h < -5", "{prise}").is_err());
+    }
+
+    #[test]
+    fn rejects_bad_templates() {
+        assert!("{price | round}".parse::<Template>().is_err());
+        assert!("{price | round(two)}".parse::<Template>().is_err());
+        assert!("{price | shout}".parse::<Template>().is_err());
+        assert!("{price | color_if(red)}".parse::<Template>().is_err());
+        assert!("{prise}".parse::<Template>().is_err());
+        assert!("{price".parse::<Template>().is_err());
+        assert!("price}".parse::<Template>().is_err());
+    }
+}
@@ -0,0 +1,55 @@
+use std::env;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Turns Termux mode on without Termux's own variables, as `--termux`
+/// does. Background refreshes inherit it through the environment.
+pub const TERMUX_VAR: &str = "CRYPTOTICKER_TERMUX";
+
+/// The notifier Termux:API installs, used when no `alert_command` is
+/// configured.
+pub const NOTIFIER: &str = "termux-notification";
+
+/// How soon interval mode tries again after bridging a dropped
+/// connection, as when a phone hops between wifi and mobile data.
+pub const RECONNECT: Duration = Duration::from_secs(15);
+
+/// Whether cryptoticker runs in Termux, which sets `TERMUX_VERSION` for
+/// everything run from its shell.
+pub fn active() -> bool {
+    env::var_os(TERMUX_VAR).is_some() || env::var_os("TERMUX_VERSION").is_some()
+}
+
+/// Android's cache directory for the Termux app, which the system may
+/// clear when storage runs low. Termux's `$PREFIX` is the app's
+/// `files/usr`, so the cache is two levels up from it.
+pub fn cache_dir() -> Option<PathBuf> {
+    let prefix = PathBuf::from(env::var_os("PREFIX")?);
+    let app = prefix.parent()?.parent()?;
+    Some(app.join("cache").join("cryptoticker"))
+}
+
+/// Whether `command` is Termux's notifier, which takes the message as
+/// `--content` rather than as its only argument.
+pub fn is_notifier(command: &str) -> bool {
+    command.rsplit('/').next() == Some(NOTIFIER)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_app_cache() {
+        env::set_var("PREFIX", "/data/data/com.termux/files/usr");
+        assert_eq!(
+            cache_dir(),
+            Some(PathBuf::from("/data/data/com.termux/cache/cryptoticker"))
+        );
+        env::remove_var("PREFIX");
+        assert!(is_notifier(
+            "/data/data/com.termux/files/usr/bin/termux-notification"
+        ));
+        assert!(!is_notifier("notify-send"));
+    }
+}
@@ -0,0 +1,63 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// What went to the outputs that cost something to write, like the
+/// `--obs-dir` files on an SSD or a `--display` on a slow bus, so a write
+/// that would change nothing is skipped, and changes come at most once
+/// every `--write-every`.
+pub struct Throttle {
+    every: Duration,
+    written: HashMap<String, (String, Instant)>,
+}
+
+impl Throttle {
+    pub fn new(every: Duration) -> Throttle {
+        Throttle {
+            every,
+            written: HashMap::new(),
+        }
+    }
+
+    /// Whether `content` should be written to `target` now, taking it as
+    /// written if so. A change that is held back goes out with the first
+    /// round after the wait that still has one.
+    pub fn due(&mut self, target: &str, content: &str, now: Instant) -> bool {
+        match self.written.get(target) {
+            Some((last, _)) if last == content => false,
+            Some(&(_, at)) if now.duration_since(at) < self.every => false,
+            _ => {
+                self.written
+                    .insert(target.to_string(), (content.to_string(), now));
+                true
+            }
+        }
+    }
+
+    /// Forgets what `target` was last given, after writing it failed, so
+    /// the next round tries again.
+    pub fn forget(&mut self, target: &str) {
+        self.written.remove(target);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_unchanged_and_hurried_writes() {
+        let start = Instant::now();
+        let later = |secs| start + Duration::from_secs(secs);
+        let mut throttle = Throttle::new(Duration::from_secs(10));
+        assert!(throttle.due("bitcoin", "BTC $2545.19", start));
+        assert!(!throttle.due("bitcoin", "BTC $2545.19", later(60)));
+        // each target has its own wait
+        assert!(throttle.due("ethereum", "ETH $348.46", later(1)));
+        assert!(throttle.due("bitcoin", "BTC $2600", later(61)));
+        // too soon after the last, so it waits for a later round
+        assert!(!throttle.due("bitcoin", "BTC $2610", later(65)));
+        assert!(throttle.due("bitcoin", "BTC $2610", later(71)));
+        throttle.forget("bitcoin");
+        assert!(throttle.due("bitcoin", "BTC $2610", later(72)));
+    }
+}
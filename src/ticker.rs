@@ -1,109 +1,853 @@
-use std::io::Read;
-use std::path::PathBuf;
-use std::fs;
+use std::collections::HashMap;
+use std::env;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
 use std::time::Duration;
-use errors::StrError;
-use reqwest;
-use serde_json;
-use app_dirs::*;
+use errors::Error;
+use cache::{Cache, Validators};
+use net::Net;
+use sources::{self, Source};
+use history::History;
+use missing;
+use template::Template;
+use reqwest::header::Headers;
+use reqwest::StatusCode;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use schema::{self, Kind};
+use serde_json::{self, Value};
 
-use super::APP_INFO;
+/// A coin as the ticker API describes it. The API sends its numbers as
+/// strings; they are parsed into exact decimals here, and anything that
+/// isn't a number becomes `None`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Currency {
+    pub id: String,
+    pub name: String,
+    pub symbol: String,
+    pub rank: String,
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Currency {
-    id: String,
-    name: String,
-    symbol: String,
-    rank: String,
+    #[serde(default, with = "decimal")]
+    pub price_usd: Option<Decimal>,
+    #[serde(default, with = "decimal")]
+    pub price_btc: Option<Decimal>,
+
+    #[serde(rename = "24h_volume_usd", default, with = "decimal")]
+    pub volume_usd_24h: Option<Decimal>,
 
-    price_usd: Option<String>,
-    price_btc: Option<String>,
+    #[serde(default, with = "decimal")]
+    pub market_cap_usd: Option<Decimal>,
+    #[serde(default, with = "decimal")]
+    pub available_supply: Option<Decimal>,
+    #[serde(default, with = "decimal")]
+    pub total_supply: Option<Decimal>,
+    #[serde(rename = "percent_change_1h", default, with = "decimal")]
+    pub percent_change_1: Option<Decimal>,
+    #[serde(rename = "percent_change_24h", default, with = "decimal")]
+    pub percent_change_24: Option<Decimal>,
+    #[serde(rename = "percent_change_7d", default, with = "decimal")]
+    pub percent_change_7: Option<Decimal>,
+    pub last_updated: Option<String>,
+    /// the provider it came from, when the config lists several
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
 
-    #[serde(rename = "24h_volume_usd")] volume_usd_24h: Option<String>,
+/// Reads decimals from the API's strings, or from plain JSON numbers, and
+/// writes them back as strings so cache entries look like API answers.
+mod decimal {
+    use rust_decimal::Decimal;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use serde_json::Value;
 
-    market_cap_usd: Option<String>,
-    available_supply: Option<String>,
-    total_supply: Option<String>,
-    percent_change_1: Option<String>,
-    percent_change_24: Option<String>,
-    percent_change_7: Option<String>,
-    last_updated: Option<String>,
+    use super::parse_price;
+
+    pub fn serialize<S: Serializer>(value: &Option<Decimal>, s: S) -> Result<S::Ok, S::Error> {
+        match *value {
+            Some(ref value) => s.serialize_some(&value.to_string()),
+            None => s.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Decimal>, D::Error> {
+        Ok(match Option::<Value>::deserialize(d)? {
+            Some(Value::String(value)) => parse_price(&value),
+            Some(Value::Number(value)) => parse_price(&value.to_string()),
+            _ => None,
+        })
+    }
 }
 
+/// Fetches `name`, sending `validators` from an earlier fetch along.
+/// Returns `None` if the API answers that the ticker hasn't changed since.
 fn fetch_ticker(
     name: &str,
-    cache_file: Option<PathBuf>,
-    debug: bool,
-) -> Result<Currency, StrError> {
-    if debug {
-        println!("retrieving latest for {}", name);
-    }
-    let url = "https://api.coinmarketcap.com/v1/ticker/".to_string() + &name;
-    let mut resp = reqwest::get(url.as_str())?;
-    if !resp.status().is_success() {
-        return Err(format!("Ticker ID {} not valid.", name))?;
+    validators: &Validators,
+    net: &Net,
+) -> Result<Option<(Currency, Validators)>, Error> {
+    net.retry(name, || {
+        debug!("retrieving latest for {}", name);
+        net.chaos.inject(name)?;
+        let url = format!("{}{}", net.api, name);
+        let mut headers = Headers::new();
+        if let Some(ref etag) = validators.etag {
+            headers.set_raw("If-None-Match", vec![etag.clone().into_bytes()]);
+        }
+        if let Some(ref last_modified) = validators.last_modified {
+            headers.set_raw(
+                "If-Modified-Since",
+                vec![last_modified.clone().into_bytes()],
+            );
+        }
+        let resp = net.get_with(url.as_str(), headers)?;
+        if resp.status == StatusCode::NotModified {
+            return Ok(None);
+        }
+        if resp.status.is_server_error() {
+            return Err(Error::Network(format!(
+                "The API answered {} for {}.",
+                resp.status, name
+            )));
+        }
+        if !resp.status.is_success() {
+            return Err(Error::InvalidTicker {
+                name: name.to_string(),
+            });
+        }
+
+        let validators = Validators {
+            etag: resp.header("ETag"),
+            last_modified: resp.header("Last-Modified"),
+        };
+        Ok(Some((parse_ticker(name, &resp.text())?, validators)))
+    })
+}
+
+/// Fetches `name` from the first of `net.sources` that answers, moving
+/// on to the next when one is down, rate limited or garbled, like
+/// `fetch_ticker`. Only the ticker API is sent `validators`; `cache` has
+/// the coin's symbol for the exchanges, which are asked by it.
+fn fetch_from_sources(
+    name: &str,
+    validators: &Validators,
+    cache: Option<&Cache>,
+    net: &Net,
+) -> Result<Option<(Currency, Validators)>, Error> {
+    let (last, rest) = match net.sources.split_last() {
+        Some(split) => split,
+        None => return fetch_ticker(name, validators, net),
+    };
+    let symbol = || {
+        cache
+            .and_then(|cache| cache.backend.get(name))
+            .map(|(ticker, _)| ticker.symbol)
+    };
+    let ask = |source: Source| -> Result<Option<(Currency, Validators)>, Error> {
+        let fetched = match source {
+            Source::Api => fetch_ticker(name, validators, net)?,
+            _ => Some((
+                sources::fetch(source, name, symbol().as_deref(), net)?,
+                Validators::default(),
+            )),
+        };
+        debug!("{} came from {}", name, source.name());
+        Ok(fetched.map(|(mut ticker, validators)| {
+            ticker.source = Some(source.name().to_string());
+            (ticker, validators)
+        }))
+    };
+    for &source in rest {
+        match ask(source) {
+            Err(err) if err.is_failed_fetch() => debug!("{}, asking the next source", err),
+            Err(Error::Other(message)) => debug!("{}, asking the next source", message),
+            answered => return answered,
+        }
     }
+    ask(*last)
+}
 
-    let mut content = String::new();
-    resp.read_to_string(&mut content)?;
+/// Fetches the `limit` highest-ranked coins, best first.
+pub fn fetch_top(limit: u32, net: &Net) -> Result<Vec<Currency>, Error> {
+    net.retry("top list", || {
+        debug!("retrieving the top {}", limit);
+        net.chaos.inject("top list")?;
+        let url = format!("{}?limit={}", net.api, limit);
+        let resp = net.get(url.as_str())?;
+        if !resp.status.is_success() {
+            return Err(Error::Network(format!(
+                "Could not fetch the top {}.",
+                limit
+            )));
+        }
+        parse_tickers("top list", &resp.text())
+    })
+}
 
-    let mut tickers: Vec<Currency> = serde_json::from_str(&content)?;
+/// The fields of `Currency` as the API names them.
+const FIELDS: &[(&str, Kind)] = &[
+    ("id", Kind::Text),
+    ("name", Kind::Text),
+    ("symbol", Kind::Text),
+    ("rank", Kind::Text),
+    ("price_usd", Kind::Number),
+    ("price_btc", Kind::Number),
+    ("24h_volume_usd", Kind::Number),
+    ("market_cap_usd", Kind::Number),
+    ("available_supply", Kind::Number),
+    ("total_supply", Kind::Number),
+    ("percent_change_1h", Kind::Number),
+    ("percent_change_24h", Kind::Number),
+    ("percent_change_7d", Kind::Number),
+    ("last_updated", Kind::OptionalText),
+];
 
-    let ticker = tickers.remove(0);
+/// Reads a list of tickers, riding out the schema drift between API
+/// versions and mirrors instead of failing on it.
+fn parse_tickers(what: &str, content: &str) -> Result<Vec<Currency>, Error> {
+    let mut value = match schema::unwrap_data(what, serde_json::from_str(content)?) {
+        object @ Value::Object(_) => {
+            info!("{}: the answer is a single ticker, not a list", what);
+            Value::Array(vec![object])
+        }
+        value => value,
+    };
+    if let Value::Array(ref mut items) = value {
+        for item in items {
+            if let Value::Object(ref mut object) = *item {
+                schema::tolerate(what, object, FIELDS);
+            }
+        }
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+/// The API answers with a one-element array; anything else is an error
+/// rather than a panic.
+fn parse_ticker(name: &str, content: &str) -> Result<Currency, Error> {
+    let mut tickers = parse_tickers(name, content)?;
+    if tickers.is_empty() {
+        return Err(Error::InvalidTicker {
+            name: name.to_string(),
+        });
+    }
+    let mut ticker = tickers.remove(0);
+    if ticker.id.is_empty() {
+        ticker.id = name.to_string();
+    }
+    Ok(ticker)
+}
 
-    if let Some(cache_file) = cache_file {
-        if debug {
-            println!("{} stored in cache", cache_file.display());
+/// Reads `name` from `cache`, returning the ticker and whether it is
+/// still fresh.
+fn cached_ticker(name: &str, cache: Option<&Cache>) -> Option<(Currency, bool)> {
+    let cache = cache?;
+    let (ticker, age) = cache.backend.get(name)?;
+    match cache.ttl {
+        Some(ttl) if age >= ttl => {
+            debug!(
+                "{} in cache is {} seconds old, cache is cold.",
+                name,
+                age.as_secs()
+            );
+            Some((ticker, false))
+        }
+        Some(ttl) => {
+            debug!(
+                "{} pulled from cache, {} seconds left until cache goes cold.",
+                name,
+                (ttl - age).as_secs()
+            );
+            Some((ticker, true))
+        }
+        None => {
+            debug!("{} pulled from cache, which never goes cold.", name);
+            Some((ticker, true))
         }
-        let file = fs::File::create(cache_file)?;
-        serde_json::to_writer(file, &ticker)?;
     }
+}
 
+/// Fetches `name` from the API and stores it in `cache` if there is one.
+/// A ticker that hasn't changed since it was cached is only revalidated,
+/// not downloaded again.
+/// An offline cache answers with whatever it has instead.
+pub fn refresh_ticker(name: &str, cache: Option<&Cache>, net: &Net) -> Result<Currency, Error> {
+    if let Some(cache) = cache.filter(|cache| cache.offline) {
+        return cached_ticker(name, Some(cache))
+            .map(|(ticker, _)| ticker)
+            .ok_or_else(|| {
+                Error::Network(format!("{} is not cached, and --offline is set.", name))
+            });
+    }
+    let lock = cache.and_then(|cache| cache.backend.lock(name));
+    // another invocation may have refreshed it while we waited for the lock
+    if let (Some(_), Some((ticker, true))) = (lock.as_ref(), cached_ticker(name, cache)) {
+        return Ok(ticker);
+    }
+    let validators = cache.map_or_else(Validators::default, |cache| cache.backend.validators(name));
+    let (ticker, validators) = match fetch_from_sources(name, &validators, cache, net)? {
+        Some(fetched) => fetched,
+        None => match cache.and_then(|cache| cache.backend.get(name)) {
+            Some((ticker, _)) => {
+                debug!("{} has not changed since it was cached", name);
+                (ticker, validators)
+            }
+            // the entry went away while we asked, so ask again for all of it
+            None => fetch_ticker(name, &Validators::default(), net)?
+                .ok_or_else(|| format!("The API answered 304 for {} unprompted.", name))?,
+        },
+    };
+    if let Some(cache) = cache {
+        match cache.backend.put(name, &ticker, &validators) {
+            Ok(()) => {
+                debug!("{} stored in cache", name);
+            }
+            // a cache that can't be written shouldn't cost us the price
+            Err(err) => {
+                debug!("{} could not be cached: {}", name, err);
+            }
+        }
+    }
     Ok(ticker)
 }
 
-pub fn print_ticker(name: String, cache: bool, debug: bool) -> Result<(), StrError> {
-    let cache_dir = app_root(AppDataType::UserCache, &APP_INFO)
-        .expect("Could not find or create the cache directory");
+/// Loads `name` from `cache` if it has a fresh enough copy, otherwise
+/// from the API, falling back to a cold copy if the fetch fails.
+/// Fetching with no cache always goes to the API.
+pub fn load_ticker(name: &str, cache: Option<&Cache>, net: &Net) -> Result<Currency, Error> {
+    match cached_ticker(name, cache) {
+        Some((ticker, true)) => Ok(ticker),
+        cold => refresh_or_fall_back(name, cold, cache, net).map(|(ticker, _)| ticker),
+    }
+}
 
-    let ticker: Currency = if !cache {
-        fetch_ticker(&name, None, debug)?
-    } else {
-        let cache_file = cache_dir.join(format!("{}{}", name, ".json"));
-        let metadata = fs::metadata(&cache_file);
-        match metadata {
-            Ok(metadata) => match metadata.modified().unwrap().elapsed() {
-                Ok(elapsed) if elapsed < Duration::from_secs(1800) => {
-                    if debug {
-                        println!(
-                            "{} pulled from cache, {} seconds left until cache goes cold.",
-                            cache_file.display(),
-                            (Duration::from_secs(1800) - elapsed).as_secs()
-                        );
+/// Refreshes `name`, settling for its `cold` cache entry when the fetch
+/// fails, and returns whether the ticker is fresh. Only a coin with
+/// nothing cached, or one the API says isn't valid, is an error.
+fn refresh_or_fall_back(
+    name: &str,
+    cold: Option<(Currency, bool)>,
+    cache: Option<&Cache>,
+    net: &Net,
+) -> Result<(Currency, bool), Error> {
+    match refresh_ticker(name, cache, net) {
+        Ok(ticker) => Ok((ticker, true)),
+        Err(err) => fall_back(name, err, cold),
+    }
+}
+
+fn fall_back(
+    name: &str,
+    err: Error,
+    cold: Option<(Currency, bool)>,
+) -> Result<(Currency, bool), Error> {
+    match cold {
+        Some((ticker, _)) if err.is_failed_fetch() => {
+            debug!("{}, using the cold cache entry for {}", err, name);
+            Ok((ticker, false))
+        }
+        _ => Err(err),
+    }
+}
+
+/// How many tickers `warm` fetches at once.
+const WARM_THREADS: usize = 8;
+
+/// The pseudo-tickers that take no argument, like `fng`.
+pub const PSEUDO: &[&str] = &["fng", "gas", "week"];
+
+/// Whether `name` is a pseudo-ticker like `news:bitcoin` or `fng`,
+/// shown on the line but not a coin the API or the cache has.
+pub fn is_pseudo(name: &str) -> bool {
+    name.contains(':') || PSEUDO.contains(&name)
+}
+
+/// Fetches the `names` that `cache` has no fresh copy of, several at a
+/// time, so a cold start with a long watchlist doesn't wait on them one
+/// after another. With `stale_ok`, only coins that were never cached are
+/// fetched, since cold ones can be shown right away. `progress` is told
+/// how many are done, out of how many needed fetching, after each one.
+/// Returns the coins that failed, with why, for `fallback_segment`, so
+/// one bad coin is only asked about once and holds up none of the others.
+pub fn warm<F>(
+    names: &[String],
+    cache: &Cache,
+    stale_ok: bool,
+    net: &Net,
+    progress: F,
+) -> HashMap<String, Error>
+where
+    F: Fn(usize, usize) + Sync,
+{
+    // with a TTL of 0, nothing warmed would still be fresh when it's shown
+    if cache.offline || cache.ttl == Some(Duration::from_secs(0)) {
+        return HashMap::new();
+    }
+    let mut cold: Vec<&String> = names
+        .iter()
+        .filter(|name| !is_pseudo(name))
+        .filter(|name| match cached_ticker(name, Some(cache)) {
+            Some((_, fresh)) => !fresh && !stale_ok,
+            None => true,
+        })
+        .collect();
+    cold.sort();
+    cold.dedup();
+    if cold.len() < 2 {
+        return HashMap::new();
+    }
+    let failed = Mutex::new(HashMap::new());
+    let next = AtomicUsize::new(0);
+    let done = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        for _ in 0..WARM_THREADS.min(cold.len()) {
+            scope.spawn(|| {
+                while let Some(name) = cold.get(next.fetch_add(1, Ordering::SeqCst)) {
+                    if let Err(err) = refresh_ticker(name, Some(cache), net) {
+                        debug!("{}", err);
+                        failed.lock().unwrap().insert(name.to_string(), err);
                     }
-                    let file = fs::File::open(cache_file)?;
-                    serde_json::from_reader(file)?
+                    progress(done.fetch_add(1, Ordering::SeqCst) + 1, cold.len());
                 }
-                _ => fetch_ticker(&name, Some(cache_file), debug)?,
-            },
-            _ => fetch_ticker(&name, Some(cache_file), debug)?,
+            });
+        }
+    });
+    failed.into_inner().unwrap()
+}
+
+/// Refreshes `names` in a detached copy of cryptoticker, so the caller
+/// can print stale prices without waiting on the network. The copy
+/// reads the same `config` and tries as hard as `net` says; the cache
+/// directory and API URL reach it through the environment.
+pub fn revalidate_in_background(names: &[String], backend: &str, config: Option<&Path>, net: &Net) {
+    let spawned = env::current_exe().and_then(|exe| {
+        let mut command = Command::new(exe);
+        command
+            .arg("--revalidate")
+            .arg("--cache-backend")
+            .arg(backend)
+            .arg("--retries")
+            .arg(net.retry.attempts.to_string());
+        if let Some(config) = config {
+            command.arg("--config").arg(config);
+        }
+        if let Some(timeout) = net.timeout {
+            command.arg("--timeout").arg(timeout.as_secs().to_string());
+        }
+        command
+            .args(names)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+    });
+    if let Err(err) = spawned {
+        debug!("could not refresh in the background: {}", err);
+    }
+}
+
+pub fn short_name(name: &str) -> String {
+    match name {
+        "ethereum" => "eth".to_string(),
+        "bitcoin" => "btc".to_string(),
+        "litecoin" => "ltc".to_string(),
+        _ => name.to_string(),
+    }
+}
+
+/// Returns the current USD price of `name`, failing if the API has none.
+pub fn usd_price(name: &str, cache: Option<&Cache>, net: &Net) -> Result<Decimal, Error> {
+    let ticker = load_ticker(name, cache, net)?;
+    ticker
+        .price_usd
+        .ok_or_else(|| Error::Other(format!("No USD price available for {}.", name)))
+}
+
+/// Parses a number from the API, like `2545.19` or `1.5e-7`. NaN and
+/// infinities aren't decimals, so they can never satisfy or fail a
+/// comparison by accident.
+pub fn parse_price(price: &str) -> Option<Decimal> {
+    let price = price.trim();
+    price
+        .parse::<Decimal>()
+        .or_else(|_| Decimal::from_scientific(price))
+        .ok()
+}
+
+/// `value` as a float, for the arithmetic that doesn't need to be exact,
+/// like indicators and alert conditions.
+pub fn to_f64(value: Option<Decimal>) -> Option<f64> {
+    value.and_then(|value| value.to_f64())
+}
+
+fn render(name: &str, ticker: &Currency, template: &Template, history: Option<&History>) -> String {
+    format!("{} ", template.render(name, ticker, history))
+}
+
+/// Renders the ticker segment for `name`, returning it with the data it
+/// was rendered from. `history` is only needed for templates with
+/// indicators.
+///
+/// With `stale` given, a cold cache entry is used right away, marked
+/// with `*`, and `name` is pushed onto `stale` to be refreshed later.
+/// An offline cache marks cold entries the same way, as does falling back
+/// to one when the API can't be reached.
+pub fn ticker_segment(
+    name: String,
+    cache: Option<&Cache>,
+    template: &Template,
+    history: Option<&History>,
+    stale: Option<&mut Vec<String>>,
+    net: &Net,
+) -> Result<(Currency, String), Error> {
+    let (ticker, fresh) = match (cached_ticker(&name, cache), stale) {
+        (Some((ticker, true)), _) => (ticker, true),
+        (Some((ticker, false)), Some(stale)) => {
+            stale.push(name.clone());
+            (ticker, false)
         }
+        (Some((ticker, false)), None) if cache.is_some_and(|cache| cache.offline) => {
+            (ticker, false)
+        }
+        (cold, _) => refresh_or_fall_back(&name, cold, cache, net)?,
     };
 
-    let price = ticker.price_usd.unwrap_or("null".to_string());
+    Ok(segment(&name, ticker, fresh, template, history, net))
+}
+
+/// Renders `name` when `warm` couldn't fetch it, from its cold cache
+/// entry if the failure allows, without asking the network again.
+pub fn fallback_segment(
+    name: &str,
+    err: Error,
+    cache: &Cache,
+    template: &Template,
+    history: Option<&History>,
+    net: &Net,
+) -> Result<(Currency, String), Error> {
+    let (ticker, fresh) = fall_back(name, err, cached_ticker(name, Some(cache)))?;
+    Ok(segment(name, ticker, fresh, template, history, net))
+}
+
+/// What `cache` has of `name`, marked `*` however fresh it is, for
+/// `--supervise` to show when the usual way panicked.
+pub fn cached_segment(
+    name: &str,
+    cache: &Cache,
+    template: &Template,
+    history: Option<&History>,
+) -> Option<(Currency, String)> {
+    let (ticker, _) = cache.backend.get(name)?;
+    let segment = format!("{}* ", template.render(name, &ticker, history));
+    Some((ticker, segment))
+}
 
-    let short_name;
-    if name == "ethereum" {
-        short_name = "eth".to_string();
-    } else if name == "bitcoin" {
-        short_name = "btc".to_string();
-    } else if name == "litecoin" {
-        short_name = "ltc".to_string();
+fn segment(
+    name: &str,
+    mut ticker: Currency,
+    fresh: bool,
+    template: &Template,
+    history: Option<&History>,
+    net: &Net,
+) -> (Currency, String) {
+    complete(&mut ticker, template, net);
+    let segment = render(name, &ticker, template, history);
+    let segment = if fresh {
+        segment
     } else {
-        short_name = name;
+        format!("{}* ", segment.trim_end())
+    };
+    (ticker, segment)
+}
+
+/// Asks CoinGecko for the fields `template` shows that `ticker` lacks,
+/// when `[missing]` says to.
+pub fn complete(ticker: &mut Currency, template: &Template, net: &Net) {
+    if template.needs_provider(ticker) {
+        if let Err(err) = missing::fill(ticker, net) {
+            debug!("nothing from CoinGecko for {}: {}", ticker.id, err);
+        }
+    }
+}
+
+/// A `width`-character window onto `line`, scrolled `offset` characters
+/// in, wrapping around with a gap. Lines that already fit don't scroll.
+pub fn marquee(line: &str, width: usize, offset: usize) -> String {
+    let line = line.trim_end();
+    if line.chars().count() <= width {
+        return line.to_string();
+    }
+    let looped: Vec<char> = line.chars().chain("   ".chars()).collect();
+    looped
+        .iter()
+        .cycle()
+        .skip(offset % looped.len())
+        .take(width)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cache::{MemoryCache, Validators};
+    use proptest::prelude::*;
+    use std::time::Duration;
+
+    fn currency(price_usd: Option<Decimal>) -> Currency {
+        Currency {
+            id: "bitcoin".to_string(),
+            name: "Bitcoin".to_string(),
+            symbol: "BTC".to_string(),
+            rank: "1".to_string(),
+            price_usd,
+            price_btc: None,
+            volume_usd_24h: None,
+            market_cap_usd: None,
+            available_supply: None,
+            total_supply: None,
+            percent_change_1: None,
+            percent_change_24: None,
+            percent_change_7: None,
+            last_updated: None,
+            source: None,
+        }
     }
 
-    print!("{}:{} ", short_name, price);
+    #[test]
+    fn empty_response_is_an_error() {
+        assert!(parse_ticker("bitcoin", "[]").is_err());
+    }
+
+    #[test]
+    fn nulls_are_accepted() {
+        let content = r#"[{"id": "bitcoin", "name": "Bitcoin", "symbol": "BTC", "rank": "1",
+            "price_usd": null, "24h_volume_usd": null, "extra": 5}]"#;
+        let ticker = parse_ticker("bitcoin", content).unwrap();
+        assert_eq!(
+            render("bitcoin", &ticker, &Template::default(), None),
+            "btc:– "
+        );
+    }
+
+    #[test]
+    fn non_finite_prices_are_rejected() {
+        for price in &["NaN", "inf", "-inf", "infinity", "", "1e400"] {
+            assert_eq!(parse_price(price), None, "{:?}", price);
+        }
+        assert_eq!(parse_price(" 4200.5 "), Some(Decimal::new(42005, 1)));
+        assert_eq!(parse_price("1.5e-7"), Some(Decimal::new(15, 8)));
+    }
+
+    #[test]
+    fn numbers_keep_their_digits() {
+        let content = r#"[{"id": "bitcoin", "name": "Bitcoin", "symbol": "BTC", "rank": "1",
+            "price_usd": "0.10", "price_btc": 1.0, "percent_change_24h": "soon"}]"#;
+        let ticker = parse_ticker("bitcoin", content).unwrap();
+        assert_eq!(ticker.price_usd.unwrap().to_string(), "0.10");
+        assert_eq!(ticker.price_btc, Some(Decimal::new(1, 0)));
+        assert_eq!(ticker.percent_change_24, None);
+        // written back the way the API sends them
+        let json = serde_json::to_string(&ticker).unwrap();
+        assert!(json.contains(r#""price_usd":"0.10""#), "{}", json);
+    }
+
+    #[test]
+    fn schema_drift_is_tolerated() {
+        let content = r#"{"data": {"name": "Bitcoin", "symbol": "BTC", "rank": 1,
+            "price_usd": 2545.19, "market_cap_usd": {"usd": 1}, "last_updated": 1497225555}}"#;
+        let ticker = parse_ticker("bitcoin", content).unwrap();
+        assert_eq!(ticker.id, "bitcoin");
+        assert_eq!(ticker.rank, "1");
+        assert_eq!(ticker.price_usd, Some(Decimal::new(254519, 2)));
+        assert_eq!(ticker.market_cap_usd, None);
+        assert_eq!(ticker.last_updated.as_deref(), Some("1497225555"));
+    }
+
+    fn fixtures() -> Vec<Currency> {
+        serde_json::from_str(include_str!("../tests/fixtures/ticker.json")).unwrap()
+    }
+
+    #[test]
+    fn tmux_output() {
+        let line: String = fixtures()
+            .iter()
+            .map(|ticker| render(&ticker.id, ticker, &Template::default(), None))
+            .collect();
+        insta::assert_snapshot!(line);
+    }
+
+    #[test]
+    fn marquee_scrolls_long_lines() {
+        assert_eq!(marquee("btc:2545.19 ", 20, 3), "btc:2545.19");
+        assert_eq!(marquee("btc:1 eth:2 ", 6, 0), "btc:1 ");
+        assert_eq!(marquee("btc:1 eth:2 ", 6, 6), "eth:2 ");
+        assert_eq!(marquee("btc:1 eth:2 ", 6, 11), "   btc");
+        assert_eq!(marquee("btc:1 eth:2 ", 6, 14), "btc:1 ");
+    }
+
+    #[test]
+    fn offline_serves_cold_entries() {
+        let cache = Cache {
+            backend: Box::new(MemoryCache::default()),
+            ttl: Some(Duration::from_secs(0)),
+            offline: true,
+        };
+        cache
+            .backend
+            .put("bitcoin", &fixtures()[0], &Validators::default())
+            .unwrap();
+        let net = Net::default();
+        let ticker = refresh_ticker("bitcoin", Some(&cache), &net).unwrap();
+        assert_eq!(ticker.id, "bitcoin");
+        let err = load_ticker("ethereum", Some(&cache), &net).unwrap_err();
+        assert!(matches!(err, Error::Network(_)), "{:?}", err);
+    }
+
+    #[test]
+    fn falls_back_to_cold_entries() {
+        use std::io::Write;
+        use std::net::TcpListener;
+        use std::thread;
+
+        let cache = Cache {
+            backend: Box::new(MemoryCache::default()),
+            ttl: Some(Duration::from_secs(0)),
+            offline: false,
+        };
+        cache
+            .backend
+            .put("bitcoin", &fixtures()[0], &Validators::default())
+            .unwrap();
+        let template = Template::default();
+        // the network is down
+        let mut net = Net::default();
+        net.retry.attempts = 0;
+        net.chaos.failure_rate = 100;
+        let (_, segment) = ticker_segment(
+            "bitcoin".to_string(),
+            Some(&cache),
+            &template,
+            None,
+            None,
+            &net,
+        )
+        .unwrap();
+        assert_eq!(segment, "btc:2545.19* ");
+        let err = ticker_segment(
+            "ethereum".to_string(),
+            Some(&cache),
+            &template,
+            None,
+            None,
+            &net,
+        )
+        .unwrap_err();
+        assert!(err.is_transient(), "{:?}", err);
+
+        // a captive portal answers every request with its sign-in page
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let api = format!("http://{}/", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let _ = stream
+                    .unwrap()
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 13\r\n\r\n<html></html>");
+            }
+        });
+        net.chaos.failure_rate = 0;
+        net.api = Box::leak(api.into_boxed_str());
+        let (_, segment) = ticker_segment(
+            "bitcoin".to_string(),
+            Some(&cache),
+            &template,
+            None,
+            None,
+            &net,
+        )
+        .unwrap();
+        assert_eq!(segment, "btc:2545.19* ");
+    }
+
+    #[test]
+    fn warming_keeps_failures_apart() {
+        let cache = Cache {
+            backend: Box::new(MemoryCache::default()),
+            ttl: Some(Duration::from_secs(60)),
+            offline: false,
+        };
+        let mut cold = fixtures()[0].clone();
+        cold.id = "litecoin".to_string();
+        cache
+            .backend
+            .put("bitcoin", &fixtures()[0], &Validators::default())
+            .unwrap();
+        let mut net = Net::default();
+        net.retry.attempts = 0;
+        net.chaos.failure_rate = 100;
+        let names: Vec<String> = ["bitcoin", "ethereum", "litecoin", "ethereum"]
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+        // bitcoin is fresh, so only the other two are fetched
+        let mut failed = warm(&names, &cache, false, &net, |_, _| {});
+        let mut keys: Vec<&String> = failed.keys().collect();
+        keys.sort();
+        assert_eq!(keys, ["ethereum", "litecoin"]);
+        let template = Template::default();
+        let err = failed.remove("ethereum").unwrap();
+        assert!(fallback_segment("ethereum", err, &cache, &template, None, &net).is_err());
+        // a cold copy stands in for a coin that failed
+        cache
+            .backend
+            .put("litecoin", &cold, &Validators::default())
+            .unwrap();
+        let cache = Cache {
+            ttl: Some(Duration::from_secs(0)),
+            ..cache
+        };
+        let err = failed.remove("litecoin").unwrap();
+        let (ticker, segment) =
+            fallback_segment("litecoin", err, &cache, &template, None, &net).unwrap();
+        assert_eq!(ticker.id, "litecoin");
+        assert_eq!(segment, "ltc:2545.19* ");
+    }
+
+    proptest! {
+        #[test]
+        fn parse_ticker_never_panics(content in ".*") {
+            let _ = parse_ticker("bitcoin", &content);
+        }
 
-    return Ok(());
+        #[test]
+        fn parse_ticker_handles_weird_prices(price in prop::option::of(".*")) {
+            let content = format!(
+                r#"[{{"id": "bitcoin", "name": "Bitcoin", "symbol": "BTC", "rank": "1", "price_usd": {}}}]"#,
+                serde_json::to_string(&price).unwrap()
+            );
+            let ticker = parse_ticker("bitcoin", &content).unwrap();
+            prop_assert_eq!(ticker.price_usd, price.and_then(|price| parse_price(&price)));
+        }
+
+        #[test]
+        fn parse_price_is_stable(price in ".*") {
+            if let Some(price) = parse_price(&price) {
+                prop_assert_eq!(parse_price(&price.to_string()), Some(price));
+            }
+        }
+
+        #[test]
+        fn parse_price_roundtrips(price in "-?[1-9][0-9]{0,14}(\\.[0-9]{1,10})?") {
+            prop_assert_eq!(parse_price(&price).map(|p| p.to_string()), Some(price));
+        }
+
+        #[test]
+        fn render_is_a_single_segment(price in prop::option::of(".*")) {
+            let price = price.and_then(|price| parse_price(&price));
+            let rendered = render("bitcoin", &currency(price), &Template::default(), None);
+            prop_assert!(rendered.starts_with("btc:"));
+            prop_assert!(rendered.ends_with(' '));
+        }
+    }
 }
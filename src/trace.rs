@@ -0,0 +1,50 @@
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use history;
+
+/// Rounds started by this process so far, so two in the same second
+/// still get different IDs.
+static ROUNDS: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// The refresh round running on this thread, if one is.
+    static CURRENT: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// A new refresh ID, like `6710f2a0-3`: when the round started, in hex
+/// seconds, and how many rounds the process had started before it.
+fn next_id(now: i64) -> String {
+    format!("{:x}-{}", now, ROUNDS.fetch_add(1, Ordering::Relaxed))
+}
+
+/// Starts a refresh round on this thread, returning its ID. Every log
+/// line the thread writes until the next round carries it.
+pub fn begin() -> String {
+    let id = next_id(history::now());
+    CURRENT.with(|current| *current.borrow_mut() = Some(id.clone()));
+    id
+}
+
+/// The ID of the refresh round running on this thread.
+pub fn current() -> Option<String> {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn tags_rounds_per_thread() {
+        let first = next_id(0x6710f2a0);
+        let second = next_id(0x6710f2a0);
+        assert!(first.starts_with("6710f2a0-"));
+        assert_ne!(first, second);
+
+        let id = begin();
+        assert_eq!(current(), Some(id));
+        assert_eq!(thread::spawn(current).join().unwrap(), None);
+    }
+}
@@ -0,0 +1,710 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io::{stdout, Stdout, Write};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use arboard::Clipboard;
+use clap::ArgMatches;
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Color, Print, ResetColor, SetAttribute, SetForegroundColor};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use time;
+
+use net::Net;
+use errors::Error;
+use history::History;
+use indicators::Indicator;
+use logo::{self, Protocol};
+use memcache::QuoteCache;
+use pages::{self, Page};
+use rust_decimal::Decimal;
+use symbols::Symbols;
+
+use ticker::{load_ticker, parse_price, to_f64, Currency};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SortKey {
+    Rank,
+    Name,
+    Price,
+    Change,
+    Volume,
+    MarketCap,
+}
+
+impl SortKey {
+    fn next(self) -> SortKey {
+        match self {
+            SortKey::Rank => SortKey::Name,
+            SortKey::Name => SortKey::Price,
+            SortKey::Price => SortKey::Change,
+            SortKey::Change => SortKey::Volume,
+            SortKey::Volume => SortKey::MarketCap,
+            SortKey::MarketCap => SortKey::Rank,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            SortKey::Rank => "rank",
+            SortKey::Name => "name",
+            SortKey::Price => "price",
+            SortKey::Change => "24h change",
+            SortKey::Volume => "volume",
+            SortKey::MarketCap => "market cap",
+        }
+    }
+}
+
+struct Row {
+    name: String,
+    /// the last successful fetch, kept on screen when a refresh fails
+    ticker: Option<Currency>,
+    error: Option<String>,
+    /// one value per `--indicators` column, recomputed on every update
+    indicators: Vec<Option<f64>>,
+}
+
+struct Dashboard<'a> {
+    rows: Vec<Row>,
+    sort: SortKey,
+    descending: bool,
+    selected: usize,
+    /// text typed so far while adding a coin
+    input: Option<String>,
+    /// feedback shown in the footer until the next key press
+    status: Option<String>,
+    /// kept open because on X11 the copied text is only served while the
+    /// clipboard that set it is alive
+    clipboard: Option<Clipboard>,
+    last_refresh: Option<time::Tm>,
+    interval: Duration,
+    cache: Arc<Mutex<QuoteCache>>,
+    /// how to draw logos, if `--logos` was given
+    logos: Option<Protocol>,
+    images: HashMap<String, Vec<u8>>,
+    indicators: Vec<Indicator>,
+    history: Option<History>,
+    /// what `w` opens for the selected coin
+    page: Page,
+    /// for the coins added with `a`, which can be symbols too
+    symbols: &'a Symbols,
+    overrides: &'a HashMap<String, String>,
+}
+
+type Update = (String, Result<Currency, Error>);
+type Logo = (String, Vec<u8>);
+
+/// Restores the terminal however the dashboard exits.
+struct Screen(Stdout);
+
+impl Screen {
+    fn new() -> Result<Screen, Error> {
+        terminal::enable_raw_mode()?;
+        let mut out = stdout();
+        execute!(out, EnterAlternateScreen, Hide)?;
+        Ok(Screen(out))
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = execute!(self.0, Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Runs the full-screen dashboard until the user quits. `w` opens the
+/// selected coin's `page`.
+pub fn tui(
+    matches: &ArgMatches,
+    symbols: &Symbols,
+    overrides: &HashMap<String, String>,
+    page: Page,
+    net: &Net,
+) -> Result<(), Error> {
+    let interval =
+        Duration::from_secs(value_t!(matches, "interval", u64).unwrap_or_else(|e| e.exit()));
+    let names: Vec<String> = matches
+        .values_of("TICKER")
+        .map(|iter| {
+            iter.map(|name| symbols.resolve(name, overrides).to_string())
+                .collect()
+        })
+        .unwrap_or_else(|| vec!["bitcoin".to_string(), "ethereum".to_string()]);
+    let capacity = value_t!(matches, "memory-cache", usize).unwrap_or_else(|e| e.exit());
+    let cache = Arc::new(Mutex::new(QuoteCache::new(capacity, interval)));
+    let logos = matches.value_of("logos").map(Protocol::parse);
+    let indicators = match matches.values_of("indicators") {
+        Some(values) => values
+            .map(|value| value.parse::<Indicator>())
+            .collect::<Result<Vec<_>, _>>()?,
+        None => vec![],
+    };
+    let history = if indicators.is_empty() {
+        None
+    } else {
+        Some(History::open()?)
+    };
+
+    let mut dashboard = Dashboard {
+        rows: names
+            .iter()
+            .map(|name| Row {
+                name: name.clone(),
+                ticker: None,
+                error: None,
+                indicators: vec![],
+            })
+            .collect(),
+        sort: SortKey::Rank,
+        descending: false,
+        selected: 0,
+        input: None,
+        status: None,
+        clipboard: None,
+        last_refresh: None,
+        interval,
+        cache: cache.clone(),
+        logos,
+        images: HashMap::new(),
+        indicators,
+        history,
+        page,
+        symbols,
+        overrides,
+    };
+
+    let (tx, rx) = channel();
+    let (logo_tx, logo_rx) = channel();
+    if logos.is_some() {
+        fetch_logos(names.clone(), net, &logo_tx);
+    }
+    let mut screen = Screen::new()?;
+    let mut next_refresh = Instant::now();
+    let mut dirty = true;
+
+    loop {
+        if Instant::now() >= next_refresh {
+            refresh(
+                dashboard.rows.iter().map(|row| row.name.clone()).collect(),
+                true,
+                net,
+                &cache,
+                &tx,
+            );
+            dashboard.last_refresh = Some(time::now());
+            next_refresh = Instant::now() + interval;
+            dirty = true;
+        }
+        dirty |= dashboard.apply(&rx);
+        dirty |= dashboard.apply_logos(&logo_rx);
+        if dirty {
+            dashboard.draw(&mut screen.0)?;
+            dirty = false;
+        }
+
+        if !event::poll(Duration::from_millis(250))? {
+            continue;
+        }
+        let event = event::read()?;
+        if let Event::Resize(..) = event {
+            dirty = true;
+        }
+        if let Event::Key(key) = event {
+            if key.kind == KeyEventKind::Release {
+                continue;
+            }
+            dirty = true;
+            match dashboard.handle_key(key) {
+                Action::Quit => return Ok(()),
+                Action::Refresh => next_refresh = Instant::now(),
+                Action::Fetch(name) => {
+                    if logos.is_some() {
+                        fetch_logos(vec![name.clone()], net, &logo_tx);
+                    }
+                    refresh(vec![name], false, net, &cache, &tx)
+                }
+                Action::None => {}
+            }
+        }
+    }
+}
+
+enum Action {
+    None,
+    Quit,
+    Refresh,
+    Fetch(String),
+}
+
+/// Fetches in the background so a slow API never freezes the keyboard.
+/// Scheduled refreshes always go to the network; anything else is served
+/// from the memory cache when it can be.
+fn refresh(
+    names: Vec<String>,
+    fresh: bool,
+    net: &Net,
+    cache: &Arc<Mutex<QuoteCache>>,
+    tx: &Sender<Update>,
+) {
+    let net = *net;
+    let cache = cache.clone();
+    let tx = tx.clone();
+    thread::spawn(move || {
+        for name in names {
+            let cached = if fresh {
+                None
+            } else {
+                cache.lock().unwrap().get(&name)
+            };
+            let result = match cached {
+                Some(ticker) => Ok(ticker),
+                None => load_ticker(&name, None, &net),
+            };
+            if let Ok(ref ticker) = result {
+                cache.lock().unwrap().insert(&name, ticker.clone());
+            }
+            if tx.send((name, result)).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// Logos that can't be fetched are simply never sent, leaving those rows
+/// on the text fallback.
+fn fetch_logos(names: Vec<String>, net: &Net, tx: &Sender<Logo>) {
+    let net = *net;
+    let tx = tx.clone();
+    thread::spawn(move || {
+        for name in names {
+            if let Ok(png) = logo::load(&name, &net) {
+                if tx.send((name, png)).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+}
+
+impl<'a> Dashboard<'a> {
+    /// Folds finished fetches into the table, returning whether anything
+    /// changed.
+    fn apply(&mut self, rx: &Receiver<Update>) -> bool {
+        let updates: Vec<Update> = rx.try_iter().collect();
+        if updates.is_empty() {
+            return false;
+        }
+        let selected = self.selected_name();
+        for (name, result) in updates {
+            let indicators = &self.indicators;
+            let history = &self.history;
+            // the coin may have been removed while its fetch was in flight
+            if let Some(row) = self.rows.iter_mut().find(|row| row.name == name) {
+                match result {
+                    Ok(ticker) => {
+                        row.indicators = indicators
+                            .iter()
+                            .map(|indicator| {
+                                history
+                                    .as_ref()
+                                    .and_then(|h| indicator.compute(h, &ticker.id).ok())
+                                    .and_then(|value| value)
+                            })
+                            .collect();
+                        row.ticker = Some(ticker);
+                        row.error = None;
+                    }
+                    Err(err) => row.error = Some(describe(&err)),
+                }
+            }
+        }
+        self.sort_rows();
+        if let Some(selected) = selected {
+            self.select(&selected);
+        }
+        true
+    }
+
+    fn apply_logos(&mut self, rx: &Receiver<Logo>) -> bool {
+        let mut changed = false;
+        for (name, png) in rx.try_iter() {
+            self.images.insert(name, png);
+            changed = true;
+        }
+        changed
+    }
+
+    fn selected_name(&self) -> Option<String> {
+        self.rows.get(self.selected).map(|row| row.name.clone())
+    }
+
+    fn select(&mut self, name: &str) {
+        if let Some(index) = self.rows.iter().position(|row| row.name == name) {
+            self.selected = index;
+        }
+    }
+
+    fn sort_rows(&mut self) {
+        let sort = self.sort;
+        let descending = self.descending;
+        self.rows.sort_by(|a, b| {
+            let ordering = match sort {
+                SortKey::Name => a.name.cmp(&b.name),
+                _ => compare(sort_value(sort, a), sort_value(sort, b)),
+            };
+            if descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> Action {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Action::Quit;
+        }
+
+        if let Some(mut input) = self.input.take() {
+            match key.code {
+                KeyCode::Enter => {
+                    let name = self
+                        .symbols
+                        .resolve(input.trim(), self.overrides)
+                        .to_lowercase();
+                    if !name.is_empty() && !self.rows.iter().any(|row| row.name == name) {
+                        self.rows.push(Row {
+                            name: name.clone(),
+                            ticker: None,
+                            error: None,
+                            indicators: vec![],
+                        });
+                        self.select(&name);
+                        return Action::Fetch(name);
+                    }
+                }
+                KeyCode::Esc => {}
+                KeyCode::Backspace => {
+                    input.pop();
+                    self.input = Some(input);
+                }
+                KeyCode::Char(c) => {
+                    input.push(c);
+                    self.input = Some(input);
+                }
+                _ => self.input = Some(input),
+            }
+            return Action::None;
+        }
+
+        self.status = None;
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Action::Quit,
+            KeyCode::Char('c') => {
+                let price = self
+                    .selected_ticker()
+                    .and_then(|ticker| ticker.price_usd)
+                    .map(|price| price.to_string());
+                self.copy(price)
+            }
+            KeyCode::Char('C') => {
+                let summary = self.selected_ticker().map(summary);
+                self.copy(summary)
+            }
+            KeyCode::Char('i') => {
+                let id = self.selected_ticker().map(|ticker| ticker.id.clone());
+                self.copy(id)
+            }
+            KeyCode::Char('w') => self.browse(),
+            KeyCode::Char('r') => return Action::Refresh,
+            KeyCode::Char('a') => self.input = Some(String::new()),
+            KeyCode::Char('d') | KeyCode::Delete => {
+                if self.selected < self.rows.len() {
+                    self.rows.remove(self.selected);
+                }
+                if self.selected > 0 && self.selected >= self.rows.len() {
+                    self.selected -= 1;
+                }
+            }
+            KeyCode::Char('s') => {
+                let selected = self.selected_name();
+                self.sort = self.sort.next();
+                self.sort_rows();
+                if let Some(selected) = selected {
+                    self.select(&selected);
+                }
+            }
+            KeyCode::Char('o') => {
+                let selected = self.selected_name();
+                self.descending = !self.descending;
+                self.sort_rows();
+                if let Some(selected) = selected {
+                    self.select(&selected);
+                }
+            }
+            KeyCode::Up | KeyCode::Char('k') if self.selected > 0 => self.selected -= 1,
+            KeyCode::Down | KeyCode::Char('j') if self.selected + 1 < self.rows.len() => {
+                self.selected += 1
+            }
+            _ => {}
+        }
+        Action::None
+    }
+
+    fn selected_ticker(&self) -> Option<&Currency> {
+        self.rows
+            .get(self.selected)
+            .and_then(|row| row.ticker.as_ref())
+    }
+
+    /// Opens the selected coin's page, doing nothing when it hasn't
+    /// loaded yet.
+    fn browse(&mut self) {
+        let url = match self.selected_ticker() {
+            Some(ticker) => self.page.url(&ticker.id, &ticker.symbol),
+            None => return,
+        };
+        let opened = url.and_then(|url| pages::browse(&url).map(|()| url));
+        self.status = Some(match opened {
+            Ok(url) => format!("opened {}", url),
+            Err(err) => format!("could not open: {}", err),
+        });
+    }
+
+    /// Copies `text` to the system clipboard, doing nothing when the
+    /// selected coin hasn't loaded yet.
+    fn copy(&mut self, text: Option<String>) {
+        let text = match text {
+            Some(text) => text,
+            None => return,
+        };
+        if self.clipboard.is_none() {
+            self.clipboard = Clipboard::new().ok();
+        }
+        let copied = self
+            .clipboard
+            .as_mut()
+            .ok_or_else(|| "no clipboard available".to_string())
+            .and_then(|clipboard| {
+                clipboard
+                    .set_text(text.clone())
+                    .map_err(|err| err.to_string())
+            });
+        self.status = Some(match copied {
+            Ok(()) => format!("copied {}", text),
+            Err(err) => format!("could not copy: {}", err),
+        });
+    }
+
+    fn draw(&self, out: &mut Stdout) -> Result<(), Error> {
+        let (width, height) = terminal::size()?;
+        queue!(out, Clear(ClearType::All), MoveTo(0, 0))?;
+
+        let refreshed = self
+            .last_refresh
+            .map(|tm| time::strftime("%H:%M:%S", &tm).unwrap_or_default())
+            .unwrap_or_else(|| "never".to_string());
+        let order = if self.descending { "desc" } else { "asc" };
+        let stats = self.cache.lock().unwrap().stats();
+        let gutter = if self.logos.is_some() { "   " } else { "" };
+        queue!(
+            out,
+            SetAttribute(Attribute::Bold),
+            Print(format!(
+                "cryptoticker  refreshed {} (every {}s)  sorted by {} {}  cache {}/{} hits",
+                refreshed,
+                self.interval.as_secs(),
+                self.sort.label(),
+                order,
+                stats.hits,
+                stats.hits + stats.misses
+            )),
+            SetAttribute(Attribute::Reset),
+            MoveTo(0, 2),
+            SetAttribute(Attribute::Underlined),
+            Print(format!(
+                "{}{:>4}  {:<20} {:<6} {:>14} {:>9} {:>10} {:>10}",
+                gutter, "#", "COIN", "SYMBOL", "PRICE (USD)", "24H", "VOLUME", "MKT CAP"
+            )),
+            Print(
+                self.indicators
+                    .iter()
+                    .map(|indicator| format!(" {:>10}", indicator.to_string().to_uppercase()))
+                    .collect::<String>()
+            ),
+            SetAttribute(Attribute::Reset)
+        )?;
+
+        let visible = (height as usize).saturating_sub(5);
+        let first = if self.selected >= visible {
+            self.selected + 1 - visible
+        } else {
+            0
+        };
+        for (index, row) in self.rows.iter().enumerate().skip(first).take(visible) {
+            queue!(out, MoveTo(0, (3 + index - first) as u16))?;
+            if index == self.selected {
+                queue!(out, SetAttribute(Attribute::Reverse))?;
+            }
+            self.draw_row(out, row)?;
+            queue!(out, SetAttribute(Attribute::Reset), ResetColor)?;
+        }
+
+        let footer = match (self.input.as_ref(), self.status.as_ref()) {
+            (Some(input), _) => format!("add coin: {}_", input),
+            (None, Some(status)) => status.clone(),
+            (None, None) => "[a]dd  [d]elete  [r]efresh  [s]ort column  [o]rder  \
+                             [c]opy price  [C] summary  [i]d  [w]eb page  [q]uit"
+                .to_string(),
+        };
+        let footer: String = footer.chars().take(width as usize).collect();
+        queue!(out, MoveTo(0, height.saturating_sub(1)), Print(footer))?;
+        out.flush()?;
+        Ok(())
+    }
+
+    fn draw_row(&self, out: &mut Stdout, row: &Row) -> Result<(), Error> {
+        if let Some(protocol) = self.logos {
+            let symbol = row
+                .ticker
+                .as_ref()
+                .map(|ticker| ticker.symbol.as_str())
+                .unwrap_or("");
+            let png = self.images.get(&row.name).map(|png| png.as_slice());
+            queue!(out, Print(protocol.render(png, symbol)), Print(" "))?;
+        }
+        let ticker = match row.ticker {
+            Some(ref ticker) => ticker,
+            None => {
+                let status = match row.error {
+                    Some(ref err) => err.clone(),
+                    None => "loading...".to_string(),
+                };
+                queue!(
+                    out,
+                    Print(format!("{:>4}  {:<20} {}", "", row.name, status))
+                )?;
+                return Ok(());
+            }
+        };
+
+        queue!(
+            out,
+            Print(format!(
+                "{:>4}  {:<20} {:<6} {:>14} ",
+                ticker.rank,
+                row.name,
+                ticker.symbol,
+                ticker.price_usd.map_or("-".to_string(), |p| p.to_string())
+            ))
+        )?;
+
+        match to_f64(ticker.percent_change_24) {
+            Some(change) => {
+                let color = if change < 0.0 {
+                    Color::Red
+                } else {
+                    Color::Green
+                };
+                queue!(
+                    out,
+                    SetForegroundColor(color),
+                    Print(format!("{:>+8.2}%", change)),
+                    ResetColor
+                )?;
+            }
+            None => queue!(out, Print(format!("{:>9}", "-")))?,
+        }
+        queue!(
+            out,
+            Print(format!(
+                " {:>10} {:>10}",
+                humanize(ticker.volume_usd_24h),
+                humanize(ticker.market_cap_usd)
+            ))
+        )?;
+        for value in &row.indicators {
+            match *value {
+                Some(value) => queue!(out, Print(format!(" {:>10.2}", value)))?,
+                None => queue!(out, Print(format!(" {:>10}", "-")))?,
+            }
+        }
+        if let Some(ref err) = row.error {
+            queue!(
+                out,
+                SetForegroundColor(Color::DarkGrey),
+                Print(format!("  stale: {}", err))
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A one-line description for pasting into chats and notes.
+fn summary(ticker: &Currency) -> String {
+    let mut summary = format!(
+        "{} ({}) ${}",
+        ticker.name,
+        ticker.symbol,
+        ticker.price_usd.map_or("-".to_string(), |p| p.to_string())
+    );
+    if let Some(change) = ticker.percent_change_24 {
+        summary.push_str(&format!(", {}% 24h", change));
+    }
+    summary.push_str(&format!(", rank #{}", ticker.rank));
+    summary
+}
+
+fn sort_value(sort: SortKey, row: &Row) -> Option<Decimal> {
+    let ticker = match row.ticker {
+        Some(ref ticker) => ticker,
+        None => return None,
+    };
+    match sort {
+        SortKey::Rank => parse_price(&ticker.rank),
+        SortKey::Price => ticker.price_usd,
+        SortKey::Change => ticker.percent_change_24,
+        SortKey::Volume => ticker.volume_usd_24h,
+        SortKey::MarketCap => ticker.market_cap_usd,
+        SortKey::Name => None,
+    }
+}
+
+/// Orders missing values after present ones.
+fn compare(a: Option<Decimal>, b: Option<Decimal>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+fn humanize(value: Option<Decimal>) -> String {
+    let value = match to_f64(value) {
+        Some(value) => value,
+        None => return "-".to_string(),
+    };
+    let units = [(1e12, "T"), (1e9, "B"), (1e6, "M"), (1e3, "K")];
+    for &(scale, unit) in &units {
+        if value.abs() >= scale {
+            return format!("{:.2}{}", value / scale, unit);
+        }
+    }
+    format!("{:.2}", value)
+}
+
+fn describe(err: &Error) -> String {
+    match *err {
+        // reqwest errors are multi-line debug dumps
+        Error::Network(_) => "network error".to_string(),
+        _ => err.to_string().lines().next().unwrap_or("").to_string(),
+    }
+}
@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use serde_json::{self, Value};
+
+use dirs;
+use errors::Error;
+use history;
+use net::Net;
+
+/// DefiLlama's token emission schedules, every tracked coin in one answer.
+pub const DEFAULT_URL: &str = "https://api.llama.fi/emissions";
+
+/// How long fetched schedules are used before asking again. They change
+/// when a project announces something, not by the minute.
+const REFRESH: i64 = 6 * 60 * 60;
+
+/// Tokens of a coin released from vesting at once, which often moves its
+/// price as insiders and early investors get to sell.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Unlock {
+    pub timestamp: i64,
+    /// how many tokens are released, if the schedule says
+    pub amount: Option<f64>,
+}
+
+/// Fetched schedules, kept in the cache directory.
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    fetched: i64,
+    unlocks: HashMap<String, Vec<Unlock>>,
+}
+
+/// How many tokens an event releases: `noOfTokens` lists them by
+/// category, like investors and the team.
+fn tokens(event: &Value) -> Option<f64> {
+    let amounts = event["noOfTokens"].as_array()?;
+    Some(amounts.iter().filter_map(Value::as_f64).sum())
+}
+
+/// Reads each coin's unlocks, by CoinGecko ID, from the schedules. A
+/// schedule without its events listed has only its next one.
+fn parse(content: &str) -> Result<HashMap<String, Vec<Unlock>>, Error> {
+    let schedules: Vec<Value> = serde_json::from_str(content)?;
+    let mut unlocks = HashMap::new();
+    for schedule in &schedules {
+        let coin = match schedule["gecko_id"].as_str() {
+            Some(coin) if !coin.is_empty() => coin,
+            _ => continue,
+        };
+        let mut found: Vec<Unlock> = match schedule["events"].as_array() {
+            Some(events) => events
+                .iter()
+                .filter_map(|event| {
+                    Some(Unlock {
+                        timestamp: event["timestamp"].as_i64()?,
+                        amount: tokens(event),
+                    })
+                })
+                .collect(),
+            None => schedule["nextEvent"]["date"]
+                .as_i64()
+                .map(|timestamp| Unlock {
+                    timestamp,
+                    amount: schedule["nextEvent"]["toUnlock"].as_f64(),
+                })
+                .into_iter()
+                .collect(),
+        };
+        found.sort_by_key(|unlock| unlock.timestamp);
+        if !found.is_empty() {
+            unlocks.insert(coin.to_string(), found);
+        }
+    }
+    Ok(unlocks)
+}
+
+fn fetch(url: &str, net: &Net) -> Result<HashMap<String, Vec<Unlock>>, Error> {
+    debug!("retrieving {}", url);
+    let resp = net.get(url)?;
+    if !resp.status.is_success() {
+        return Err(Error::Network(format!("{} answered {}", url, resp.status)));
+    }
+    parse(&resp.text())
+}
+
+fn path() -> Result<PathBuf, Error> {
+    Ok(dirs::cache_dir()?.join("unlocks.json"))
+}
+
+/// Every coin's unlocks from `url`, DefiLlama's by default, from the
+/// cache directory if they were fetched recently. A failed fetch falls
+/// back to the older schedules, and offline there are only ever those.
+pub fn load(
+    url: Option<&str>,
+    offline: bool,
+    net: &Net,
+) -> Result<HashMap<String, Vec<Unlock>>, Error> {
+    let path = path()?;
+    let cached: Option<Entry> = fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok());
+    match cached {
+        Some(entry) if offline || history::now() - entry.fetched < REFRESH => {
+            return Ok(entry.unlocks)
+        }
+        None if offline => {
+            return Err(Error::Network(
+                "no token unlocks are cached, and --offline is set".to_string(),
+            ))
+        }
+        _ => {}
+    }
+    match fetch(url.unwrap_or(DEFAULT_URL), net) {
+        Ok(unlocks) => {
+            let entry = Entry {
+                fetched: history::now(),
+                unlocks,
+            };
+            fs::write(&path, serde_json::to_string(&entry)?)?;
+            Ok(entry.unlocks)
+        }
+        Err(err) => match cached {
+            Some(entry) => {
+                debug!("{}, using older token unlocks", err);
+                Ok(entry.unlocks)
+            }
+            None => Err(err),
+        },
+    }
+}
+
+/// The soonest unlock of `coin` that hasn't happened yet, with the
+/// seconds left until it.
+pub fn next(unlocks: &HashMap<String, Vec<Unlock>>, coin: &str, now: i64) -> Option<(Unlock, i64)> {
+    unlocks
+        .get(coin)?
+        .iter()
+        .find(|unlock| unlock.timestamp > now)
+        .map(|&unlock| (unlock, unlock.timestamp - now))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_emission_schedules() {
+        let content = r#"[
+            {"name": "Arbitrum", "gecko_id": "arbitrum", "events": [
+                {"timestamp": 3000, "noOfTokens": [60000000, 32650000]},
+                {"timestamp": 1000, "noOfTokens": [92650000]}
+            ]},
+            {"name": "Aptos", "gecko_id": "aptos", "nextEvent": {"date": 2000, "toUnlock": 11310000}},
+            {"name": "Unlisted", "gecko_id": null, "events": [{"timestamp": 1000, "noOfTokens": [1]}]},
+            {"name": "Done", "gecko_id": "done", "events": []}
+        ]"#;
+        let unlocks = parse(content).unwrap();
+        assert_eq!(unlocks.len(), 2);
+        assert_eq!(
+            next(&unlocks, "arbitrum", 1500),
+            Some((
+                Unlock {
+                    timestamp: 3000,
+                    amount: Some(92_650_000.0),
+                },
+                1500
+            ))
+        );
+        assert_eq!(next(&unlocks, "arbitrum", 999).unwrap().1, 1);
+        assert_eq!(
+            next(&unlocks, "aptos", 0).unwrap().0.amount,
+            Some(11_310_000.0)
+        );
+        assert_eq!(next(&unlocks, "aptos", 2000), None);
+        assert_eq!(next(&unlocks, "bitcoin", 0), None);
+        assert!(parse(r#"{"message": "Internal server error"}"#).is_err());
+    }
+}
@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use clap::ArgMatches;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+
+use errors::Error;
+use net::Net;
+use condition::Comparison;
+use symbols::Symbols;
+use ticker::{short_name, usd_price};
+
+/// Polls a ticker until its price satisfies the condition given on the
+/// command line, returning the process exit code: 0 once the condition
+/// holds, 1 if the timeout ran out first, and like `--exit-if`, 4 for a
+/// ticker that doesn't exist and 5 for any other error that asking again
+/// won't fix.
+pub fn wait(
+    matches: &ArgMatches,
+    symbols: &Symbols,
+    overrides: &HashMap<String, String>,
+    net: &Net,
+) -> i32 {
+    let name = symbols.resolve(matches.value_of("TICKER").unwrap(), overrides);
+    let comparison = Comparison::parse(matches.value_of("OPERATOR").unwrap()).unwrap();
+    let threshold = value_t!(matches, "PRICE", f64).unwrap_or_else(|e| e.exit());
+    let interval =
+        Duration::from_secs(value_t!(matches, "interval", u64).unwrap_or_else(|e| e.exit()));
+    let timeout = if matches.is_present("timeout") {
+        Some(Duration::from_secs(
+            value_t!(matches, "timeout", u64).unwrap_or_else(|e| e.exit()),
+        ))
+    } else {
+        None
+    };
+
+    match until(name, comparison, threshold, interval, timeout, net) {
+        Ok(Some(price)) => {
+            println!("{}:{}", short_name(name), price);
+            0
+        }
+        Ok(None) => {
+            eprintln!(
+                "timed out waiting for {} {} {}",
+                name, comparison, threshold
+            );
+            1
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            match err {
+                Error::InvalidTicker { .. } => 4,
+                _ => 5,
+            }
+        }
+    }
+}
+
+/// Fetches `name` every `interval` until its price is `comparison`
+/// `threshold`, returning the price, or `None` once `timeout` runs out.
+/// Network trouble is waited out; any other error ends the wait.
+pub fn until(
+    name: &str,
+    comparison: Comparison,
+    threshold: f64,
+    interval: Duration,
+    timeout: Option<Duration>,
+    net: &Net,
+) -> Result<Option<Decimal>, Error> {
+    let start = Instant::now();
+    loop {
+        match usd_price(name, None, net) {
+            Ok(price) => {
+                if price
+                    .to_f64()
+                    .is_some_and(|value| comparison.holds(value, threshold))
+                {
+                    return Ok(Some(price));
+                }
+                debug!(
+                    "{} is {}, waiting for {} {}",
+                    name, price, comparison, threshold
+                );
+            }
+            Err(ref err) if err.is_transient() => {
+                debug!("{}", err);
+            }
+            Err(err) => return Err(err),
+        }
+
+        let mut pause = interval;
+        if let Some(timeout) = timeout {
+            let elapsed = start.elapsed();
+            if elapsed >= timeout {
+                return Ok(None);
+            }
+            if timeout - elapsed < pause {
+                pause = timeout - elapsed;
+            }
+        }
+        sleep(pause);
+    }
+}
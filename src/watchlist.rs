@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use alerts::Rule;
+use dirs;
+use errors::Error;
+
+/// One line of a watchlist: a coin and whatever was set for it, like
+/// `bitcoin alias=BTC above=70000 below=50000`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub coin: String,
+    /// shown by `{short}` instead of the usual short name
+    pub alias: Option<String>,
+    /// alert when the price rises above this
+    pub above: Option<f64>,
+    /// alert when the price falls below this
+    pub below: Option<f64>,
+    /// show progress towards this price, and alert when it gets there
+    pub target: Option<f64>,
+}
+
+/// Reads one coin per line. Blank lines and everything after a `#` are
+/// ignored.
+pub fn parse(content: &str) -> Result<Vec<Entry>, String> {
+    let mut entries = Vec::new();
+    for (number, line) in content.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("");
+        let mut words = line.split_whitespace();
+        let coin = match words.next() {
+            Some(coin) => coin,
+            None => continue,
+        };
+        let mut entry = Entry {
+            coin: coin.to_string(),
+            alias: None,
+            above: None,
+            below: None,
+            target: None,
+        };
+        for word in words {
+            let fail = |why: &str| format!("line {}: {} {:?}", number + 1, why, word);
+            let (key, value) = match word.find('=') {
+                Some(at) => (&word[..at], &word[at + 1..]),
+                None => return Err(fail("expected key=value, not")),
+            };
+            let threshold = || value.parse::<f64>().map_err(|_| fail("not a price:"));
+            match key {
+                "alias" => entry.alias = Some(value.to_string()),
+                "above" => entry.above = Some(threshold()?),
+                "below" => entry.below = Some(threshold()?),
+                "target" => entry.target = Some(threshold()?),
+                _ => return Err(fail("unknown setting")),
+            }
+        }
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// The `above`, `below` and `target` thresholds as price alerts.
+pub fn rules(entries: &[Entry]) -> Vec<Rule> {
+    let mut rules = Vec::new();
+    for entry in entries {
+        let thresholds = [(">", entry.above), ("<", entry.below), (">=", entry.target)];
+        for &(op, threshold) in &thresholds {
+            if let Some(threshold) = threshold {
+                rules.push(Rule::Price {
+                    coin: entry.coin.clone(),
+                    condition: format!("price{}{}", op, threshold),
+                    cooldown: None,
+                    rearm: None,
+                });
+            }
+        }
+    }
+    rules
+}
+
+/// The aliases set, by coin.
+pub fn aliases(entries: &[Entry]) -> HashMap<String, String> {
+    entries
+        .iter()
+        .filter_map(|entry| Some((entry.coin.clone(), entry.alias.clone()?)))
+        .collect()
+}
+
+/// The targets set, by coin.
+pub fn targets(entries: &[Entry]) -> HashMap<String, f64> {
+    entries
+        .iter()
+        .filter_map(|entry| Some((entry.coin.clone(), entry.target?)))
+        .collect()
+}
+
+/// `watchlist` in the config directory, used when no tickers are given.
+pub fn default_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("watchlist"))
+}
+
+/// A watchlist file, read again whenever it is asked for, so it can be
+/// edited while interval mode runs.
+pub struct Watchlist {
+    path: PathBuf,
+    content: Option<String>,
+}
+
+impl Watchlist {
+    pub fn new(path: &Path) -> Watchlist {
+        Watchlist {
+            path: path.to_path_buf(),
+            content: None,
+        }
+    }
+
+    /// The entries, if the file changed since it was last read.
+    pub fn reload(&mut self) -> Result<Option<Vec<Entry>>, Error> {
+        let content = fs::read_to_string(&self.path)
+            .map_err(|err| format!("Could not read {}: {}", self.path.display(), err))?;
+        if self.content.as_ref() == Some(&content) {
+            return Ok(None);
+        }
+        let entries = parse(&content)
+            .map_err(|err| Error::Parse(format!("{}: {}", self.path.display(), err)))?;
+        self.content = Some(content);
+        Ok(Some(entries))
+    }
+
+    /// Has the next `reload` return the entries even if the file hasn't
+    /// changed.
+    pub fn forget(&mut self) {
+        self.content = None;
+    }
+
+    /// Whether the file has been read successfully at least once.
+    pub fn loaded(&self) -> bool {
+        self.content.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_entries() {
+        let entries =
+            parse("# coins\nbitcoin alias=BTC above=70000 below=50000\n\n  ethereum target=5000 # the rest\n")
+                .unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                Entry {
+                    coin: "bitcoin".to_string(),
+                    alias: Some("BTC".to_string()),
+                    above: Some(70000.0),
+                    below: Some(50000.0),
+                    target: None,
+                },
+                Entry {
+                    coin: "ethereum".to_string(),
+                    alias: None,
+                    above: None,
+                    below: None,
+                    target: Some(5000.0),
+                },
+            ]
+        );
+        assert_eq!(aliases(&entries)["bitcoin"], "BTC");
+        assert_eq!(targets(&entries)["ethereum"], 5000.0);
+        assert_eq!(
+            rules(&entries)[1],
+            Rule::Price {
+                coin: "bitcoin".to_string(),
+                condition: "price<50000".to_string(),
+                cooldown: None,
+                rearm: None,
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_bad_settings() {
+        assert!(parse("bitcoin above=soon")
+            .unwrap_err()
+            .starts_with("line 1:"));
+        assert!(parse("bitcoin\nethereum color=blue")
+            .unwrap_err()
+            .starts_with("line 2:"));
+        assert!(parse("bitcoin BTC").is_err());
+    }
+
+    #[test]
+    fn reloads_only_changes() {
+        let path = ::std::env::temp_dir().join(format!("watchlist-{}", ::std::process::id()));
+        fs::write(&path, "bitcoin\n").unwrap();
+        let mut watchlist = Watchlist::new(&path);
+        assert!(!watchlist.loaded());
+        assert_eq!(watchlist.reload().unwrap().unwrap().len(), 1);
+        assert_eq!(watchlist.reload().unwrap(), None);
+        fs::write(&path, "bitcoin\nethereum\n").unwrap();
+        assert_eq!(watchlist.reload().unwrap().unwrap().len(), 2);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,103 @@
+use errors::Error;
+use history::{self, History};
+use ticker::{is_pseudo, short_name};
+
+const WEEK: i64 = 7 * 86_400;
+
+/// A week's change says little until the history spans a day of it.
+const MIN_SPAN: i64 = 86_400;
+
+/// How long the `week` pseudo-ticker shows the best coin before it turns
+/// to the worst, by the clock, so one-shot runs alternate too.
+const TURN: i64 = 60;
+
+/// The change in percent from the first to the last price in `prices`,
+/// `(timestamp, price)` oldest first, if they span at least a day.
+fn change(prices: &[(i64, f64)]) -> Option<f64> {
+    let &(start, first) = prices.first()?;
+    let &(end, last) = prices.last()?;
+    if end - start < MIN_SPAN || first <= 0.0 {
+        return None;
+    }
+    Some((last / first - 1.0) * 100.0)
+}
+
+/// How each of `coins` did over the week before `now`, leaving out the
+/// ones without enough history.
+fn changes(history: &History, coins: &[String], now: i64) -> Result<Vec<(String, f64)>, Error> {
+    let mut changes = Vec::new();
+    for coin in coins.iter().filter(|coin| !is_pseudo(coin)) {
+        let prices: Vec<(i64, f64)> = history
+            .samples(coin, now - WEEK)?
+            .iter()
+            .filter_map(|sample| sample.price_usd.map(|price| (sample.timestamp, price)))
+            .collect();
+        if let Some(change) = change(&prices) {
+            changes.push((coin.clone(), change));
+        }
+    }
+    Ok(changes)
+}
+
+/// Like `week best eth +12.3%`, or the worst on every other turn of
+/// the clock at `now`.
+fn summary(changes: &[(String, f64)], now: i64) -> Option<String> {
+    let by_change = |a: &&(String, f64), b: &&(String, f64)| a.1.total_cmp(&b.1);
+    let (label, &(ref coin, change)) = if (now / TURN) % 2 == 0 {
+        ("best", changes.iter().max_by(by_change)?)
+    } else {
+        ("worst", changes.iter().min_by(by_change)?)
+    };
+    Some(format!(
+        "week {} {} {:+.1}%",
+        label,
+        short_name(coin),
+        change
+    ))
+}
+
+/// The `week` pseudo-ticker: the best or worst of `coins` over the last
+/// week, from the history interval mode records.
+pub fn segment(history: Option<&History>, coins: &[String]) -> Result<String, Error> {
+    let opened;
+    let history = match history {
+        Some(history) => history,
+        None => {
+            opened = History::open()?;
+            &opened
+        }
+    };
+    let now = history::now();
+    summary(&changes(history, coins, now)?, now).ok_or_else(|| {
+        Error::Other("No coin has a day of history yet; interval mode records it.".to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_the_best_and_worst() {
+        let day = 86_400;
+        assert_eq!(
+            change(&[(0, 100.0), (day, 112.0)]).map(f64::round),
+            Some(12.0)
+        );
+        assert_eq!(change(&[(0, 100.0), (3600, 112.0)]), None);
+        let changes = vec![
+            ("ethereum".to_string(), 12.34),
+            ("dogecoin".to_string(), -8.06),
+            ("bitcoin".to_string(), 1.5),
+        ];
+        assert_eq!(
+            summary(&changes, 0).as_deref(),
+            Some("week best eth +12.3%")
+        );
+        assert_eq!(
+            summary(&changes, TURN).as_deref(),
+            Some("week worst dogecoin -8.1%")
+        );
+        assert_eq!(summary(&[], 0), None);
+    }
+}
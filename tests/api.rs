@@ -0,0 +1,198 @@
+//! Runs the fetch, cache and render path end to end against a mock API
+//! on localhost.
+
+extern crate cryptoticker;
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use cryptoticker::cache::{MemoryCache, Validators};
+use cryptoticker::condition::Comparison;
+use cryptoticker::net::Retry;
+use cryptoticker::ticker::{load_ticker, refresh_ticker, ticker_segment, warm};
+use cryptoticker::wait;
+use cryptoticker::{Cache, Error, Net, Template};
+
+const TICKER: &str = include_str!("fixtures/ticker.json");
+
+/// A mock API answering every request with the next of `responses`,
+/// the last one over and over once they run out.
+struct Mock {
+    net: Net,
+    hits: Arc<AtomicUsize>,
+}
+
+fn mock(responses: Vec<(u16, &'static str)>) -> Mock {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/v1/ticker/", listener.local_addr().unwrap());
+    let hits = Arc::new(AtomicUsize::new(0));
+    let counter = hits.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap() == 0 || line.trim().is_empty() {
+                    break;
+                }
+            }
+            let hit = counter.fetch_add(1, Ordering::SeqCst);
+            let (status, body) = responses[hit.min(responses.len() - 1)];
+            let _ = write!(
+                &stream,
+                "HTTP/1.1 {} Mock\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+        }
+    });
+    Mock {
+        net: Net {
+            retry: Retry {
+                attempts: 0,
+                ..Retry::default()
+            },
+            api: Box::leak(url.into_boxed_str()),
+            ..Net::default()
+        },
+        hits,
+    }
+}
+
+fn cache(ttl: Option<Duration>) -> Cache {
+    Cache {
+        backend: Box::new(MemoryCache::default()),
+        ttl,
+        offline: false,
+    }
+}
+
+#[test]
+fn deserializes_tickers() {
+    let api = mock(vec![(200, TICKER)]);
+    let ticker = refresh_ticker("bitcoin", None, &api.net).unwrap();
+    assert_eq!(ticker.id, "bitcoin");
+    assert_eq!(ticker.symbol, "BTC");
+    assert_eq!(ticker.price_usd.unwrap().to_string(), "2545.19");
+    assert_eq!(ticker.volume_usd_24h.unwrap().to_string(), "1286430000.0");
+    assert_eq!(ticker.percent_change_24.unwrap().to_string(), "-3.39");
+}
+
+#[test]
+fn serves_from_the_cache_until_it_expires() {
+    let api = mock(vec![(200, TICKER)]);
+    let warm = cache(Some(Duration::from_secs(3600)));
+    load_ticker("bitcoin", Some(&warm), &api.net).unwrap();
+    load_ticker("bitcoin", Some(&warm), &api.net).unwrap();
+    assert_eq!(api.hits.load(Ordering::SeqCst), 1);
+
+    let expired = cache(Some(Duration::from_secs(0)));
+    load_ticker("bitcoin", Some(&expired), &api.net).unwrap();
+    load_ticker("bitcoin", Some(&expired), &api.net).unwrap();
+    assert_eq!(api.hits.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn falls_back_to_cold_entries_when_the_api_fails() {
+    let api = mock(vec![(503, "down for maintenance")]);
+    let cold = cache(Some(Duration::from_secs(0)));
+    let ticker = refresh_ticker(
+        "bitcoin",
+        None,
+        &mock(vec![(200, TICKER)]).net,
+    )
+    .unwrap();
+    cold.backend
+        .put("bitcoin", &ticker, &Validators::default())
+        .unwrap();
+
+    let (_, segment) = ticker_segment(
+        "bitcoin".to_string(),
+        Some(&cold),
+        &Template::default(),
+        None,
+        None,
+        &api.net,
+    )
+    .unwrap();
+    assert_eq!(segment, "btc:2545.19* ");
+
+    let err = refresh_ticker("bitcoin", None, &api.net).unwrap_err();
+    assert!(matches!(err, Error::Network(_)), "{:?}", err);
+}
+
+#[test]
+fn reports_invalid_tickers() {
+    let api = mock(vec![(404, r#"{"error": "id not found"}"#)]);
+    match refresh_ticker("ethereem", None, &api.net).unwrap_err() {
+        Error::InvalidTicker { name } => assert_eq!(name, "ethereem"),
+        err => panic!("{:?}", err),
+    }
+
+    let api = mock(vec![(200, "[]")]);
+    let err = refresh_ticker("bitcoin", None, &api.net).unwrap_err();
+    assert!(matches!(err, Error::InvalidTicker { .. }), "{:?}", err);
+}
+
+#[test]
+fn waits_until_the_price_is_right() {
+    let above = Comparison::parse(">=").unwrap();
+    let (every, timeout) = (Duration::from_millis(10), Some(Duration::from_secs(5)));
+    let api = mock(vec![(503, ""), (200, TICKER)]);
+    let price = wait::until("bitcoin", above, 2000.0, every, timeout, &api.net);
+    assert_eq!(price.unwrap().unwrap().to_string(), "2545.19");
+    let soon = Some(Duration::from_millis(50));
+    let price = wait::until("bitcoin", above, 3000.0, every, soon, &api.net);
+    assert!(price.unwrap().is_none());
+
+    // a typo is reported at once instead of polled until the timeout
+    let api = mock(vec![(404, r#"{"error": "id not found"}"#)]);
+    let err = wait::until("bitcoinn", above, 70000.0, every, None, &api.net);
+    assert!(matches!(err, Err(Error::InvalidTicker { .. })), "{:?}", err);
+    assert_eq!(api.hits.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn renders_templates() {
+    let api = mock(vec![(200, TICKER)]);
+    let template = "{symbol} {price} ({change_24h}%)".parse::<Template>().unwrap();
+    let (ticker, segment) = ticker_segment(
+        "bitcoin".to_string(),
+        Some(&cache(Some(Duration::from_secs(3600)))),
+        &template,
+        None,
+        None,
+        &api.net,
+    )
+    .unwrap();
+    assert_eq!(ticker.name, "Bitcoin");
+    assert_eq!(segment, "BTC 2545.19 (-3.39%) ");
+}
+
+#[test]
+fn warms_cold_entries_concurrently() {
+    let api = mock(vec![(200, TICKER)]);
+    let cache = cache(Some(Duration::from_secs(3600)));
+    let names: Vec<String> = ["bitcoin", "ethereum", "litecoin", "news:bitcoin", "bitcoin"]
+        .iter()
+        .map(|name| name.to_string())
+        .collect();
+    let reported = AtomicUsize::new(0);
+    warm(&names, &cache, false, &api.net, |done, total| {
+        assert_eq!(total, 3);
+        reported.fetch_max(done, Ordering::SeqCst);
+    });
+    assert_eq!(reported.load(Ordering::SeqCst), 3);
+    assert_eq!(api.hits.load(Ordering::SeqCst), 3);
+    assert!(cache.backend.get("litecoin").is_some());
+
+    // everything is fresh now, so there's nothing left to warm
+    warm(&names, &cache, false, &api.net, |_, _| panic!("nothing to warm"));
+    assert_eq!(api.hits.load(Ordering::SeqCst), 3);
+}